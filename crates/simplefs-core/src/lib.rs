@@ -5,6 +5,8 @@ pub const MAGIC: [u8; 8] = *b"ERESFS1\0";
 pub const VERSION: u32 = 1;
 pub const DIR_ENTRY_NAME_LEN: usize = 32;
 pub const DIR_ENTRY_SIZE: usize = 64;
+/// `DirEntry::flags` bit for subdirectories; they own no data blocks.
+pub const FLAG_DIRECTORY: u32 = 1;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum FsError {
@@ -92,6 +94,11 @@ pub struct DirEntry {
     pub file_block_count: u32,
     pub file_size: u32,
     pub flags: u32,
+    // Slot index + 1 of the containing directory; 0 is the root, so older images stay flat.
+    pub parent: u32,
+    // Seconds since the Unix epoch, 0 if unknown.
+    pub created: u32,
+    pub modified: u32,
 }
 
 impl DirEntry {
@@ -109,6 +116,9 @@ impl DirEntry {
             file_block_count,
             file_size,
             flags: 0,
+            parent: 0,
+            created: 0,
+            modified: 0,
         })
     }
 
@@ -116,6 +126,10 @@ impl DirEntry {
         self.name_len == 0
     }
 
+    pub fn is_directory(&self) -> bool {
+        self.flags & FLAG_DIRECTORY != 0
+    }
+
     pub fn name(&self) -> Result<&str, FsError> {
         let len = self.name_len as usize;
         if len > DIR_ENTRY_NAME_LEN {
@@ -132,6 +146,9 @@ impl DirEntry {
         write_u32(out, 40, self.file_block_count);
         write_u32(out, 44, self.file_size);
         write_u32(out, 48, self.flags);
+        write_u32(out, 52, self.parent);
+        write_u32(out, 56, self.created);
+        write_u32(out, 60, self.modified);
     }
 
     pub fn decode(input: &[u8; DIR_ENTRY_SIZE]) -> Self {
@@ -144,6 +161,9 @@ impl DirEntry {
             file_block_count: read_u32(input, 40),
             file_size: read_u32(input, 44),
             flags: read_u32(input, 48),
+            parent: read_u32(input, 52),
+            created: read_u32(input, 56),
+            modified: read_u32(input, 60),
         }
     }
 }
@@ -172,7 +192,9 @@ fn read_u32(input: &[u8], offset: usize) -> u32 {
 
 #[cfg(test)]
 mod tests {
-    use super::{blocks_for_size, dir_blocks_for_entries, DirEntry, Superblock, BLOCK_SIZE, DIR_ENTRY_SIZE};
+    use super::{
+        blocks_for_size, dir_blocks_for_entries, DirEntry, Superblock, BLOCK_SIZE, DIR_ENTRY_SIZE, FLAG_DIRECTORY,
+    };
 
     #[test]
     fn superblock_roundtrip() {
@@ -186,12 +208,19 @@ mod tests {
 
     #[test]
     fn dir_entry_roundtrip() {
-        let entry = DirEntry::new("hello.txt", 3, 2, 700).expect("entry");
+        let mut entry = DirEntry::new("hello.txt", 3, 2, 700).expect("entry");
+        entry.parent = 4;
+        entry.modified = 1_700_000_000;
         let mut buf = [0_u8; DIR_ENTRY_SIZE];
         entry.encode(&mut buf);
         let parsed = DirEntry::decode(&buf);
         assert_eq!(parsed.name().expect("name"), "hello.txt");
         assert_eq!(parsed.file_size, 700);
+        assert_eq!(parsed, entry);
+        assert!(!parsed.is_directory());
+
+        entry.flags = FLAG_DIRECTORY;
+        assert!(entry.is_directory());
     }
 
     #[test]
@@ -3,6 +3,7 @@ use simplefs_core::{blocks_for_size, dir_blocks_for_entries, DirEntry, Superbloc
 use std::ffi::OsString;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
 
 #[derive(Debug, Clone, Parser)]
 #[command(name = "simplefs-tool", about = "Build a simplefs disk image from host files")]
@@ -16,12 +17,20 @@ pub struct Cli {
     /// Include all regular files from this directory.
     #[arg(long = "input-dir", value_name = "DIR")]
     pub input_dir: Option<PathBuf>,
+    /// Free directory slots for files created at runtime.
+    #[arg(long = "spare-entries", value_name = "N", default_value_t = 0)]
+    pub spare_entries: usize,
+    /// Free data blocks for file contents written at runtime.
+    #[arg(long = "spare-blocks", value_name = "N", default_value_t = 0)]
+    pub spare_blocks: u32,
 }
 
 #[derive(Debug)]
 struct InputFile {
     name: String,
     data: Vec<u8>,
+    // Host modification time in seconds since the Unix epoch, 0 if unavailable.
+    modified: u32,
 }
 
 pub fn run_from<I, T>(args: I) -> Result<(), String>
@@ -39,22 +48,37 @@ pub fn run_with_cli(cli: Cli) -> Result<(), String> {
         return Err("at least one input file is required (use --file or --input-dir)".to_string());
     }
 
-    write_image(&cli.output, &sources)?;
+    write_image(&cli.output, &sources, cli.spare_entries, cli.spare_blocks)?;
     println!("wrote {}", cli.output.display());
     Ok(())
 }
 
-pub fn write_image(output: &Path, sources: &[PathBuf]) -> Result<(), String> {
-    let image = build_image_from_paths(sources)?;
+pub fn write_image(
+    output: &Path,
+    sources: &[PathBuf],
+    spare_entries: usize,
+    spare_blocks: u32,
+) -> Result<(), String> {
+    let image = build_image_with_spare(sources, spare_entries, spare_blocks)?;
     fs::write(output, image).map_err(|e| format!("write {}: {e}", output.display()))
 }
 
 pub fn build_image_from_paths(sources: &[PathBuf]) -> Result<Vec<u8>, String> {
+    build_image_with_spare(sources, 0, 0)
+}
+
+/// Like [`build_image_from_paths`], but leaves free directory slots and data blocks so the
+/// kernel can create and grow files.
+pub fn build_image_with_spare(
+    sources: &[PathBuf],
+    spare_entries: usize,
+    spare_blocks: u32,
+) -> Result<Vec<u8>, String> {
     let mut files = Vec::new();
     for source in sources {
         files.push(load_input_file(source)?);
     }
-    build_image(&files)
+    build_image(&files, spare_entries, spare_blocks)
 }
 
 pub fn collect_sources(files: &[PathBuf], input_dir: Option<&Path>) -> Result<Vec<PathBuf>, String> {
@@ -87,31 +111,37 @@ fn load_input_file(path: &Path) -> Result<InputFile, String> {
         .to_string_lossy()
         .to_string();
     let data = fs::read(path).map_err(|e| format!("read {}: {e}", path.display()))?;
-    Ok(InputFile { name, data })
+    let modified = fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |age| u32::try_from(age.as_secs()).unwrap_or(u32::MAX));
+    Ok(InputFile { name, data, modified })
 }
 
-fn build_image(files: &[InputFile]) -> Result<Vec<u8>, String> {
-    let dir_blocks = dir_blocks_for_entries(files.len()) as usize;
+fn build_image(files: &[InputFile], spare_entries: usize, spare_blocks: u32) -> Result<Vec<u8>, String> {
+    let dir_blocks = dir_blocks_for_entries(files.len() + spare_entries) as usize;
     let mut current_data_block = 1 + dir_blocks as u32;
     let mut entries = Vec::new();
     let mut total_data_blocks = 0_u32;
 
     for file in files {
         let blocks = blocks_for_size(file.data.len());
-        entries.push(
-            DirEntry::new(
-                &file.name,
-                current_data_block,
-                blocks,
-                file.data.len() as u32,
-            )
-            .map_err(|_| format!("invalid entry name: {}", file.name))?,
-        );
+        let mut entry = DirEntry::new(
+            &file.name,
+            current_data_block,
+            blocks,
+            file.data.len() as u32,
+        )
+        .map_err(|_| format!("invalid entry name: {}", file.name))?;
+        entry.created = file.modified;
+        entry.modified = file.modified;
+        entries.push(entry);
         current_data_block += blocks;
         total_data_blocks += blocks;
     }
 
-    let total_blocks = 1 + dir_blocks as u32 + total_data_blocks;
+    let total_blocks = 1 + dir_blocks as u32 + total_data_blocks + spare_blocks;
     let sb = Superblock::new(total_blocks, entries.len() as u32, dir_blocks as u32);
     let mut image = vec![0_u8; total_blocks as usize * BLOCK_SIZE];
 
@@ -138,7 +168,7 @@ fn build_image(files: &[InputFile]) -> Result<Vec<u8>, String> {
 #[cfg(test)]
 mod tests {
     use super::{collect_sources, run_from};
-    use simplefs_core::Superblock;
+    use simplefs_core::{DirEntry, Superblock, DIR_ENTRY_SIZE};
     use std::fs;
     use std::path::PathBuf;
     use std::time::{SystemTime, UNIX_EPOCH};
@@ -197,6 +227,48 @@ mod tests {
         let sb = Superblock::decode(&sb_buf).expect("decode superblock");
         assert_eq!(sb.dir_entry_count, 2);
 
+        // Entries carry the host modification time.
+        let offset = sb.dir_start_block as usize * simplefs_core::BLOCK_SIZE;
+        let mut raw = [0_u8; DIR_ENTRY_SIZE];
+        raw.copy_from_slice(&image[offset..offset + DIR_ENTRY_SIZE]);
+        let entry = DirEntry::decode(&raw);
+        assert_eq!(entry.name().expect("name"), "hello.txt");
+        assert!(entry.modified > 0);
+        assert_eq!(entry.created, entry.modified);
+
+        let _ = fs::remove_file(out);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn reserves_spare_entries_and_blocks() {
+        let dir = temp_path("spare-input");
+        fs::create_dir_all(&dir).expect("create dir");
+        fs::write(dir.join("hello.txt"), b"hello").expect("write hello");
+
+        let out = temp_path("spare.img");
+        let args = [
+            "simplefs-tool",
+            "--output",
+            out.to_str().expect("out str"),
+            "--input-dir",
+            dir.to_str().expect("dir str"),
+            "--spare-entries",
+            "16",
+            "--spare-blocks",
+            "100",
+        ];
+        run_from(args).expect("run cli");
+
+        let image = fs::read(&out).expect("read image");
+        let mut sb_buf = [0_u8; simplefs_core::BLOCK_SIZE];
+        sb_buf.copy_from_slice(&image[..simplefs_core::BLOCK_SIZE]);
+        let sb = Superblock::decode(&sb_buf).expect("decode superblock");
+        assert_eq!(sb.dir_entry_count, 1);
+        assert_eq!(sb.dir_block_count, 3);
+        assert_eq!(sb.total_blocks, 1 + 3 + 1 + 100);
+        assert_eq!(image.len(), sb.total_blocks as usize * simplefs_core::BLOCK_SIZE);
+
         let _ = fs::remove_file(out);
         let _ = fs::remove_dir_all(dir);
     }
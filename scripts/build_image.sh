@@ -38,14 +38,30 @@ fi
 
 as --64 "$ROOT_DIR/boot/stage2.S" -o "$BUILD_DIR/stage2.o"
 
-ld.lld \
-    -m elf_x86_64 \
-    -nostdlib \
-    --gc-sections \
-    -T "$ROOT_DIR/build/linker.ld" \
-    -o "$BUILD_DIR/stage2.elf" \
-    "$BUILD_DIR/stage2.o" \
-    "$TARGET_DIR/liberes_os.a"
+link_kernel() {
+    ld.lld \
+        -m elf_x86_64 \
+        -nostdlib \
+        --gc-sections \
+        -T "$ROOT_DIR/build/linker.ld" \
+        -o "$BUILD_DIR/stage2.elf" \
+        "$BUILD_DIR/stage2.o" \
+        "$@" \
+        "$TARGET_DIR/liberes_os.a"
+}
+
+# Zwei Durchläufe: der erste liefert die Adressen für die Symboltabelle, der zweite linkt sie
+# hinter .data ein. Code und Daten bleiben dabei unverändert.
+link_kernel
+"$ROOT_DIR/scripts/gen_ksymtab.sh" "$BUILD_DIR/stage2.elf" "$BUILD_DIR/ksymtab.S"
+as --64 "$BUILD_DIR/ksymtab.S" -o "$BUILD_DIR/ksymtab.o"
+link_kernel "$BUILD_DIR/ksymtab.o"
+
+"$ROOT_DIR/scripts/gen_ksymtab.sh" "$BUILD_DIR/stage2.elf" "$BUILD_DIR/ksymtab.check.S"
+if ! cmp -s "$BUILD_DIR/ksymtab.S" "$BUILD_DIR/ksymtab.check.S"; then
+    echo "Symbol addresses changed between link passes" >&2
+    exit 1
+fi
 
 "$OBJCOPY" -O binary "$BUILD_DIR/stage2.elf" "$BUILD_DIR/stage2.bin"
 
@@ -80,7 +96,8 @@ echo "Stage2 size: $stage2_size bytes ($stage2_sectors sectors)"
 if [[ -d "$SIMPLEFS_DIR" ]]; then
     mapfile -t SIMPLEFS_FILES < <(find "$SIMPLEFS_DIR" -maxdepth 1 -type f | sort)
     if (( ${#SIMPLEFS_FILES[@]} > 0 )); then
-        cargo run -q -p simplefs-tool -- --output "$SIMPLEFS_IMAGE" --input-dir "$SIMPLEFS_DIR"
+        cargo run -q -p simplefs-tool -- --output "$SIMPLEFS_IMAGE" --input-dir "$SIMPLEFS_DIR" \
+            --spare-entries 32 --spare-blocks 2048
         echo "Built $SIMPLEFS_IMAGE with ${#SIMPLEFS_FILES[@]} files"
     fi
 fi
@@ -0,0 +1,36 @@
+#!/usr/bin/env bash
+# Erzeugt aus einem gelinkten Kernel-ELF eine Assembler-Datei mit der Section `.ksymtab`.
+#
+# Format je Eintrag (aufsteigend nach Adresse), siehe src/debug/symbols.rs:
+#   u64 Adresse, u16 Namenslänge, Name ohne Nullbyte
+set -euo pipefail
+
+if (( $# != 2 )); then
+    echo "Usage: $0 <kernel.elf> <output.S>" >&2
+    exit 1
+fi
+
+NM="${NM:-nm}"
+
+{
+    echo '.section .ksymtab, "a"'
+    LC_ALL=C "$NM" --defined-only --numeric-sort --demangle "$1" | LC_ALL=C awk '
+        # Vom Linker-Skript definierte Markierungen sind keine Funktionen und hängen vom
+        # Durchlauf ab.
+        $2 ~ /^[tTwW]$/ && $3 !~ /^__(ksymtab|bss)_/ {
+            name = $3
+            for (i = 4; i <= NF; i++) {
+                name = name " " $i
+            }
+            # Hash-Suffix der Rust-Legacy-Mangling-Namen entfernen.
+            sub(/::h[0-9a-f]+$/, "", name)
+            if (length(name) > 255) {
+                name = substr(name, 1, 255)
+            }
+            len = length(name)
+            gsub(/\\/, "\\\\", name)
+            gsub(/"/, "\\\"", name)
+            printf "    .quad 0x%s\n    .short %d\n    .ascii \"%s\"\n", $1, len, name
+        }
+    '
+} > "$2"
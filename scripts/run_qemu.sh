@@ -4,15 +4,19 @@ set -euo pipefail
 ROOT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")/.." && pwd)"
 IMAGE="$ROOT_DIR/build/os.img"
 FS_IMAGE="$ROOT_DIR/build/simplefs.img"
+DEBUGCON_LOG="$ROOT_DIR/build/debugcon.log"
 HEADLESS=0
 TEST_MODE=0
+SERIAL=0
 
 usage() {
     cat <<'EOF'
-Usage: ./scripts/run_qemu.sh [--headless] [--test]
+Usage: ./scripts/run_qemu.sh [--headless] [--serial] [--test]
 
 Options:
   --headless   Run without a GUI window and print debug console output.
+  --serial     Attach COM1 to this terminal (shell input/output over serial).
+               Headless debug console output then goes to build/debugcon.log.
   --test       Build with qemu-test feature and exit with command-test result.
   -h, --help   Show this help.
 EOF
@@ -24,6 +28,10 @@ while [[ $# -gt 0 ]]; do
             HEADLESS=1
             shift
             ;;
+        --serial)
+            SERIAL=1
+            shift
+            ;;
         --test)
             TEST_MODE=1
             HEADLESS=1
@@ -57,11 +65,15 @@ if [[ "$HEADLESS" -eq 1 ]]; then
         -drive "if=ide,index=0,media=disk,format=raw,file=$IMAGE"
         -display none
         -monitor none
-        -serial none
-        -debugcon stdio
         -no-reboot
         -no-shutdown
     )
+    if [[ "$SERIAL" -eq 1 && "$TEST_MODE" -eq 0 ]]; then
+        # stdio kann nur einem Gerät gehören: serielle Konsole hat Vorrang.
+        QEMU_ARGS+=(-serial stdio -debugcon "file:$DEBUGCON_LOG")
+    else
+        QEMU_ARGS+=(-serial none -debugcon stdio)
+    fi
     if [[ -f "$FS_IMAGE" ]]; then
         QEMU_ARGS+=(-drive "if=ide,index=1,media=disk,format=raw,file=$FS_IMAGE")
     fi
@@ -88,6 +100,9 @@ else
         -no-reboot
         -no-shutdown
     )
+    if [[ "$SERIAL" -eq 1 ]]; then
+        QEMU_ARGS+=(-serial stdio)
+    fi
     if [[ -f "$FS_IMAGE" ]]; then
         QEMU_ARGS+=(-drive "if=ide,index=1,media=disk,format=raw,file=$FS_IMAGE")
     fi
@@ -1,9 +1,12 @@
 #[cfg(eres_kernel)]
 use core::arch::asm;
 
+pub mod gdt;
 pub mod interrupts;
 pub mod keyboard;
+pub mod keymap;
 pub mod pit;
+pub mod rtc;
 pub(crate) mod io;
 mod pic;
 
@@ -18,6 +21,19 @@ pub fn halt() {
     core::hint::spin_loop();
 }
 
+/// Gibt Interrupts frei und hält an; ein IRQ wird frühestens im `hlt` angenommen, sodass
+/// zwischen einer Prüfung unter `cli` und dem Schlafen keiner verloren geht.
+#[inline]
+pub fn enable_interrupts_and_halt() {
+    #[cfg(eres_kernel)]
+    unsafe {
+        asm!("sti", "hlt", options(nomem, nostack));
+    }
+
+    #[cfg(not(eres_kernel))]
+    core::hint::spin_loop();
+}
+
 #[inline]
 pub fn disable_interrupts() {
     #[cfg(eres_kernel)]
@@ -65,6 +81,29 @@ pub fn restore_interrupts(was_enabled: bool) {
     }
 }
 
+/// Setzt `RSP` auf `stack_top` und springt nach `entry`; der alte Stack wird verlassen.
+pub fn switch_stack(stack_top: u64, entry: extern "C" fn() -> !) -> ! {
+    #[cfg(not(eres_kernel))]
+    {
+        let _ = stack_top;
+        entry();
+    }
+
+    #[cfg(eres_kernel)]
+    unsafe {
+        asm!(
+            "mov rsp, {stack}",
+            // Leerer Framepointer beendet spätere Stack-Walks sauber.
+            "xor ebp, ebp",
+            "call {entry}",
+            "ud2",
+            stack = in(reg) stack_top,
+            entry = in(reg) entry,
+            options(noreturn)
+        );
+    }
+}
+
 pub fn halt_loop() -> ! {
     loop {
         halt();
@@ -0,0 +1,153 @@
+//! Globale Deskriptortabelle (GDT) und Task State Segment (TSS).
+//!
+//! Stage 2 lädt eine minimale GDT im unteren Speicher, der nach dem Boot nicht mehr abgebildet
+//! ist. Der Kernel ersetzt sie durch eine eigene Tabelle im Higher Half mit denselben
+//! Selektoren plus einem TSS. Dessen Interrupt Stack Table (IST) gibt dem Double-Fault-Handler
+//! einen eigenen Stack, damit auch ein Überlauf des Kernel-Stacks noch gemeldet werden kann.
+//!
+//! Hintergrund:
+//! - GDT: <https://wiki.osdev.org/Global_Descriptor_Table>
+//! - TSS: <https://wiki.osdev.org/Task_State_Segment>
+
+use core::arch::asm;
+use core::mem::size_of;
+
+/// 64-Bit-Codesegment (gleicher Selektor wie in Stage 2).
+pub const KERNEL_CODE_SELECTOR: u16 = 0x18;
+/// Datensegment (gleicher Selektor wie in Stage 2).
+pub const KERNEL_DATA_SELECTOR: u16 = 0x10;
+/// Selektor des 16 Byte großen TSS-Deskriptors.
+const TSS_SELECTOR: u16 = 0x20;
+/// IST-Eintrag (1-basiert), auf dem Double Faults laufen.
+pub const DOUBLE_FAULT_IST: u8 = 1;
+
+const IST_STACK_SIZE: usize = 16 * 1024;
+const GDT_ENTRIES: usize = 6;
+
+#[repr(C, packed)]
+#[derive(Clone, Copy)]
+struct TaskStateSegment {
+    reserved0: u32,
+    privilege_stacks: [u64; 3],
+    reserved1: u64,
+    interrupt_stacks: [u64; 7],
+    reserved2: u64,
+    reserved3: u16,
+    iomap_base: u16,
+}
+
+impl TaskStateSegment {
+    const fn empty() -> Self {
+        Self {
+            reserved0: 0,
+            privilege_stacks: [0; 3],
+            reserved1: 0,
+            interrupt_stacks: [0; 7],
+            reserved2: 0,
+            reserved3: 0,
+            // Keine I/O-Permission-Bitmap: Offset hinter das Segmentende.
+            iomap_base: size_of::<TaskStateSegment>() as u16,
+        }
+    }
+}
+
+#[repr(C, align(16))]
+struct IstStack([u8; IST_STACK_SIZE]);
+
+#[repr(C, packed)]
+struct GdtPointer {
+    limit: u16,
+    base: u64,
+}
+
+static mut TSS: TaskStateSegment = TaskStateSegment::empty();
+static mut DOUBLE_FAULT_STACK: IstStack = IstStack([0; IST_STACK_SIZE]);
+static mut GDT: [u64; GDT_ENTRIES] = [
+    0,
+    // 32-Bit Code (nur für Stage 2, bleibt für identische Selektoren erhalten).
+    0x00CF_9A00_0000_FFFF,
+    // Daten, read/write, ring0.
+    0x00CF_9200_0000_FFFF,
+    // 64-Bit Code (L-Bit gesetzt).
+    0x00AF_9A00_0000_FFFF,
+    // TSS-Deskriptor (zwei Einträge), wird in `init` gesetzt.
+    0,
+    0,
+];
+
+/// Lädt die Kernel-GDT, setzt die Segmentregister neu und aktiviert das TSS.
+pub fn init() {
+    unsafe {
+        let stack_top = (&raw const DOUBLE_FAULT_STACK) as u64 + IST_STACK_SIZE as u64;
+        let mut tss = TaskStateSegment::empty();
+        let mut interrupt_stacks = [0_u64; 7];
+        interrupt_stacks[DOUBLE_FAULT_IST as usize - 1] = stack_top;
+        tss.interrupt_stacks = interrupt_stacks;
+        TSS = tss;
+
+        let (low, high) = tss_descriptor(
+            (&raw const TSS) as u64,
+            size_of::<TaskStateSegment>() as u64 - 1,
+        );
+        GDT[4] = low;
+        GDT[5] = high;
+
+        let gdtr = GdtPointer {
+            limit: (size_of::<u64>() * GDT_ENTRIES - 1) as u16,
+            base: (&raw const GDT) as u64,
+        };
+
+        asm!(
+            "lgdt [{gdtr}]",
+            "mov ds, {data:x}",
+            "mov es, {data:x}",
+            "mov fs, {data:x}",
+            "mov gs, {data:x}",
+            "mov ss, {data:x}",
+            // CS lässt sich nur über einen Far Return neu laden.
+            "push {code}",
+            "lea {tmp}, [rip + 2f]",
+            "push {tmp}",
+            "retfq",
+            "2:",
+            "ltr {tss:x}",
+            gdtr = in(reg) &gdtr,
+            data = in(reg) KERNEL_DATA_SELECTOR as u64,
+            code = in(reg) KERNEL_CODE_SELECTOR as u64,
+            tss = in(reg) TSS_SELECTOR as u64,
+            tmp = out(reg) _,
+            options(preserves_flags)
+        );
+    }
+}
+
+/// Baut den 16 Byte großen Systemdeskriptor eines verfügbaren 64-Bit-TSS.
+fn tss_descriptor(base: u64, limit: u64) -> (u64, u64) {
+    let mut low = limit & 0xffff;
+    low |= (base & 0xff_ffff) << 16;
+    // Present, DPL 0, Typ 0x9 = "64-bit TSS (available)".
+    low |= 0x89 << 40;
+    low |= ((limit >> 16) & 0xf) << 48;
+    low |= ((base >> 24) & 0xff) << 56;
+    (low, base >> 32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TaskStateSegment, tss_descriptor};
+
+    #[test]
+    fn tss_has_architectural_size() {
+        assert_eq!(core::mem::size_of::<TaskStateSegment>(), 104);
+    }
+
+    #[test]
+    fn encodes_tss_descriptor() {
+        let (low, high) = tss_descriptor(0xffff_ffff_8012_3456, 103);
+        assert_eq!(low & 0xffff, 103);
+        assert_eq!((low >> 16) & 0xff_ffff, 0x12_3456);
+        assert_eq!((low >> 40) & 0xff, 0x89);
+        assert_eq!((low >> 56) & 0xff, 0x80);
+        assert_eq!(high, 0xffff_ffff);
+    }
+}
@@ -7,17 +7,121 @@
 use core::arch::{asm, global_asm};
 use core::mem::size_of;
 
-use crate::{arch, console};
+use crate::memory::fault::{FaultError, PageFaultCode};
+use crate::storage::ata::{self, Channel};
+use crate::{arch, console, memory};
 
+use super::gdt::{DOUBLE_FAULT_IST, KERNEL_CODE_SELECTOR};
 use super::{keyboard, pic, pit};
 
 const IDT_ENTRIES: usize = 256;
-const KERNEL_CODE_SELECTOR: u16 = 0x18;
 const INTERRUPT_GATE_FLAGS: u8 = 0x8E;
 
 const IRQ_BASE: u8 = pic::PIC1_OFFSET;
 const IRQ_TIMER: u8 = IRQ_BASE;
 const IRQ_KEYBOARD: u8 = IRQ_BASE + 1;
+const IRQ_COM1: u8 = IRQ_BASE + 4;
+const IRQ_ATA_PRIMARY: u8 = IRQ_BASE + 14;
+const IRQ_ATA_SECONDARY: u8 = IRQ_BASE + 15;
+
+/// Anzahl der architektonisch reservierten Exception-Vektoren.
+const EXCEPTION_COUNT: usize = 32;
+const VECTOR_BREAKPOINT: u8 = 3;
+const VECTOR_DOUBLE_FAULT: u8 = 8;
+const VECTOR_PAGE_FAULT: u8 = 14;
+
+/// Namen der CPU-Exceptions, indiziert nach Vektor.
+const EXCEPTION_NAMES: [&[u8]; EXCEPTION_COUNT] = [
+    b"divide by zero",
+    b"debug",
+    b"non-maskable interrupt",
+    b"breakpoint",
+    b"overflow",
+    b"bound range exceeded",
+    b"invalid opcode",
+    b"device not available",
+    b"double fault",
+    b"coprocessor segment overrun",
+    b"invalid TSS",
+    b"segment not present",
+    b"stack-segment fault",
+    b"general protection fault",
+    b"page fault",
+    b"reserved",
+    b"x87 floating-point exception",
+    b"alignment check",
+    b"machine check",
+    b"SIMD floating-point exception",
+    b"virtualization exception",
+    b"control protection exception",
+    b"reserved",
+    b"reserved",
+    b"reserved",
+    b"reserved",
+    b"reserved",
+    b"reserved",
+    b"hypervisor injection exception",
+    b"VMM communication exception",
+    b"security exception",
+    b"reserved",
+];
+
+/// Vom Stub und von `isr_common` gesicherter Zustand, in Stack-Reihenfolge (niedrigste Adresse zuerst).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct InterruptFrame {
+    pub r15: u64,
+    pub r14: u64,
+    pub r13: u64,
+    pub r12: u64,
+    pub r11: u64,
+    pub r10: u64,
+    pub r9: u64,
+    pub r8: u64,
+    pub rbp: u64,
+    pub rdi: u64,
+    pub rsi: u64,
+    pub rdx: u64,
+    pub rcx: u64,
+    pub rbx: u64,
+    pub rax: u64,
+    pub vector: u64,
+    /// Fehlercode der CPU oder 0, wenn der Vektor keinen liefert.
+    pub error_code: u64,
+    // Ab hier von der CPU gesichert.
+    pub rip: u64,
+    pub cs: u64,
+    pub rflags: u64,
+    pub rsp: u64,
+    pub ss: u64,
+}
+
+/// Name einer CPU-Exception für Fehlermeldungen.
+fn exception_name(vector: u8) -> &'static [u8] {
+    EXCEPTION_NAMES
+        .get(vector as usize)
+        .copied()
+        .unwrap_or(b"unknown exception")
+}
+
+/// Exceptions, für die die CPU einen Fehlercode auf den Stack legt, als Bitmaske über die
+/// Vektoren. Die Stubs in `global_asm!` entscheiden anhand derselben Maske, ob sie selbst eine
+/// 0 als Fehlercode pushen.
+const ERROR_CODE_VECTORS: u32 = 1 << 8
+    | 1 << 10
+    | 1 << 11
+    | 1 << 12
+    | 1 << 13
+    | 1 << 14
+    | 1 << 17
+    | 1 << 21
+    | 1 << 29
+    | 1 << 30;
+
+/// Gibt an, ob die CPU für `vector` einen Fehlercode auf den Stack legt.
+fn has_error_code(vector: u8) -> bool {
+    (vector as usize) < EXCEPTION_COUNT && ERROR_CODE_VECTORS & (1 << vector) != 0
+}
 
 #[repr(C, packed)]
 #[derive(Clone, Copy)]
@@ -54,6 +158,11 @@ impl IdtEntry {
         self.offset_high = (addr >> 32) as u32;
         self.reserved = 0;
     }
+
+    /// Lässt den Handler auf dem Stack aus IST-Eintrag `ist` (1..=7) laufen.
+    fn set_ist(&mut self, ist: u8) {
+        self.ist = ist & 0x7;
+    }
 }
 
 #[repr(C, packed)]
@@ -65,13 +174,13 @@ struct IdtPointer {
 static mut IDT: [IdtEntry; IDT_ENTRIES] = [IdtEntry::missing(); IDT_ENTRIES];
 
 unsafe extern "C" {
-    fn isr_divide_by_zero();
-    fn isr_invalid_opcode();
-    fn isr_double_fault();
-    fn isr_general_protection_fault();
-    fn isr_page_fault();
+    /// Adressen der 32 Exception-Stubs aus `global_asm!`, indiziert nach Vektor.
+    static isr_exception_stubs: [unsafe extern "C" fn(); EXCEPTION_COUNT];
     fn isr_irq0_timer();
     fn isr_irq1_keyboard();
+    fn isr_irq4_com1();
+    fn isr_irq14_ata_primary();
+    fn isr_irq15_ata_secondary();
 }
 
 /// Initialisiert IDT, PIC und PIT für den Kernelbetrieb.
@@ -79,19 +188,24 @@ pub fn init() {
     arch::x86_64::disable_interrupts();
 
     unsafe {
-        set_gate(0, isr_divide_by_zero);
-        set_gate(6, isr_invalid_opcode);
-        set_gate(8, isr_double_fault);
-        set_gate(13, isr_general_protection_fault);
-        set_gate(14, isr_page_fault);
+        for (vector, stub) in isr_exception_stubs.iter().enumerate() {
+            set_gate(vector as u8, *stub);
+        }
+        // Eigener Stack: ein Double Fault kann von einem Kernel-Stack-Überlauf stammen.
+        IDT[VECTOR_DOUBLE_FAULT as usize].set_ist(DOUBLE_FAULT_IST);
         set_gate(IRQ_TIMER, isr_irq0_timer);
         set_gate(IRQ_KEYBOARD, isr_irq1_keyboard);
+        set_gate(IRQ_COM1, isr_irq4_com1);
+        set_gate(IRQ_ATA_PRIMARY, isr_irq14_ata_primary);
+        set_gate(IRQ_ATA_SECONDARY, isr_irq15_ata_secondary);
         load_idt();
     }
 
     pic::remap();
     pit::init();
-    pic::set_masks(0b1111_1100, 0xff);
+    // Freigegeben: IRQ0 (Timer), IRQ1 (Tastatur), IRQ2 (Kaskade zum Slave-PIC), IRQ4 (COM1),
+    // IRQ14/IRQ15 (ATA primär/sekundär).
+    pic::set_masks(0b1110_1000, 0b0011_1111);
 }
 
 unsafe fn set_gate(index: u8, handler: unsafe extern "C" fn()) {
@@ -116,60 +230,120 @@ unsafe fn load_idt() {
 }
 
 #[unsafe(no_mangle)]
-/// Zentraler Dispatcher, von den ASM-ISR-Stubs aufgerufen.
-extern "C" fn interrupt_dispatch(vector: u64, error_code: u64, rip: u64) {
-    match vector as u8 {
-        0 => handle_exception(b"EXC: divide by zero", vector, error_code, rip, false),
-        6 => handle_exception(b"EXC: invalid opcode", vector, error_code, rip, false),
-        8 => handle_exception(b"EXC: double fault", vector, error_code, rip, false),
-        13 => handle_exception(
-            b"EXC: general protection fault",
-            vector,
-            error_code,
-            rip,
-            false,
+/// Zentraler Dispatcher, von `isr_common` mit dem gesicherten Register-Frame aufgerufen.
+extern "C" fn interrupt_dispatch(frame: &mut InterruptFrame) {
+    let vector = frame.vector as u8;
+    match vector {
+        VECTOR_BREAKPOINT => {
+            // Trap: RIP zeigt bereits hinter `int3`, die Ausführung kann weiterlaufen.
+            console::write_line(b"EXC: breakpoint");
+            dump_frame(frame);
+        }
+        VECTOR_DOUBLE_FAULT if memory::stack::is_guard_page(read_cr2()) => handle_exception(
+            b"EXC: double fault (kernel stack overflow, guard page hit)",
+            frame,
         ),
-        14 => handle_exception(b"EXC: page fault", vector, error_code, rip, true),
+        VECTOR_PAGE_FAULT => {
+            let addr = read_cr2();
+            let code = PageFaultCode(frame.error_code);
+            if let Err(err) = memory::fault::handle_page_fault(addr, code) {
+                report_page_fault(addr, code, frame, err);
+            }
+        }
         IRQ_TIMER => {
             pit::on_tick();
         }
         IRQ_KEYBOARD => {
             keyboard::handle_irq();
         }
-        _ => handle_exception(b"EXC: unhandled vector", vector, error_code, rip, false),
+        IRQ_COM1 => {
+            console::serial::handle_irq();
+        }
+        IRQ_ATA_PRIMARY => ata::handle_irq(Channel::Primary),
+        IRQ_ATA_SECONDARY => ata::handle_irq(Channel::Secondary),
+        _ if (vector as usize) < EXCEPTION_COUNT => {
+            console::write_str(b"EXC: ");
+            handle_exception(exception_name(vector), frame);
+        }
+        _ => handle_exception(b"EXC: unhandled vector", frame),
     }
 
-    if (IRQ_BASE..IRQ_BASE + 16).contains(&(vector as u8)) {
-        pic::send_eoi((vector as u8) - IRQ_BASE);
+    if (IRQ_BASE..IRQ_BASE + 16).contains(&vector) {
+        pic::send_eoi(vector - IRQ_BASE);
     }
 }
 
 /// Gibt Diagnosedaten aus und hält danach das System an.
-fn handle_exception(
-    message: &[u8],
-    vector: u64,
-    error_code: u64,
-    rip: u64,
-    include_cr2: bool,
-) -> ! {
+fn handle_exception(message: &[u8], frame: &InterruptFrame) -> ! {
     console::write_line(message);
-    console::write_str(b"  vector=");
-    console::write_u64(vector);
-    console::write_byte(b'\n');
-    console::write_str(b"  error=");
-    console::write_hex_u64(error_code);
-    console::write_byte(b'\n');
-    console::write_str(b"  rip=");
-    console::write_hex_u64(rip);
+    dump_frame(frame);
+    arch::x86_64::hang();
+}
+
+/// Meldet einen nicht auflösbaren Page Fault mit dekodiertem Fehlercode und hält an.
+fn report_page_fault(addr: u64, code: PageFaultCode, frame: &InterruptFrame, err: FaultError) -> ! {
+    console::write_line(b"EXC: page fault");
+    console::write_str(b"  reason=");
+    console::write_line(err.describe());
+    console::write_str(b"  access=");
+    console::write_str(code.mode());
+    console::write_byte(b' ');
+    console::write_str(code.access());
+    console::write_str(b", page ");
+    console::write_line(code.page_state());
+    console::write_str(b"  addr=");
+    console::write_hex_u64(addr);
     console::write_byte(b'\n');
-    if include_cr2 {
-        console::write_str(b"  cr2=");
-        console::write_hex_u64(read_cr2());
-        console::write_byte(b'\n');
-    }
+    dump_frame(frame);
     arch::x86_64::hang();
 }
 
+/// Gibt Vektor, Fehlercode, Kontrollregister und alle Allzweckregister aus.
+fn dump_frame(frame: &InterruptFrame) {
+    if has_error_code(frame.vector as u8) {
+        dump_registers(&[
+            (b"vector", frame.vector),
+            (b"error", frame.error_code),
+            (b"cr2", read_cr2()),
+        ]);
+    } else {
+        dump_registers(&[(b"vector", frame.vector), (b"cr2", read_cr2())]);
+    }
+    dump_registers(&[
+        (b"rip", frame.rip),
+        (b"cs", frame.cs),
+        (b"rflags", frame.rflags),
+    ]);
+    dump_registers(&[(b"rsp", frame.rsp), (b"ss", frame.ss), (b"rbp", frame.rbp)]);
+    dump_registers(&[
+        (b"rax", frame.rax),
+        (b"rbx", frame.rbx),
+        (b"rcx", frame.rcx),
+    ]);
+    dump_registers(&[
+        (b"rdx", frame.rdx),
+        (b"rsi", frame.rsi),
+        (b"rdi", frame.rdi),
+    ]);
+    dump_registers(&[(b"r8", frame.r8), (b"r9", frame.r9), (b"r10", frame.r10)]);
+    dump_registers(&[
+        (b"r11", frame.r11),
+        (b"r12", frame.r12),
+        (b"r13", frame.r13),
+    ]);
+    dump_registers(&[(b"r14", frame.r14), (b"r15", frame.r15)]);
+}
+
+fn dump_registers(registers: &[(&[u8], u64)]) {
+    for (name, value) in registers {
+        console::write_str(b"  ");
+        console::write_str(name);
+        console::write_byte(b'=');
+        console::write_hex_u64(*value);
+    }
+    console::write_byte(b'\n');
+}
+
 /// Liest Register `CR2` (relevant bei Page Faults).
 fn read_cr2() -> u64 {
     let value: u64;
@@ -189,9 +363,12 @@ global_asm!(
     jmp isr_common
 .endm
 
-.macro ISR_ERR handler_name, vector
-.global \handler_name
-\handler_name:
+.macro ISR_EXCEPTION vector
+.global isr_exception_\vector
+isr_exception_\vector:
+.if (({error_code_vectors} >> \vector) & 1) == 0
+    push 0
+.endif
     push \vector
     jmp isr_common
 .endm
@@ -214,9 +391,7 @@ isr_common:
     push r14
     push r15
 
-    mov rdi, [rsp + 120]
-    mov rsi, [rsp + 128]
-    mov rdx, [rsp + 136]
+    mov rdi, rsp
     call interrupt_dispatch
 
     pop r15
@@ -238,12 +413,128 @@ isr_common:
     add rsp, 16
     iretq
 
-ISR_NOERR isr_divide_by_zero, 0
-ISR_NOERR isr_invalid_opcode, 6
-ISR_ERR   isr_double_fault, 8
-ISR_ERR   isr_general_protection_fault, 13
-ISR_ERR   isr_page_fault, 14
+ISR_EXCEPTION 0
+ISR_EXCEPTION 1
+ISR_EXCEPTION 2
+ISR_EXCEPTION 3
+ISR_EXCEPTION 4
+ISR_EXCEPTION 5
+ISR_EXCEPTION 6
+ISR_EXCEPTION 7
+ISR_EXCEPTION 8
+ISR_EXCEPTION 9
+ISR_EXCEPTION 10
+ISR_EXCEPTION 11
+ISR_EXCEPTION 12
+ISR_EXCEPTION 13
+ISR_EXCEPTION 14
+ISR_EXCEPTION 15
+ISR_EXCEPTION 16
+ISR_EXCEPTION 17
+ISR_EXCEPTION 18
+ISR_EXCEPTION 19
+ISR_EXCEPTION 20
+ISR_EXCEPTION 21
+ISR_EXCEPTION 22
+ISR_EXCEPTION 23
+ISR_EXCEPTION 24
+ISR_EXCEPTION 25
+ISR_EXCEPTION 26
+ISR_EXCEPTION 27
+ISR_EXCEPTION 28
+ISR_EXCEPTION 29
+ISR_EXCEPTION 30
+ISR_EXCEPTION 31
 ISR_NOERR isr_irq0_timer, 32
 ISR_NOERR isr_irq1_keyboard, 33
-"#
+ISR_NOERR isr_irq4_com1, 36
+ISR_NOERR isr_irq14_ata_primary, 46
+ISR_NOERR isr_irq15_ata_secondary, 47
+
+.section .rodata.isr_exception_stubs, "a"
+.balign 8
+.global isr_exception_stubs
+isr_exception_stubs:
+    .quad isr_exception_0
+    .quad isr_exception_1
+    .quad isr_exception_2
+    .quad isr_exception_3
+    .quad isr_exception_4
+    .quad isr_exception_5
+    .quad isr_exception_6
+    .quad isr_exception_7
+    .quad isr_exception_8
+    .quad isr_exception_9
+    .quad isr_exception_10
+    .quad isr_exception_11
+    .quad isr_exception_12
+    .quad isr_exception_13
+    .quad isr_exception_14
+    .quad isr_exception_15
+    .quad isr_exception_16
+    .quad isr_exception_17
+    .quad isr_exception_18
+    .quad isr_exception_19
+    .quad isr_exception_20
+    .quad isr_exception_21
+    .quad isr_exception_22
+    .quad isr_exception_23
+    .quad isr_exception_24
+    .quad isr_exception_25
+    .quad isr_exception_26
+    .quad isr_exception_27
+    .quad isr_exception_28
+    .quad isr_exception_29
+    .quad isr_exception_30
+    .quad isr_exception_31
+.text
+"#,
+    error_code_vectors = const ERROR_CODE_VECTORS,
 );
+
+#[cfg(test)]
+mod tests {
+    use core::mem::{offset_of, size_of};
+
+    use super::{
+        ERROR_CODE_VECTORS, EXCEPTION_COUNT, EXCEPTION_NAMES, InterruptFrame, exception_name,
+        has_error_code,
+    };
+
+    #[test]
+    fn frame_matches_isr_common_layout() {
+        // 15 Allzweckregister, Vektor, Fehlercode und 5 von der CPU gesicherte Werte.
+        assert_eq!(size_of::<InterruptFrame>(), 22 * 8);
+        assert_eq!(offset_of!(InterruptFrame, rax), 14 * 8);
+        assert_eq!(offset_of!(InterruptFrame, vector), 120);
+        assert_eq!(offset_of!(InterruptFrame, rip), 136);
+        assert_eq!(offset_of!(InterruptFrame, ss), 168);
+    }
+
+    #[test]
+    fn names_every_exception_vector() {
+        assert_eq!(exception_name(0), b"divide by zero");
+        assert_eq!(exception_name(14), b"page fault");
+        assert_eq!(exception_name(19), b"SIMD floating-point exception");
+        assert_eq!(exception_name(40), b"unknown exception");
+        assert!(EXCEPTION_NAMES.iter().all(|name| !name.is_empty()));
+    }
+
+    #[test]
+    fn error_code_mask_matches_architecture() {
+        // Laut Intel SDM legt die CPU nur bei diesen Exceptions einen Fehlercode ab.
+        const WITH_ERROR_CODE: [u8; 10] = [8, 10, 11, 12, 13, 14, 17, 21, 29, 30];
+        let expected = WITH_ERROR_CODE
+            .iter()
+            .fold(0_u32, |mask, vector| mask | 1 << vector);
+        assert_eq!(ERROR_CODE_VECTORS, expected);
+        for vector in 0..EXCEPTION_COUNT as u8 {
+            assert_eq!(
+                has_error_code(vector),
+                WITH_ERROR_CODE.contains(&vector),
+                "vector {vector}"
+            );
+        }
+        assert!(!has_error_code(40));
+    }
+}
@@ -1,14 +1,42 @@
-//! PS/2-Tastaturtreiber mit Scancode-Dekodierung (Set 1, QWERTZ-orientiert).
+//! PS/2-Tastaturtreiber mit Scancode-Dekodierung (Set 1).
+//!
+//! [`ScancodeDecoder`] verfolgt Umschalt-, Strg- und Alt-Tasten sowie die Lock-Tasten und
+//! übersetzt Scancodes in [`KeyEvent`]s, einschließlich der `E0`-Erweiterungstasten,
+//! Funktionstasten und des Ziffernblocks. Zeichentasten übersetzt die aktive [`Keymap`]
+//! (Standard: deutsch, umschaltbar mit [`set_keymap`]). Losgelassene Tasten werden nur
+//! gemeldet, wenn das mit [`set_release_events`] eingeschaltet ist.
+//!
+//! Jede virtuelle Konsole hat einen eigenen Eingabepuffer; Tasten landen in dem der gerade
+//! angezeigten Konsole. Alt+F1 … Alt+F4 wechseln die Konsole, Shift+PageUp/PageDown blättert
+//! im Rückblick. Die Lock-Tasten schalten die Tastatur-LEDs.
 //!
 //! Hintergrund: <https://wiki.osdev.org/PS/2_Keyboard>
 
 use core::cell::UnsafeCell;
 
-use crate::arch;
+use crate::{arch, console};
 
 use super::io;
+use super::keymap::{self, DeadKey, Keymap, Keysym, ShiftLevel};
 
 const BUFFER_SIZE: usize = 256;
+/// Zeilen pro Shift+PageUp/PageDown (halbe Bildschirmhöhe, wie unter Linux).
+const SCROLL_LINES: isize = 12;
+
+const DATA_PORT: u16 = 0x60;
+const STATUS_PORT: u16 = 0x64;
+const STATUS_OUTPUT_FULL: u8 = 1 << 0;
+const STATUS_INPUT_FULL: u8 = 1 << 1;
+const COMMAND_SET_LEDS: u8 = 0xED;
+/// Antworten der Tastatur auf Befehle; keine Tasten.
+const RESPONSE_ACK: u8 = 0xFA;
+const RESPONSE_RESEND: u8 = 0xFE;
+/// Obergrenze für das Warten auf Controller und Tastatur.
+const SPIN_LIMIT: usize = 100_000;
+
+const LED_SCROLL_LOCK: u8 = 1 << 0;
+const LED_NUM_LOCK: u8 = 1 << 1;
+const LED_CAPS_LOCK: u8 = 1 << 2;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 /// Abstraktes Tastenereignis für die Shell.
@@ -18,21 +46,320 @@ pub enum KeyEvent {
     Backspace,
     Up,
     Down,
+    Left,
+    Right,
+    Home,
+    End,
+    Insert,
+    Delete,
+    PageUp,
+    PageDown,
+    /// Funktionstaste F1 … F12.
+    Function(u8),
+    /// Zeichen bei gedrückter Strg-Taste, Buchstaben immer klein (`Ctrl(b'c')`).
+    Ctrl(u8),
+    /// Zeichen bei gedrückter Alt-Taste.
+    Alt(u8),
+    /// Losgelassene Taste: Make-Code, bei `E0`-Tasten mit `0xE0` im oberen Byte.
+    Release(u16),
 }
 
-struct KeyboardState {
-    shift: bool,
+/// Zustand der Umschalt- und Lock-Tasten.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    /// Rechte Alt-Taste.
+    pub altgr: bool,
+    pub caps_lock: bool,
+    pub num_lock: bool,
+    pub scroll_lock: bool,
+}
+
+impl Modifiers {
+    const fn new() -> Self {
+        Self {
+            shift: false,
+            ctrl: false,
+            alt: false,
+            altgr: false,
+            caps_lock: false,
+            num_lock: false,
+            scroll_lock: false,
+        }
+    }
+
+    /// LED-Bitmaske für den Befehl `0xED`.
+    pub fn leds(&self) -> u8 {
+        let mut leds = 0;
+        if self.scroll_lock {
+            leds |= LED_SCROLL_LOCK;
+        }
+        if self.num_lock {
+            leds |= LED_NUM_LOCK;
+        }
+        if self.caps_lock {
+            leds |= LED_CAPS_LOCK;
+        }
+        leds
+    }
+}
+
+/// Übersetzt Scancodes (Set 1) in Tastenereignisse.
+#[derive(Clone, Copy, Debug)]
+pub struct ScancodeDecoder {
+    modifiers: Modifiers,
     e0_prefix: bool,
+    /// Restbytes der Pause-Sequenz `E1 1D 45 E1 9D C5`.
+    pause_bytes: u8,
+    report_releases: bool,
+    leds_changed: bool,
+    keymap: Keymap,
+    /// Gedrückte tote Taste, die auf das nächste Zeichen wartet.
+    dead_key: Option<DeadKey>,
+    /// Zweites Zeichen, wenn sich eine tote Taste nicht kombinieren ließ.
+    pending: Option<KeyEvent>,
+}
+
+impl ScancodeDecoder {
+    pub const fn new() -> Self {
+        Self {
+            modifiers: Modifiers::new(),
+            e0_prefix: false,
+            pause_bytes: 0,
+            report_releases: false,
+            leds_changed: false,
+            keymap: keymap::DE,
+            dead_key: None,
+            pending: None,
+        }
+    }
+
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    /// Wechselt die Belegung; eine angefangene tote Taste verfällt.
+    pub fn set_keymap(&mut self, keymap: &Keymap) {
+        self.keymap = *keymap;
+        self.dead_key = None;
+    }
+
+    /// Liefert ein Ereignis, das [`feed`](Self::feed) nicht mehr zurückgeben konnte.
+    pub fn take_pending(&mut self) -> Option<KeyEvent> {
+        self.pending.take()
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    pub fn set_release_events(&mut self, enabled: bool) {
+        self.report_releases = enabled;
+    }
+
+    /// `true`, wenn sich seit dem letzten Aufruf eine Lock-Taste geändert hat.
+    pub fn take_leds_changed(&mut self) -> bool {
+        core::mem::take(&mut self.leds_changed)
+    }
+
+    pub fn feed(&mut self, scancode: u8) -> Option<KeyEvent> {
+        if self.pause_bytes > 0 {
+            self.pause_bytes -= 1;
+            return None;
+        }
+
+        match scancode {
+            0xE0 => {
+                self.e0_prefix = true;
+                return None;
+            }
+            0xE1 => {
+                self.pause_bytes = 5;
+                return None;
+            }
+            RESPONSE_ACK | RESPONSE_RESEND => return None,
+            _ => {}
+        }
+
+        let extended = core::mem::take(&mut self.e0_prefix);
+        let code = scancode & 0x7F;
+        let pressed = scancode & 0x80 == 0;
+
+        // Manche Tastaturen schicken vor Erweiterungstasten ein künstliches `E0 2A`/`E0 36`.
+        if extended && matches!(code, 0x2A | 0x36) {
+            return None;
+        }
+
+        let modifiers = &mut self.modifiers;
+        match (extended, code) {
+            (false, 0x2A | 0x36) => modifiers.shift = pressed,
+            (_, 0x1D) => modifiers.ctrl = pressed,
+            (false, 0x38) => modifiers.alt = pressed,
+            (true, 0x38) => modifiers.altgr = pressed,
+            (false, 0x3A) if pressed => modifiers.caps_lock = !modifiers.caps_lock,
+            (false, 0x45) if pressed => modifiers.num_lock = !modifiers.num_lock,
+            (false, 0x46) if pressed => modifiers.scroll_lock = !modifiers.scroll_lock,
+            _ => {
+                return if pressed {
+                    self.decode_press(extended, code)
+                } else {
+                    self.release(extended, code)
+                };
+            }
+        }
+
+        if pressed && matches!(code, 0x3A | 0x45 | 0x46) {
+            self.leds_changed = true;
+        }
+        if pressed {
+            None
+        } else {
+            self.release(extended, code)
+        }
+    }
+
+    fn release(&self, extended: bool, code: u8) -> Option<KeyEvent> {
+        let prefix = if extended { 0xE000 } else { 0 };
+        self.report_releases
+            .then_some(KeyEvent::Release(prefix | u16::from(code)))
+    }
+
+    fn decode_press(&mut self, extended: bool, code: u8) -> Option<KeyEvent> {
+        if extended {
+            return match code {
+                0x1C => Some(KeyEvent::Enter),
+                0x35 => self.with_modifiers(b'/'),
+                _ => navigation_key(code),
+            };
+        }
+
+        match code {
+            0x0E => return Some(KeyEvent::Backspace),
+            0x1C => return Some(KeyEvent::Enter),
+            0x3B..=0x44 => return Some(KeyEvent::Function(code - 0x3A)),
+            0x57 | 0x58 => return Some(KeyEvent::Function(code - 0x4C)),
+            0x37 => return self.with_modifiers(b'*'),
+            0x4A => return self.with_modifiers(b'-'),
+            0x4E => return self.with_modifiers(b'+'),
+            0x47..=0x53 => {
+                // Ziffernblock: mit NumLock Ziffern, sonst (oder mit Shift) Navigation.
+                return if self.modifiers.num_lock && !self.modifiers.shift {
+                    keypad_digit(code).and_then(|ch| self.with_modifiers(ch))
+                } else {
+                    navigation_key(code)
+                };
+            }
+            _ => {}
+        }
+
+        let shift =
+            self.modifiers.shift ^ (self.modifiers.caps_lock && self.keymap.is_letter(code));
+        let level = if shift {
+            ShiftLevel::Shift
+        } else {
+            ShiftLevel::Normal
+        };
+
+        // Tastenkürzel nutzen die Grundebenen; tote Tasten wirken dort wie ihr Akzentzeichen.
+        if self.modifiers.ctrl || self.modifiers.alt {
+            return match self.keymap.lookup(code, level) {
+                Keysym::Char(ch) => self.with_modifiers(ch),
+                Keysym::Dead(dead) => self.with_modifiers(dead.symbol()),
+                Keysym::None => None,
+            };
+        }
+
+        let level = if self.modifiers.altgr {
+            ShiftLevel::AltGr
+        } else {
+            level
+        };
+        match (self.keymap.lookup(code, level), self.dead_key.take()) {
+            (Keysym::None, dead_key) => {
+                self.dead_key = dead_key;
+                None
+            }
+            (Keysym::Char(ch), None) => Some(KeyEvent::Char(ch)),
+            (Keysym::Char(b' '), Some(dead)) => Some(KeyEvent::Char(dead.symbol())),
+            (Keysym::Char(ch), Some(dead)) => match dead.compose(ch) {
+                Some(composed) => Some(KeyEvent::Char(composed)),
+                None => {
+                    self.pending = Some(KeyEvent::Char(ch));
+                    Some(KeyEvent::Char(dead.symbol()))
+                }
+            },
+            (Keysym::Dead(dead), None) => {
+                self.dead_key = Some(dead);
+                None
+            }
+            // Zweimal dieselbe tote Taste ergibt den Akzent, eine andere beginnt neu.
+            (Keysym::Dead(dead), Some(previous)) => {
+                if dead != previous {
+                    self.dead_key = Some(dead);
+                }
+                Some(KeyEvent::Char(previous.symbol()))
+            }
+        }
+    }
+
+    fn with_modifiers(&self, ch: u8) -> Option<KeyEvent> {
+        Some(if self.modifiers.ctrl {
+            KeyEvent::Ctrl(ch.to_ascii_lowercase())
+        } else if self.modifiers.alt {
+            KeyEvent::Alt(ch)
+        } else {
+            KeyEvent::Char(ch)
+        })
+    }
+}
+
+/// Navigationstasten; gleiche Codes für den Block mit `E0` und den Ziffernblock ohne NumLock.
+fn navigation_key(code: u8) -> Option<KeyEvent> {
+    match code {
+        0x47 => Some(KeyEvent::Home),
+        0x48 => Some(KeyEvent::Up),
+        0x49 => Some(KeyEvent::PageUp),
+        0x4B => Some(KeyEvent::Left),
+        0x4D => Some(KeyEvent::Right),
+        0x4F => Some(KeyEvent::End),
+        0x50 => Some(KeyEvent::Down),
+        0x51 => Some(KeyEvent::PageDown),
+        0x52 => Some(KeyEvent::Insert),
+        0x53 => Some(KeyEvent::Delete),
+        _ => None,
+    }
+}
+
+fn keypad_digit(code: u8) -> Option<u8> {
+    match code {
+        0x47 => Some(b'7'),
+        0x48 => Some(b'8'),
+        0x49 => Some(b'9'),
+        0x4B => Some(b'4'),
+        0x4C => Some(b'5'),
+        0x4D => Some(b'6'),
+        0x4F => Some(b'1'),
+        0x50 => Some(b'2'),
+        0x51 => Some(b'3'),
+        0x52 => Some(b'0'),
+        0x53 => Some(b'.'),
+        _ => None,
+    }
+}
+
+/// Eingabepuffer einer virtuellen Konsole.
+#[derive(Clone, Copy)]
+struct EventQueue {
     head: usize,
     tail: usize,
-    buffer: [u16; BUFFER_SIZE],
+    buffer: [u32; BUFFER_SIZE],
 }
 
-impl KeyboardState {
+impl EventQueue {
     const fn new() -> Self {
         Self {
-            shift: false,
-            e0_prefix: false,
             head: 0,
             tail: 0,
             buffer: [0; BUFFER_SIZE],
@@ -40,6 +367,20 @@ impl KeyboardState {
     }
 }
 
+struct KeyboardState {
+    decoder: ScancodeDecoder,
+    queues: [EventQueue; console::CONSOLE_COUNT],
+}
+
+impl KeyboardState {
+    const fn new() -> Self {
+        Self {
+            decoder: ScancodeDecoder::new(),
+            queues: [EventQueue::new(); console::CONSOLE_COUNT],
+        }
+    }
+}
+
 struct KeyboardCell(UnsafeCell<KeyboardState>);
 
 unsafe impl Sync for KeyboardCell {}
@@ -48,7 +389,7 @@ static KEYBOARD_STATE: KeyboardCell = KeyboardCell(UnsafeCell::new(KeyboardState
 
 /// IRQ-Handler-Einstieg: liest einen Scancode von Port `0x60`.
 pub fn handle_irq() {
-    let scancode = io::inb(0x60);
+    let scancode = io::inb(DATA_PORT);
     feed_scancode(scancode);
 }
 
@@ -62,472 +403,218 @@ pub fn try_read_char() -> Option<u8> {
     }
 }
 
-/// Liest, falls vorhanden, das nächste rohe Tastenereignis.
+/// Liest, falls vorhanden, das nächste rohe Tastenereignis der angezeigten Konsole.
 pub fn try_read_key() -> Option<KeyEvent> {
+    try_read_key_from(console::active_console())
+}
+
+/// Liest, falls vorhanden, das nächste rohe Tastenereignis einer bestimmten Konsole.
+pub fn try_read_key_from(console: usize) -> Option<KeyEvent> {
     let interrupts_were_enabled = arch::x86_64::save_and_disable_interrupts();
 
     let result = unsafe {
         let state = &mut *KEYBOARD_STATE.0.get();
-        if state.head == state.tail {
-            None
-        } else {
-            let code = state.buffer[state.tail];
-            state.tail = (state.tail + 1) % BUFFER_SIZE;
-            decode_event(code)
-        }
+        state.queues.get_mut(console).and_then(|queue| {
+            if queue.head == queue.tail {
+                None
+            } else {
+                let code = queue.buffer[queue.tail];
+                queue.tail = (queue.tail + 1) % BUFFER_SIZE;
+                decode_event(code)
+            }
+        })
     };
 
     arch::x86_64::restore_interrupts(interrupts_were_enabled);
     result
 }
 
+/// Reiht ein Ereignis aus einer anderen Eingabequelle (z. B. serielle Konsole) für `console` ein.
+pub fn push_key_event(console: usize, event: KeyEvent) {
+    let interrupts_were_enabled = arch::x86_64::save_and_disable_interrupts();
+    unsafe {
+        if let Some(queue) = (*KEYBOARD_STATE.0.get()).queues.get_mut(console) {
+            push_event(queue, event);
+        }
+    }
+    arch::x86_64::restore_interrupts(interrupts_were_enabled);
+}
+
+/// Aktueller Zustand der Umschalt- und Lock-Tasten.
+pub fn modifiers() -> Modifiers {
+    unsafe { (*KEYBOARD_STATE.0.get()).decoder.modifiers() }
+}
+
+/// Schaltet die Meldung losgelassener Tasten ([`KeyEvent::Release`]) ein oder aus.
+pub fn set_release_events(enabled: bool) {
+    let interrupts_were_enabled = arch::x86_64::save_and_disable_interrupts();
+    unsafe {
+        (*KEYBOARD_STATE.0.get())
+            .decoder
+            .set_release_events(enabled)
+    };
+    arch::x86_64::restore_interrupts(interrupts_were_enabled);
+}
+
+/// Aktiviert eine Tastaturbelegung (siehe [`keymap`]).
+pub fn set_keymap(keymap: &Keymap) {
+    let interrupts_were_enabled = arch::x86_64::save_and_disable_interrupts();
+    unsafe { (*KEYBOARD_STATE.0.get()).decoder.set_keymap(keymap) };
+    arch::x86_64::restore_interrupts(interrupts_were_enabled);
+}
+
+/// Name der aktiven Tastaturbelegung.
+pub fn keymap_name() -> &'static [u8] {
+    unsafe { (*KEYBOARD_STATE.0.get()).decoder.keymap().name() }
+}
+
 /// Test-Helfer: speist einen Scancode künstlich ein.
 pub fn inject_scancode(scancode: u8) {
     feed_scancode(scancode);
 }
 
-/// Leert den internen Ringpuffer.
+/// Leert die Eingabepuffer aller Konsolen.
 pub fn clear_buffer() {
     let interrupts_were_enabled = arch::x86_64::save_and_disable_interrupts();
     unsafe {
         let state = &mut *KEYBOARD_STATE.0.get();
-        state.head = 0;
-        state.tail = 0;
+        for queue in &mut state.queues {
+            queue.head = 0;
+            queue.tail = 0;
+        }
     }
     arch::x86_64::restore_interrupts(interrupts_were_enabled);
 }
 
 fn feed_scancode(scancode: u8) {
-    unsafe {
-        let state = &mut *KEYBOARD_STATE.0.get();
+    let state = unsafe { &mut *KEYBOARD_STATE.0.get() };
+    let event = state.decoder.feed(scancode);
+    let modifiers = state.decoder.modifiers();
+    if state.decoder.take_leds_changed() {
+        set_leds(modifiers.leds());
+    }
 
-        if scancode == 0xE0 {
-            state.e0_prefix = true;
-            return;
+    match event {
+        // Alt+F1 … Alt+F4
+        Some(KeyEvent::Function(n @ 1..=4)) if modifiers.alt => {
+            console::switch_console(usize::from(n - 1));
         }
-
-        if state.e0_prefix {
-            state.e0_prefix = false;
-            if (scancode & 0x80) != 0 {
-                return;
-            }
-
-            match scancode {
-                0x48 => push_event(state, KeyEvent::Up),
-                0x50 => push_event(state, KeyEvent::Down),
-                _ => {}
+        Some(KeyEvent::PageUp) if modifiers.shift => console::scroll_view(SCROLL_LINES),
+        Some(KeyEvent::PageDown) if modifiers.shift => console::scroll_view(-SCROLL_LINES),
+        Some(event) => {
+            let queue = &mut state.queues[console::active_console()];
+            push_event(queue, event);
+            if let Some(pending) = state.decoder.take_pending() {
+                push_event(queue, pending);
             }
-            return;
-        }
-
-        match scancode {
-            0x2A | 0x36 => {
-                state.shift = true;
-                return;
-            }
-            0xAA | 0xB6 => {
-                state.shift = false;
-                return;
-            }
-            _ => {}
-        }
-
-        if (scancode & 0x80) != 0 {
-            return;
         }
+        None => {}
+    }
+}
 
-        if let Some(ch) = decode_scancode(scancode, state.shift) {
-            match ch {
-                8 => push_event(state, KeyEvent::Backspace),
-                b'\n' => push_event(state, KeyEvent::Enter),
-                _ => push_event(state, KeyEvent::Char(ch)),
+/// Setzt die Tastatur-LEDs (Befehl `0xED`, danach die Bitmaske).
+///
+/// Die `ACK`-Antworten werden hier abgeholt; falls doch eine als IRQ ankommt, verwirft sie der
+/// Decoder.
+fn set_leds(leds: u8) {
+    #[cfg(eres_kernel)]
+    {
+        for byte in [COMMAND_SET_LEDS, leds] {
+            for _ in 0..SPIN_LIMIT {
+                if io::inb(STATUS_PORT) & STATUS_INPUT_FULL == 0 {
+                    break;
+                }
+                core::hint::spin_loop();
+            }
+            io::outb(DATA_PORT, byte);
+            for _ in 0..SPIN_LIMIT {
+                if io::inb(STATUS_PORT) & STATUS_OUTPUT_FULL != 0 {
+                    let _ = io::inb(DATA_PORT);
+                    break;
+                }
+                core::hint::spin_loop();
             }
         }
     }
+    #[cfg(not(eres_kernel))]
+    let _ = leds;
 }
 
-fn push_event(state: &mut KeyboardState, event: KeyEvent) {
-    let next_head = (state.head + 1) % BUFFER_SIZE;
-    if next_head == state.tail {
+fn push_event(queue: &mut EventQueue, event: KeyEvent) {
+    let next_head = (queue.head + 1) % BUFFER_SIZE;
+    if next_head == queue.tail {
         return;
     }
 
-    state.buffer[state.head] = encode_event(event);
-    state.head = next_head;
+    queue.buffer[queue.head] = encode_event(event);
+    queue.head = next_head;
 }
 
-const KEY_ENTER: u16 = 0x100;
-const KEY_BACKSPACE: u16 = 0x101;
-const KEY_UP: u16 = 0x102;
-const KEY_DOWN: u16 = 0x103;
-
-fn encode_event(event: KeyEvent) -> u16 {
+/// Ereignisse ohne Nutzdaten, kodiert als `0x100 + Index`.
+const SIMPLE_KEYS: [KeyEvent; 12] = [
+    KeyEvent::Enter,
+    KeyEvent::Backspace,
+    KeyEvent::Up,
+    KeyEvent::Down,
+    KeyEvent::Left,
+    KeyEvent::Right,
+    KeyEvent::Home,
+    KeyEvent::End,
+    KeyEvent::Insert,
+    KeyEvent::Delete,
+    KeyEvent::PageUp,
+    KeyEvent::PageDown,
+];
+const KEY_SIMPLE: u32 = 0x100;
+const KEY_FUNCTION: u32 = 0x200;
+const KEY_CTRL: u32 = 0x300;
+const KEY_ALT: u32 = 0x400;
+const KEY_RELEASE: u32 = 0x1_0000;
+
+fn encode_event(event: KeyEvent) -> u32 {
     match event {
-        KeyEvent::Char(ch) => ch as u16,
-        KeyEvent::Enter => KEY_ENTER,
-        KeyEvent::Backspace => KEY_BACKSPACE,
-        KeyEvent::Up => KEY_UP,
-        KeyEvent::Down => KEY_DOWN,
+        KeyEvent::Char(ch) => u32::from(ch),
+        KeyEvent::Function(n) => KEY_FUNCTION | u32::from(n),
+        KeyEvent::Ctrl(ch) => KEY_CTRL | u32::from(ch),
+        KeyEvent::Alt(ch) => KEY_ALT | u32::from(ch),
+        KeyEvent::Release(code) => KEY_RELEASE | u32::from(code),
+        simple => {
+            let index = SIMPLE_KEYS.iter().position(|key| *key == simple);
+            KEY_SIMPLE + index.unwrap_or(0) as u32
+        }
     }
 }
 
-fn decode_event(code: u16) -> Option<KeyEvent> {
-    match code {
-        0..=0xFF => Some(KeyEvent::Char(code as u8)),
-        KEY_ENTER => Some(KeyEvent::Enter),
-        KEY_BACKSPACE => Some(KeyEvent::Backspace),
-        KEY_UP => Some(KeyEvent::Up),
-        KEY_DOWN => Some(KeyEvent::Down),
+fn decode_event(code: u32) -> Option<KeyEvent> {
+    let payload = code as u8;
+    match code & !0xFF {
+        0 => Some(KeyEvent::Char(payload)),
+        KEY_SIMPLE => SIMPLE_KEYS.get(usize::from(payload)).copied(),
+        KEY_FUNCTION => Some(KeyEvent::Function(payload)),
+        KEY_CTRL => Some(KeyEvent::Ctrl(payload)),
+        KEY_ALT => Some(KeyEvent::Alt(payload)),
+        _ if code & KEY_RELEASE != 0 => Some(KeyEvent::Release(code as u16)),
         _ => None,
     }
 }
 
-fn decode_scancode(scancode: u8, shift: bool) -> Option<u8> {
-    // German QWERTZ layout with ASCII fallbacks for non-ASCII symbols.
-    let ch = match scancode {
-        0x01 => 0x1B,
-        0x02 => {
-            if shift {
-                b'!'
-            } else {
-                b'1'
-            }
-        }
-        0x03 => {
-            if shift {
-                b'"'
-            } else {
-                b'2'
-            }
-        }
-        0x04 => {
-            if shift {
-                b'#'
-            } else {
-                b'3'
-            }
-        }
-        0x05 => {
-            if shift {
-                b'$'
-            } else {
-                b'4'
-            }
-        }
-        0x06 => {
-            if shift {
-                b'%'
-            } else {
-                b'5'
-            }
-        }
-        0x07 => {
-            if shift {
-                b'&'
-            } else {
-                b'6'
-            }
-        }
-        0x08 => {
-            if shift {
-                b'/'
-            } else {
-                b'7'
-            }
-        }
-        0x09 => {
-            if shift {
-                b'('
-            } else {
-                b'8'
-            }
-        }
-        0x0A => {
-            if shift {
-                b')'
-            } else {
-                b'9'
-            }
-        }
-        0x0B => {
-            if shift {
-                b'='
-            } else {
-                b'0'
-            }
-        }
-        0x0C => {
-            if shift {
-                b'?'
-            } else {
-                b'-'
-            }
-        }
-        0x0D => {
-            if shift {
-                b'`'
-            } else {
-                b'+'
-            }
-        }
-        0x0E => 8,
-        0x0F => b'\t',
-        0x10 => {
-            if shift {
-                b'Q'
-            } else {
-                b'q'
-            }
-        }
-        0x11 => {
-            if shift {
-                b'W'
-            } else {
-                b'w'
-            }
-        }
-        0x12 => {
-            if shift {
-                b'E'
-            } else {
-                b'e'
-            }
-        }
-        0x13 => {
-            if shift {
-                b'R'
-            } else {
-                b'r'
-            }
-        }
-        0x14 => {
-            if shift {
-                b'T'
-            } else {
-                b't'
-            }
-        }
-        0x15 => {
-            if shift {
-                b'Z'
-            } else {
-                b'z'
-            }
-        }
-        0x16 => {
-            if shift {
-                b'U'
-            } else {
-                b'u'
-            }
-        }
-        0x17 => {
-            if shift {
-                b'I'
-            } else {
-                b'i'
-            }
-        }
-        0x18 => {
-            if shift {
-                b'O'
-            } else {
-                b'o'
-            }
-        }
-        0x19 => {
-            if shift {
-                b'P'
-            } else {
-                b'p'
-            }
-        }
-        0x1A => {
-            if shift {
-                b'U'
-            } else {
-                b'u'
-            }
-        }
-        0x1B => {
-            if shift {
-                b'+'
-            } else {
-                b'#'
-            }
-        }
-        0x1C => b'\n',
-        0x1E => {
-            if shift {
-                b'A'
-            } else {
-                b'a'
-            }
-        }
-        0x1F => {
-            if shift {
-                b'S'
-            } else {
-                b's'
-            }
-        }
-        0x20 => {
-            if shift {
-                b'D'
-            } else {
-                b'd'
-            }
-        }
-        0x21 => {
-            if shift {
-                b'F'
-            } else {
-                b'f'
-            }
-        }
-        0x22 => {
-            if shift {
-                b'G'
-            } else {
-                b'g'
-            }
-        }
-        0x23 => {
-            if shift {
-                b'H'
-            } else {
-                b'h'
-            }
-        }
-        0x24 => {
-            if shift {
-                b'J'
-            } else {
-                b'j'
-            }
-        }
-        0x25 => {
-            if shift {
-                b'K'
-            } else {
-                b'k'
-            }
-        }
-        0x26 => {
-            if shift {
-                b'L'
-            } else {
-                b'l'
-            }
-        }
-        0x27 => {
-            if shift {
-                b':'
-            } else {
-                b';'
-            }
-        }
-        0x28 => {
-            if shift {
-                b'"'
-            } else {
-                b'\''
-            }
-        }
-        0x29 => {
-            if shift {
-                b'~'
-            } else {
-                b'`'
-            }
-        }
-        0x2B => {
-            if shift {
-                b'*'
-            } else {
-                b'\''
-            }
-        }
-        0x2C => {
-            if shift {
-                b'Y'
-            } else {
-                b'y'
-            }
-        }
-        0x2D => {
-            if shift {
-                b'X'
-            } else {
-                b'x'
-            }
-        }
-        0x2E => {
-            if shift {
-                b'C'
-            } else {
-                b'c'
-            }
-        }
-        0x2F => {
-            if shift {
-                b'V'
-            } else {
-                b'v'
-            }
-        }
-        0x30 => {
-            if shift {
-                b'B'
-            } else {
-                b'b'
-            }
-        }
-        0x31 => {
-            if shift {
-                b'N'
-            } else {
-                b'n'
-            }
-        }
-        0x32 => {
-            if shift {
-                b'M'
-            } else {
-                b'm'
-            }
-        }
-        0x33 => {
-            if shift {
-                b';'
-            } else {
-                b','
-            }
-        }
-        0x34 => {
-            if shift {
-                b':'
-            } else {
-                b'.'
-            }
-        }
-        0x35 => {
-            if shift {
-                b'_'
-            } else {
-                b'-'
-            }
-        }
-        0x39 => b' ',
-        _ => return None,
-    };
-
-    Some(ch)
-}
-
 #[cfg(test)]
 mod tests {
-    use super::{KeyEvent, clear_buffer, inject_scancode, try_read_key};
+    use alloc::vec::Vec;
+
+    use super::{
+        KeyEvent, ScancodeDecoder, clear_buffer, decode_event, encode_event, inject_scancode,
+        keymap, try_read_key,
+    };
+
+    fn feed_all(decoder: &mut ScancodeDecoder, scancodes: &[u8]) -> Vec<KeyEvent> {
+        let mut events = Vec::new();
+        for scancode in scancodes {
+            events.extend(decoder.feed(*scancode));
+            events.extend(decoder.take_pending());
+        }
+        events
+    }
 
     #[test]
     fn decodes_arrow_up_down() {
@@ -541,4 +628,172 @@ mod tests {
         assert_eq!(try_read_key(), Some(KeyEvent::Up));
         assert_eq!(try_read_key(), Some(KeyEvent::Down));
     }
+
+    #[test]
+    fn applies_ctrl_alt_and_caps_lock() {
+        let mut decoder = ScancodeDecoder::new();
+        // Strg+C, Alt+x, Shift+a
+        let events = feed_all(
+            &mut decoder,
+            &[0x1D, 0x2E, 0x9D, 0x38, 0x2D, 0xB8, 0x2A, 0x1E, 0xAA],
+        );
+        assert_eq!(
+            events,
+            [
+                KeyEvent::Ctrl(b'c'),
+                KeyEvent::Alt(b'x'),
+                KeyEvent::Char(b'A')
+            ]
+        );
+
+        // CapsLock kehrt die Groß-/Kleinschreibung nur bei Buchstaben um.
+        assert_eq!(feed_all(&mut decoder, &[0x3A, 0xBA]), []);
+        assert!(decoder.modifiers().caps_lock);
+        assert!(decoder.take_leds_changed());
+        assert_eq!(decoder.modifiers().leds(), 0b100);
+        let events = feed_all(&mut decoder, &[0x1E, 0x2A, 0x1E, 0x02, 0xAA]);
+        assert_eq!(
+            events,
+            [
+                KeyEvent::Char(b'A'),
+                KeyEvent::Char(b'a'),
+                KeyEvent::Char(b'!')
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_extended_function_and_keypad_keys() {
+        let mut decoder = ScancodeDecoder::new();
+        let events = feed_all(
+            &mut decoder,
+            &[
+                0xE0, 0x4B, 0xE0, 0x4D, 0xE0, 0x47, 0xE0, 0x4F, 0xE0, 0x53, 0xE0, 0x49, 0xE0, 0x51,
+                0x3B, 0x44, 0x58, 0xE0, 0x1C,
+            ],
+        );
+        assert_eq!(
+            events,
+            [
+                KeyEvent::Left,
+                KeyEvent::Right,
+                KeyEvent::Home,
+                KeyEvent::End,
+                KeyEvent::Delete,
+                KeyEvent::PageUp,
+                KeyEvent::PageDown,
+                KeyEvent::Function(1),
+                KeyEvent::Function(10),
+                KeyEvent::Function(12),
+                KeyEvent::Enter,
+            ]
+        );
+
+        // Ziffernblock ohne NumLock navigiert, mit NumLock liefert er Ziffern.
+        assert_eq!(
+            feed_all(&mut decoder, &[0x47, 0x4E]),
+            [KeyEvent::Home, KeyEvent::Char(b'+')]
+        );
+        let events = feed_all(&mut decoder, &[0x45, 0xC5, 0x47, 0x53, 0xE0, 0x35]);
+        assert_eq!(
+            events,
+            [
+                KeyEvent::Char(b'7'),
+                KeyEvent::Char(b'.'),
+                KeyEvent::Char(b'/')
+            ]
+        );
+        assert_eq!(decoder.modifiers().leds(), 0b010);
+    }
+
+    #[test]
+    fn follows_keymap_with_altgr_and_dead_keys() {
+        let mut decoder = ScancodeDecoder::new();
+        // Z/Y, AltGr+Q, AltGr+ß, Umlaut mit CapsLock
+        let events = feed_all(
+            &mut decoder,
+            &[
+                0x15, 0x2C, 0xE0, 0x38, 0x10, 0x0C, 0xE0, 0xB8, 0x3A, 0x27, 0x3A,
+            ],
+        );
+        assert_eq!(
+            events,
+            [
+                KeyEvent::Char(b'z'),
+                KeyEvent::Char(b'y'),
+                KeyEvent::Char(b'@'),
+                KeyEvent::Char(b'\\'),
+                KeyEvent::Char(0x99)
+            ]
+        );
+
+        // ^ e, ´ Leertaste, ^ ^, ^ x
+        let events = feed_all(
+            &mut decoder,
+            &[0x29, 0x12, 0x0D, 0x39, 0x29, 0x29, 0x29, 0x2D],
+        );
+        assert_eq!(
+            events,
+            [
+                KeyEvent::Char(0x88),
+                KeyEvent::Char(b'\''),
+                KeyEvent::Char(b'^'),
+                KeyEvent::Char(b'^'),
+                KeyEvent::Char(b'x')
+            ]
+        );
+
+        decoder.set_keymap(&keymap::US);
+        assert_eq!(decoder.keymap().name(), b"us");
+        assert_eq!(
+            feed_all(&mut decoder, &[0x15, 0x2C, 0x29, 0x1D, 0x15]),
+            [
+                KeyEvent::Char(b'y'),
+                KeyEvent::Char(b'z'),
+                KeyEvent::Char(b'`'),
+                KeyEvent::Ctrl(b'y')
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_releases_only_when_enabled() {
+        let mut decoder = ScancodeDecoder::new();
+        assert_eq!(
+            feed_all(&mut decoder, &[0x1E, 0x9E]),
+            [KeyEvent::Char(b'a')]
+        );
+
+        decoder.set_release_events(true);
+        // Künstliches E0 AA, ACK und Pause-Sequenz werden ignoriert.
+        let events = feed_all(
+            &mut decoder,
+            &[
+                0x1E, 0x9E, 0xE0, 0xAA, 0xE0, 0xC8, 0xFA, 0xE1, 0x1D, 0x45, 0xE1, 0x9D, 0xC5,
+            ],
+        );
+        assert_eq!(
+            events,
+            [
+                KeyEvent::Char(b'a'),
+                KeyEvent::Release(0x1E),
+                KeyEvent::Release(0xE048)
+            ]
+        );
+    }
+
+    #[test]
+    fn encodes_every_event_kind_for_the_queue() {
+        for event in [
+            KeyEvent::Char(b'q'),
+            KeyEvent::Enter,
+            KeyEvent::PageDown,
+            KeyEvent::Function(11),
+            KeyEvent::Ctrl(b'w'),
+            KeyEvent::Alt(b'f'),
+            KeyEvent::Release(0xE053),
+        ] {
+            assert_eq!(decode_event(encode_event(event)), Some(event));
+        }
+    }
 }
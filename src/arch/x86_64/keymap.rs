@@ -0,0 +1,426 @@
+//! Tastaturbelegungen für Scancode-Set 1.
+//!
+//! Eine [`Keymap`] ordnet jeder Zeichentaste drei Ebenen zu: normal, mit Shift und mit AltGr.
+//! Ein Eintrag ist ein Zeichen, eine tote Taste (Akzent für das nächste Zeichen) oder leer.
+//! Zeichen sind Bytes in Codepage 437, dem Zeichensatz des VGA-Textmodus.
+//!
+//! Eingebaut sind [`US`] und [`DE`]. Weitere Belegungen lassen sich aus Textdateien laden
+//! ([`parse`]):
+//!
+//! ```text
+//! # Kommentar
+//! name dvorak
+//! base us
+//! key 0x10 ' "
+//! key 0x29 dead_circumflex 0xf8
+//! ```
+//!
+//! `key <scancode> <normal> [<shift> [<altgr>]]` ändert nur die angegebenen Ebenen. Ein Symbol
+//! ist ein einzelnes Zeichen, `0xNN`, `space`, `-` (leer) oder `dead_grave`, `dead_acute`,
+//! `dead_circumflex`, `dead_diaeresis`, `dead_tilde`.
+
+/// Anzahl der Scancodes, die eine Belegung abdeckt (`0x00..=0x58`).
+pub const KEYMAP_KEYS: usize = 0x59;
+/// Maximale Länge eines Belegungsnamens.
+pub const KEYMAP_NAME_LEN: usize = 16;
+
+/// Akzent einer toten Taste.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeadKey {
+    Grave,
+    Acute,
+    Circumflex,
+    Diaeresis,
+    Tilde,
+}
+
+impl DeadKey {
+    const ALL: [DeadKey; 5] = [
+        DeadKey::Grave,
+        DeadKey::Acute,
+        DeadKey::Circumflex,
+        DeadKey::Diaeresis,
+        DeadKey::Tilde,
+    ];
+
+    pub fn name(self) -> &'static [u8] {
+        match self {
+            DeadKey::Grave => b"dead_grave",
+            DeadKey::Acute => b"dead_acute",
+            DeadKey::Circumflex => b"dead_circumflex",
+            DeadKey::Diaeresis => b"dead_diaeresis",
+            DeadKey::Tilde => b"dead_tilde",
+        }
+    }
+
+    /// Der Akzent als eigenständiges Zeichen (tote Taste gefolgt von Leertaste).
+    pub fn symbol(self) -> u8 {
+        match self {
+            DeadKey::Grave => b'`',
+            // Codepage 437 kennt keinen Akut.
+            DeadKey::Acute => b'\'',
+            DeadKey::Circumflex => b'^',
+            DeadKey::Diaeresis => b'"',
+            DeadKey::Tilde => b'~',
+        }
+    }
+
+    /// Kombiniert den Akzent mit `base`, falls Codepage 437 das Zeichen enthält.
+    pub fn compose(self, base: u8) -> Option<u8> {
+        let table: &[(u8, u8)] = match self {
+            DeadKey::Grave => &[
+                (b'a', 0x85),
+                (b'e', 0x8A),
+                (b'i', 0x8D),
+                (b'o', 0x95),
+                (b'u', 0x97),
+            ],
+            DeadKey::Acute => &[
+                (b'a', 0xA0),
+                (b'e', 0x82),
+                (b'i', 0xA1),
+                (b'o', 0xA2),
+                (b'u', 0xA3),
+                (b'E', 0x90),
+            ],
+            DeadKey::Circumflex => &[
+                (b'a', 0x83),
+                (b'e', 0x88),
+                (b'i', 0x8C),
+                (b'o', 0x93),
+                (b'u', 0x96),
+            ],
+            DeadKey::Diaeresis => &[
+                (b'a', 0x84),
+                (b'e', 0x89),
+                (b'i', 0x8B),
+                (b'o', 0x94),
+                (b'u', 0x81),
+                (b'y', 0x98),
+                (b'A', 0x8E),
+                (b'O', 0x99),
+                (b'U', 0x9A),
+            ],
+            DeadKey::Tilde => &[(b'n', 0xA4), (b'N', 0xA5)],
+        };
+        table
+            .iter()
+            .find(|(from, _)| *from == base)
+            .map(|(_, to)| *to)
+    }
+}
+
+/// Belegung einer Taste auf einer Ebene.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Keysym {
+    None,
+    Char(u8),
+    Dead(DeadKey),
+}
+
+/// Ebene innerhalb einer Belegung.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShiftLevel {
+    Normal = 0,
+    Shift = 1,
+    AltGr = 2,
+}
+
+/// Fehler beim Einlesen einer Belegungsdatei.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeymapError {
+    UnknownKeyword,
+    UnknownBase,
+    InvalidScancode,
+    InvalidSymbol,
+    MissingArgument,
+    NameTooLong,
+}
+
+impl KeymapError {
+    pub fn describe(self) -> &'static [u8] {
+        match self {
+            KeymapError::UnknownKeyword => b"unknown keyword (expected name, base or key)",
+            KeymapError::UnknownBase => b"unknown base keymap",
+            KeymapError::InvalidScancode => b"invalid scancode",
+            KeymapError::InvalidSymbol => b"invalid symbol",
+            KeymapError::MissingArgument => b"missing argument",
+            KeymapError::NameTooLong => b"name too long",
+        }
+    }
+}
+
+/// Tastaturbelegung mit drei Ebenen.
+#[derive(Clone, Copy, Debug)]
+pub struct Keymap {
+    name: [u8; KEYMAP_NAME_LEN],
+    name_len: usize,
+    levels: [[Keysym; KEYMAP_KEYS]; 3],
+}
+
+impl Keymap {
+    /// Leere Belegung; Esc, Tab und Leertaste sind in allen Belegungen gleich.
+    const fn base(name: &[u8]) -> Self {
+        let mut keymap = Self {
+            name: [0; KEYMAP_NAME_LEN],
+            name_len: name.len(),
+            levels: [[Keysym::None; KEYMAP_KEYS]; 3],
+        };
+        let mut index = 0;
+        while index < name.len() {
+            keymap.name[index] = name[index];
+            index += 1;
+        }
+        keymap
+            .row(0x01, b"\x1b", b"\x1b", b"")
+            .row(0x0F, b"\t", b"\t", b"")
+            .row(0x39, b" ", b" ", b"")
+    }
+
+    /// Belegt aufeinanderfolgende Scancodes ab `first`; `0` bedeutet „nicht belegt“.
+    const fn row(self, first: u8, normal: &[u8], shift: &[u8], altgr: &[u8]) -> Self {
+        self.fill(ShiftLevel::Normal, first, normal)
+            .fill(ShiftLevel::Shift, first, shift)
+            .fill(ShiftLevel::AltGr, first, altgr)
+    }
+
+    const fn fill(mut self, level: ShiftLevel, first: u8, chars: &[u8]) -> Self {
+        let mut index = 0;
+        while index < chars.len() {
+            if chars[index] != 0 {
+                self.levels[level as usize][first as usize + index] = Keysym::Char(chars[index]);
+            }
+            index += 1;
+        }
+        self
+    }
+
+    const fn dead(mut self, scancode: u8, level: ShiftLevel, dead: DeadKey) -> Self {
+        self.levels[level as usize][scancode as usize] = Keysym::Dead(dead);
+        self
+    }
+
+    pub fn name(&self) -> &[u8] {
+        &self.name[..self.name_len]
+    }
+
+    pub fn lookup(&self, scancode: u8, level: ShiftLevel) -> Keysym {
+        self.levels[level as usize]
+            .get(usize::from(scancode))
+            .copied()
+            .unwrap_or(Keysym::None)
+    }
+
+    /// Buchstabentaste: die Shift-Ebene ist die Großschreibung der normalen Ebene.
+    /// Nur für solche Tasten gilt CapsLock.
+    pub fn is_letter(&self, scancode: u8) -> bool {
+        match (
+            self.lookup(scancode, ShiftLevel::Normal),
+            self.lookup(scancode, ShiftLevel::Shift),
+        ) {
+            (Keysym::Char(lower), Keysym::Char(upper)) => cp437_upper(lower) == Some(upper),
+            _ => false,
+        }
+    }
+
+    fn set_name(&mut self, name: &[u8]) -> Result<(), KeymapError> {
+        if name.len() > KEYMAP_NAME_LEN {
+            return Err(KeymapError::NameTooLong);
+        }
+        self.name = [0; KEYMAP_NAME_LEN];
+        self.name[..name.len()].copy_from_slice(name);
+        self.name_len = name.len();
+        Ok(())
+    }
+}
+
+/// US-amerikanische Belegung (QWERTY).
+pub const US: Keymap = Keymap::base(b"us")
+    .row(0x02, b"1234567890-=", b"!@#$%^&*()_+", b"")
+    .row(0x10, b"qwertyuiop[]", b"QWERTYUIOP{}", b"")
+    .row(0x1E, b"asdfghjkl;'`", b"ASDFGHJKL:\"~", b"")
+    .row(0x2B, b"\\zxcvbnm,./", b"|ZXCVBNM<>?", b"")
+    .row(0x56, b"\\", b"|", b"");
+
+/// Deutsche Belegung (QWERTZ) mit AltGr-Ebene und toten Tasten für `´`, `` ` `` und `^`.
+pub const DE: Keymap = Keymap::base(b"de")
+    .row(
+        0x02,
+        b"1234567890\xe1",
+        b"!\"\x15$%&/()=?",
+        b"\x00\xfd\x00\x00\x00\x00{[]}\\",
+    )
+    .dead(0x0D, ShiftLevel::Normal, DeadKey::Acute)
+    .dead(0x0D, ShiftLevel::Shift, DeadKey::Grave)
+    .row(
+        0x10,
+        b"qwertzuiop\x81+",
+        b"QWERTZUIOP\x9a*",
+        b"@\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00~",
+    )
+    .row(0x1E, b"asdfghjkl\x94\x84", b"ASDFGHJKL\x99\x8e\xf8", b"")
+    .dead(0x29, ShiftLevel::Normal, DeadKey::Circumflex)
+    .row(
+        0x2B,
+        b"#yxcvbnm,.-",
+        b"'YXCVBNM;:_",
+        b"\x00\x00\x00\x00\x00\x00\x00\xe6",
+    )
+    .row(0x56, b"<", b">", b"|");
+
+pub const BUILTIN: [&Keymap; 2] = [&US, &DE];
+
+pub fn builtin(name: &[u8]) -> Option<&'static Keymap> {
+    BUILTIN.into_iter().find(|keymap| keymap.name() == name)
+}
+
+/// Liest eine Belegungsdatei (Format siehe Modulbeschreibung). Ohne `base` startet sie leer.
+///
+/// Fehler enthalten die Zeilennummer (ab 1).
+pub fn parse(text: &[u8]) -> Result<Keymap, (usize, KeymapError)> {
+    let mut keymap = Keymap::base(b"custom");
+    for (index, line) in text.split(|byte| *byte == b'\n').enumerate() {
+        parse_line(&mut keymap, line).map_err(|err| (index + 1, err))?;
+    }
+    Ok(keymap)
+}
+
+fn parse_line(keymap: &mut Keymap, line: &[u8]) -> Result<(), KeymapError> {
+    let mut tokens = line
+        .split(|byte| byte.is_ascii_whitespace())
+        .filter(|token| !token.is_empty());
+    let Some(keyword) = tokens.next() else {
+        return Ok(());
+    };
+    if keyword.starts_with(b"#") {
+        return Ok(());
+    }
+
+    let mut argument = || tokens.next().ok_or(KeymapError::MissingArgument);
+    match keyword {
+        b"name" => keymap.set_name(argument()?),
+        b"base" => {
+            let name = keymap.name;
+            let name_len = keymap.name_len;
+            *keymap = *builtin(argument()?).ok_or(KeymapError::UnknownBase)?;
+            keymap.name = name;
+            keymap.name_len = name_len;
+            Ok(())
+        }
+        b"key" => {
+            let scancode = parse_number(argument()?)
+                .filter(|code| usize::from(*code) < KEYMAP_KEYS)
+                .ok_or(KeymapError::InvalidScancode)?;
+            let normal = parse_symbol(argument()?)?;
+            keymap.levels[ShiftLevel::Normal as usize][usize::from(scancode)] = normal;
+            for level in [ShiftLevel::Shift, ShiftLevel::AltGr] {
+                if let Some(token) = tokens.next() {
+                    keymap.levels[level as usize][usize::from(scancode)] = parse_symbol(token)?;
+                }
+            }
+            Ok(())
+        }
+        _ => Err(KeymapError::UnknownKeyword),
+    }
+}
+
+fn parse_number(token: &[u8]) -> Option<u8> {
+    let text = core::str::from_utf8(token).ok()?;
+    match text.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+fn parse_symbol(token: &[u8]) -> Result<Keysym, KeymapError> {
+    match token {
+        b"-" => Ok(Keysym::None),
+        b"space" => Ok(Keysym::Char(b' ')),
+        [byte] => Ok(Keysym::Char(*byte)),
+        _ if token.starts_with(b"0x") => parse_number(token)
+            .map(Keysym::Char)
+            .ok_or(KeymapError::InvalidSymbol),
+        _ => DeadKey::ALL
+            .into_iter()
+            .find(|dead| dead.name() == token)
+            .map(Keysym::Dead)
+            .ok_or(KeymapError::InvalidSymbol),
+    }
+}
+
+/// Großbuchstabe zu `byte` in Codepage 437 (ASCII und die Umlaute/Akzente mit Gegenstück).
+fn cp437_upper(byte: u8) -> Option<u8> {
+    match byte {
+        b'a'..=b'z' => Some(byte.to_ascii_uppercase()),
+        0x81 => Some(0x9A),
+        0x82 => Some(0x90),
+        0x84 => Some(0x8E),
+        0x86 => Some(0x8F),
+        0x87 => Some(0x80),
+        0x91 => Some(0x92),
+        0x94 => Some(0x99),
+        0xA4 => Some(0xA5),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DE, DeadKey, KeymapError, Keysym, ShiftLevel, US, builtin, parse};
+
+    #[test]
+    fn builtin_layouts_differ_in_letters_and_altgr() {
+        assert_eq!(US.lookup(0x15, ShiftLevel::Normal), Keysym::Char(b'y'));
+        assert_eq!(DE.lookup(0x15, ShiftLevel::Normal), Keysym::Char(b'z'));
+        assert_eq!(DE.lookup(0x10, ShiftLevel::AltGr), Keysym::Char(b'@'));
+        assert_eq!(DE.lookup(0x0C, ShiftLevel::AltGr), Keysym::Char(b'\\'));
+        assert_eq!(DE.lookup(0x56, ShiftLevel::AltGr), Keysym::Char(b'|'));
+        assert_eq!(
+            DE.lookup(0x29, ShiftLevel::Normal),
+            Keysym::Dead(DeadKey::Circumflex)
+        );
+        assert_eq!(US.lookup(0x10, ShiftLevel::AltGr), Keysym::None);
+        assert!(DE.is_letter(0x27));
+        assert!(!DE.is_letter(0x0C));
+        assert_eq!(builtin(b"de").map(|keymap| keymap.name()), Some(&b"de"[..]));
+    }
+
+    #[test]
+    fn composes_dead_keys() {
+        assert_eq!(DeadKey::Circumflex.compose(b'e'), Some(0x88));
+        assert_eq!(DeadKey::Diaeresis.compose(b'U'), Some(0x9A));
+        assert_eq!(DeadKey::Acute.compose(b'x'), None);
+    }
+
+    #[test]
+    fn parses_keymap_files() {
+        let keymap = parse(
+            b"# Dvorak-Ausschnitt\nname dvorak\nbase us\nkey 0x10 ' \"\nkey 17 , <\n\
+              key 0x29 dead_tilde 0xf8 -\n",
+        )
+        .expect("keymap");
+        assert_eq!(keymap.name(), b"dvorak");
+        assert_eq!(keymap.lookup(0x10, ShiftLevel::Shift), Keysym::Char(b'"'));
+        assert_eq!(keymap.lookup(0x11, ShiftLevel::Normal), Keysym::Char(b','));
+        assert_eq!(
+            keymap.lookup(0x29, ShiftLevel::Normal),
+            Keysym::Dead(DeadKey::Tilde)
+        );
+        assert_eq!(keymap.lookup(0x29, ShiftLevel::Shift), Keysym::Char(0xF8));
+        assert_eq!(keymap.lookup(0x1E, ShiftLevel::Normal), Keysym::Char(b'a'));
+
+        assert_eq!(
+            parse(b"name x\nkey 0x99 a").err(),
+            Some((2, KeymapError::InvalidScancode))
+        );
+        assert_eq!(parse(b"base fr").err(), Some((1, KeymapError::UnknownBase)));
+        let dvorak = parse(include_bytes!("../../../fs/root/dvorak.map")).expect("dvorak.map");
+        assert_eq!(dvorak.lookup(0x28, ShiftLevel::Normal), Keysym::Char(b'-'));
+        assert!(dvorak.is_letter(0x35));
+
+        assert_eq!(
+            parse(b"key 0x10 ab").err(),
+            Some((1, KeymapError::InvalidSymbol))
+        );
+    }
+}
@@ -9,7 +9,8 @@ use super::io::outb;
 const PIT_COMMAND: u16 = 0x43;
 const PIT_CHANNEL0_DATA: u16 = 0x40;
 const PIT_BASE_FREQUENCY: u32 = 1_193_182;
-const PIT_TARGET_HZ: u32 = 100;
+/// Tick-Frequenz des Timers.
+pub const PIT_TARGET_HZ: u32 = 100;
 
 static TICKS: AtomicU64 = AtomicU64::new(0);
 
@@ -37,7 +38,7 @@ mod tests {
 
     #[test]
     fn pit_target_frequency_is_reasonable() {
-        assert!(PIT_TARGET_HZ >= 10);
-        assert!(PIT_TARGET_HZ <= 1000);
+        const { assert!(PIT_TARGET_HZ >= 10) };
+        const { assert!(PIT_TARGET_HZ <= 1000) };
     }
 }
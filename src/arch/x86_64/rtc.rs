@@ -0,0 +1,224 @@
+//! Echtzeituhr (CMOS-RTC): Datum und Uhrzeit für Zeitstempel im Dateisystem.
+//!
+//! Die RTC liefert nur Datum und Uhrzeit ohne Zeitzone; wie bei QEMU üblich wird UTC
+//! angenommen. Das Jahrhundert-Register ist nicht standardisiert, Jahre gelten als 2000–2099.
+//!
+//! Hintergrund: <https://wiki.osdev.org/CMOS#The_Real-Time_Clock>
+
+use core::fmt;
+
+use super::io::{inb, outb};
+
+const CMOS_ADDRESS: u16 = 0x70;
+const CMOS_DATA: u16 = 0x71;
+/// Gesetztes Bit 7 im Adressport hält NMIs während des Zugriffs gesperrt.
+const NMI_DISABLE: u8 = 0x80;
+
+const REG_SECONDS: u8 = 0x00;
+const REG_MINUTES: u8 = 0x02;
+const REG_HOURS: u8 = 0x04;
+const REG_DAY: u8 = 0x07;
+const REG_MONTH: u8 = 0x08;
+const REG_YEAR: u8 = 0x09;
+const REG_STATUS_A: u8 = 0x0A;
+const REG_STATUS_B: u8 = 0x0B;
+
+/// Status A: die Uhr aktualisiert gerade ihre Register.
+const UPDATE_IN_PROGRESS: u8 = 0x80;
+/// Status B: Werte binär statt BCD.
+const BINARY_MODE: u8 = 0x04;
+/// Status B: 24-Stunden-Format.
+const HOUR_24: u8 = 0x02;
+/// Im 12-Stunden-Format markiert Bit 7 der Stunde „PM“.
+const HOUR_PM: u8 = 0x80;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+/// Tage vom 0000-03-01 bis 1970-01-01 im proleptischen gregorianischen Kalender.
+const UNIX_EPOCH_DAYS: u64 = 719_468;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DateTime {
+    /// Sekunden seit 1970-01-01 00:00:00 UTC.
+    pub fn to_unix(self) -> u64 {
+        // Jahre beginnen am 1. März, damit der Schalttag am Jahresende liegt.
+        let month = u64::from(self.month);
+        let year = u64::from(self.year) - u64::from(month <= 2);
+        let era = year / 400;
+        let year_of_era = year % 400;
+        let shifted_month = if month > 2 { month - 3 } else { month + 9 };
+        let day_of_year = (153 * shifted_month + 2) / 5 + u64::from(self.day) - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146_097 + day_of_era - UNIX_EPOCH_DAYS;
+
+        days * SECONDS_PER_DAY
+            + u64::from(self.hour) * 3600
+            + u64::from(self.minute) * 60
+            + u64::from(self.second)
+    }
+
+    /// Umkehrung von [`DateTime::to_unix`].
+    pub fn from_unix(seconds: u64) -> Self {
+        let days = seconds / SECONDS_PER_DAY + UNIX_EPOCH_DAYS;
+        let time = seconds % SECONDS_PER_DAY;
+
+        let era = days / 146_097;
+        let day_of_era = days % 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        };
+        let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+        Self {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (time / 3600) as u8,
+            minute: (time / 60 % 60) as u8,
+            second: (time % 60) as u8,
+        }
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+fn read_register(register: u8) -> u8 {
+    outb(CMOS_ADDRESS, NMI_DISABLE | register);
+    inb(CMOS_DATA)
+}
+
+/// Rohwerte der Zeitregister in der Reihenfolge Sekunde … Jahr.
+fn read_raw() -> [u8; 6] {
+    while read_register(REG_STATUS_A) & UPDATE_IN_PROGRESS != 0 {
+        core::hint::spin_loop();
+    }
+    [
+        REG_SECONDS,
+        REG_MINUTES,
+        REG_HOURS,
+        REG_DAY,
+        REG_MONTH,
+        REG_YEAR,
+    ]
+    .map(read_register)
+}
+
+fn from_bcd(value: u8) -> u8 {
+    (value >> 4) * 10 + (value & 0x0F)
+}
+
+/// Wandelt die Rohwerte je nach Format aus Status B (BCD/binär, 12/24 Stunden) um.
+fn decode(raw: [u8; 6], status_b: u8) -> DateTime {
+    let [second, minute, hour, day, month, year] = raw;
+    let pm = hour & HOUR_PM != 0;
+    let value = |byte: u8| {
+        if status_b & BINARY_MODE != 0 {
+            byte
+        } else {
+            from_bcd(byte)
+        }
+    };
+
+    let mut hour = value(hour & !HOUR_PM);
+    if status_b & HOUR_24 == 0 {
+        // 12 Uhr mittags ist 12 PM, Mitternacht 12 AM.
+        hour = hour % 12 + if pm { 12 } else { 0 };
+    }
+    DateTime {
+        year: 2000 + u16::from(value(year)),
+        month: value(month),
+        day: value(day),
+        hour,
+        minute: value(minute),
+        second: value(second),
+    }
+}
+
+/// Aktuelles Datum und Uhrzeit. Es wird gelesen, bis zwei Durchläufe übereinstimmen, damit
+/// kein Sekundenwechsel mitten im Auslesen zu gemischten Werten führt.
+pub fn now() -> DateTime {
+    let mut raw = read_raw();
+    loop {
+        let again = read_raw();
+        if again == raw {
+            break;
+        }
+        raw = again;
+    }
+    decode(raw, read_register(REG_STATUS_B))
+}
+
+/// Sekunden seit 1970-01-01 00:00:00 UTC.
+pub fn unix_time() -> u64 {
+    now().to_unix()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BINARY_MODE, DateTime, HOUR_24, decode};
+
+    fn date(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> DateTime {
+        DateTime {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        }
+    }
+
+    #[test]
+    fn converts_between_dates_and_unix_time() {
+        assert_eq!(DateTime::from_unix(0), date(1970, 1, 1, 0, 0, 0));
+        assert_eq!(date(2000, 3, 1, 0, 0, 0).to_unix(), 951_868_800);
+        assert_eq!(
+            DateTime::from_unix(1_700_000_000),
+            date(2023, 11, 14, 22, 13, 20)
+        );
+        for seconds in [0, 68_169_599, 951_782_400, 1_709_210_096, 4_102_444_800] {
+            assert_eq!(DateTime::from_unix(seconds).to_unix(), seconds);
+        }
+        assert_eq!(
+            alloc::format!("{}", date(2024, 2, 29, 9, 5, 0)),
+            "2024-02-29 09:05:00"
+        );
+    }
+
+    #[test]
+    fn decodes_bcd_and_12_hour_registers() {
+        let raw = [0x59, 0x30, 0x89, 0x31, 0x12, 0x25];
+        assert_eq!(decode(raw, 0), date(2025, 12, 31, 21, 30, 59));
+        assert_eq!(
+            decode([0x00, 0x00, 0x12, 0x01, 0x01, 0x24], 0),
+            date(2024, 1, 1, 0, 0, 0)
+        );
+        assert_eq!(
+            decode([7, 8, 23, 4, 5, 26], BINARY_MODE | HOUR_24),
+            date(2026, 5, 4, 23, 8, 7)
+        );
+    }
+}
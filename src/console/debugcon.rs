@@ -1,19 +1,34 @@
 //! Debug-Ausgabe über I/O-Port `0xE9`.
 //!
 //! Viele Emulatoren (z. B. Bochs/QEMU mit passender Konfiguration) können diese Bytes
-//! direkt im Host-Terminal anzeigen.
+//! direkt im Host-Terminal anzeigen. Escape-Sequenzen (Farben, Cursorbewegung) werden
+//! herausgefiltert, damit Mitschnitte wie `build/debugcon.log` reiner Text bleiben.
 
 use core::arch::asm;
+use core::cell::UnsafeCell;
+
+use super::terminal::{AnsiAction, AnsiParser};
+use crate::arch;
+
+struct ParserCell(UnsafeCell<AnsiParser>);
+unsafe impl Sync for ParserCell {}
+
+static PARSER: ParserCell = ParserCell(UnsafeCell::new(AnsiParser::new()));
 
 pub fn write_bytes(msg: &[u8]) {
+    let interrupts_were_enabled = arch::x86_64::save_and_disable_interrupts();
+    let parser = unsafe { &mut *PARSER.0.get() };
     for byte in msg {
-        unsafe {
-            asm!(
-                "out dx, al",
-                in("dx") 0xe9_u16,
-                in("al") *byte,
-                options(nomem, nostack, preserves_flags)
-            );
+        if let Some(AnsiAction::Print(byte) | AnsiAction::Control(byte)) = parser.feed(*byte) {
+            unsafe {
+                asm!(
+                    "out dx, al",
+                    in("dx") 0xe9_u16,
+                    in("al") byte,
+                    options(nomem, nostack, preserves_flags)
+                );
+            }
         }
     }
+    arch::x86_64::restore_interrupts(interrupts_were_enabled);
 }
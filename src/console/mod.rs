@@ -1,31 +1,131 @@
 //! Konsolenabstraktion.
 //!
-//! Schreibt parallel in den VGA-Textmodus und auf Port `0xE9` (Debug-Ausgabe für Emulatoren).
+//! Schreibt parallel in den VGA-Textmodus (auf die gewählte virtuelle Konsole), auf Port `0xE9`
+//! (Debug-Ausgabe für Emulatoren) und, falls vorhanden, auf die serielle Schnittstelle COM1.
+//! Ausgaben dürfen ANSI-Escape-Sequenzen enthalten (siehe [`terminal`]); VGA und COM1 setzen
+//! sie um, debugcon filtert sie heraus.
+//! Zeilen über `write_*` werden außerdem (bis zum Shell-Start) im Kernel-Log mitgeschrieben.
+
+use core::fmt;
+
+use crate::log::kmsg;
 
 mod debugcon;
+pub mod serial;
+pub mod terminal;
 mod vga;
 
+pub use vga::{
+    CONSOLE_COUNT, LOG_CONSOLE, active_console, output_console, set_output_console, switch_console,
+};
+
+/// Einzeln ansprechbarer Ausgabekanal der Konsole.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sink {
+    Vga,
+    Debugcon,
+    Serial,
+}
+
+impl Sink {
+    pub const ALL: [Sink; 3] = [Sink::Vga, Sink::Debugcon, Sink::Serial];
+
+    pub fn name(self) -> &'static [u8] {
+        match self {
+            Sink::Vga => b"vga",
+            Sink::Debugcon => b"debugcon",
+            Sink::Serial => b"serial",
+        }
+    }
+
+    pub fn from_name(name: &[u8]) -> Option<Self> {
+        Self::ALL.into_iter().find(|sink| sink.name() == name)
+    }
+}
+
+/// `fmt::Write`-Adapter für formatierte Ausgaben auf allen Kanälen
+/// (z. B. `write!(Writer, "{}", value)`).
+pub struct Writer;
+
+impl fmt::Write for Writer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        write_str(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// `fmt::Write`-Adapter für genau einen Ausgabekanal.
+pub struct SinkWriter(pub Sink);
+
+impl fmt::Write for SinkWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        write_to(self.0, s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Ausgabe für [`kprint!`](crate::kprint) und [`kprintln!`](crate::kprintln).
+#[doc(hidden)]
+pub fn print_fmt(args: fmt::Arguments<'_>) {
+    let _ = fmt::Write::write_fmt(&mut Writer, args);
+}
+
+/// Formatierte Ausgabe auf alle Konsolenkanäle.
+#[macro_export]
+macro_rules! kprint {
+    ($($arg:tt)*) => {
+        $crate::console::print_fmt(format_args!($($arg)*))
+    };
+}
+
+/// Wie [`kprint!`](crate::kprint), mit abschließendem Zeilenumbruch.
+#[macro_export]
+macro_rules! kprintln {
+    () => {
+        $crate::console::write_byte(b'\n')
+    };
+    ($($arg:tt)*) => {
+        $crate::console::print_fmt(format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
+
 /// Löscht den sichtbaren Textbildschirm.
 pub fn clear() {
     vga::clear();
 }
 
-/// Schreibt genau ein Byte auf beide Ausgabekanäle.
+/// Verschiebt die angezeigte VGA-Konsole im Rückblick; positive Werte zeigen ältere Zeilen.
+pub fn scroll_view(lines: isize) {
+    vga::scroll_view(lines);
+}
+
+/// Schreibt genau ein Byte wie [`write_str`].
 pub fn write_byte(byte: u8) {
     vga::write_byte(byte);
     debugcon::write_bytes(&[byte]);
+    serial::write_bytes(&[byte]);
+    kmsg::capture_console(&[byte]);
 }
 
-/// Führt eine Backspace-Operation aus.
-pub fn backspace() {
-    vga::backspace();
-    debugcon::write_bytes(&[8, b' ', 8]);
+/// Schreibt ein Byte-Slice nur auf den angegebenen Kanal.
+///
+/// Gedacht für Log-Einträge: auf VGA erscheinen sie auf der Ausgabekonsole und zusätzlich auf
+/// der Log-Konsole [`LOG_CONSOLE`].
+pub fn write_to(sink: Sink, msg: &[u8]) {
+    match sink {
+        Sink::Vga => vga::write_log_bytes(msg),
+        Sink::Debugcon => debugcon::write_bytes(msg),
+        Sink::Serial => serial::write_bytes(msg),
+    }
 }
 
-/// Schreibt ein Byte-Slice ohne automatischen Zeilenumbruch.
+/// Schreibt ein Byte-Slice ohne automatischen Zeilenumbruch auf VGA, Debugcon und COM1;
+/// solange das Mitschreiben der Konsole eingeschaltet ist, landet es auch im Kernel-Log.
 pub fn write_str(msg: &[u8]) {
     vga::write_bytes(msg);
     debugcon::write_bytes(msg);
+    serial::write_bytes(msg);
+    kmsg::capture_console(msg);
 }
 
 /// Schreibt ein Byte-Slice plus `\n`.
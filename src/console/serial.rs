@@ -0,0 +1,297 @@
+//! Treiber für den 16550-UART an COM1 (Port `0x3F8`).
+//!
+//! Senden läuft per Polling auf das Transmit-Holding-Register, Empfang per IRQ4. Empfangene
+//! Bytes übersetzt [`SerialDecoder`] (inklusive VT100-Cursor- und Editiertasten sowie
+//! Steuerzeichen als Strg-Kombinationen) in Tastenereignisse und reiht sie wie die
+//! PS/2-Tastatur ein, immer für die erste virtuelle Konsole. So lässt sich die Shell auch über
+//! `qemu -serial stdio` bedienen.
+//!
+//! Hintergrund: <https://wiki.osdev.org/Serial_Ports>
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::arch::x86_64::io::{inb, outb};
+use crate::arch::x86_64::keyboard::{self, KeyEvent};
+
+const COM1: u16 = 0x3F8;
+const REG_DATA: u16 = 0;
+const REG_INTERRUPT_ENABLE: u16 = 1;
+const REG_FIFO_CONTROL: u16 = 2;
+const REG_LINE_CONTROL: u16 = 3;
+const REG_MODEM_CONTROL: u16 = 4;
+const REG_LINE_STATUS: u16 = 5;
+
+const LINE_STATUS_DATA_READY: u8 = 1 << 0;
+const LINE_STATUS_TRANSMIT_EMPTY: u8 = 1 << 5;
+/// Virtuelle Konsole, deren Eingabepuffer die seriellen Tasten erhält.
+const INPUT_CONSOLE: usize = 0;
+/// Teiler für 115200 Baud (Basistakt 115200 Hz).
+const BAUD_DIVISOR: u16 = 1;
+/// Obergrenze für das Warten auf ein freies Senderegister.
+const TRANSMIT_SPIN_LIMIT: usize = 100_000;
+
+static READY: AtomicBool = AtomicBool::new(false);
+
+/// Zustand des Eingabe-Dekoders für Escape-Sequenzen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum DecodeState {
+    Normal,
+    Escape,
+    /// Innerhalb von `ESC [` (Control Sequence Introducer).
+    Csi,
+    /// Innerhalb von `ESC O` (Single Shift 3, z. B. Pfeiltasten im Application-Modus).
+    Ss3,
+}
+
+/// Übersetzt Bytes eines Terminals in [`KeyEvent`]s.
+#[derive(Clone, Copy, Debug)]
+pub struct SerialDecoder {
+    state: DecodeState,
+    /// Letztes Byte war `\r`; ein folgendes `\n` gehört zum selben Zeilenende.
+    after_cr: bool,
+    /// Erster Zahlenparameter der laufenden CSI-Sequenz (z. B. `3` in `ESC [3~`).
+    param: u16,
+    /// Nach dem ersten Parameter folgt ein weiterer (`;`); nur der erste zählt.
+    param_done: bool,
+}
+
+impl SerialDecoder {
+    pub const fn new() -> Self {
+        Self {
+            state: DecodeState::Normal,
+            after_cr: false,
+            param: 0,
+            param_done: false,
+        }
+    }
+
+    pub fn feed(&mut self, byte: u8) -> Option<KeyEvent> {
+        let after_cr = core::mem::replace(&mut self.after_cr, false);
+        match self.state {
+            DecodeState::Normal => match byte {
+                0x1B => {
+                    self.state = DecodeState::Escape;
+                    None
+                }
+                b'\r' => {
+                    self.after_cr = true;
+                    Some(KeyEvent::Enter)
+                }
+                b'\n' if after_cr => None,
+                b'\n' => Some(KeyEvent::Enter),
+                0x08 | 0x7F => Some(KeyEvent::Backspace),
+                b'\t' => Some(KeyEvent::Char(byte)),
+                0x01..=0x1A => Some(KeyEvent::Ctrl(b'a' + byte - 1)),
+                0x20..=0x7E => Some(KeyEvent::Char(byte)),
+                _ => None,
+            },
+            DecodeState::Escape => {
+                self.param = 0;
+                self.param_done = false;
+                self.state = match byte {
+                    b'[' => DecodeState::Csi,
+                    b'O' => DecodeState::Ss3,
+                    _ => DecodeState::Normal,
+                };
+                None
+            }
+            DecodeState::Csi => match byte {
+                b'0'..=b'9' if !self.param_done => {
+                    self.param = self
+                        .param
+                        .saturating_mul(10)
+                        .saturating_add(u16::from(byte - b'0'));
+                    None
+                }
+                // Weitere Parameter- und Zwischenbytes überspringen.
+                0x20..=0x3F => {
+                    self.param_done = true;
+                    None
+                }
+                b'~' => {
+                    self.state = DecodeState::Normal;
+                    tilde_key(self.param)
+                }
+                _ => {
+                    self.state = DecodeState::Normal;
+                    cursor_key(byte)
+                }
+            },
+            DecodeState::Ss3 => {
+                self.state = DecodeState::Normal;
+                cursor_key(byte)
+            }
+        }
+    }
+}
+
+/// Cursortasten `ESC [ x` bzw. `ESC O x`.
+fn cursor_key(final_byte: u8) -> Option<KeyEvent> {
+    match final_byte {
+        b'A' => Some(KeyEvent::Up),
+        b'B' => Some(KeyEvent::Down),
+        b'C' => Some(KeyEvent::Right),
+        b'D' => Some(KeyEvent::Left),
+        b'H' => Some(KeyEvent::Home),
+        b'F' => Some(KeyEvent::End),
+        _ => None,
+    }
+}
+
+/// Editiertasten im VT220-Stil `ESC [ n ~`.
+fn tilde_key(param: u16) -> Option<KeyEvent> {
+    match param {
+        1 | 7 => Some(KeyEvent::Home),
+        2 => Some(KeyEvent::Insert),
+        3 => Some(KeyEvent::Delete),
+        4 | 8 => Some(KeyEvent::End),
+        5 => Some(KeyEvent::PageUp),
+        6 => Some(KeyEvent::PageDown),
+        _ => None,
+    }
+}
+
+struct DecoderCell(UnsafeCell<SerialDecoder>);
+unsafe impl Sync for DecoderCell {}
+
+static DECODER: DecoderCell = DecoderCell(UnsafeCell::new(SerialDecoder::new()));
+
+/// Initialisiert COM1 mit 115200 8N1 und aktiviert den Empfangs-Interrupt.
+///
+/// Liefert `false`, wenn kein UART antwortet (Loopback-Test schlägt fehl).
+pub fn init() -> bool {
+    outb(COM1 + REG_INTERRUPT_ENABLE, 0x00);
+    // DLAB setzen, Teiler schreiben, danach 8 Datenbits, keine Parität, 1 Stoppbit.
+    outb(COM1 + REG_LINE_CONTROL, 0x80);
+    outb(COM1 + REG_DATA, (BAUD_DIVISOR & 0xff) as u8);
+    outb(COM1 + REG_INTERRUPT_ENABLE, (BAUD_DIVISOR >> 8) as u8);
+    outb(COM1 + REG_LINE_CONTROL, 0x03);
+    // FIFOs aktivieren und leeren, Interrupt ab 14 Bytes.
+    outb(COM1 + REG_FIFO_CONTROL, 0xC7);
+
+    // Loopback-Test: gesendetes Byte muss zurückkommen.
+    outb(COM1 + REG_MODEM_CONTROL, 0x1E);
+    outb(COM1 + REG_DATA, 0xAE);
+    if inb(COM1 + REG_DATA) != 0xAE {
+        return false;
+    }
+
+    // Normalbetrieb: DTR, RTS und OUT2 (leitet den Interrupt an den PIC weiter).
+    outb(COM1 + REG_MODEM_CONTROL, 0x0B);
+    outb(COM1 + REG_INTERRUPT_ENABLE, 0x01);
+    READY.store(true, Ordering::Release);
+    true
+}
+
+pub fn is_ready() -> bool {
+    READY.load(Ordering::Acquire)
+}
+
+/// Sendet Bytes; `\n` wird für Terminals als `\r\n` übertragen.
+pub fn write_bytes(msg: &[u8]) {
+    if !is_ready() {
+        return;
+    }
+
+    for &byte in msg {
+        if byte == b'\n' {
+            write_raw(b'\r');
+        }
+        write_raw(byte);
+    }
+}
+
+fn write_raw(byte: u8) {
+    for _ in 0..TRANSMIT_SPIN_LIMIT {
+        if inb(COM1 + REG_LINE_STATUS) & LINE_STATUS_TRANSMIT_EMPTY != 0 {
+            break;
+        }
+        core::hint::spin_loop();
+    }
+    outb(COM1 + REG_DATA, byte);
+}
+
+/// IRQ4-Handler: leert den Empfangs-FIFO und reiht die dekodierten Tasten ein.
+pub fn handle_irq() {
+    if !is_ready() {
+        return;
+    }
+
+    let decoder = unsafe { &mut *DECODER.0.get() };
+    while inb(COM1 + REG_LINE_STATUS) & LINE_STATUS_DATA_READY != 0 {
+        let byte = inb(COM1 + REG_DATA);
+        if let Some(event) = decoder.feed(byte) {
+            keyboard::push_key_event(INPUT_CONSOLE, event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::SerialDecoder;
+    use crate::arch::x86_64::keyboard::KeyEvent;
+
+    fn decode(input: &[u8]) -> Vec<KeyEvent> {
+        let mut decoder = SerialDecoder::new();
+        input
+            .iter()
+            .filter_map(|byte| decoder.feed(*byte))
+            .collect()
+    }
+
+    #[test]
+    fn decodes_text_and_line_endings() {
+        assert_eq!(
+            decode(b"ls\r\nx\ny\r"),
+            [
+                KeyEvent::Char(b'l'),
+                KeyEvent::Char(b's'),
+                KeyEvent::Enter,
+                KeyEvent::Char(b'x'),
+                KeyEvent::Enter,
+                KeyEvent::Char(b'y'),
+                KeyEvent::Enter,
+            ]
+        );
+        assert_eq!(
+            decode(&[0x7F, 0x08, 0x01, 0x03, 0x17, b'\t']),
+            [
+                KeyEvent::Backspace,
+                KeyEvent::Backspace,
+                KeyEvent::Ctrl(b'a'),
+                KeyEvent::Ctrl(b'c'),
+                KeyEvent::Ctrl(b'w'),
+                KeyEvent::Char(b'\t')
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_arrow_escape_sequences() {
+        assert_eq!(
+            decode(b"\x1b[A\x1bOB\x1b[1;5Aq"),
+            [
+                KeyEvent::Up,
+                KeyEvent::Down,
+                KeyEvent::Up,
+                KeyEvent::Char(b'q')
+            ]
+        );
+        assert_eq!(
+            decode(b"\x1b[D\x1bOC\x1b[H\x1b[4~\x1b[3~\x1b[1;5D"),
+            [
+                KeyEvent::Left,
+                KeyEvent::Right,
+                KeyEvent::Home,
+                KeyEvent::End,
+                KeyEvent::Delete,
+                KeyEvent::Left
+            ]
+        );
+        // Unbekannte Sequenzen werden vollständig verschluckt.
+        assert_eq!(decode(b"\x1b[99~z"), [KeyEvent::Char(b'z')]);
+    }
+}
@@ -0,0 +1,592 @@
+//! Terminalemulation für den VGA-Textmodus.
+//!
+//! [`AnsiParser`] zerlegt den Ausgabestrom in druckbare Zeichen, Steuerzeichen und
+//! Escape-Sequenzen. [`Terminal`] wendet sie auf einen Zeichenpuffer an, der neben den
+//! sichtbaren Zeilen einen Rückblick (Scrollback) von [`SCROLLBACK_LINES`] Zeilen aufbewahrt.
+//!
+//! Unterstützte Teilmenge von VT100/ECMA-48:
+//! - Cursor: `CSI n A/B/C/D` (hoch/runter/vor/zurück), `CSI n E/F` (Zeilenanfang darunter/
+//!   darüber), `CSI n G` (Spalte), `CSI r;c H` bzw. `f` (Position), `CSI s`/`u` und
+//!   `ESC 7`/`ESC 8` (sichern/wiederherstellen)
+//! - Löschen: `CSI n J` (Bildschirm, Modus 0–3) und `CSI n K` (Zeile, Modus 0–2)
+//! - Farben: `CSI … m` mit 0, 1, 7, 22, 27, 30–37, 39, 40–47, 49, 90–97, 100–107
+//! - `ESC c` setzt das Terminal zurück
+//!
+//! Hintergrund: <https://vt100.net/emu/dec_ansi_parser>
+
+pub const WIDTH: usize = 80;
+pub const HEIGHT: usize = 25;
+/// Anzahl der Zeilen, die oberhalb des Bildschirms erhalten bleiben.
+pub const SCROLLBACK_LINES: usize = 200;
+/// Standardattribut: hellweiß auf Schwarz.
+pub const DEFAULT_ATTR: u8 = 0x0f;
+
+const RING_LINES: usize = HEIGHT + SCROLLBACK_LINES;
+const ALL_ROWS: u32 = (1 << HEIGHT) - 1;
+const TAB_WIDTH: usize = 8;
+const MAX_PARAMS: usize = 8;
+/// ANSI-Farbnummer (0–7) → VGA-Farbnummer (andere Reihenfolge von Rot/Blau).
+const ANSI_TO_VGA: [u8; 8] = [0, 4, 2, 6, 1, 5, 3, 7];
+
+const _: () = assert!(HEIGHT <= 32, "dirty mask holds one bit per visible row");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ParseState {
+    Ground,
+    Escape,
+    /// Innerhalb von `ESC [` (Control Sequence Introducer).
+    Csi,
+}
+
+/// Eine vollständige CSI-Sequenz (`ESC [ Parameter Endbyte`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CsiSequence {
+    params: [u16; MAX_PARAMS],
+    len: usize,
+    /// Private Sequenz (`ESC [ ?`), z. B. Cursor ein/aus; wird hier nicht ausgewertet.
+    pub private: bool,
+    pub final_byte: u8,
+}
+
+impl CsiSequence {
+    const fn empty() -> Self {
+        Self {
+            params: [0; MAX_PARAMS],
+            len: 0,
+            private: false,
+            final_byte: 0,
+        }
+    }
+
+    /// Alle angegebenen Parameter; leere Parameter sind 0.
+    pub fn params(&self) -> &[u16] {
+        &self.params[..self.len]
+    }
+
+    /// Parameter `index`; fehlt er oder ist er 0, gilt `default`.
+    pub fn param(&self, index: usize, default: u16) -> u16 {
+        match self.params().get(index) {
+            Some(&value) if value != 0 => value,
+            _ => default,
+        }
+    }
+}
+
+/// Ergebnis eines Bytes im [`AnsiParser`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnsiAction {
+    Print(u8),
+    Control(u8),
+    /// `ESC` gefolgt von einem Byte außer `[`.
+    Escape(u8),
+    Csi(CsiSequence),
+}
+
+/// Zustandsautomat für Escape-Sequenzen; Sequenzen dürfen über mehrere Aufrufe verteilt sein.
+#[derive(Clone, Copy, Debug)]
+pub struct AnsiParser {
+    state: ParseState,
+    csi: CsiSequence,
+}
+
+impl AnsiParser {
+    pub const fn new() -> Self {
+        Self {
+            state: ParseState::Ground,
+            csi: CsiSequence::empty(),
+        }
+    }
+
+    pub fn feed(&mut self, byte: u8) -> Option<AnsiAction> {
+        match self.state {
+            ParseState::Ground => match byte {
+                0x1B => {
+                    self.state = ParseState::Escape;
+                    None
+                }
+                0x00..=0x1F | 0x7F => Some(AnsiAction::Control(byte)),
+                _ => Some(AnsiAction::Print(byte)),
+            },
+            ParseState::Escape => match byte {
+                b'[' => {
+                    self.state = ParseState::Csi;
+                    self.csi = CsiSequence::empty();
+                    None
+                }
+                0x1B => None,
+                _ => {
+                    self.state = ParseState::Ground;
+                    Some(AnsiAction::Escape(byte))
+                }
+            },
+            ParseState::Csi => self.feed_csi(byte),
+        }
+    }
+
+    fn feed_csi(&mut self, byte: u8) -> Option<AnsiAction> {
+        let csi = &mut self.csi;
+        match byte {
+            b'0'..=b'9' => {
+                if csi.len == 0 {
+                    csi.len = 1;
+                }
+                let param = &mut csi.params[csi.len - 1];
+                *param = param
+                    .saturating_mul(10)
+                    .saturating_add(u16::from(byte - b'0'));
+            }
+            b';' => {
+                if csi.len == 0 {
+                    csi.len = 1;
+                }
+                if csi.len < MAX_PARAMS {
+                    csi.len += 1;
+                }
+            }
+            b'<'..=b'?' => csi.private = true,
+            // Zwischenbytes und eingestreute Steuerzeichen werden übersprungen.
+            0x00..=0x1A | 0x1C..=0x2F => {}
+            0x1B => self.state = ParseState::Escape,
+            0x40..=0x7E => {
+                self.state = ParseState::Ground;
+                csi.final_byte = byte;
+                return Some(AnsiAction::Csi(*csi));
+            }
+            _ => self.state = ParseState::Ground,
+        }
+        None
+    }
+}
+
+/// Textterminal mit Rückblick.
+///
+/// Zeilen liegen in einem Ringpuffer; die letzten [`HEIGHT`] davon bilden den Bildschirm.
+/// Eine Zelle ist wie im VGA-Speicher kodiert (`attr << 8 | zeichen`). Geänderte
+/// Bildschirmzeilen werden in einer Bitmaske gesammelt, damit der Treiber nur diese kopiert.
+///
+/// [`Terminal::new`] ist komplett nullinitialisiert (der Puffer landet so in `.bss` statt im
+/// Image); die Farben werden bei der ersten Ausgabe gesetzt.
+pub struct Terminal {
+    lines: [[u16; WIDTH]; RING_LINES],
+    /// Ringindex der obersten Bildschirmzeile.
+    top: usize,
+    /// Anzahl gültiger Zeilen oberhalb des Bildschirms.
+    history: usize,
+    /// Wie viele Zeilen die Anzeige in den Rückblick verschoben ist (0 = aktuelle Ausgabe).
+    view_offset: usize,
+    row: usize,
+    /// Darf `WIDTH` erreichen: der Umbruch passiert erst beim nächsten Zeichen.
+    col: usize,
+    saved_cursor: (usize, usize),
+    fg: u8,
+    bg: u8,
+    bold: bool,
+    reverse: bool,
+    parser: AnsiParser,
+    dirty: u32,
+    ready: bool,
+}
+
+impl Terminal {
+    pub const fn new() -> Self {
+        Self {
+            lines: [[0; WIDTH]; RING_LINES],
+            top: 0,
+            history: 0,
+            view_offset: 0,
+            row: 0,
+            col: 0,
+            saved_cursor: (0, 0),
+            fg: 0,
+            bg: 0,
+            bold: false,
+            reverse: false,
+            parser: AnsiParser::new(),
+            dirty: 0,
+            ready: false,
+        }
+    }
+
+    /// Setzt Farben, Cursor, Bildschirm und Rückblick zurück.
+    pub fn reset(&mut self) {
+        self.reset_attributes();
+        self.parser = AnsiParser::new();
+        let blank = self.blank();
+        for line in &mut self.lines {
+            line.fill(blank);
+        }
+        self.top = 0;
+        self.history = 0;
+        self.view_offset = 0;
+        self.row = 0;
+        self.col = 0;
+        self.saved_cursor = (0, 0);
+        self.dirty = ALL_ROWS;
+        self.ready = true;
+    }
+
+    /// Löscht den Bildschirm und setzt den Cursor nach oben links; der Rückblick bleibt.
+    pub fn clear(&mut self) {
+        self.prepare_output();
+        self.erase_display(2);
+        self.row = 0;
+        self.col = 0;
+    }
+
+    pub fn write_byte(&mut self, byte: u8) {
+        self.prepare_output();
+        match self.parser.feed(byte) {
+            Some(AnsiAction::Print(ch)) => self.put_char(ch),
+            Some(AnsiAction::Control(ch)) => self.control(ch),
+            Some(AnsiAction::Escape(ch)) => self.escape(ch),
+            Some(AnsiAction::Csi(seq)) => self.csi(&seq),
+            None => {}
+        }
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_byte(byte);
+        }
+    }
+
+    /// Verschiebt die Anzeige; positive Werte zeigen ältere Zeilen.
+    pub fn scroll_view(&mut self, lines: isize) {
+        let target = self
+            .view_offset
+            .saturating_add_signed(lines)
+            .min(self.history);
+        if target != self.view_offset {
+            self.view_offset = target;
+            self.dirty = ALL_ROWS;
+        }
+    }
+
+    /// Angezeigte Zeile `row` unter Berücksichtigung des Rückblicks.
+    pub fn visible_row(&self, row: usize) -> &[u16; WIDTH] {
+        let index = (self.top + RING_LINES - self.view_offset + row) % RING_LINES;
+        &self.lines[index]
+    }
+
+    /// Cursorposition `(zeile, spalte)`; `None`, solange im Rückblick geblättert wird.
+    pub fn cursor(&self) -> Option<(usize, usize)> {
+        (self.view_offset == 0).then_some((self.row, self.col.min(WIDTH - 1)))
+    }
+
+    /// Liefert die seit dem letzten Aufruf geänderten Bildschirmzeilen (Bit `n` = Zeile `n`).
+    pub fn take_dirty(&mut self) -> u32 {
+        core::mem::take(&mut self.dirty)
+    }
+
+    /// Fordert ein vollständiges Neuzeichnen an (z. B. nach einem Konsolenwechsel).
+    pub fn redraw(&mut self) {
+        if !self.ready {
+            self.reset();
+        }
+        self.dirty = ALL_ROWS;
+    }
+
+    /// Neue Ausgabe springt wie bei Linux zurück ans Ende des Rückblicks.
+    fn prepare_output(&mut self) {
+        if !self.ready {
+            self.reset();
+        }
+        self.scroll_view(-(self.view_offset as isize));
+    }
+
+    fn reset_attributes(&mut self) {
+        self.fg = DEFAULT_ATTR & 0x0f;
+        self.bg = DEFAULT_ATTR >> 4;
+        self.bold = false;
+        self.reverse = false;
+    }
+
+    fn attr(&self) -> u8 {
+        let fg = self.fg | if self.bold { 0x08 } else { 0 };
+        let (fg, bg) = if self.reverse {
+            (self.bg, fg & 0x07)
+        } else {
+            (fg, self.bg)
+        };
+        (bg << 4) | fg
+    }
+
+    fn blank(&self) -> u16 {
+        u16::from(self.attr()) << 8 | u16::from(b' ')
+    }
+
+    fn line_index(&self, row: usize) -> usize {
+        (self.top + row) % RING_LINES
+    }
+
+    fn set_cell(&mut self, row: usize, col: usize, cell: u16) {
+        let index = self.line_index(row);
+        self.lines[index][col] = cell;
+        self.dirty |= 1 << row;
+    }
+
+    fn fill(&mut self, row: usize, cols: core::ops::Range<usize>) {
+        let blank = self.blank();
+        let index = self.line_index(row);
+        self.lines[index][cols].fill(blank);
+        self.dirty |= 1 << row;
+    }
+
+    fn put_char(&mut self, ch: u8) {
+        if self.col >= WIDTH {
+            self.newline();
+        }
+        let cell = u16::from(self.attr()) << 8 | u16::from(ch);
+        self.set_cell(self.row, self.col, cell);
+        self.col += 1;
+    }
+
+    fn newline(&mut self) {
+        self.col = 0;
+        if self.row + 1 >= HEIGHT {
+            self.scroll_up();
+        } else {
+            self.row += 1;
+        }
+    }
+
+    /// Schiebt den Bildschirm eine Zeile hoch; die oberste Zeile wandert in den Rückblick.
+    fn scroll_up(&mut self) {
+        self.top = (self.top + 1) % RING_LINES;
+        self.history = (self.history + 1).min(SCROLLBACK_LINES);
+        self.fill(HEIGHT - 1, 0..WIDTH);
+        self.dirty = ALL_ROWS;
+    }
+
+    fn control(&mut self, ch: u8) {
+        match ch {
+            b'\n' => self.newline(),
+            b'\r' => self.col = 0,
+            8 => self.col = self.col.min(WIDTH - 1).saturating_sub(1),
+            b'\t' => self.col = ((self.col / TAB_WIDTH + 1) * TAB_WIDTH).min(WIDTH - 1),
+            _ => {}
+        }
+    }
+
+    fn escape(&mut self, ch: u8) {
+        match ch {
+            b'7' => self.saved_cursor = (self.row, self.col),
+            b'8' => (self.row, self.col) = self.saved_cursor,
+            b'c' => self.reset(),
+            _ => {}
+        }
+    }
+
+    fn csi(&mut self, seq: &CsiSequence) {
+        if seq.private {
+            return;
+        }
+
+        let count = usize::from(seq.param(0, 1));
+        match seq.final_byte {
+            b'A' => self.row = self.row.saturating_sub(count),
+            b'B' => self.row = (self.row + count).min(HEIGHT - 1),
+            b'C' => self.col = (self.col + count).min(WIDTH - 1),
+            b'D' => self.col = self.col.min(WIDTH - 1).saturating_sub(count),
+            b'E' => {
+                self.row = (self.row + count).min(HEIGHT - 1);
+                self.col = 0;
+            }
+            b'F' => {
+                self.row = self.row.saturating_sub(count);
+                self.col = 0;
+            }
+            b'G' => self.col = (count - 1).min(WIDTH - 1),
+            b'H' | b'f' => {
+                self.row = usize::from(seq.param(0, 1) - 1).min(HEIGHT - 1);
+                self.col = usize::from(seq.param(1, 1) - 1).min(WIDTH - 1);
+            }
+            b'J' => self.erase_display(seq.param(0, 0)),
+            b'K' => self.erase_line(seq.param(0, 0)),
+            b'm' => self.select_graphic_rendition(seq.params()),
+            b's' => self.saved_cursor = (self.row, self.col),
+            b'u' => (self.row, self.col) = self.saved_cursor,
+            _ => {}
+        }
+    }
+
+    fn erase_display(&mut self, mode: u16) {
+        match mode {
+            0 => {
+                self.erase_line(0);
+                for row in self.row + 1..HEIGHT {
+                    self.fill(row, 0..WIDTH);
+                }
+            }
+            1 => {
+                for row in 0..self.row {
+                    self.fill(row, 0..WIDTH);
+                }
+                self.erase_line(1);
+            }
+            2 | 3 => {
+                for row in 0..HEIGHT {
+                    self.fill(row, 0..WIDTH);
+                }
+                if mode == 3 {
+                    self.history = 0;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn erase_line(&mut self, mode: u16) {
+        let col = self.col.min(WIDTH);
+        match mode {
+            0 => self.fill(self.row, col..WIDTH),
+            1 => self.fill(self.row, 0..(col + 1).min(WIDTH)),
+            2 => self.fill(self.row, 0..WIDTH),
+            _ => {}
+        }
+    }
+
+    fn select_graphic_rendition(&mut self, params: &[u16]) {
+        if params.is_empty() {
+            self.reset_attributes();
+            return;
+        }
+
+        for &param in params {
+            match param {
+                0 => self.reset_attributes(),
+                1 => self.bold = true,
+                7 => self.reverse = true,
+                22 => self.bold = false,
+                27 => self.reverse = false,
+                30..=37 => self.fg = ANSI_TO_VGA[usize::from(param - 30)],
+                39 => self.fg = DEFAULT_ATTR & 0x0f,
+                40..=47 => self.bg = ANSI_TO_VGA[usize::from(param - 40)],
+                49 => self.bg = DEFAULT_ATTR >> 4,
+                90..=97 => self.fg = ANSI_TO_VGA[usize::from(param - 90)] | 0x08,
+                // Bit 3 des Hintergrunds bedeutet im Textmodus Blinken, daher ohne Aufhellung.
+                100..=107 => self.bg = ANSI_TO_VGA[usize::from(param - 100)],
+                // 256-Farben/RGB (`38;5;n`, `48;2;r;g;b`): Rest der Sequenz ignorieren.
+                38 | 48 => return,
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::string::String;
+
+    use super::{AnsiAction, AnsiParser, HEIGHT, SCROLLBACK_LINES, Terminal, WIDTH};
+
+    fn terminal() -> Box<Terminal> {
+        Box::new(Terminal::new())
+    }
+
+    fn row_text(term: &Terminal, row: usize) -> String {
+        let text: String = term
+            .visible_row(row)
+            .iter()
+            .map(|cell| char::from(*cell as u8))
+            .collect();
+        String::from(text.trim_end())
+    }
+
+    fn attr_at(term: &Terminal, row: usize, col: usize) -> u8 {
+        (term.visible_row(row)[col] >> 8) as u8
+    }
+
+    #[test]
+    fn parses_csi_sequences_across_calls() {
+        let mut parser = AnsiParser::new();
+        let mut actions = b"a\x1b[12;"
+            .iter()
+            .chain(b"5H\x1b[?25l\n")
+            .filter_map(|byte| parser.feed(*byte));
+
+        assert_eq!(actions.next(), Some(AnsiAction::Print(b'a')));
+        let Some(AnsiAction::Csi(position)) = actions.next() else {
+            panic!("expected CSI");
+        };
+        assert_eq!(
+            (position.params(), position.final_byte),
+            (&[12, 5][..], b'H')
+        );
+        assert_eq!(position.param(2, 1), 1);
+        let Some(AnsiAction::Csi(hide_cursor)) = actions.next() else {
+            panic!("expected CSI");
+        };
+        assert!(hide_cursor.private);
+        assert_eq!(actions.next(), Some(AnsiAction::Control(b'\n')));
+    }
+
+    #[test]
+    fn moves_cursor_and_erases() {
+        let mut term = terminal();
+        term.write_bytes(b"hello\nworld\x1b[1;3Hxy\x1b[K");
+        assert_eq!(row_text(&term, 0), "hexy");
+        assert_eq!(row_text(&term, 1), "world");
+        assert_eq!(term.cursor(), Some((0, 4)));
+
+        term.write_bytes(b"\x1b[2B\x1b[2D!\r\x1b[A\x1b[2K");
+        assert_eq!(row_text(&term, 2), "  !");
+        assert_eq!(row_text(&term, 1), "");
+
+        term.write_bytes(b"\x1b[2J");
+        assert!((0..HEIGHT).all(|row| row_text(&term, row).is_empty()));
+    }
+
+    #[test]
+    fn applies_sgr_colors() {
+        let mut term = terminal();
+        term.write_bytes(b"\x1b[31mR\x1b[1;44mB\x1b[0mN\x1b[7mI\x1b[27;92mG");
+        assert_eq!(attr_at(&term, 0, 0), 0x04);
+        assert_eq!(attr_at(&term, 0, 1), 0x1c);
+        assert_eq!(attr_at(&term, 0, 2), 0x0f);
+        assert_eq!(attr_at(&term, 0, 3), 0x70);
+        assert_eq!(attr_at(&term, 0, 4), 0x0a);
+    }
+
+    #[test]
+    fn wraps_long_lines_and_expands_tabs() {
+        let mut term = terminal();
+        term.write_bytes(&[b'x'; WIDTH]);
+        assert_eq!(term.cursor(), Some((0, WIDTH - 1)));
+        term.write_bytes(b"y\ta\x08b");
+        assert_eq!(row_text(&term, 1), "y       b");
+    }
+
+    #[test]
+    fn keeps_scrollback_and_returns_on_output() {
+        let mut term = terminal();
+        for line in 0..HEIGHT + 10 {
+            term.write_bytes(alloc::format!("line {line}\n").as_bytes());
+        }
+        assert_eq!(row_text(&term, 0), "line 11");
+
+        term.scroll_view(5);
+        assert_eq!(row_text(&term, 0), "line 6");
+        assert_eq!(term.cursor(), None);
+        term.scroll_view(100);
+        assert_eq!(row_text(&term, 0), "line 0");
+        term.scroll_view(-1);
+        assert_eq!(row_text(&term, 0), "line 1");
+
+        term.take_dirty();
+        term.write_byte(b'!');
+        assert_eq!(term.cursor(), Some((HEIGHT - 1, 1)));
+        assert_eq!(term.take_dirty(), (1 << HEIGHT) - 1);
+        assert_eq!(row_text(&term, HEIGHT - 1), "!");
+
+        for _ in 0..SCROLLBACK_LINES + HEIGHT {
+            term.write_byte(b'\n');
+        }
+        term.scroll_view(isize::MAX);
+        term.scroll_view(-(SCROLLBACK_LINES as isize) + 1);
+        assert_eq!(term.cursor(), None);
+        term.scroll_view(-1);
+        assert_eq!(term.cursor(), Some((HEIGHT - 1, 0)));
+    }
+}
@@ -1,115 +1,133 @@
-//! VGA-Textmodus-Treiber (80x25).
+//! VGA-Textmodus-Treiber (80x25) mit virtuellen Konsolen.
+//!
+//! Jede Konsole hat ein eigenes [`Terminal`] (Zeichen, Farben, Escape-Sequenzen, Rückblick).
+//! Sichtbar ist nur die aktive Konsole; dieser Treiber kopiert deren geänderte Zeilen in den
+//! Bildspeicher und setzt den Hardware-Cursor. Ausgaben landen auf der Ausgabekonsole, die
+//! unabhängig von der angezeigten gewählt wird.
 //!
 //! Siehe: <https://wiki.osdev.org/Text_UI>
 
-const WIDTH: usize = 80;
-const HEIGHT: usize = 25;
-const VGA_TEXT_BUFFER: *mut u8 = 0xb8000 as *mut u8;
-const VGA_WHITE_ON_BLACK: u8 = 0x0f;
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use super::terminal::{HEIGHT, Terminal, WIDTH};
+use crate::arch;
+use crate::memory::vm::KERNEL_OFFSET;
+
+const VGA_TEXT_BUFFER: *mut u16 = (KERNEL_OFFSET + 0xb8000) as *mut u16;
 const CRTC_INDEX_PORT: u16 = 0x3D4;
 const CRTC_DATA_PORT: u16 = 0x3D5;
 
-static mut COLUMN: usize = 0;
-static mut ROW: usize = 0;
+/// Anzahl der virtuellen Konsolen (Alt+F1 … Alt+F4).
+pub const CONSOLE_COUNT: usize = 4;
+/// Konsole, auf der zusätzlich alle Log-Einträge für VGA erscheinen.
+pub const LOG_CONSOLE: usize = CONSOLE_COUNT - 1;
 
-pub fn clear() {
-    unsafe {
-        for row in 0..HEIGHT {
-            for col in 0..WIDTH {
-                write_cell(row, col, b' ', VGA_WHITE_ON_BLACK);
-            }
-        }
-        ROW = 0;
-        COLUMN = 0;
-        update_hardware_cursor();
+struct TerminalsCell(UnsafeCell<[Terminal; CONSOLE_COUNT]>);
+unsafe impl Sync for TerminalsCell {}
+
+static TERMINALS: TerminalsCell =
+    TerminalsCell(UnsafeCell::new([const { Terminal::new() }; CONSOLE_COUNT]));
+/// Angezeigte Konsole.
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+/// Ziel von `write_*`; unabhängig davon, welche Konsole gerade angezeigt wird.
+static OUTPUT: AtomicUsize = AtomicUsize::new(0);
+
+/// Führt `f` auf dem Terminal einer Konsole aus (auch aus dem Tastatur-IRQ erreichbar, daher
+/// mit gesperrten Interrupts). Ist die Konsole sichtbar, wird danach der Bildschirm
+/// aktualisiert.
+fn with_terminal<T>(console: usize, f: impl FnOnce(&mut Terminal) -> T) -> T {
+    let interrupts_were_enabled = arch::x86_64::save_and_disable_interrupts();
+    let terminal = unsafe { &mut (*TERMINALS.0.get())[console] };
+    let result = f(terminal);
+    if console == ACTIVE.load(Ordering::Relaxed) {
+        render(terminal);
     }
+    arch::x86_64::restore_interrupts(interrupts_were_enabled);
+    result
+}
+
+fn with_output<T>(f: impl FnOnce(&mut Terminal) -> T) -> T {
+    with_terminal(output_console(), f)
+}
+
+pub fn clear() {
+    with_output(Terminal::clear);
 }
 
 pub fn write_byte(byte: u8) {
-    unsafe {
-        match byte {
-            b'\n' => newline(),
-            8 => backspace_inner(),
-            b => {
-                if COLUMN >= WIDTH {
-                    newline();
-                }
-                write_cell(ROW, COLUMN, b, VGA_WHITE_ON_BLACK);
-                COLUMN += 1;
-            }
-        }
-        update_hardware_cursor();
-    }
+    with_output(|terminal| terminal.write_byte(byte));
 }
 
 pub fn write_bytes(msg: &[u8]) {
-    for byte in msg {
-        write_byte(*byte);
-    }
+    with_output(|terminal| terminal.write_bytes(msg));
 }
 
-pub fn backspace() {
-    unsafe {
-        backspace_inner();
-        update_hardware_cursor();
+/// Schreibt auf die Ausgabekonsole und zusätzlich auf [`LOG_CONSOLE`].
+pub fn write_log_bytes(msg: &[u8]) {
+    let output = output_console();
+    with_terminal(output, |terminal| terminal.write_bytes(msg));
+    if output != LOG_CONSOLE {
+        with_terminal(LOG_CONSOLE, |terminal| terminal.write_bytes(msg));
     }
 }
 
-unsafe fn backspace_inner() {
-    unsafe {
-        if COLUMN > 0 {
-            COLUMN -= 1;
-            write_cell(ROW, COLUMN, b' ', VGA_WHITE_ON_BLACK);
-        }
-    }
+/// Blättert im Rückblick der angezeigten Konsole; positive Werte zeigen ältere Zeilen.
+pub fn scroll_view(lines: isize) {
+    with_terminal(active_console(), |terminal| terminal.scroll_view(lines));
 }
 
-unsafe fn newline() {
-    unsafe {
-        COLUMN = 0;
-        if ROW + 1 >= HEIGHT {
-            scroll_one_line();
-        } else {
-            ROW += 1;
-        }
+pub fn active_console() -> usize {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+pub fn output_console() -> usize {
+    OUTPUT.load(Ordering::Relaxed)
+}
+
+/// Wählt die Konsole für alle folgenden Ausgaben; ungültige Nummern werden ignoriert.
+pub fn set_output_console(console: usize) {
+    if console < CONSOLE_COUNT {
+        OUTPUT.store(console, Ordering::Relaxed);
     }
 }
 
-unsafe fn scroll_one_line() {
-    unsafe {
-        for row in 1..HEIGHT {
-            for col in 0..WIDTH {
-                let from = (row * WIDTH + col) * 2;
-                let to = ((row - 1) * WIDTH + col) * 2;
-                *VGA_TEXT_BUFFER.add(to) = *VGA_TEXT_BUFFER.add(from);
-                *VGA_TEXT_BUFFER.add(to + 1) = *VGA_TEXT_BUFFER.add(from + 1);
-            }
-        }
+/// Zeigt `console` an; ungültige Nummern werden ignoriert.
+pub fn switch_console(console: usize) {
+    if console >= CONSOLE_COUNT {
+        return;
+    }
 
-        for col in 0..WIDTH {
-            write_cell(HEIGHT - 1, col, b' ', VGA_WHITE_ON_BLACK);
-        }
+    let interrupts_were_enabled = arch::x86_64::save_and_disable_interrupts();
+    ACTIVE.store(console, Ordering::Relaxed);
+    let terminal = unsafe { &mut (*TERMINALS.0.get())[console] };
+    terminal.redraw();
+    render(terminal);
+    arch::x86_64::restore_interrupts(interrupts_were_enabled);
+}
 
-        ROW = HEIGHT - 1;
+fn render(terminal: &mut Terminal) {
+    let dirty = terminal.take_dirty();
+    for row in (0..HEIGHT).filter(|row| dirty & (1 << row) != 0) {
+        for (col, cell) in terminal.visible_row(row).iter().enumerate() {
+            unsafe { VGA_TEXT_BUFFER.add(row * WIDTH + col).write_volatile(*cell) };
+        }
     }
+
+    // Eine Position außerhalb des Bildschirms blendet den Cursor aus.
+    let position = terminal
+        .cursor()
+        .map_or(WIDTH * HEIGHT, |(row, col)| row * WIDTH + col);
+    update_hardware_cursor(position as u16);
 }
 
-fn update_hardware_cursor() {
-    let position = unsafe { (ROW * WIDTH + COLUMN) as u16 };
+fn update_hardware_cursor(position: u16) {
     outb(CRTC_INDEX_PORT, 0x0F);
     outb(CRTC_DATA_PORT, (position & 0xFF) as u8);
     outb(CRTC_INDEX_PORT, 0x0E);
     outb(CRTC_DATA_PORT, (position >> 8) as u8);
 }
 
-unsafe fn write_cell(row: usize, col: usize, byte: u8, attr: u8) {
-    let offset = (row * WIDTH + col) * 2;
-    unsafe {
-        *VGA_TEXT_BUFFER.add(offset) = byte;
-        *VGA_TEXT_BUFFER.add(offset + 1) = attr;
-    }
-}
-
 fn outb(port: u16, value: u8) {
     unsafe {
         core::arch::asm!(
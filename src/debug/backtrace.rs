@@ -0,0 +1,144 @@
+//! Stack-Backtraces über die Framepointer-Kette.
+//!
+//! Der Kernel wird mit `-C force-frame-pointers=yes` gebaut (siehe `.cargo/config.toml`). Jeder
+//! Frame beginnt daher mit dem gesicherten `RBP` des Aufrufers, direkt gefolgt von der
+//! Rücksprungadresse. `switch_stack` und Stage 2 setzen `RBP` vor dem ersten Rust-Aufruf auf 0,
+//! damit die Kette sauber endet.
+
+use core::fmt::Write;
+
+use crate::console;
+
+use super::symbols::{SymbolTable, kernel_symbols};
+
+/// Maximale Anzahl ausgegebener Frames.
+pub const MAX_FRAMES: usize = 16;
+
+/// Folgt der Framepointer-Kette ab `rbp` und schreibt die Rücksprungadressen nach `out`.
+///
+/// `read_u64` liefert `None` für Adressen, die nicht gelesen werden dürfen. Der Walk endet
+/// außerdem bei `RBP = 0`, falsch ausgerichteten Zeigern oder wenn die Kette nicht streng
+/// aufwärts läuft (Schutz vor Zyklen).
+pub fn walk(mut rbp: u64, mut read_u64: impl FnMut(u64) -> Option<u64>, out: &mut [u64]) -> usize {
+    let mut count = 0;
+    while count < out.len() && rbp != 0 && rbp.is_multiple_of(8) {
+        let Some(return_addr) = rbp.checked_add(8).and_then(&mut read_u64) else {
+            break;
+        };
+        let Some(next) = read_u64(rbp) else {
+            break;
+        };
+        if return_addr == 0 {
+            break;
+        }
+
+        out[count] = return_addr;
+        count += 1;
+        if next <= rbp {
+            break;
+        }
+        rbp = next;
+    }
+    count
+}
+
+/// Schreibt eine Zeile `#n 0x... name+0x..` je Frame.
+pub fn print(frames: &[u64], symbols: &SymbolTable<'_>) {
+    for (index, addr) in frames.iter().enumerate() {
+        console::write_str(b"  #");
+        console::write_u64(index as u64);
+        console::write_byte(b' ');
+        console::write_hex_u64(*addr);
+        if let Some((symbol, offset)) = symbols.lookup(*addr) {
+            console::write_byte(b' ');
+            console::write_str(symbol.name);
+            let _ = write!(console::Writer, "+{offset:#x}");
+        }
+        console::write_byte(b'\n');
+    }
+}
+
+/// Gibt den Backtrace des aufrufenden Codes aus.
+#[cfg(eres_kernel)]
+#[inline(never)]
+pub fn print_current() {
+    use core::arch::asm;
+
+    use crate::memory::paging;
+    use crate::memory::vm::VirtAddr;
+
+    let rbp: u64;
+    unsafe {
+        asm!("mov {}, rbp", out(reg) rbp, options(nomem, nostack, preserves_flags));
+    }
+
+    let mapper = paging::active_mapper();
+    let mut frames = [0_u64; MAX_FRAMES];
+    let count = walk(
+        rbp,
+        |addr| {
+            mapper
+                .translate(VirtAddr(addr))
+                .map(|_| unsafe { (addr as *const u64).read_volatile() })
+        },
+        &mut frames,
+    );
+
+    console::write_line(b"backtrace:");
+    if count == 0 {
+        console::write_line(b"  <no frames>");
+    }
+    print(&frames[..count], &kernel_symbols());
+}
+
+/// Host-Build-Fallback: ohne Kernel-Stack gibt es nichts zu durchlaufen.
+#[cfg(not(eres_kernel))]
+pub fn print_current() {
+    print(&[], &kernel_symbols());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::walk;
+
+    /// Simulierter Stack ab Adresse `BASE`, ein `u64` pro Slot.
+    const BASE: u64 = 0x1000;
+
+    fn reader(stack: &[u64]) -> impl FnMut(u64) -> Option<u64> + '_ {
+        |addr| {
+            let index = addr.checked_sub(BASE)? / 8;
+            stack.get(index as usize).copied()
+        }
+    }
+
+    #[test]
+    fn follows_frame_chain_until_null() {
+        // Frame 0 bei BASE, Frame 1 bei BASE + 0x20, danach RBP = 0.
+        let stack = [BASE + 0x20, 0xaaaa, 0, 0, 0, 0xbbbb];
+        let mut frames = [0; 8];
+        let count = walk(BASE, reader(&stack), &mut frames);
+        assert_eq!(&frames[..count], &[0xaaaa, 0xbbbb]);
+    }
+
+    #[test]
+    fn stops_on_loops_unreadable_and_misaligned_frames() {
+        let mut frames = [0; 8];
+
+        let looping = [BASE, 0xaaaa];
+        assert_eq!(walk(BASE, reader(&looping), &mut frames), 1);
+
+        let dangling = [0x9000, 0xaaaa];
+        assert_eq!(walk(BASE, reader(&dangling), &mut frames), 1);
+
+        assert_eq!(walk(BASE + 4, reader(&looping), &mut frames), 0);
+        assert_eq!(walk(0, reader(&looping), &mut frames), 0);
+    }
+
+    #[test]
+    fn respects_output_capacity() {
+        let stack = [BASE + 0x10, 0x1, BASE + 0x20, 0x2, 0, 0x3];
+        let mut frames = [0; 2];
+        assert_eq!(walk(BASE, reader(&stack), &mut frames), 2);
+        assert_eq!(frames, [0x1, 0x2]);
+    }
+}
@@ -0,0 +1,6 @@
+//! Diagnosehilfen für Fehlerberichte.
+
+/// Stack-Backtraces über die Framepointer-Kette.
+pub mod backtrace;
+/// Beim Build eingebettete Kernel-Symboltabelle.
+pub mod symbols;
@@ -0,0 +1,142 @@
+//! Beim Build eingebettete Kernel-Symboltabelle.
+//!
+//! `scripts/gen_ksymtab.sh` liest nach einem ersten Link-Durchlauf alle Funktionssymbole aus
+//! dem Kernel-ELF und erzeugt daraus die Section `.ksymtab`, die im zweiten Durchlauf hinter
+//! `.data` gelinkt wird. Da sie hinter Code und Daten liegt, verschieben sich dabei keine
+//! Adressen. Ohne zweiten Durchlauf ist die Tabelle leer und Backtraces zeigen nur Adressen.
+//!
+//! Format je Eintrag, aufsteigend nach Adresse sortiert:
+//! `u64` Adresse (LE), `u16` Namenslänge (LE), Name ohne Nullbyte.
+
+const ADDR_LEN: usize = 8;
+const NAME_LEN_LEN: usize = 2;
+
+/// Ein Symbol mit Startadresse und Namen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Symbol<'a> {
+    pub addr: u64,
+    pub name: &'a [u8],
+}
+
+/// Lesesicht auf eine Symboltabelle im `.ksymtab`-Format.
+#[derive(Clone, Copy)]
+pub struct SymbolTable<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> SymbolTable<'a> {
+    pub const fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn iter(&self) -> SymbolIter<'a> {
+        SymbolIter { rest: self.data }
+    }
+
+    /// Sucht das Symbol, in dem `addr` liegt, und liefert es mit dem Offset von `addr`.
+    pub fn lookup(&self, addr: u64) -> Option<(Symbol<'a>, u64)> {
+        self.iter()
+            .take_while(|symbol| symbol.addr <= addr)
+            .last()
+            .map(|symbol| (symbol, addr - symbol.addr))
+    }
+}
+
+/// Iterator über die Einträge einer [`SymbolTable`]; endet bei kaputten Einträgen.
+pub struct SymbolIter<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Iterator for SymbolIter<'a> {
+    type Item = Symbol<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (addr, rest) = self.rest.split_first_chunk::<ADDR_LEN>()?;
+        let (len, rest) = rest.split_first_chunk::<NAME_LEN_LEN>()?;
+        let len = u16::from_le_bytes(*len) as usize;
+        if rest.len() < len {
+            self.rest = &[];
+            return None;
+        }
+
+        let (name, rest) = rest.split_at(len);
+        self.rest = rest;
+        Some(Symbol {
+            addr: u64::from_le_bytes(*addr),
+            name,
+        })
+    }
+}
+
+/// Liefert die in den Kernel gelinkte Symboltabelle.
+#[cfg(eres_kernel)]
+pub fn kernel_symbols() -> SymbolTable<'static> {
+    unsafe extern "C" {
+        static __ksymtab_start: u8;
+        static __ksymtab_end: u8;
+    }
+
+    let start = &raw const __ksymtab_start;
+    let end = &raw const __ksymtab_end;
+    let len = end as usize - start as usize;
+    SymbolTable::new(unsafe { core::slice::from_raw_parts(start, len) })
+}
+
+/// Host-Build-Fallback: keine eingebettete Tabelle.
+#[cfg(not(eres_kernel))]
+pub fn kernel_symbols() -> SymbolTable<'static> {
+    SymbolTable::new(&[])
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{Symbol, SymbolTable};
+
+    fn encode(symbols: &[(u64, &[u8])]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for (addr, name) in symbols {
+            data.extend_from_slice(&addr.to_le_bytes());
+            data.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            data.extend_from_slice(name);
+        }
+        data
+    }
+
+    #[test]
+    fn resolves_address_to_containing_symbol() {
+        let data = encode(&[
+            (0x1000, b"eres_os::kernel_main"),
+            (0x1200, b"eres_os::shell::run"),
+            (0x1800, b"core::panicking::panic"),
+        ]);
+        let table = SymbolTable::new(&data);
+
+        let (symbol, offset) = table.lookup(0x1234).expect("symbol");
+        assert_eq!(symbol.name, b"eres_os::shell::run");
+        assert_eq!(offset, 0x34);
+        assert_eq!(
+            table.lookup(0x1000).map(|(s, o)| (s.addr, o)),
+            Some((0x1000, 0))
+        );
+        assert_eq!(table.lookup(0xfff), None);
+        assert_eq!(table.iter().count(), 3);
+    }
+
+    #[test]
+    fn stops_at_truncated_entry() {
+        let mut data = encode(&[(0x1000, b"first"), (0x2000, b"second")]);
+        data.truncate(data.len() - 2);
+        let table = SymbolTable::new(&data);
+
+        assert_eq!(
+            table.iter().collect::<Vec<_>>(),
+            [Symbol {
+                addr: 0x1000,
+                name: b"first"
+            }]
+        );
+        assert!(SymbolTable::new(&[]).lookup(0x1000).is_none());
+    }
+}
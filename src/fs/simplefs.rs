@@ -1,16 +1,20 @@
-use alloc::vec;
 use alloc::vec::Vec;
 use core::cell::RefCell;
 
-use simplefs_core::{DirEntry, FsError, Superblock, BLOCK_SIZE, DIR_ENTRY_SIZE};
+use simplefs_core::{blocks_for_size, DirEntry, FsError, Superblock, BLOCK_SIZE, DIR_ENTRY_SIZE, FLAG_DIRECTORY};
 
 use crate::fs::vfs::{DirEntry as VfsDirEntry, FileSystem, Metadata, NodeId, NodeType, VfsError};
 use crate::storage::block::{BlockDevice, BlockError};
 
+const ENTRIES_PER_BLOCK: usize = BLOCK_SIZE / DIR_ENTRY_SIZE;
+
 pub struct SimpleFs<D: BlockDevice> {
     device: RefCell<D>,
     superblock: Superblock,
-    entries: Vec<DirEntry>,
+    // Every directory slot of the image, including unused ones, so that node ids stay stable.
+    slots: Vec<DirEntry>,
+    // Current time in seconds since the Unix epoch, used for entry timestamps.
+    clock: fn() -> u32,
 }
 
 impl<D: BlockDevice> SimpleFs<D> {
@@ -21,40 +25,35 @@ impl<D: BlockDevice> SimpleFs<D> {
         let superblock = Superblock::decode(&sector).map_err(map_fs_error)?;
 
         // Directory data is stored as a contiguous block range right after the superblock.
-        let dir_bytes = superblock.dir_block_count as usize * BLOCK_SIZE;
-        let mut dir_data = vec![0_u8; dir_bytes];
+        // All slots in that range are scanned; images may contain free slots for new files.
+        let mut slots = Vec::new();
         for i in 0..superblock.dir_block_count as usize {
-            let start = i * BLOCK_SIZE;
-            let end = start + BLOCK_SIZE;
             device
-                .read_sector((superblock.dir_start_block as usize + i) as u64, &mut dir_data[start..end])
+                .read_sector((superblock.dir_start_block as usize + i) as u64, &mut sector)
                 .map_err(map_block_error)?;
-        }
-
-        let mut entries = Vec::new();
-        for i in 0..superblock.dir_entry_count as usize {
-            let start = i * DIR_ENTRY_SIZE;
-            let end = start + DIR_ENTRY_SIZE;
-            if end > dir_data.len() {
-                return Err(VfsError::Io);
-            }
-            let mut raw = [0_u8; DIR_ENTRY_SIZE];
-            raw.copy_from_slice(&dir_data[start..end]);
-            let entry = DirEntry::decode(&raw);
-            if !entry.is_unused() {
-                entries.push(entry);
+            for raw in sector.chunks_exact(DIR_ENTRY_SIZE) {
+                let mut entry = [0_u8; DIR_ENTRY_SIZE];
+                entry.copy_from_slice(raw);
+                slots.push(DirEntry::decode(&entry));
             }
         }
 
         Ok(Self {
             device: RefCell::new(device),
             superblock,
-            entries,
+            slots,
+            clock: || 0,
         })
     }
 
+    // Without a clock, new and modified entries get timestamp 0 ("unknown").
+    pub fn with_clock(mut self, clock: fn() -> u32) -> Self {
+        self.clock = clock;
+        self
+    }
+
     pub fn entry_count(&self) -> usize {
-        self.entries.len()
+        self.slots.iter().filter(|entry| !entry.is_unused()).count()
     }
 
     pub fn superblock(&self) -> Superblock {
@@ -76,6 +75,213 @@ impl<D: BlockDevice> SimpleFs<D> {
     fn entry_name(entry: &DirEntry) -> Option<&str> {
         entry.name().ok()
     }
+
+    fn used_entries(&self) -> impl Iterator<Item = (usize, &DirEntry)> {
+        self.slots.iter().enumerate().filter(|(_, entry)| !entry.is_unused())
+    }
+
+    // Slot of a file or subdirectory; the root has no slot.
+    fn entry_index(&self, node: NodeId) -> Result<usize, VfsError> {
+        let index = Self::node_entry_index(node).ok_or(VfsError::InvalidPath)?;
+        match self.slots.get(index) {
+            Some(entry) if !entry.is_unused() => Ok(index),
+            _ => Err(VfsError::NotFound),
+        }
+    }
+
+    fn file_index(&self, node: NodeId) -> Result<usize, VfsError> {
+        if node.0 == 0 {
+            return Err(VfsError::NotFile);
+        }
+        let index = self.entry_index(node)?;
+        if self.slots[index].is_directory() {
+            return Err(VfsError::NotFile);
+        }
+        Ok(index)
+    }
+
+    // Parent value that entries inside the directory `node` carry (0 for the root).
+    fn directory(&self, node: NodeId) -> Result<u32, VfsError> {
+        if node.0 == 0 {
+            return Ok(0);
+        }
+        let index = self.entry_index(node)?;
+        if !self.slots[index].is_directory() {
+            return Err(VfsError::NotDirectory);
+        }
+        Ok(node.0 as u32)
+    }
+
+    fn children(&self, parent: u32) -> impl Iterator<Item = (usize, &DirEntry)> {
+        self.used_entries().filter(move |(_, entry)| entry.parent == parent)
+    }
+
+    fn find_child(&self, parent: u32, name: &str) -> Option<usize> {
+        self.children(parent)
+            .find(|(_, entry)| Self::entry_name(entry) == Some(name))
+            .map(|(index, _)| index)
+    }
+
+    fn check_name(name: &str) -> Result<(), VfsError> {
+        if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+            return Err(VfsError::InvalidPath);
+        }
+        Ok(())
+    }
+
+    // Updates the modification time of a directory after its entries changed.
+    fn touch_dir(&mut self, dir: u32) -> Result<(), VfsError> {
+        if dir == 0 {
+            return Ok(());
+        }
+        let index = dir as usize - 1;
+        self.slots[index].modified = (self.clock)();
+        self.store_entry(index)
+    }
+
+    fn create_entry(&mut self, parent: NodeId, name: &str, flags: u32) -> Result<NodeId, VfsError> {
+        let parent = self.directory(parent)?;
+        Self::check_name(name)?;
+        if self.find_child(parent, name).is_some() {
+            return Err(VfsError::AlreadyExists);
+        }
+
+        let index = self
+            .slots
+            .iter()
+            .position(DirEntry::is_unused)
+            .ok_or(VfsError::NoSpace)?;
+        let now = (self.clock)();
+        let mut entry = DirEntry::new(name, self.superblock.data_start_block, 0, 0).map_err(map_fs_error)?;
+        entry.flags = flags;
+        entry.parent = parent;
+        entry.created = now;
+        entry.modified = now;
+        self.slots[index] = entry;
+        self.store_entry(index)?;
+        self.store_superblock()?;
+        self.touch_dir(parent)?;
+        Ok(Self::entry_node(index))
+    }
+
+    // Whether `dir` is `node` itself or lies somewhere below it.
+    fn is_within(&self, dir: u32, node: NodeId) -> bool {
+        let mut current = dir;
+        // Bounded by the slot count so that a corrupted parent cycle cannot hang the kernel.
+        for _ in 0..=self.slots.len() {
+            if current == 0 {
+                return false;
+            }
+            if current as u64 == node.0 {
+                return true;
+            }
+            current = self.slots[current as usize - 1].parent;
+        }
+        true
+    }
+
+    fn store_entry(&mut self, index: usize) -> Result<(), VfsError> {
+        let lba = (self.superblock.dir_start_block as usize + index / ENTRIES_PER_BLOCK) as u64;
+        let offset = (index % ENTRIES_PER_BLOCK) * DIR_ENTRY_SIZE;
+        let mut raw = [0_u8; DIR_ENTRY_SIZE];
+        self.slots[index].encode(&mut raw);
+
+        let device = self.device.get_mut();
+        let mut sector = [0_u8; BLOCK_SIZE];
+        device.read_sector(lba, &mut sector).map_err(map_block_error)?;
+        sector[offset..offset + DIR_ENTRY_SIZE].copy_from_slice(&raw);
+        device.write_sector(lba, &sector).map_err(map_block_error)
+    }
+
+    fn store_superblock(&mut self) -> Result<(), VfsError> {
+        self.superblock.dir_entry_count = self.entry_count() as u32;
+        let mut sector = [0_u8; BLOCK_SIZE];
+        self.superblock.encode(&mut sector);
+        self.device
+            .get_mut()
+            .write_sector(0, &sector)
+            .map_err(map_block_error)
+    }
+
+    /// Finds the first free run of `blocks` data blocks, ignoring the extent of slot `skip`.
+    fn allocate(&self, blocks: u32, skip: usize) -> Result<u32, VfsError> {
+        let extents: Vec<(u32, u32)> = self
+            .used_entries()
+            .filter(|(index, entry)| *index != skip && entry.file_block_count > 0)
+            .map(|(_, entry)| (entry.file_start_block, entry.file_start_block + entry.file_block_count))
+            .collect();
+
+        // A free run starts either at the beginning of the data area or right after a file.
+        core::iter::once(self.superblock.data_start_block)
+            .chain(extents.iter().map(|(_, end)| *end))
+            .filter(|start| start.saturating_add(blocks) <= self.superblock.total_blocks)
+            .filter(|start| {
+                extents
+                    .iter()
+                    .all(|(used_start, used_end)| start + blocks <= *used_start || *start >= *used_end)
+            })
+            .min()
+            .ok_or(VfsError::NoSpace)
+    }
+
+    /// Writes `data` at byte `offset` of the extent starting at `start_block`.
+    fn store_data(&mut self, start_block: u64, offset: usize, data: &[u8]) -> Result<(), VfsError> {
+        let device = self.device.get_mut();
+        let mut written = 0_usize;
+        let mut sector = [0_u8; BLOCK_SIZE];
+        while written < data.len() {
+            let cursor = offset + written;
+            let lba = start_block + (cursor / BLOCK_SIZE) as u64;
+            let block_offset = cursor % BLOCK_SIZE;
+            let to_copy = core::cmp::min(data.len() - written, BLOCK_SIZE - block_offset);
+            if to_copy < BLOCK_SIZE {
+                device.read_sector(lba, &mut sector).map_err(map_block_error)?;
+            }
+            sector[block_offset..block_offset + to_copy].copy_from_slice(&data[written..written + to_copy]);
+            device.write_sector(lba, &sector).map_err(map_block_error)?;
+            written += to_copy;
+        }
+        Ok(())
+    }
+
+    /// Makes room for `blocks` data blocks in slot `index`, moving the file if it cannot grow in place.
+    fn reserve(&mut self, index: usize, blocks: u32) -> Result<(), VfsError> {
+        let entry = self.slots[index];
+        if blocks <= entry.file_block_count {
+            return Ok(());
+        }
+
+        let start = self.allocate(blocks, index)?;
+        if start != entry.file_start_block {
+            // The new extent never starts inside the old one, so copying upwards is safe.
+            let device = self.device.get_mut();
+            let mut sector = [0_u8; BLOCK_SIZE];
+            for block in 0..blocks_for_size(entry.file_size as usize) {
+                device
+                    .read_sector((entry.file_start_block + block) as u64, &mut sector)
+                    .map_err(map_block_error)?;
+                device
+                    .write_sector((start + block) as u64, &sector)
+                    .map_err(map_block_error)?;
+            }
+        }
+
+        self.slots[index].file_start_block = start;
+        self.slots[index].file_block_count = blocks;
+        Ok(())
+    }
+}
+
+// Mounts the first registered drive that carries a simplefs superblock, stamping entries with the RTC time.
+#[cfg(eres_kernel)]
+pub fn mount_registered()
+-> Option<(&'static str, SimpleFs<crate::storage::cache::CachedBlockDevice<crate::storage::registry::AtaDevice>>)> {
+    use crate::storage::cache::CachedBlockDevice;
+
+    crate::storage::registry::drives().into_iter().find_map(|drive| {
+        let fs = SimpleFs::mount(CachedBlockDevice::new(drive.open(), 16)).ok()?;
+        Some((drive.name(), fs.with_clock(|| crate::arch::x86_64::rtc::unix_time() as u32)))
+    })
 }
 
 fn map_block_error(err: BlockError) -> VfsError {
@@ -98,41 +304,38 @@ impl<D: BlockDevice> FileSystem for SimpleFs<D> {
     }
 
     fn lookup(&self, parent: NodeId, name: &str) -> Result<NodeId, VfsError> {
-        if parent.0 != 0 {
-            return Err(VfsError::NotDirectory);
-        }
-
-        for (i, entry) in self.entries.iter().enumerate() {
-            if Self::entry_name(entry) == Some(name) {
-                return Ok(Self::entry_node(i));
-            }
-        }
-        Err(VfsError::NotFound)
+        let parent = self.directory(parent)?;
+        self.find_child(parent, name)
+            .map(Self::entry_node)
+            .ok_or(VfsError::NotFound)
     }
 
     fn metadata(&self, node: NodeId) -> Result<Metadata, VfsError> {
         if node.0 == 0 {
             return Ok(Metadata {
                 node_type: NodeType::Directory,
-                size: self.entries.len() as u64,
+                size: self.children(0).count() as u64,
+                created: 0,
+                modified: 0,
             });
         }
 
-        let index = Self::node_entry_index(node).ok_or(VfsError::NotFound)?;
-        let entry = self.entries.get(index).ok_or(VfsError::NotFound)?;
+        let entry = &self.slots[self.entry_index(node)?];
+        let (node_type, size) = if entry.is_directory() {
+            (NodeType::Directory, self.children(node.0 as u32).count() as u64)
+        } else {
+            (NodeType::File, entry.file_size as u64)
+        };
         Ok(Metadata {
-            node_type: NodeType::File,
-            size: entry.file_size as u64,
+            node_type,
+            size,
+            created: entry.created as u64,
+            modified: entry.modified as u64,
         })
     }
 
     fn read(&self, node: NodeId, offset: u64, out: &mut [u8]) -> Result<usize, VfsError> {
-        if node.0 == 0 {
-            return Err(VfsError::NotFile);
-        }
-
-        let index = Self::node_entry_index(node).ok_or(VfsError::NotFound)?;
-        let entry = self.entries.get(index).ok_or(VfsError::NotFound)?;
+        let entry = &self.slots[self.file_index(node)?];
         if offset >= entry.file_size as u64 {
             return Ok(0);
         }
@@ -163,17 +366,114 @@ impl<D: BlockDevice> FileSystem for SimpleFs<D> {
     }
 
     fn list(&self, dir: NodeId) -> Result<Vec<VfsDirEntry>, VfsError> {
-        if dir.0 != 0 {
-            return Err(VfsError::NotDirectory);
-        }
-
+        let dir = self.directory(dir)?;
         let mut out = Vec::new();
-        for (i, entry) in self.entries.iter().enumerate() {
+        for (i, entry) in self.children(dir) {
             let name = Self::entry_name(entry).ok_or(VfsError::Io)?;
-            out.push(VfsDirEntry::new(name, Self::entry_node(i), NodeType::File)?);
+            let node_type = if entry.is_directory() {
+                NodeType::Directory
+            } else {
+                NodeType::File
+            };
+            out.push(VfsDirEntry::new(name, Self::entry_node(i), node_type)?);
         }
         Ok(out)
     }
+
+    fn create(&mut self, parent: NodeId, name: &str) -> Result<NodeId, VfsError> {
+        self.create_entry(parent, name, 0)
+    }
+
+    fn write(&mut self, node: NodeId, offset: u64, data: &[u8]) -> Result<usize, VfsError> {
+        let index = self.file_index(node)?;
+        let end = offset + data.len() as u64;
+        if end > u32::MAX as u64 {
+            return Err(VfsError::NoSpace);
+        }
+
+        // Refuse sizes the data area can never hold before touching the disk.
+        let blocks = blocks_for_size(end as usize);
+        if blocks > self.superblock.total_blocks - self.superblock.data_start_block {
+            return Err(VfsError::NoSpace);
+        }
+        self.reserve(index, blocks)?;
+        let start_block = self.slots[index].file_start_block as u64;
+
+        // Writing past the end leaves no hole: the gap is zeroed one sector at a time.
+        let zeros = [0_u8; BLOCK_SIZE];
+        let mut cursor = self.slots[index].file_size as usize;
+        while cursor < offset as usize {
+            let len = core::cmp::min(offset as usize - cursor, BLOCK_SIZE - cursor % BLOCK_SIZE);
+            self.store_data(start_block, cursor, &zeros[..len])?;
+            cursor += len;
+        }
+        self.store_data(start_block, offset as usize, data)?;
+
+        let entry = &mut self.slots[index];
+        entry.file_size = entry.file_size.max(end as u32);
+        entry.modified = (self.clock)();
+        self.store_entry(index)?;
+        Ok(data.len())
+    }
+
+    fn truncate(&mut self, node: NodeId, size: u64) -> Result<(), VfsError> {
+        let index = self.file_index(node)?;
+        let current = self.slots[index].file_size as u64;
+        if size > current {
+            // An empty write at the new size zero-fills everything up to it.
+            return self.write(node, size, &[]).map(|_| ());
+        }
+
+        let entry = &mut self.slots[index];
+        entry.file_size = size as u32;
+        entry.file_block_count = blocks_for_size(size as usize);
+        entry.modified = (self.clock)();
+        self.store_entry(index)
+    }
+
+    fn create_dir(&mut self, parent: NodeId, name: &str) -> Result<NodeId, VfsError> {
+        self.create_entry(parent, name, FLAG_DIRECTORY)
+    }
+
+    fn remove(&mut self, node: NodeId) -> Result<(), VfsError> {
+        let index = self.entry_index(node)?;
+        let entry = self.slots[index];
+        if entry.is_directory() && self.children(node.0 as u32).next().is_some() {
+            return Err(VfsError::NotEmpty);
+        }
+
+        // Data blocks need no bookkeeping: space is free wherever no entry's extent lies.
+        self.slots[index] = DirEntry::decode(&[0_u8; DIR_ENTRY_SIZE]);
+        self.store_entry(index)?;
+        self.store_superblock()?;
+        self.touch_dir(entry.parent)
+    }
+
+    fn rename(&mut self, node: NodeId, parent: NodeId, name: &str) -> Result<(), VfsError> {
+        let index = self.entry_index(node)?;
+        let new_parent = self.directory(parent)?;
+        Self::check_name(name)?;
+        if self.is_within(new_parent, node) {
+            return Err(VfsError::InvalidPath);
+        }
+        match self.find_child(new_parent, name) {
+            Some(existing) if existing != index => return Err(VfsError::AlreadyExists),
+            _ => {}
+        }
+
+        let renamed = DirEntry::new(name, 0, 0, 0).map_err(map_fs_error)?;
+        let old_parent = self.slots[index].parent;
+        let entry = &mut self.slots[index];
+        entry.name = renamed.name;
+        entry.name_len = renamed.name_len;
+        entry.parent = new_parent;
+        self.store_entry(index)?;
+        self.touch_dir(old_parent)?;
+        if new_parent != old_parent {
+            self.touch_dir(new_parent)?;
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -187,9 +487,9 @@ mod tests {
     use simplefs_core::{
         blocks_for_size, dir_blocks_for_entries, DirEntry, Superblock, BLOCK_SIZE, DIR_ENTRY_SIZE,
     };
-    use simplefs_tool::build_image_from_paths;
+    use simplefs_tool::{build_image_from_paths, build_image_with_spare};
 
-    use crate::fs::vfs::FileSystem;
+    use crate::fs::vfs::{resolve_path, FileSystem, NodeType, VfsError};
     use crate::storage::block::{BlockDevice, BlockError};
 
     use super::SimpleFs;
@@ -207,6 +507,12 @@ mod tests {
             out.copy_from_slice(s);
             Ok(())
         }
+
+        fn write_sector(&mut self, lba: u64, data: &[u8]) -> Result<(), BlockError> {
+            let s = self.sectors.get_mut(lba as usize).ok_or(BlockError::Unsupported)?;
+            s.copy_from_slice(data);
+            Ok(())
+        }
     }
 
     #[test]
@@ -274,4 +580,161 @@ mod tests {
 
         let _ = fs::remove_dir_all(dir);
     }
+
+    fn read_all(fs: &SimpleFs<MemDisk>, name: &str) -> Vec<u8> {
+        let node = fs.lookup(fs.root(), name).expect("lookup file");
+        let mut out = vec![0_u8; fs.metadata(node).expect("metadata").size as usize];
+        let read = fs.read(node, 0, &mut out).expect("read");
+        assert_eq!(read, out.len());
+        out
+    }
+
+    #[test]
+    fn creates_writes_and_grows_files() {
+        let dir = temp_path("writable");
+        fs::create_dir_all(&dir).expect("create dir");
+        let first = dir.join("a.txt");
+        let second = dir.join("b.txt");
+        fs::write(&first, b"first").expect("write a");
+        fs::write(&second, b"second").expect("write b");
+
+        let image = build_image_with_spare(&[first, second], 2, 8).expect("build image");
+        let mut fs = SimpleFs::mount(MemDisk { sectors: sectors_from_image(&image) }).expect("mount");
+
+        // a.txt is followed directly by b.txt, so growing it past one block moves it.
+        let a = fs.lookup(fs.root(), "a.txt").expect("lookup a");
+        let long = vec![b'x'; BLOCK_SIZE];
+        fs.write(a, 5, &long).expect("grow a");
+        assert_eq!(fs.read(a, 0, &mut [0_u8; 8]).expect("read"), 8);
+        assert_eq!(&read_all(&fs, "a.txt")[..6], b"firstx");
+        assert_eq!(read_all(&fs, "b.txt"), b"second");
+
+        let log = fs.create(fs.root(), "log.txt").expect("create");
+        assert_eq!(fs.create(fs.root(), "log.txt"), Err(VfsError::AlreadyExists));
+        fs.write(log, 0, b"one\n").expect("write");
+        fs.write(log, 4, b"two\n").expect("append");
+        fs.truncate(log, 2).expect("shrink");
+        fs.write(log, 4, b"!").expect("write past end");
+        assert_eq!(read_all(&fs, "log.txt"), b"on\0\0!");
+
+        // Everything is persisted: a fresh mount of the same disk sees the same files.
+        let fs = SimpleFs::mount(fs.device.into_inner()).expect("remount");
+        assert_eq!(fs.entry_count(), 3);
+        assert_eq!(fs.superblock().dir_entry_count, 3);
+        assert_eq!(read_all(&fs, "log.txt"), b"on\0\0!");
+        assert_eq!(read_all(&fs, "b.txt"), b"second");
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn builds_directory_tree_with_timestamps() {
+        let dir = temp_path("tree");
+        fs::create_dir_all(&dir).expect("create dir");
+        let file = dir.join("a.txt");
+        fs::write(&file, b"a").expect("write a");
+
+        let image = build_image_with_spare(&[file], 8, 8).expect("build image");
+        let mut fs = SimpleFs::mount(MemDisk { sectors: sectors_from_image(&image) })
+            .expect("mount")
+            .with_clock(|| 1_700_000_000);
+        let root = fs.root();
+        let docs = fs.create_dir(root, "docs").expect("mkdir docs");
+        let old = fs.create_dir(docs, "old").expect("mkdir old");
+        let intro = fs.create(docs, "intro.txt").expect("create intro");
+        fs.write(intro, 0, b"intro").expect("write intro");
+        assert_eq!(fs.create(intro, "x"), Err(VfsError::NotDirectory));
+        assert_eq!(fs.create_dir(root, ".."), Err(VfsError::InvalidPath));
+
+        // Names only have to be unique within their directory.
+        fs.create(old, "a.txt").expect("create nested a");
+        assert_eq!(fs.list(root).expect("list root").len(), 2);
+        let entries = fs.list(docs).expect("list docs");
+        let names: Vec<_> = entries.iter().map(|entry| (entry.name(), entry.node_type)).collect();
+        assert_eq!(names, [("old", NodeType::Directory), ("intro.txt", NodeType::File)]);
+
+        let meta = fs.metadata(intro).expect("metadata");
+        assert_eq!((meta.size, meta.created, meta.modified), (5, 1_700_000_000, 1_700_000_000));
+        assert_eq!(fs.metadata(docs).expect("metadata").size, 2);
+        assert_eq!(fs.read(docs, 0, &mut [0_u8; 4]), Err(VfsError::NotFile));
+
+        // Moving a directory below itself is refused, moving a file keeps its data.
+        assert_eq!(fs.rename(docs, old, "loop"), Err(VfsError::InvalidPath));
+        assert_eq!(fs.rename(intro, root, "a.txt"), Err(VfsError::AlreadyExists));
+        fs.rename(intro, old, "moved.txt").expect("move intro");
+        assert_eq!(fs.remove(docs), Err(VfsError::NotEmpty));
+        assert_eq!(fs.remove(root), Err(VfsError::InvalidPath));
+
+        let mut fs = SimpleFs::mount(fs.device.into_inner()).expect("remount");
+        let moved = resolve_path(&fs, "/docs/old/moved.txt").expect("resolve moved");
+        let mut out = [0_u8; 8];
+        assert_eq!(fs.read(moved, 0, &mut out).expect("read"), 5);
+        assert_eq!(&out[..5], b"intro");
+
+        let nested = resolve_path(&fs, "/docs/old/a.txt").expect("resolve nested");
+        fs.remove(nested).expect("rm nested");
+        fs.remove(moved).expect("rm moved");
+        fs.remove(old).expect("rmdir old");
+        fs.remove(docs).expect("rmdir docs");
+        assert_eq!(fs.entry_count(), 1);
+        assert_eq!(fs.superblock().dir_entry_count, 1);
+        assert_eq!(fs.lookup(fs.root(), "docs"), Err(VfsError::NotFound));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn reports_full_disk_and_directory() {
+        let dir = temp_path("full");
+        fs::create_dir_all(&dir).expect("create dir");
+        let file = dir.join("a.txt");
+        fs::write(&file, b"a").expect("write a");
+
+        let image = build_image_with_spare(&[file], 0, 1).expect("build image");
+        let mut fs = SimpleFs::mount(MemDisk { sectors: sectors_from_image(&image) }).expect("mount");
+        let a = fs.lookup(fs.root(), "a.txt").expect("lookup a");
+        assert_eq!(fs.write(a, 0, &[0_u8; 3 * BLOCK_SIZE]), Err(VfsError::NoSpace));
+        fs.write(a, 0, &[1_u8; 2 * BLOCK_SIZE]).expect("grow in place");
+
+        // The single directory block holds eight slots.
+        for i in 1..8 {
+            fs.create(fs.root(), &format!("f{i}")).expect("create");
+        }
+        assert_eq!(fs.create(fs.root(), "f8"), Err(VfsError::NoSpace));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn zero_fills_gaps_without_buffering_them() {
+        let dir = temp_path("gap");
+        fs::create_dir_all(&dir).expect("create dir");
+        let file = dir.join("a.txt");
+        fs::write(&file, b"abc").expect("write a");
+
+        let image = build_image_with_spare(&[file], 0, 4).expect("build image");
+        let mut fs = SimpleFs::mount(MemDisk { sectors: sectors_from_image(&image) }).expect("mount");
+        let a = fs.lookup(fs.root(), "a.txt").expect("lookup a");
+
+        // Like `dd … seek=20000`: far beyond the disk, refused before any gap is written.
+        assert_eq!(fs.write(a, 20_000 * BLOCK_SIZE as u64, b"x"), Err(VfsError::NoSpace));
+        assert_eq!(fs.truncate(a, u32::MAX as u64), Err(VfsError::NoSpace));
+        assert_eq!(read_all(&fs, "a.txt"), b"abc");
+
+        // A gap over several blocks ends up as zeros on disk.
+        fs.write(a, 3 * BLOCK_SIZE as u64 + 1, b"!").expect("write past end");
+        let data = read_all(&fs, "a.txt");
+        assert_eq!(data.len(), 3 * BLOCK_SIZE + 2);
+        assert_eq!(&data[..3], b"abc");
+        assert!(data[3..3 * BLOCK_SIZE + 1].iter().all(|b| *b == 0));
+        assert_eq!(data[3 * BLOCK_SIZE + 1], b'!');
+
+        fs.truncate(a, 1).expect("shrink");
+        fs.truncate(a, 2 * BLOCK_SIZE as u64).expect("grow");
+        let data = read_all(&fs, "a.txt");
+        assert_eq!(&data[..1], b"a");
+        assert!(data[1..].iter().all(|b| *b == 0));
+
+        let _ = fs::remove_dir_all(dir);
+    }
 }
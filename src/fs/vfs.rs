@@ -7,10 +7,29 @@ pub enum VfsError {
     InvalidPath,
     NotDirectory,
     NotFile,
+    NotEmpty,
+    NoSpace,
     Io,
     Unsupported,
 }
 
+impl VfsError {
+    pub fn describe(self) -> &'static [u8] {
+        match self {
+            VfsError::NotFound => b"no such file or directory",
+            VfsError::AlreadyExists => b"file exists",
+            VfsError::InvalidPath => b"invalid path",
+            VfsError::NotDirectory => b"not a directory",
+            VfsError::NotFile => b"is a directory",
+            VfsError::NotEmpty => b"directory not empty",
+            VfsError::NoSpace => b"no space left on device",
+            VfsError::Io => b"i/o error",
+            // Only the write operations have unsupported defaults.
+            VfsError::Unsupported => b"read-only file system",
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum NodeType {
     File,
@@ -23,7 +42,11 @@ pub struct NodeId(pub u64);
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Metadata {
     pub node_type: NodeType,
+    // Bytes for files, entries for directories.
     pub size: u64,
+    // Seconds since the Unix epoch, 0 if unknown.
+    pub created: u64,
+    pub modified: u64,
 }
 
 pub trait FileSystem {
@@ -32,6 +55,33 @@ pub trait FileSystem {
     fn metadata(&self, node: NodeId) -> Result<Metadata, VfsError>;
     fn read(&self, node: NodeId, offset: u64, out: &mut [u8]) -> Result<usize, VfsError>;
     fn list(&self, dir: NodeId) -> Result<Vec<DirEntry>, VfsError>;
+
+    // Writable file systems override these; read-only ones keep the defaults.
+    fn create(&mut self, _parent: NodeId, _name: &str) -> Result<NodeId, VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    fn write(&mut self, _node: NodeId, _offset: u64, _data: &[u8]) -> Result<usize, VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    fn truncate(&mut self, _node: NodeId, _size: u64) -> Result<(), VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    fn create_dir(&mut self, _parent: NodeId, _name: &str) -> Result<NodeId, VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    // Removes a file or an empty directory.
+    fn remove(&mut self, _node: NodeId) -> Result<(), VfsError> {
+        Err(VfsError::Unsupported)
+    }
+
+    // Moves `node` into `parent` under `name`; the target name must not exist yet.
+    fn rename(&mut self, _node: NodeId, _parent: NodeId, _name: &str) -> Result<(), VfsError> {
+        Err(VfsError::Unsupported)
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -81,6 +131,18 @@ pub fn split_path(path: &str) -> Result<Vec<&str>, VfsError> {
     Ok(out)
 }
 
+// Splits a normalized absolute path into its parent directory and last component.
+pub fn split_parent(path: &str) -> Result<(&str, &str), VfsError> {
+    let trimmed = path.trim_end_matches('/');
+    let slash = trimmed.rfind('/').ok_or(VfsError::InvalidPath)?;
+    let name = &trimmed[slash + 1..];
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(VfsError::InvalidPath);
+    }
+    let parent = if slash == 0 { "/" } else { &trimmed[..slash] };
+    Ok((parent, name))
+}
+
 pub fn resolve_path<F: FileSystem + ?Sized>(fs: &F, path: &str) -> Result<NodeId, VfsError> {
     let parts = split_path(path)?;
     let mut current = fs.root();
@@ -101,7 +163,7 @@ mod tests {
     use alloc::vec;
     use alloc::vec::Vec;
 
-    use super::{resolve_path, split_path, DirEntry, FileSystem, Metadata, NodeId, NodeType, VfsError};
+    use super::{resolve_path, split_parent, split_path, DirEntry, FileSystem, Metadata, NodeId, NodeType, VfsError};
 
     struct MockFs;
 
@@ -123,10 +185,14 @@ mod tests {
                 1 | 2 => Ok(Metadata {
                     node_type: NodeType::Directory,
                     size: 0,
+                    created: 0,
+                    modified: 0,
                 }),
                 3 => Ok(Metadata {
                     node_type: NodeType::File,
                     size: 10,
+                    created: 0,
+                    modified: 0,
                 }),
                 _ => Err(VfsError::NotFound),
             }
@@ -152,6 +218,15 @@ mod tests {
         assert_eq!(split_path("etc/hosts"), Err(VfsError::InvalidPath));
     }
 
+    #[test]
+    fn splits_parent_and_name() {
+        assert_eq!(split_parent("/etc/hosts"), Ok(("/etc", "hosts")));
+        assert_eq!(split_parent("/motd.txt"), Ok(("/", "motd.txt")));
+        assert_eq!(split_parent("/docs/"), Ok(("/", "docs")));
+        assert_eq!(split_parent("/"), Err(VfsError::InvalidPath));
+        assert_eq!(split_parent("/a/.."), Err(VfsError::InvalidPath));
+    }
+
     #[test]
     fn resolves_existing_path() {
         let fs = MockFs;
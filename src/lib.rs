@@ -3,11 +3,18 @@
 
 extern crate alloc;
 
-mod arch;
+#[macro_use]
 mod console;
+#[macro_use]
+mod log;
+
+mod arch;
+mod debug;
 mod fs;
 mod memory;
+mod pci;
 mod storage;
+mod sync;
 #[cfg(eres_kernel)]
 mod panic_handler;
 mod shell;
@@ -16,31 +23,67 @@ mod shell;
 #[unsafe(no_mangle)]
 pub extern "C" fn kernel_main(boot_info_ptr: *const memory::bootinfo::BootInfoRaw) -> ! {
     console::clear();
+    let serial_ready = console::serial::init();
     console::write_line(b"Eres OS: Rust kernel reached long mode.");
+    arch::x86_64::gdt::init();
+    // Früh laden, damit auch Fehler beim Speicheraufbau als Exception gemeldet werden. Die
+    // Interrupts selbst bleiben bis nach den Smoke-Tests gesperrt; nur das Warten auf einen
+    // Laufwerks-IRQ gibt sie kurz frei.
+    arch::x86_64::interrupts::init();
+    if serial_ready {
+        kinfo!("COM1 serial console ready");
+    } else {
+        kwarn!("COM1 not present");
+    }
     memory::bootinfo::set_boot_info(boot_info_ptr);
-    if let Some(info) = memory::bootinfo::boot_info() {
-        if info.entries().is_empty() {
-            console::write_line(b"Eres OS: boot info map empty.");
-        } else {
-            console::write_line(b"Eres OS: boot info map OK.");
-            memory::frame_allocator::init_from_memory_map(info.entries());
-            if memory::frame_allocator::alloc_frame().is_some() {
-                console::write_line(b"Eres OS: frame allocator OK.");
-            } else {
-                console::write_line(b"Eres OS: frame allocator empty.");
-            }
-            memory::heap::init();
-            heap_smoke_test();
-            block_device_smoke_test();
-            vm_smoke_test();
-            fs_smoke_test();
+    if init_physical_memory() {
+        memory::paging::init();
+        kinfo!("higher half only, null page unmapped");
+        if let Some(stack_top) = memory::stack::map_kernel_stack() {
+            kinfo!("guarded kernel stack OK");
+            arch::x86_64::switch_stack(stack_top, kernel_main_on_stack);
         }
-    } else {
-        console::write_line(b"Eres OS: boot info invalid.");
+        kerror!("guarded kernel stack FAILED");
+    }
+    kernel_main_on_stack()
+}
+
+/// Liest die Speicherkarte ein und startet den Frame-Allocator.
+#[cfg(eres_kernel)]
+fn init_physical_memory() -> bool {
+    let Some(info) = memory::bootinfo::boot_info() else {
+        kerror!("boot info invalid");
+        return false;
+    };
+    if info.entries().is_empty() {
+        kerror!("boot info map empty");
+        return false;
+    }
+
+    kinfo!("boot info map OK");
+    let untracked = memory::frame_allocator::init_from_memory_map(info.entries());
+    if untracked > 0 {
+        kwarn!("frames beyond bitmap ignored: {untracked}");
+    }
+    frame_allocator_smoke_test();
+    true
+}
+
+/// Zweiter Teil des Starts, läuft (wenn möglich) auf dem Kernel-Stack mit Guard Page.
+#[cfg(eres_kernel)]
+extern "C" fn kernel_main_on_stack() -> ! {
+    if memory::frame_allocator::stats().is_some() && memory::heap::init() {
+        heap_smoke_test();
+        demand_paging_smoke_test();
+        pci::init();
+        storage::registry::probe();
+        block_device_smoke_test();
+        vm_smoke_test();
+        paging_smoke_test();
+        fs_smoke_test();
     }
-    arch::x86_64::interrupts::init();
     arch::x86_64::enable_interrupts();
-    console::write_line(b"Eres OS: IDT/PIC initialized.");
+    kinfo!("IDT/PIC initialized");
 
     #[cfg(feature = "qemu-test")]
     {
@@ -63,7 +106,7 @@ pub extern "C" fn kernel_main(boot_info_ptr: *const memory::bootinfo::BootInfoRa
 
 #[cfg(eres_kernel)]
 fn keyboard_smoke_test() {
-    use arch::x86_64::keyboard;
+    use arch::x86_64::{keyboard, keymap};
 
     keyboard::clear_buffer();
     for scancode in [0x23_u8, 0x12, 0x26, 0x26, 0x18] {
@@ -82,22 +125,48 @@ fn keyboard_smoke_test() {
         ok = false;
     }
 
-    keyboard::inject_scancode(0x15);
-    if keyboard::try_read_char() != Some(b'z') {
-        ok = false;
-    }
-
-    keyboard::inject_scancode(0x2c);
-    if keyboard::try_read_char() != Some(b'y') {
-        ok = false;
+    // Z und Y tauschen zwischen QWERTZ und QWERTY die Plätze.
+    for (layout, expected) in [(&keymap::DE, b"zy"), (&keymap::US, b"yz")] {
+        keyboard::set_keymap(layout);
+        keyboard::inject_scancode(0x15);
+        keyboard::inject_scancode(0x2c);
+        for expected in expected {
+            if keyboard::try_read_char() != Some(*expected) {
+                ok = false;
+            }
+        }
     }
+    keyboard::set_keymap(&keymap::DE);
 
     keyboard::clear_buffer();
 
     if ok {
-        console::write_line(b"Eres OS: keyboard decode OK.");
+        kinfo!("keyboard decode OK");
+    } else {
+        kerror!("keyboard decode FAILED");
+    }
+}
+
+#[cfg(eres_kernel)]
+fn frame_allocator_smoke_test() {
+    use memory::frame_allocator::{self, FRAME_SIZE};
+
+    let Some(frame) = frame_allocator::alloc_frame() else {
+        kerror!("frame allocator empty");
+        return;
+    };
+
+    let before = frame_allocator::stats();
+    let freed = frame_allocator::free_frame(frame).is_ok();
+    let reused = frame_allocator::alloc_frame() == Some(frame);
+    let block = frame_allocator::alloc_frames(4, 4 * FRAME_SIZE);
+    let aligned = block.is_some_and(|b| b.start.is_multiple_of(4 * FRAME_SIZE));
+    let block_freed = block.is_some_and(|b| frame_allocator::free_frames(b, 4).is_ok());
+
+    if freed && reused && aligned && block_freed && frame_allocator::stats() == before {
+        kinfo!("frame allocator OK");
     } else {
-        console::write_line(b"Eres OS: keyboard decode FAILED.");
+        kerror!("frame allocator FAILED");
     }
 }
 
@@ -111,29 +180,43 @@ fn heap_smoke_test() {
     values.push(3_u8);
 
     if values.as_slice() == [1, 2, 3] {
-        console::write_line(b"Eres OS: heap allocator OK.");
+        kinfo!("heap allocator OK");
     } else {
-        console::write_line(b"Eres OS: heap allocator FAILED.");
+        kerror!("heap allocator FAILED");
     }
 }
 
+#[cfg(eres_kernel)]
+fn demand_paging_smoke_test() {
+    use alloc::vec;
+
+    const PAGES: usize = 16;
+
+    let before = memory::fault::demand_mapped_pages();
+    let buffer = vec![0xa5_u8; PAGES * 4096];
+    let faulted = memory::fault::demand_mapped_pages() - before;
+
+    if faulted >= PAGES as u64 && buffer.iter().all(|byte| *byte == 0xa5) {
+        kinfo!("demand paging OK");
+    } else {
+        kerror!("demand paging FAILED");
+    }
+}
+
+/// Liest Sektor 0 aller erkannten Laufwerke; das Bootlaufwerk trägt die Signatur 0x55AA.
 #[cfg(eres_kernel)]
 fn block_device_smoke_test() {
-    use storage::ata_pio::AtaPio;
     use storage::block::BlockDevice;
 
-    let mut dev = AtaPio::primary_master();
     let mut sector = [0_u8; 512];
-    match dev.read_sector(0, &mut sector) {
-        Ok(()) if sector[510] == 0x55 && sector[511] == 0xAA => {
-            console::write_line(b"Eres OS: block device OK.");
-        }
-        Ok(()) => {
-            console::write_line(b"Eres OS: block device invalid signature.");
-        }
-        Err(_) => {
-            console::write_line(b"Eres OS: block device read failed.");
-        }
+    let boot_drive = storage::registry::drives().into_iter().find(|drive| {
+        drive.open().read_sector(0, &mut sector).is_ok()
+            && sector[510] == 0x55
+            && sector[511] == 0xAA
+    });
+    match boot_drive {
+        Some(drive) => kinfo!("block device OK ({})", drive.name()),
+        None => kerror!("block device FAILED (no drive with boot signature)"),
     }
 }
 
@@ -143,26 +226,61 @@ fn vm_smoke_test() {
     let mapper = memory::vm::boot_mapper();
     let entry0 = mapper.entry(0);
     if entry0.is_present() {
-        console::write_line(b"Eres OS: vm mapper OK.");
+        kinfo!("vm mapper OK");
     } else {
-        console::write_line(b"Eres OS: vm mapper invalid.");
+        kerror!("vm mapper invalid");
     }
 }
 
 #[cfg(eres_kernel)]
-fn fs_smoke_test() {
-    use fs::simplefs::SimpleFs;
-    use storage::ata_pio::AtaPio;
-    use storage::cache::CachedBlockDevice;
+fn paging_smoke_test() {
+    use memory::frame_allocator;
+    use memory::paging::{self, GlobalFrameSource};
+    use memory::vm::{FLAG_WRITABLE, Page, PhysAddr, VirtAddr, phys_to_virt};
+
+    const TEST_PAGE: u64 = 0x0000_7000_0000_0000;
+
+    let Some(frame) = frame_allocator::alloc_frame() else {
+        kwarn!("paging test skipped (no frame)");
+        return;
+    };
+
+    let mut mapper = paging::active_mapper();
+    let page = Page::new(VirtAddr(TEST_PAGE));
+    let phys = PhysAddr(frame.start);
+    let mut ok = mapper
+        .map_4k(page, phys, FLAG_WRITABLE, &mut GlobalFrameSource)
+        .is_ok();
+
+    if ok {
+        let virt_ptr = (TEST_PAGE + 0x10) as *mut u64;
+        let phys_ptr = (phys_to_virt(phys).0 + 0x10) as *const u64;
+        unsafe {
+            virt_ptr.write_volatile(0x5a5a_1234);
+            ok &= phys_ptr.read_volatile() == 0x5a5a_1234;
+        }
+        ok &= mapper.translate(VirtAddr(TEST_PAGE + 0x10)) == Some(PhysAddr(frame.start + 0x10));
+        ok &= mapper.unmap_4k(page) == Ok(phys);
+        ok &= mapper.translate(VirtAddr(TEST_PAGE)).is_none();
+    }
+    let _ = frame_allocator::free_frame(frame);
 
-    let dev = CachedBlockDevice::new(AtaPio::primary_slave(), 16);
-    match SimpleFs::mount(dev) {
-        Ok(fs) => {
+    if ok {
+        kinfo!("4-level paging OK");
+    } else {
+        kerror!("4-level paging FAILED");
+    }
+}
+
+#[cfg(eres_kernel)]
+fn fs_smoke_test() {
+    match fs::simplefs::mount_registered() {
+        Some((name, fs)) => {
             let _ = fs.superblock();
-            console::write_line(b"Eres OS: simplefs mounted.");
+            kinfo!("simplefs mounted ({name})");
         }
-        Err(_) => {
-            console::write_line(b"Eres OS: simplefs not present.");
+        None => {
+            kwarn!("simplefs not present (inspect the disks with lsblk and hexdump)");
         }
     }
 }
@@ -0,0 +1,266 @@
+//! Ringpuffer für Kernel-Meldungen (`dmesg`).
+//!
+//! Jeder Log-Eintrag landet hier unabhängig davon, auf welchen Kanälen er ausgegeben wurde.
+//! Zusätzlich werden normale Konsolenzeilen mitgeschrieben, bis die Shell startet; danach
+//! würden sonst Kommandoausgaben (auch die von `dmesg` selbst) den Puffer füllen.
+//!
+//! Einträge haben feste Größe; ist der Puffer voll, wird der älteste überschrieben.
+
+use core::cell::UnsafeCell;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use super::Level;
+use crate::arch;
+use crate::arch::x86_64::pit;
+
+/// Anzahl der Einträge im Kernel-Ringpuffer.
+pub const KMSG_ENTRIES: usize = 256;
+/// Maximale Textlänge je Eintrag; längere Zeilen werden abgeschnitten.
+pub const KMSG_TEXT_LEN: usize = 120;
+
+/// Zeilenpuffer fester Größe, der überlange Eingaben abschneidet.
+#[derive(Clone, Copy)]
+pub struct LineBuffer {
+    bytes: [u8; KMSG_TEXT_LEN],
+    len: usize,
+}
+
+impl LineBuffer {
+    pub const fn new() -> Self {
+        Self {
+            bytes: [0; KMSG_TEXT_LEN],
+            len: 0,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    pub fn push_bytes(&mut self, bytes: &[u8]) {
+        let count = bytes.len().min(KMSG_TEXT_LEN - self.len);
+        self.bytes[self.len..self.len + count].copy_from_slice(&bytes[..count]);
+        self.len += count;
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl Write for LineBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_bytes(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Ein gespeicherter Eintrag.
+#[derive(Clone, Copy)]
+pub struct LogEntry {
+    /// Fortlaufende Nummer, auch über überschriebene Einträge hinweg.
+    pub seq: u64,
+    pub ticks: u64,
+    pub level: Level,
+    pub text: LineBuffer,
+}
+
+/// Ringpuffer mit `N` Einträgen.
+pub struct LogRing<const N: usize> {
+    entries: [Option<LogEntry>; N],
+    /// Nummer des ältesten nicht verworfenen Eintrags (nach `clear`).
+    first_seq: u64,
+    next_seq: u64,
+}
+
+impl<const N: usize> LogRing<N> {
+    pub const fn new() -> Self {
+        Self {
+            entries: [None; N],
+            first_seq: 0,
+            next_seq: 0,
+        }
+    }
+
+    pub fn push(&mut self, ticks: u64, level: Level, text: &[u8]) {
+        let mut line = LineBuffer::new();
+        line.push_bytes(text);
+        let seq = self.next_seq;
+        self.entries[(seq % N as u64) as usize] = Some(LogEntry {
+            seq,
+            ticks,
+            level,
+            text: line,
+        });
+        self.next_seq += 1;
+    }
+
+    /// Liefert den ältesten noch vorhandenen Eintrag mit Nummer `>= seq`.
+    pub fn read_from(&self, seq: u64) -> Option<LogEntry> {
+        let oldest = self.next_seq.saturating_sub(N as u64).max(self.first_seq);
+        let seq = seq.max(oldest);
+        if seq >= self.next_seq {
+            return None;
+        }
+        self.entries[(seq % N as u64) as usize]
+    }
+
+    /// Verwirft alle Einträge; die Nummerierung läuft weiter.
+    pub fn clear(&mut self) {
+        self.entries = [None; N];
+        self.first_seq = self.next_seq;
+    }
+}
+
+/// Syslog-Priorität eines Levels (wie in `/dev/kmsg`).
+fn syslog_priority(level: Level) -> u8 {
+    match level {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    }
+}
+
+/// Formatiert einen Eintrag im Zeilenformat von Linux' `/dev/kmsg`:
+/// `<priorität>,<nummer>,<mikrosekunden>,-;<text>`.
+///
+/// Genutzt von `dmesg -r`; eine künftige procfs-Datei `/proc/kmsg` kann die Einträge über
+/// [`read_from`] durchlaufen und hiermit formatieren.
+pub fn write_kmsg_line(out: &mut impl Write, entry: &LogEntry) -> fmt::Result {
+    let micros = entry.ticks * 1_000_000 / pit::PIT_TARGET_HZ as u64;
+    write!(
+        out,
+        "{},{},{},-;",
+        syslog_priority(entry.level),
+        entry.seq,
+        micros
+    )?;
+    // Wie `from_utf8_lossy`: Gültiges UTF-8 bleibt erhalten, ungültige Bytes (etwa eine beim
+    // Kürzen abgeschnittene Sequenz) werden zu U+FFFD.
+    for chunk in entry.text.as_bytes().utf8_chunks() {
+        out.write_str(chunk.valid())?;
+        if !chunk.invalid().is_empty() {
+            out.write_char(char::REPLACEMENT_CHARACTER)?;
+        }
+    }
+    out.write_char('\n')
+}
+
+struct RingCell(UnsafeCell<LogRing<KMSG_ENTRIES>>);
+unsafe impl Sync for RingCell {}
+
+struct PendingCell(UnsafeCell<LineBuffer>);
+unsafe impl Sync for PendingCell {}
+
+static KMSG: RingCell = RingCell(UnsafeCell::new(LogRing::new()));
+static CONSOLE_LINE: PendingCell = PendingCell(UnsafeCell::new(LineBuffer::new()));
+static CAPTURE_CONSOLE: AtomicBool = AtomicBool::new(true);
+
+fn with_ring<T>(f: impl FnOnce(&mut LogRing<KMSG_ENTRIES>) -> T) -> T {
+    let interrupts_were_enabled = arch::x86_64::save_and_disable_interrupts();
+    let result = f(unsafe { &mut *KMSG.0.get() });
+    arch::x86_64::restore_interrupts(interrupts_were_enabled);
+    result
+}
+
+/// Speichert einen Eintrag mit aktuellem Zeitstempel.
+pub fn push(level: Level, text: &[u8]) {
+    let ticks = pit::ticks();
+    with_ring(|ring| ring.push(ticks, level, text));
+}
+
+/// Siehe [`LogRing::read_from`].
+pub fn read_from(seq: u64) -> Option<LogEntry> {
+    with_ring(|ring| ring.read_from(seq))
+}
+
+pub fn clear() {
+    with_ring(|ring| ring.clear());
+}
+
+/// Schaltet das Mitschreiben normaler Konsolenzeilen ein oder aus.
+pub fn set_console_capture(enabled: bool) {
+    CAPTURE_CONSOLE.store(enabled, Ordering::Relaxed);
+}
+
+/// Von der Konsole aufgerufen: sammelt Ausgaben zeilenweise als `info`-Einträge.
+pub fn capture_console(bytes: &[u8]) {
+    if !CAPTURE_CONSOLE.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let interrupts_were_enabled = arch::x86_64::save_and_disable_interrupts();
+    let line = unsafe { &mut *CONSOLE_LINE.0.get() };
+    for chunk in bytes.split_inclusive(|byte| *byte == b'\n') {
+        match chunk.split_last() {
+            Some((b'\n', text)) => {
+                line.push_bytes(text);
+                push(Level::Info, line.as_bytes());
+                line.clear();
+            }
+            _ => line.push_bytes(chunk),
+        }
+    }
+    arch::x86_64::restore_interrupts(interrupts_were_enabled);
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+
+    use super::{KMSG_TEXT_LEN, Level, LogRing, write_kmsg_line};
+
+    #[test]
+    fn keeps_newest_entries_when_full() {
+        let mut ring = LogRing::<3>::new();
+        for index in 0..5_u8 {
+            ring.push(u64::from(index), Level::Info, &[b'a' + index]);
+        }
+
+        let first = ring.read_from(0).expect("oldest");
+        assert_eq!(first.seq, 2);
+        assert_eq!(first.text.as_bytes(), b"c");
+        assert_eq!(ring.read_from(4).map(|e| e.text.as_bytes()[0]), Some(b'e'));
+        assert!(ring.read_from(5).is_none());
+    }
+
+    #[test]
+    fn truncates_long_lines_and_clears() {
+        let mut ring = LogRing::<2>::new();
+        ring.push(0, Level::Warn, &[b'x'; KMSG_TEXT_LEN + 10]);
+        assert_eq!(
+            ring.read_from(0).expect("entry").text.as_bytes().len(),
+            KMSG_TEXT_LEN
+        );
+
+        ring.clear();
+        assert!(ring.read_from(0).is_none());
+        ring.push(0, Level::Warn, b"again");
+        assert_eq!(ring.read_from(0).map(|e| e.seq), Some(1));
+    }
+
+    #[test]
+    fn formats_kmsg_record() {
+        let mut ring = LogRing::<4>::new();
+        ring.push(0, Level::Info, b"boot");
+        ring.push(150, Level::Error, b"memory: out of frames");
+
+        let mut out = String::new();
+        write_kmsg_line(&mut out, &ring.read_from(1).expect("entry")).expect("format");
+        assert_eq!(out, "3,1,1500000,-;memory: out of frames\n");
+    }
+
+    #[test]
+    fn keeps_utf8_text_in_kmsg_record() {
+        let mut ring = LogRing::<2>::new();
+        ring.push(0, Level::Info, "Größe: 4 MiB".as_bytes());
+        ring.push(0, Level::Info, &"Größe".as_bytes()[..3]);
+
+        let mut out = String::new();
+        write_kmsg_line(&mut out, &ring.read_from(0).expect("entry")).expect("format");
+        write_kmsg_line(&mut out, &ring.read_from(1).expect("entry")).expect("format");
+        assert_eq!(out, "6,0,0,-;Größe: 4 MiB\n6,1,0,-;Gr\u{fffd}\n");
+    }
+}
@@ -0,0 +1,389 @@
+//! Strukturiertes Kernel-Logging mit Levels, Modulfiltern und getrennten Ausgabekanälen.
+//!
+//! Einträge entstehen über [`kerror!`](crate::kerror) … [`ktrace!`](crate::ktrace). Ein Eintrag
+//! wird nur erzeugt, wenn sein Level den Filter seines Moduls passiert. Danach entscheidet für
+//! jeden Konsolenkanal (VGA, debugcon, serial) ein eigenes Maximal-Level, ob die Zeile dort
+//! erscheint.
+//!
+//! Zeilenformat: `[    1.230] info  memory::heap: Nachricht` (Sekunden seit Start laut PIT);
+//! das Level ist auf VGA und COM1 farbig.
+//! Jeder Eintrag, der den Modulfilter passiert, wird außerdem im Ringpuffer [`kmsg`] abgelegt.
+
+/// Ringpuffer für Kernel-Meldungen (`dmesg`).
+pub mod kmsg;
+
+use core::cell::UnsafeCell;
+use core::fmt::{self, Write};
+
+use crate::arch::x86_64::pit;
+use crate::console::{self, Sink};
+
+/// Maximale Anzahl gleichzeitig gesetzter Modulfilter.
+pub const MAX_MODULE_FILTERS: usize = 8;
+/// Maximale Länge eines Modulpfads in einem Filter.
+pub const MODULE_NAME_LEN: usize = 48;
+
+/// Präfix aller Modulpfade dieses Crates; wird für Anzeige und Filter entfernt.
+const CRATE_PREFIX: &str = "eres_os::";
+
+/// Schweregrad eines Log-Eintrags; kleinere Werte sind wichtiger.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub const ALL: [Level; 5] = [
+        Level::Error,
+        Level::Warn,
+        Level::Info,
+        Level::Debug,
+        Level::Trace,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        }
+    }
+
+    /// SGR-Farbcode für die farbige Ausgabe des Levels.
+    pub fn color(self) -> &'static str {
+        match self {
+            Level::Error => "91",
+            Level::Warn => "93",
+            Level::Info => "92",
+            Level::Debug => "96",
+            Level::Trace => "90",
+        }
+    }
+
+    pub fn from_name(name: &[u8]) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|level| level.name().as_bytes() == name)
+    }
+}
+
+/// Fehler beim Ändern der Log-Konfiguration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    InvalidLevel,
+    InvalidModule,
+    TooManyFilters,
+}
+
+impl ConfigError {
+    pub fn describe(self) -> &'static [u8] {
+        match self {
+            ConfigError::InvalidLevel => b"invalid level (error|warn|info|debug|trace|off)",
+            ConfigError::InvalidModule => b"invalid module path",
+            ConfigError::TooManyFilters => b"too many module filters",
+        }
+    }
+}
+
+/// Maximal-Level für ein Modul und alle seine Untermodule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ModuleFilter {
+    name: [u8; MODULE_NAME_LEN],
+    len: u8,
+    pub level: Level,
+}
+
+impl ModuleFilter {
+    pub fn name(&self) -> &[u8] {
+        &self.name[..self.len as usize]
+    }
+
+    /// Passt auf das Modul selbst und auf alles unterhalb von `modul::`.
+    fn matches(&self, module: &[u8]) -> bool {
+        let name = self.name();
+        module.starts_with(name)
+            && (module.len() == name.len() || module[name.len()..].starts_with(b"::"))
+    }
+}
+
+/// Vollständige Log-Konfiguration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LogConfig {
+    /// Level für Module ohne eigenen Filter.
+    pub default_level: Level,
+    modules: [Option<ModuleFilter>; MAX_MODULE_FILTERS],
+    /// Maximal-Level je Kanal in der Reihenfolge von [`Sink::ALL`]; `None` = aus.
+    sink_levels: [Option<Level>; Sink::ALL.len()],
+}
+
+impl LogConfig {
+    pub const fn new() -> Self {
+        Self {
+            default_level: Level::Debug,
+            modules: [None; MAX_MODULE_FILTERS],
+            sink_levels: [Some(Level::Info), Some(Level::Trace), Some(Level::Info)],
+        }
+    }
+
+    /// Maximal-Level für `module` (ohne Crate-Präfix); der längste passende Filter gewinnt.
+    pub fn module_level(&self, module: &[u8]) -> Level {
+        self.modules
+            .iter()
+            .flatten()
+            .filter(|filter| filter.matches(module))
+            .max_by_key(|filter| filter.len)
+            .map_or(self.default_level, |filter| filter.level)
+    }
+
+    pub fn sink_level(&self, sink: Sink) -> Option<Level> {
+        self.sink_levels[sink_index(sink)]
+    }
+
+    pub fn set_sink_level(&mut self, sink: Sink, level: Option<Level>) {
+        self.sink_levels[sink_index(sink)] = level;
+    }
+
+    /// Setzt den Filter für `module` oder ersetzt einen vorhandenen.
+    pub fn set_module_level(&mut self, module: &[u8], level: Level) -> Result<(), ConfigError> {
+        let module = strip_crate_prefix(module);
+        if module.is_empty()
+            || module.len() > MODULE_NAME_LEN
+            || !module
+                .iter()
+                .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b':'))
+        {
+            return Err(ConfigError::InvalidModule);
+        }
+
+        let mut name = [0_u8; MODULE_NAME_LEN];
+        name[..module.len()].copy_from_slice(module);
+        let filter = ModuleFilter {
+            name,
+            len: module.len() as u8,
+            level,
+        };
+
+        if let Some(existing) = self
+            .modules
+            .iter_mut()
+            .flatten()
+            .find(|existing| existing.name() == module)
+        {
+            existing.level = level;
+            return Ok(());
+        }
+        let slot = self
+            .modules
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .ok_or(ConfigError::TooManyFilters)?;
+        *slot = Some(filter);
+        Ok(())
+    }
+
+    pub fn module_filters(&self) -> impl Iterator<Item = &ModuleFilter> {
+        self.modules.iter().flatten()
+    }
+
+    /// Wendet eine Einstellung `<ziel> <wert>` an, wie sie das Shell-Kommando `log` annimmt.
+    ///
+    /// Ziele: `default`, ein Kanalname (`vga`, `debugcon`, `serial`) oder ein Modulpfad.
+    /// Werte: ein Level-Name, für Kanäle zusätzlich `off`.
+    pub fn apply(&mut self, target: &[u8], value: &[u8]) -> Result<(), ConfigError> {
+        if let Some(sink) = Sink::from_name(target) {
+            let level = if value == b"off" {
+                None
+            } else {
+                Some(Level::from_name(value).ok_or(ConfigError::InvalidLevel)?)
+            };
+            self.set_sink_level(sink, level);
+            return Ok(());
+        }
+
+        let level = Level::from_name(value).ok_or(ConfigError::InvalidLevel)?;
+        if target == b"default" {
+            self.default_level = level;
+            Ok(())
+        } else {
+            self.set_module_level(target, level)
+        }
+    }
+}
+
+fn sink_index(sink: Sink) -> usize {
+    Sink::ALL
+        .iter()
+        .position(|candidate| *candidate == sink)
+        .unwrap_or(0)
+}
+
+fn strip_crate_prefix(module: &[u8]) -> &[u8] {
+    module
+        .strip_prefix(CRATE_PREFIX.as_bytes())
+        .unwrap_or(module)
+}
+
+/// Schreibt den Zeilenkopf `[sssss.mmm] level `; mit `color` wird das Level per
+/// ANSI-Escape-Sequenz eingefärbt.
+pub fn write_prefix(out: &mut impl Write, ticks: u64, level: Level, color: bool) -> fmt::Result {
+    let millis = ticks * 1000 / pit::PIT_TARGET_HZ as u64;
+    write!(out, "[{:5}.{:03}] ", millis / 1000, millis % 1000)?;
+    if color {
+        write!(out, "\x1b[{}m{:<5}\x1b[0m ", level.color(), level.name())
+    } else {
+        write!(out, "{:<5} ", level.name())
+    }
+}
+
+struct ConfigCell(UnsafeCell<LogConfig>);
+unsafe impl Sync for ConfigCell {}
+
+static CONFIG: ConfigCell = ConfigCell(UnsafeCell::new(LogConfig::new()));
+
+/// Liefert eine Kopie der aktuellen Konfiguration.
+pub fn config() -> LogConfig {
+    unsafe { *CONFIG.0.get() }
+}
+
+/// Ändert die Konfiguration; Interrupts sind währenddessen gesperrt.
+pub fn update_config<T>(f: impl FnOnce(&mut LogConfig) -> T) -> T {
+    let interrupts_were_enabled = crate::arch::x86_64::save_and_disable_interrupts();
+    let result = f(unsafe { &mut *CONFIG.0.get() });
+    crate::arch::x86_64::restore_interrupts(interrupts_were_enabled);
+    result
+}
+
+/// Ziel der Log-Makros: filtert und verteilt einen Eintrag auf die Kanäle.
+#[doc(hidden)]
+pub fn record(level: Level, module_path: &str, args: fmt::Arguments<'_>) {
+    let config = config();
+    let module = module_path
+        .strip_prefix(CRATE_PREFIX)
+        .unwrap_or(module_path);
+    if level > config.module_level(module.as_bytes()) {
+        return;
+    }
+
+    let mut line = kmsg::LineBuffer::new();
+    let _ = write!(line, "{module}: {args}");
+    kmsg::push(level, line.as_bytes());
+
+    let ticks = pit::ticks();
+    for sink in Sink::ALL {
+        if config.sink_level(sink).is_some_and(|max| level <= max) {
+            let mut out = console::SinkWriter(sink);
+            let _ = write_prefix(&mut out, ticks, level, true);
+            let _ = writeln!(out, "{module}: {args}");
+        }
+    }
+}
+
+/// Log-Eintrag mit explizitem Level.
+#[macro_export]
+macro_rules! klog {
+    ($level:expr, $($arg:tt)*) => {
+        $crate::log::record($level, module_path!(), format_args!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! kerror {
+    ($($arg:tt)*) => { $crate::klog!($crate::log::Level::Error, $($arg)*) };
+}
+
+#[macro_export]
+macro_rules! kwarn {
+    ($($arg:tt)*) => { $crate::klog!($crate::log::Level::Warn, $($arg)*) };
+}
+
+#[macro_export]
+macro_rules! kinfo {
+    ($($arg:tt)*) => { $crate::klog!($crate::log::Level::Info, $($arg)*) };
+}
+
+#[macro_export]
+macro_rules! kdebug {
+    ($($arg:tt)*) => { $crate::klog!($crate::log::Level::Debug, $($arg)*) };
+}
+
+#[macro_export]
+macro_rules! ktrace {
+    ($($arg:tt)*) => { $crate::klog!($crate::log::Level::Trace, $($arg)*) };
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+
+    use super::{ConfigError, Level, LogConfig, write_prefix};
+    use crate::console::Sink;
+
+    #[test]
+    fn longest_module_filter_wins() {
+        let mut config = LogConfig::new();
+        config.apply(b"memory", b"warn").expect("memory");
+        config
+            .apply(b"eres_os::memory::heap", b"trace")
+            .expect("heap");
+
+        assert_eq!(config.module_level(b"memory"), Level::Warn);
+        assert_eq!(config.module_level(b"memory::paging"), Level::Warn);
+        assert_eq!(
+            config.module_level(b"memory::heap::kernel_heap"),
+            Level::Trace
+        );
+        assert_eq!(config.module_level(b"memoryx"), Level::Debug);
+        assert_eq!(config.module_level(b"shell"), Level::Debug);
+    }
+
+    #[test]
+    fn configures_sinks_independently() {
+        let mut config = LogConfig::new();
+        config.apply(b"vga", b"off").expect("vga");
+        config.apply(b"debugcon", b"warn").expect("debugcon");
+        config.apply(b"default", b"trace").expect("default");
+
+        assert_eq!(config.sink_level(Sink::Vga), None);
+        assert_eq!(config.sink_level(Sink::Debugcon), Some(Level::Warn));
+        assert_eq!(config.default_level, Level::Trace);
+        assert_eq!(
+            config.apply(b"vga", b"loud"),
+            Err(ConfigError::InvalidLevel)
+        );
+        assert_eq!(
+            config.apply(b"mem ory", b"info"),
+            Err(ConfigError::InvalidModule)
+        );
+    }
+
+    #[test]
+    fn limits_module_filters() {
+        let mut config = LogConfig::new();
+        for name in [b"a", b"b", b"c", b"d", b"e", b"f", b"g", b"h"] {
+            config.apply(name, b"info").expect("filter");
+        }
+        config.apply(b"a", b"error").expect("replace");
+        assert_eq!(
+            config.apply(b"i", b"info"),
+            Err(ConfigError::TooManyFilters)
+        );
+        assert_eq!(config.module_filters().count(), 8);
+    }
+
+    #[test]
+    fn formats_prefix_with_uptime() {
+        let mut out = String::new();
+        write_prefix(&mut out, 123, Level::Warn, false).expect("format");
+        assert_eq!(out, "[    1.230] warn  ");
+
+        out.clear();
+        write_prefix(&mut out, 0, Level::Error, true).expect("format");
+        assert_eq!(out, "[    0.000] \x1b[91merror\x1b[0m ");
+    }
+}
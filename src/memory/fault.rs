@@ -0,0 +1,379 @@
+//! Page-Fault-Behandlung und Demand Paging.
+//!
+//! Bereiche, die über [`register_lazy_region`] angemeldet wurden, bekommen ihre Frames erst
+//! beim ersten Zugriff. Alle anderen Page Faults sind echte Fehler; der Interrupt-Handler gibt
+//! dafür einen ausführlichen Bericht aus und hält an.
+//!
+//! Hintergrund: <https://wiki.osdev.org/Exceptions#Page_Fault>
+
+use super::paging::PAGE_SIZE_4K;
+use super::stack;
+
+/// Maximale Anzahl gleichzeitig registrierter Lazy-Bereiche.
+pub const MAX_LAZY_REGIONS: usize = 8;
+
+/// Vom Prozessor gelieferter Fehlercode eines Page Faults (Vektor 14).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PageFaultCode(pub u64);
+
+impl PageFaultCode {
+    pub const PRESENT: u64 = 1 << 0;
+    pub const WRITE: u64 = 1 << 1;
+    pub const USER: u64 = 1 << 2;
+    pub const RESERVED_BIT: u64 = 1 << 3;
+    pub const INSTRUCTION_FETCH: u64 = 1 << 4;
+
+    /// Seite war vorhanden, der Zugriff hat aber ihre Rechte verletzt.
+    pub fn is_protection_violation(self) -> bool {
+        (self.0 & Self::PRESENT) != 0
+    }
+
+    pub fn is_write(self) -> bool {
+        (self.0 & Self::WRITE) != 0
+    }
+
+    pub fn is_user(self) -> bool {
+        (self.0 & Self::USER) != 0
+    }
+
+    /// Ein reserviertes Bit in einem Tabelleneintrag war gesetzt (kaputte Page Tables).
+    pub fn is_reserved_bit(self) -> bool {
+        (self.0 & Self::RESERVED_BIT) != 0
+    }
+
+    pub fn is_instruction_fetch(self) -> bool {
+        (self.0 & Self::INSTRUCTION_FETCH) != 0
+    }
+
+    /// Art des Zugriffs als Text.
+    pub fn access(self) -> &'static [u8] {
+        if self.is_instruction_fetch() {
+            b"instruction fetch"
+        } else if self.is_write() {
+            b"write"
+        } else {
+            b"read"
+        }
+    }
+
+    /// Privilegstufe des Zugriffs als Text.
+    pub fn mode(self) -> &'static [u8] {
+        if self.is_user() { b"user" } else { b"kernel" }
+    }
+
+    /// Zustand der Zielseite als Text.
+    pub fn page_state(self) -> &'static [u8] {
+        if self.is_protection_violation() {
+            b"present (protection violation)"
+        } else {
+            b"not present"
+        }
+    }
+}
+
+/// Ein virtueller Bereich, dessen Seiten bei Bedarf mit frischen, genullten Frames hinterlegt werden.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LazyRegion {
+    pub start: u64,
+    pub end: u64,
+    /// Seiten-Flags (`FLAG_*`) für die neu angelegten Abbildungen.
+    pub flags: u64,
+}
+
+/// Gründe, warum ein Page Fault nicht aufgelöst werden konnte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultError {
+    NullPointer,
+    StackOverflow,
+    ProtectionViolation,
+    ReservedBit,
+    InstructionFetch,
+    NotMapped,
+    OutOfMemory,
+    MapFailed,
+}
+
+impl FaultError {
+    pub fn describe(self) -> &'static [u8] {
+        match self {
+            Self::NullPointer => b"null pointer dereference",
+            Self::StackOverflow => b"kernel stack overflow (guard page)",
+            Self::ProtectionViolation => b"access violates page protection",
+            Self::ReservedBit => b"reserved bit set in page table entry",
+            Self::InstructionFetch => b"instruction fetch from demand-paged data",
+            Self::NotMapped => b"address is not mapped",
+            Self::OutOfMemory => b"no free frame for demand paging",
+            Self::MapFailed => b"mapping demand page failed",
+        }
+    }
+}
+
+/// Fehler beim Registrieren eines Lazy-Bereichs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegionError {
+    Misaligned,
+    Overlap,
+    TableFull,
+}
+
+/// Tabelle der registrierten Lazy-Bereiche.
+pub struct LazyRegions {
+    regions: [Option<LazyRegion>; MAX_LAZY_REGIONS],
+}
+
+impl LazyRegions {
+    pub const fn new() -> Self {
+        Self {
+            regions: [None; MAX_LAZY_REGIONS],
+        }
+    }
+
+    pub fn register(&mut self, start: u64, size: u64, flags: u64) -> Result<(), RegionError> {
+        if size == 0 || !start.is_multiple_of(PAGE_SIZE_4K) || !size.is_multiple_of(PAGE_SIZE_4K) {
+            return Err(RegionError::Misaligned);
+        }
+        let end = start.checked_add(size).ok_or(RegionError::Misaligned)?;
+        if self
+            .regions
+            .iter()
+            .flatten()
+            .any(|region| start < region.end && region.start < end)
+        {
+            return Err(RegionError::Overlap);
+        }
+
+        let slot = self
+            .regions
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .ok_or(RegionError::TableFull)?;
+        *slot = Some(LazyRegion { start, end, flags });
+        Ok(())
+    }
+
+    pub fn find(&self, addr: u64) -> Option<LazyRegion> {
+        self.regions
+            .iter()
+            .flatten()
+            .find(|region| (region.start..region.end).contains(&addr))
+            .copied()
+    }
+}
+
+/// Entscheidet, ob ein Fault per Demand Paging auflösbar ist, und liefert den zuständigen Bereich.
+pub fn classify(
+    regions: &LazyRegions,
+    addr: u64,
+    code: PageFaultCode,
+) -> Result<LazyRegion, FaultError> {
+    if code.is_reserved_bit() {
+        return Err(FaultError::ReservedBit);
+    }
+    if code.is_protection_violation() {
+        return Err(FaultError::ProtectionViolation);
+    }
+    if addr < PAGE_SIZE_4K {
+        return Err(FaultError::NullPointer);
+    }
+    if stack::is_guard_page(addr) {
+        return Err(FaultError::StackOverflow);
+    }
+
+    let region = regions.find(addr).ok_or(FaultError::NotMapped)?;
+    if code.is_instruction_fetch() {
+        return Err(FaultError::InstructionFetch);
+    }
+    Ok(region)
+}
+
+#[cfg(eres_kernel)]
+mod kernel_fault {
+    use core::cell::UnsafeCell;
+    use core::sync::atomic::{AtomicU64, Ordering};
+
+    use super::{FaultError, LazyRegions, PageFaultCode, RegionError, classify};
+    use crate::arch;
+    use crate::memory::frame_allocator;
+    use crate::memory::paging::{self, GlobalFrameSource, PAGE_SIZE_4K};
+    use crate::memory::vm::{Page, PhysAddr, VirtAddr, align_down, phys_to_virt};
+
+    struct RegionsCell(UnsafeCell<LazyRegions>);
+    unsafe impl Sync for RegionsCell {}
+
+    static LAZY_REGIONS: RegionsCell = RegionsCell(UnsafeCell::new(LazyRegions::new()));
+    static DEMAND_PAGES: AtomicU64 = AtomicU64::new(0);
+
+    /// Meldet einen Bereich an, dessen Seiten erst beim ersten Zugriff abgebildet werden.
+    pub fn register_lazy_region(start: u64, size: u64, flags: u64) -> Result<(), RegionError> {
+        let interrupts_were_enabled = arch::x86_64::save_and_disable_interrupts();
+        let result = unsafe { (*LAZY_REGIONS.0.get()).register(start, size, flags) };
+        arch::x86_64::restore_interrupts(interrupts_were_enabled);
+        result
+    }
+
+    /// Versucht, einen Page Fault an `addr` durch Demand Paging aufzulösen.
+    ///
+    /// Läuft im Exception-Kontext mit gesperrten Interrupts und darf daher den Heap nicht nutzen.
+    pub fn handle_page_fault(addr: u64, code: PageFaultCode) -> Result<(), FaultError> {
+        let region = classify(unsafe { &*LAZY_REGIONS.0.get() }, addr, code)?;
+
+        let frame = frame_allocator::alloc_frame().ok_or(FaultError::OutOfMemory)?;
+        unsafe {
+            core::ptr::write_bytes(
+                phys_to_virt(PhysAddr(frame.start)).0 as *mut u8,
+                0,
+                PAGE_SIZE_4K as usize,
+            );
+        }
+
+        let page = Page::new(VirtAddr(align_down(addr, PAGE_SIZE_4K)));
+        if paging::active_mapper()
+            .map_4k(
+                page,
+                PhysAddr(frame.start),
+                region.flags,
+                &mut GlobalFrameSource,
+            )
+            .is_err()
+        {
+            let _ = frame_allocator::free_frame(frame);
+            return Err(FaultError::MapFailed);
+        }
+
+        DEMAND_PAGES.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Anzahl der bisher per Demand Paging abgebildeten Seiten.
+    pub fn demand_mapped_pages() -> u64 {
+        DEMAND_PAGES.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(eres_kernel)]
+pub use kernel_fault::{demand_mapped_pages, handle_page_fault, register_lazy_region};
+
+/// Host-Build-Fallback: ohne Page Tables ist kein Fault auflösbar.
+#[cfg(not(eres_kernel))]
+pub fn handle_page_fault(_addr: u64, _code: PageFaultCode) -> Result<(), FaultError> {
+    Err(FaultError::NotMapped)
+}
+
+#[cfg(not(eres_kernel))]
+pub fn demand_mapped_pages() -> u64 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FaultError, LazyRegion, LazyRegions, PageFaultCode, RegionError, classify};
+    use crate::memory::stack::KERNEL_STACK_GUARD;
+    use crate::memory::vm::FLAG_WRITABLE;
+
+    const BASE: u64 = 0xffff_ffff_d000_0000;
+
+    fn regions() -> LazyRegions {
+        let mut regions = LazyRegions::new();
+        regions
+            .register(BASE, 0x4000, FLAG_WRITABLE)
+            .expect("register");
+        regions
+    }
+
+    #[test]
+    fn decodes_error_code_bits() {
+        let code = PageFaultCode(PageFaultCode::WRITE | PageFaultCode::USER);
+        assert!(code.is_write());
+        assert!(code.is_user());
+        assert!(!code.is_protection_violation());
+        assert_eq!(code.access(), b"write");
+        assert_eq!(code.mode(), b"user");
+
+        let fetch = PageFaultCode(PageFaultCode::PRESENT | PageFaultCode::INSTRUCTION_FETCH);
+        assert_eq!(fetch.access(), b"instruction fetch");
+        assert_eq!(fetch.mode(), b"kernel");
+        assert_eq!(fetch.page_state(), b"present (protection violation)");
+    }
+
+    #[test]
+    fn resolves_fault_inside_lazy_region() {
+        let regions = regions();
+        let expected = LazyRegion {
+            start: BASE,
+            end: BASE + 0x4000,
+            flags: FLAG_WRITABLE,
+        };
+        assert_eq!(
+            classify(&regions, BASE + 0x3ff8, PageFaultCode(PageFaultCode::WRITE)),
+            Ok(expected)
+        );
+        assert_eq!(
+            classify(&regions, BASE + 0x4000, PageFaultCode(0)),
+            Err(FaultError::NotMapped)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_accesses() {
+        let regions = regions();
+        assert_eq!(
+            classify(&regions, 0x8, PageFaultCode(0)),
+            Err(FaultError::NullPointer)
+        );
+        assert_eq!(
+            classify(
+                &regions,
+                KERNEL_STACK_GUARD + 0xff8,
+                PageFaultCode(PageFaultCode::WRITE)
+            ),
+            Err(FaultError::StackOverflow)
+        );
+        assert_eq!(
+            classify(
+                &regions,
+                BASE,
+                PageFaultCode(PageFaultCode::PRESENT | PageFaultCode::WRITE)
+            ),
+            Err(FaultError::ProtectionViolation)
+        );
+        assert_eq!(
+            classify(&regions, BASE, PageFaultCode(PageFaultCode::RESERVED_BIT)),
+            Err(FaultError::ReservedBit)
+        );
+        assert_eq!(
+            classify(
+                &regions,
+                BASE,
+                PageFaultCode(PageFaultCode::INSTRUCTION_FETCH)
+            ),
+            Err(FaultError::InstructionFetch)
+        );
+    }
+
+    #[test]
+    fn validates_region_registration() {
+        let mut regions = regions();
+        assert_eq!(
+            regions.register(BASE + 0x2000, 0x4000, 0),
+            Err(RegionError::Overlap)
+        );
+        assert_eq!(
+            regions.register(BASE + 0x8001, 0x1000, 0),
+            Err(RegionError::Misaligned)
+        );
+        assert_eq!(
+            regions.register(BASE + 0x8000, 0, 0),
+            Err(RegionError::Misaligned)
+        );
+
+        for index in 1..super::MAX_LAZY_REGIONS as u64 {
+            regions
+                .register(BASE + index * 0x10_0000, 0x1000, 0)
+                .expect("register");
+        }
+        assert_eq!(
+            regions.register(BASE + 0x100_0000, 0x1000, 0),
+            Err(RegionError::TableFull)
+        );
+    }
+}
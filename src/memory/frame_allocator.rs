@@ -1,7 +1,11 @@
-//! Sehr einfacher physischer Frame-Allocator.
+//! Physischer Frame-Allocator auf Bitmap-Basis.
 //!
-//! Er durchläuft die vom Bootloader gemeldeten nutzbaren Speicherbereiche (`entry_type == 1`)
-//! und vergibt 4-KiB-Rahmen sequenziell. Das reicht für frühe Kernel-Phasen.
+//! Jedes Bit der Bitmap steht für einen 4-KiB-Rahmen ab der Basisadresse. Ein gesetztes Bit
+//! bedeutet "belegt oder nicht nutzbar". Freigegeben werden kann nur, was in einer nutzbaren
+//! E820-Region (`entry_type == 1`) liegt und tatsächlich allokiert war.
+//!
+//! Neben einzelnen Frames lassen sich zusammenhängende, ausgerichtete Bereiche anfordern
+//! (z. B. für DMA-Puffer oder Page Tables).
 
 use super::bootinfo::MemoryMapEntry;
 
@@ -9,6 +13,13 @@ use super::bootinfo::MemoryMapEntry;
 pub const FRAME_SIZE: u64 = 4096;
 /// Untergrenze für allokierbaren Speicher (2 MiB, um niedrige Bereiche zu meiden).
 pub const MIN_ALLOCATABLE_ADDR: u64 = 0x20_0000;
+/// Obergrenze für allokierbaren Speicher (1 GiB, soweit der Kernel physischen Speicher abbildet).
+pub const MAX_ALLOCATABLE_ADDR: u64 = 0x4000_0000;
+/// Größe der globalen Bitmap in 64-Bit-Wörtern (deckt `MIN_ALLOCATABLE_ADDR..MAX_ALLOCATABLE_ADDR` ab).
+pub const BITMAP_WORDS: usize =
+    ((MAX_ALLOCATABLE_ADDR - MIN_ALLOCATABLE_ADDR) / FRAME_SIZE) as usize / BITS_PER_WORD;
+
+const BITS_PER_WORD: usize = 64;
 
 /// Ein physischer Speicherrahmen.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -25,82 +36,227 @@ pub struct FrameStats {
     pub free_frames: u64,
 }
 
-/// Sequentieller Iterator über allokierbare Speicherrahmen.
+/// Fehler beim Freigeben von Frames.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameError {
+    /// Adresse ist nicht auf `FRAME_SIZE` ausgerichtet.
+    Misaligned,
+    /// Adresse liegt außerhalb des von der Bitmap abgedeckten Bereichs.
+    OutOfRange,
+    /// Frame war nicht allokiert (doppelte Freigabe oder reservierter Speicher).
+    NotAllocated,
+}
+
+/// Bitmap-Allocator über die nutzbaren Speicherregionen.
 pub struct FrameAllocator<'a> {
     regions: &'a [MemoryMapEntry],
-    region_index: usize,
-    next_addr: u64,
-    region_end: u64,
-    min_addr: u64,
+    bitmap: &'a mut [u64],
+    base: u64,
+    frame_count: usize,
+    total_frames: u64,
+    allocated_frames: u64,
+    next_free: usize,
 }
 
 impl<'a> FrameAllocator<'a> {
     /// Erzeugt einen neuen Allocator über gegebene Speicherregionen.
-    pub fn new(regions: &'a [MemoryMapEntry], min_addr: u64) -> Self {
+    ///
+    /// `bitmap` bestimmt die maximale Anzahl verwaltbarer Frames (64 pro Wort); nutzbarer
+    /// Speicher jenseits davon wird ignoriert.
+    pub fn new(regions: &'a [MemoryMapEntry], min_addr: u64, bitmap: &'a mut [u64]) -> Self {
+        let base = align_up(min_addr, FRAME_SIZE);
+        let highest_end = regions
+            .iter()
+            .filter(|region| is_usable(region))
+            .map(|region| align_down(region.base.saturating_add(region.length), FRAME_SIZE))
+            .max()
+            .unwrap_or(0);
+        let span = (highest_end.saturating_sub(base) / FRAME_SIZE) as usize;
+        let frame_count = span.min(bitmap.len() * BITS_PER_WORD);
+
+        bitmap.fill(u64::MAX);
+
         let mut allocator = Self {
             regions,
-            region_index: 0,
-            next_addr: 0,
-            region_end: 0,
-            min_addr,
+            bitmap,
+            base,
+            frame_count,
+            total_frames: 0,
+            allocated_frames: 0,
+            next_free: 0,
         };
-        allocator.select_next_region();
+
+        for region in regions.iter().filter(|region| is_usable(region)) {
+            let start = align_up(region.base.max(base), FRAME_SIZE);
+            let end = align_down(region.base.saturating_add(region.length), FRAME_SIZE);
+            let mut addr = start;
+            while addr < end {
+                let index = ((addr - base) / FRAME_SIZE) as usize;
+                if index >= frame_count {
+                    break;
+                }
+                // Überlappende E820-Einträge dürfen einen Frame nicht doppelt zählen.
+                if allocator.is_used(index) {
+                    allocator.set_used(index, false);
+                    allocator.total_frames += 1;
+                }
+                addr += FRAME_SIZE;
+            }
+        }
+
         allocator
     }
 
     /// Reserviert den nächsten verfügbaren Frame.
     pub fn alloc(&mut self) -> Option<PhysicalFrame> {
+        let index = self.find_free_from(self.next_free)?;
+        self.set_used(index, true);
+        self.allocated_frames += 1;
+        self.next_free = index + 1;
+        Some(PhysicalFrame {
+            start: self.frame_addr(index),
+        })
+    }
+
+    /// Reserviert `count` zusammenhängende Frames, deren Start auf `align` Bytes ausgerichtet ist.
+    ///
+    /// `align` muss eine Zweierpotenz sein; Werte kleiner als `FRAME_SIZE` gelten als `FRAME_SIZE`.
+    pub fn alloc_contiguous(&mut self, count: usize, align: u64) -> Option<PhysicalFrame> {
+        if count == 0 || !align.is_power_of_two() {
+            return None;
+        }
+        if count == 1 && align <= FRAME_SIZE {
+            return self.alloc();
+        }
+
+        let align = align.max(FRAME_SIZE);
+        let mut addr = align_up(self.frame_addr(self.next_free), align);
         loop {
-            if self.next_addr >= self.region_end {
-                self.select_next_region();
-                if self.next_addr >= self.region_end {
-                    return None;
+            let start = ((addr - self.base) / FRAME_SIZE) as usize;
+            if start + count > self.frame_count {
+                return None;
+            }
+
+            match (start..start + count).find(|index| self.is_used(*index)) {
+                Some(used) => {
+                    addr = align_up(self.frame_addr(used + 1), align);
+                }
+                None => {
+                    for index in start..start + count {
+                        self.set_used(index, true);
+                    }
+                    self.allocated_frames += count as u64;
+                    if start == self.next_free {
+                        self.next_free = start + count;
+                    }
+                    return Some(PhysicalFrame { start: addr });
                 }
             }
+        }
+    }
+
+    /// Gibt einen einzelnen Frame zurück.
+    pub fn free(&mut self, frame: PhysicalFrame) -> Result<(), FrameError> {
+        self.free_contiguous(frame, 1)
+    }
 
-            let frame_start = align_up(self.next_addr, FRAME_SIZE);
-            if frame_start + FRAME_SIZE > self.region_end {
-                self.next_addr = self.region_end;
-                continue;
+    /// Gibt `count` zusammenhängende Frames ab `frame` zurück.
+    ///
+    /// Die Operation ist atomar: Ist einer der Frames ungültig, bleibt die Bitmap unverändert.
+    pub fn free_contiguous(&mut self, frame: PhysicalFrame, count: usize) -> Result<(), FrameError> {
+        if !frame.start.is_multiple_of(FRAME_SIZE) {
+            return Err(FrameError::Misaligned);
+        }
+        if frame.start < self.base {
+            return Err(FrameError::OutOfRange);
+        }
+
+        let start = ((frame.start - self.base) / FRAME_SIZE) as usize;
+        if start.saturating_add(count) > self.frame_count {
+            return Err(FrameError::OutOfRange);
+        }
+
+        for index in start..start + count {
+            if !self.is_used(index) || !self.is_usable_addr(self.frame_addr(index)) {
+                return Err(FrameError::NotAllocated);
             }
+        }
 
-            self.next_addr = frame_start + FRAME_SIZE;
-            return Some(PhysicalFrame { start: frame_start });
+        for index in start..start + count {
+            self.set_used(index, false);
         }
+        self.allocated_frames -= count as u64;
+        self.next_free = self.next_free.min(start);
+        Ok(())
     }
 
-    /// Springt zur nächsten nutzbaren Speicherregion.
-    fn select_next_region(&mut self) {
-        while self.region_index < self.regions.len() {
-            let region = self.regions[self.region_index];
-            self.region_index += 1;
+    /// Aktuelle Belegungsstatistik.
+    pub fn stats(&self) -> FrameStats {
+        FrameStats {
+            total_frames: self.total_frames,
+            allocated_frames: self.allocated_frames,
+            free_frames: self.total_frames - self.allocated_frames,
+        }
+    }
 
-            // E820: Typ 1 steht für "usable RAM".
-            if region.entry_type != 1 || region.length == 0 {
-                continue;
-            }
+    fn find_free_from(&self, start: usize) -> Option<usize> {
+        let mut word_index = start / BITS_PER_WORD;
+        let words = self.frame_count.div_ceil(BITS_PER_WORD);
+        let mut mask = u64::MAX << (start % BITS_PER_WORD);
 
-            let start = region.base.max(self.min_addr);
-            let end = region.base.saturating_add(region.length);
-            if start >= end {
-                continue;
+        while word_index < words {
+            let free_bits = !self.bitmap[word_index] & mask;
+            if free_bits != 0 {
+                let index = word_index * BITS_PER_WORD + free_bits.trailing_zeros() as usize;
+                return (index < self.frame_count).then_some(index);
             }
+            word_index += 1;
+            mask = u64::MAX;
+        }
+
+        None
+    }
+
+    fn frame_addr(&self, index: usize) -> u64 {
+        self.base + index as u64 * FRAME_SIZE
+    }
 
-            self.next_addr = start;
-            self.region_end = end;
-            return;
+    fn is_used(&self, index: usize) -> bool {
+        (self.bitmap[index / BITS_PER_WORD] & (1 << (index % BITS_PER_WORD))) != 0
+    }
+
+    fn set_used(&mut self, index: usize, used: bool) {
+        let bit = 1 << (index % BITS_PER_WORD);
+        if used {
+            self.bitmap[index / BITS_PER_WORD] |= bit;
+        } else {
+            self.bitmap[index / BITS_PER_WORD] &= !bit;
         }
+    }
 
-        self.next_addr = 0;
-        self.region_end = 0;
+    fn is_usable_addr(&self, addr: u64) -> bool {
+        self.regions.iter().any(|region| {
+            is_usable(region)
+                && addr >= region.base
+                && addr + FRAME_SIZE <= region.base.saturating_add(region.length)
+        })
     }
 }
 
+/// E820: Typ 1 steht für "usable RAM".
+fn is_usable(region: &MemoryMapEntry) -> bool {
+    region.entry_type == 1 && region.length != 0
+}
+
 const fn align_up(value: u64, align: u64) -> u64 {
     let mask = align - 1;
     (value + mask) & !mask
 }
 
+const fn align_down(value: u64, align: u64) -> u64 {
+    value & !(align - 1)
+}
+
 #[cfg(eres_kernel)]
 use core::cell::UnsafeCell;
 #[cfg(eres_kernel)]
@@ -115,57 +271,69 @@ static FRAME_ALLOCATOR: FrameAllocatorCell = FrameAllocatorCell(UnsafeCell::new(
 #[cfg(eres_kernel)]
 static FRAME_ALLOCATOR_READY: AtomicBool = AtomicBool::new(false);
 #[cfg(eres_kernel)]
-static TOTAL_FRAMES: core::sync::atomic::AtomicU64 = core::sync::atomic::AtomicU64::new(0);
-#[cfg(eres_kernel)]
-static ALLOCATED_FRAMES: core::sync::atomic::AtomicU64 = core::sync::atomic::AtomicU64::new(0);
+static mut FRAME_BITMAP: [u64; BITMAP_WORDS] = [0; BITMAP_WORDS];
 
 /// Initialisiert den globalen Frame-Allocator aus der Boot-Speicherkarte.
+///
+/// Liefert die Anzahl nutzbarer Frames, die nicht mehr in die Bitmap passen (normalerweise 0).
 #[cfg(eres_kernel)]
-pub fn init_from_memory_map(entries: &'static [MemoryMapEntry]) {
-    let total = count_usable_frames(entries, MIN_ALLOCATABLE_ADDR);
+pub fn init_from_memory_map(entries: &'static [MemoryMapEntry]) -> u64 {
+    let usable = count_usable_frames(entries, MIN_ALLOCATABLE_ADDR);
+    let bitmap = unsafe { &mut *core::ptr::addr_of_mut!(FRAME_BITMAP) };
+    let allocator = FrameAllocator::new(entries, MIN_ALLOCATABLE_ADDR, bitmap);
+    let tracked = allocator.stats().total_frames;
 
     unsafe {
-        *FRAME_ALLOCATOR.0.get() = Some(FrameAllocator::new(entries, MIN_ALLOCATABLE_ADDR));
+        *FRAME_ALLOCATOR.0.get() = Some(allocator);
     }
 
-    TOTAL_FRAMES.store(total, Ordering::Release);
-    ALLOCATED_FRAMES.store(0, Ordering::Release);
     FRAME_ALLOCATOR_READY.store(true, Ordering::Release);
+    usable.saturating_sub(tracked)
 }
 
-/// Allokiert einen einzelnen physischen Frame aus dem globalen Allocator.
+/// Führt `f` mit exklusivem Zugriff auf den globalen Allocator aus.
 #[cfg(eres_kernel)]
-pub fn alloc_frame() -> Option<PhysicalFrame> {
+fn with_allocator<T>(f: impl FnOnce(&mut FrameAllocator<'static>) -> T) -> Option<T> {
     if !FRAME_ALLOCATOR_READY.load(Ordering::Acquire) {
         return None;
     }
 
-    let frame = unsafe {
-        let allocator = &mut *FRAME_ALLOCATOR.0.get();
-        allocator.as_mut().and_then(FrameAllocator::alloc)
-    };
+    // Frames werden auch aus Interrupt-Kontext (Page Faults) angefordert.
+    let interrupts_were_enabled = crate::arch::x86_64::save_and_disable_interrupts();
+    let result = unsafe { (*FRAME_ALLOCATOR.0.get()).as_mut().map(f) };
+    crate::arch::x86_64::restore_interrupts(interrupts_were_enabled);
+    result
+}
 
-    if frame.is_some() {
-        ALLOCATED_FRAMES.fetch_add(1, Ordering::AcqRel);
-    }
+/// Allokiert einen einzelnen physischen Frame aus dem globalen Allocator.
+#[cfg(eres_kernel)]
+pub fn alloc_frame() -> Option<PhysicalFrame> {
+    with_allocator(FrameAllocator::alloc).flatten()
+}
 
-    frame
+/// Allokiert `count` zusammenhängende Frames mit Startausrichtung `align` (in Bytes).
+#[cfg(eres_kernel)]
+pub fn alloc_frames(count: usize, align: u64) -> Option<PhysicalFrame> {
+    with_allocator(|allocator| allocator.alloc_contiguous(count, align)).flatten()
+}
+
+/// Gibt einen Frame an den globalen Allocator zurück.
+#[cfg(eres_kernel)]
+pub fn free_frame(frame: PhysicalFrame) -> Result<(), FrameError> {
+    with_allocator(|allocator| allocator.free(frame)).unwrap_or(Err(FrameError::OutOfRange))
+}
+
+/// Gibt `count` zusammenhängende Frames an den globalen Allocator zurück.
+#[cfg(eres_kernel)]
+pub fn free_frames(frame: PhysicalFrame, count: usize) -> Result<(), FrameError> {
+    with_allocator(|allocator| allocator.free_contiguous(frame, count))
+        .unwrap_or(Err(FrameError::OutOfRange))
 }
 
 /// Gibt statistische Informationen über den globalen Allocator zurück.
 #[cfg(eres_kernel)]
 pub fn stats() -> Option<FrameStats> {
-    if !FRAME_ALLOCATOR_READY.load(Ordering::Acquire) {
-        return None;
-    }
-
-    let total = TOTAL_FRAMES.load(Ordering::Acquire);
-    let allocated = ALLOCATED_FRAMES.load(Ordering::Acquire);
-    Some(FrameStats {
-        total_frames: total,
-        allocated_frames: allocated,
-        free_frames: total.saturating_sub(allocated),
-    })
+    with_allocator(|allocator| allocator.stats())
 }
 
 /// Test-/Host-Build-Fallback ohne globalen Allocatorzustand.
@@ -179,11 +347,26 @@ pub fn alloc_frame() -> Option<PhysicalFrame> {
     None
 }
 
+#[cfg(not(eres_kernel))]
+pub fn alloc_frames(_count: usize, _align: u64) -> Option<PhysicalFrame> {
+    None
+}
+
+#[cfg(not(eres_kernel))]
+pub fn free_frame(_frame: PhysicalFrame) -> Result<(), FrameError> {
+    Err(FrameError::OutOfRange)
+}
+
+#[cfg(not(eres_kernel))]
+pub fn free_frames(_frame: PhysicalFrame, _count: usize) -> Result<(), FrameError> {
+    Err(FrameError::OutOfRange)
+}
+
 fn count_usable_frames(entries: &[MemoryMapEntry], min_addr: u64) -> u64 {
     let mut count = 0;
 
     for region in entries {
-        if region.entry_type != 1 || region.length == 0 {
+        if !is_usable(region) {
             continue;
         }
 
@@ -199,9 +382,18 @@ fn count_usable_frames(entries: &[MemoryMapEntry], min_addr: u64) -> u64 {
 
 #[cfg(test)]
 mod tests {
-    use super::{FRAME_SIZE, FrameAllocator};
+    use super::{FRAME_SIZE, FrameAllocator, FrameError, PhysicalFrame};
     use crate::memory::bootinfo::MemoryMapEntry;
 
+    fn usable(base: u64, frames: u64) -> MemoryMapEntry {
+        MemoryMapEntry {
+            base,
+            length: FRAME_SIZE * frames,
+            entry_type: 1,
+            acpi_extended_attributes: 0,
+        }
+    }
+
     #[test]
     fn allocates_consecutive_frames() {
         let regions = [MemoryMapEntry {
@@ -211,7 +403,8 @@ mod tests {
             acpi_extended_attributes: 0,
         }];
 
-        let mut alloc = FrameAllocator::new(&regions, 0x200000);
+        let mut bitmap = [0_u64; 4];
+        let mut alloc = FrameAllocator::new(&regions, 0x200000, &mut bitmap);
         assert_eq!(alloc.alloc().map(|f| f.start), Some(0x200000));
         assert_eq!(alloc.alloc().map(|f| f.start), Some(0x201000));
         assert_eq!(alloc.alloc().map(|f| f.start), Some(0x202000));
@@ -235,7 +428,8 @@ mod tests {
             },
         ];
 
-        let mut alloc = FrameAllocator::new(&regions, 0x200000);
+        let mut bitmap = [0_u64; 8];
+        let mut alloc = FrameAllocator::new(&regions, 0x200000, &mut bitmap);
         assert_eq!(alloc.alloc().map(|f| f.start), Some(0x300000));
         assert_eq!(alloc.alloc().map(|f| f.start), None);
     }
@@ -249,7 +443,8 @@ mod tests {
             acpi_extended_attributes: 0,
         }];
 
-        let mut alloc = FrameAllocator::new(&regions, 0x102000);
+        let mut bitmap = [0_u64; 4];
+        let mut alloc = FrameAllocator::new(&regions, 0x102000, &mut bitmap);
         assert_eq!(alloc.alloc().map(|f| f.start), Some(0x102000));
         assert_eq!(alloc.alloc().map(|f| f.start), Some(0x103000));
         assert_eq!(alloc.alloc().map(|f| f.start), None);
@@ -275,4 +470,103 @@ mod tests {
         let total = super::count_usable_frames(&regions, 0x200000);
         assert_eq!(total, 3);
     }
+
+    #[test]
+    fn freed_frames_are_reused() {
+        let regions = [usable(0x200000, 4)];
+        let mut bitmap = [0_u64; 1];
+        let mut alloc = FrameAllocator::new(&regions, 0x200000, &mut bitmap);
+
+        let first = alloc.alloc().expect("frame");
+        let second = alloc.alloc().expect("frame");
+        assert_eq!(alloc.free(first), Ok(()));
+        assert_eq!(alloc.alloc(), Some(first));
+        assert_ne!(alloc.alloc(), Some(second));
+    }
+
+    #[test]
+    fn rejects_double_free_and_foreign_frames() {
+        let regions = [
+            usable(0x200000, 2),
+            MemoryMapEntry {
+                base: 0x202000,
+                length: FRAME_SIZE,
+                entry_type: 2,
+                acpi_extended_attributes: 0,
+            },
+            usable(0x203000, 1),
+        ];
+        let mut bitmap = [0_u64; 1];
+        let mut alloc = FrameAllocator::new(&regions, 0x200000, &mut bitmap);
+
+        let frame = alloc.alloc().expect("frame");
+        assert_eq!(alloc.free(frame), Ok(()));
+        assert_eq!(alloc.free(frame), Err(FrameError::NotAllocated));
+        assert_eq!(
+            alloc.free(PhysicalFrame { start: 0x202000 }),
+            Err(FrameError::NotAllocated)
+        );
+        assert_eq!(
+            alloc.free(PhysicalFrame { start: 0x200800 }),
+            Err(FrameError::Misaligned)
+        );
+        assert_eq!(
+            alloc.free(PhysicalFrame { start: 0x100000 }),
+            Err(FrameError::OutOfRange)
+        );
+    }
+
+    #[test]
+    fn allocates_aligned_contiguous_ranges() {
+        let regions = [usable(0x201000, 64)];
+        let mut bitmap = [0_u64; 2];
+        let mut alloc = FrameAllocator::new(&regions, 0x200000, &mut bitmap);
+
+        let single = alloc.alloc().expect("frame");
+        assert_eq!(single.start, 0x201000);
+
+        let block = alloc.alloc_contiguous(4, 0x4000).expect("aligned block");
+        assert_eq!(block.start, 0x204000);
+        assert_eq!(alloc.alloc().map(|f| f.start), Some(0x202000));
+
+        assert_eq!(alloc.free_contiguous(block, 4), Ok(()));
+        assert_eq!(alloc.alloc_contiguous(4, 0x4000), Some(block));
+        assert_eq!(alloc.alloc_contiguous(128, FRAME_SIZE), None);
+    }
+
+    #[test]
+    fn contiguous_allocation_skips_holes() {
+        let regions = [usable(0x200000, 2), usable(0x203000, 3)];
+        let mut bitmap = [0_u64; 1];
+        let mut alloc = FrameAllocator::new(&regions, 0x200000, &mut bitmap);
+
+        let block = alloc.alloc_contiguous(3, FRAME_SIZE).expect("block");
+        assert_eq!(block.start, 0x203000);
+        assert_eq!(alloc.alloc_contiguous(2, FRAME_SIZE).map(|f| f.start), Some(0x200000));
+    }
+
+    #[test]
+    fn stats_track_alloc_and_free() {
+        let regions = [usable(0x200000, 8)];
+        let mut bitmap = [0_u64; 1];
+        let mut alloc = FrameAllocator::new(&regions, 0x200000, &mut bitmap);
+
+        let frame = alloc.alloc().expect("frame");
+        let block = alloc.alloc_contiguous(3, FRAME_SIZE).expect("block");
+        let stats = alloc.stats();
+        assert_eq!((stats.total_frames, stats.allocated_frames, stats.free_frames), (8, 4, 4));
+
+        alloc.free(frame).expect("free frame");
+        alloc.free_contiguous(block, 3).expect("free block");
+        let stats = alloc.stats();
+        assert_eq!((stats.allocated_frames, stats.free_frames), (0, 8));
+    }
+
+    #[test]
+    fn bitmap_capacity_limits_tracked_frames() {
+        let regions = [usable(0x200000, 100)];
+        let mut bitmap = [0_u64; 1];
+        let alloc = FrameAllocator::new(&regions, 0x200000, &mut bitmap);
+        assert_eq!(alloc.stats().total_frames, 64);
+    }
 }
@@ -1,47 +1,138 @@
-//! Sehr einfacher Bump-Allocator für frühe Kernel-Entwicklung.
+//! Kernel-Heap mit Freiliste.
 //!
-//! Der Allocator kann Speicher nur nach vorne vergeben und nie freigeben.
-//! Das ist für frühe Boot- und Prototyp-Phasen oft ausreichend.
+//! Freie Blöcke stehen nach Adresse sortiert in einer verketteten Liste, deren Knoten im freien
+//! Speicher selbst liegen. Vergeben wird der erste passende Block (First Fit); freigegebene
+//! Blöcke verschmelzen mit ihren Nachbarn, damit der Heap bei wiederholten Allokationen nicht
+//! zerfasert.
+//!
+//! Im Kernel liegt der Heap in einem per Demand Paging hinterlegten Bereich: Frames werden
+//! erst beim ersten Zugriff auf eine Heap-Seite belegt. Weil kleine Adressen zuerst vergeben
+//! werden, berührt der Allocator Seiten am Ende des Bereichs erst, wenn sie gebraucht werden.
 
 use core::alloc::Layout;
+use core::ptr;
+
+/// Granularität aller Blöcke; ein freier Block muss seinen Listenknoten fassen.
+const BLOCK_ALIGN: usize = 16;
+
+/// Listenknoten am Anfang eines freien Blocks.
+struct FreeBlock {
+    size: usize,
+    next: *mut FreeBlock,
+}
 
-#[derive(Clone, Copy, Debug)]
-/// Interner Zustand des linearen Allocators.
-struct BumpCursor {
-    start: usize,
-    end: usize,
-    next: usize,
+/// Interner Zustand des Allocators: nach Adresse sortierte Liste freier Blöcke.
+struct FreeList {
+    head: *mut FreeBlock,
 }
 
-impl BumpCursor {
+impl FreeList {
     const fn new() -> Self {
         Self {
-            start: 0,
-            end: 0,
-            next: 0,
+            head: ptr::null_mut(),
         }
     }
 
     /// Setzt den verwalteten Heap-Bereich.
-    fn init(&mut self, start: usize, size: usize) {
-        self.start = start;
-        self.end = start.saturating_add(size);
-        self.next = start;
+    ///
+    /// # Safety
+    /// `start..start + size` muss beschreibbar sein und darf sonst nicht benutzt werden.
+    unsafe fn init(&mut self, start: usize, size: usize) {
+        let begin = align_up(start, BLOCK_ALIGN);
+        let end = (start + size) & !(BLOCK_ALIGN - 1);
+        self.head = ptr::null_mut();
+        if end > begin {
+            unsafe { self.insert(begin, end - begin) };
+        }
     }
 
     /// Allokiert einen Block mit gewünschter Größe/Ausrichtung.
     fn alloc(&mut self, layout: Layout) -> *mut u8 {
-        let aligned = align_up(self.next, layout.align());
-        let next = aligned.saturating_add(layout.size());
-        if next > self.end {
-            core::ptr::null_mut()
+        let size = block_size(layout);
+        let align = layout.align().max(BLOCK_ALIGN);
+
+        let mut previous: *mut FreeBlock = ptr::null_mut();
+        let mut current = self.head;
+        while !current.is_null() {
+            let (start, block_size, next) =
+                unsafe { (current as usize, (*current).size, (*current).next) };
+            let end = start + block_size;
+            let aligned = align_up(start, align);
+            if aligned.checked_add(size).is_some_and(|used| used <= end) {
+                unsafe {
+                    self.link(previous, next);
+                    // Verschnitt vor und hinter dem Block ist ein Vielfaches von BLOCK_ALIGN und
+                    // geht zurück in die Liste.
+                    if aligned > start {
+                        self.insert(start, aligned - start);
+                    }
+                    if aligned + size < end {
+                        self.insert(aligned + size, end - aligned - size);
+                    }
+                }
+                return aligned as *mut u8;
+            }
+            previous = current;
+            current = next;
+        }
+        ptr::null_mut()
+    }
+
+    /// Gibt einen mit `alloc(layout)` vergebenen Block zurück.
+    ///
+    /// # Safety
+    /// `ptr` muss von [`FreeList::alloc`] mit demselben `layout` stammen.
+    unsafe fn dealloc(&mut self, ptr: *mut u8, layout: Layout) {
+        unsafe { self.insert(ptr as usize, block_size(layout)) };
+    }
+
+    /// Hängt `start..start + size` sortiert ein und verschmilzt es mit angrenzenden Blöcken.
+    unsafe fn insert(&mut self, start: usize, size: usize) {
+        let mut previous: *mut FreeBlock = ptr::null_mut();
+        let mut current = self.head;
+        while !current.is_null() && (current as usize) < start {
+            previous = current;
+            current = unsafe { (*current).next };
+        }
+
+        let mut size = size;
+        if !current.is_null() && start + size == current as usize {
+            unsafe {
+                size += (*current).size;
+                current = (*current).next;
+            }
+        }
+
+        unsafe {
+            if !previous.is_null() && previous as usize + (*previous).size == start {
+                (*previous).size += size;
+                (*previous).next = current;
+            } else {
+                let block = start as *mut FreeBlock;
+                block.write(FreeBlock {
+                    size,
+                    next: current,
+                });
+                self.link(previous, block);
+            }
+        }
+    }
+
+    /// Lässt `previous` (oder den Listenkopf) auf `next` zeigen.
+    unsafe fn link(&mut self, previous: *mut FreeBlock, next: *mut FreeBlock) {
+        if previous.is_null() {
+            self.head = next;
         } else {
-            self.next = next;
-            aligned as *mut u8
+            unsafe { (*previous).next = next };
         }
     }
 }
 
+/// Tatsächlich belegte Größe eines Blocks für `layout`.
+fn block_size(layout: Layout) -> usize {
+    align_up(layout.size().max(BLOCK_ALIGN), BLOCK_ALIGN)
+}
+
 const fn align_up(value: usize, align: usize) -> usize {
     (value + align - 1) & !(align - 1)
 }
@@ -52,75 +143,89 @@ mod kernel_heap {
     use core::cell::UnsafeCell;
     use core::sync::atomic::{AtomicBool, Ordering};
 
-    use super::BumpCursor;
+    use super::FreeList;
+    use crate::memory::fault;
+    use crate::memory::vm::FLAG_WRITABLE;
 
-    const HEAP_SIZE: usize = 256 * 1024;
+    /// Virtuelle Startadresse des Heaps (oberhalb des Kernel-Stacks).
+    pub const HEAP_START: usize = 0xffff_ffff_d000_0000;
+    /// Größe des reservierten virtuellen Heap-Bereichs.
+    pub const HEAP_SIZE: usize = 4 * 1024 * 1024;
 
-    struct LockedCursor {
+    struct LockedHeap {
         lock: AtomicBool,
-        cursor: UnsafeCell<BumpCursor>,
+        heap: UnsafeCell<FreeList>,
     }
 
-    unsafe impl Sync for LockedCursor {}
+    unsafe impl Sync for LockedHeap {}
 
-    impl LockedCursor {
+    impl LockedHeap {
         const fn new() -> Self {
             Self {
                 lock: AtomicBool::new(false),
-                cursor: UnsafeCell::new(BumpCursor::new()),
+                heap: UnsafeCell::new(FreeList::new()),
             }
         }
 
-        fn with_lock<T>(&self, f: impl FnOnce(&mut BumpCursor) -> T) -> T {
+        fn with_lock<T>(&self, f: impl FnOnce(&mut FreeList) -> T) -> T {
             while self
                 .lock
                 .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
                 .is_err()
             {}
 
-            let result = unsafe { f(&mut *self.cursor.get()) };
+            let result = unsafe { f(&mut *self.heap.get()) };
             self.lock.store(false, Ordering::Release);
             result
         }
     }
 
-    /// `GlobalAlloc`-Wrapper um den gesperrten Cursor.
+    /// `GlobalAlloc`-Wrapper um die gesperrte Freiliste.
     pub struct KernelAllocator {
-        state: LockedCursor,
+        state: LockedHeap,
     }
 
     impl KernelAllocator {
         const fn new() -> Self {
             Self {
-                state: LockedCursor::new(),
+                state: LockedHeap::new(),
             }
         }
     }
 
     unsafe impl GlobalAlloc for KernelAllocator {
         unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-            self.state.with_lock(|cursor| cursor.alloc(layout))
+            self.state.with_lock(|heap| heap.alloc(layout))
         }
 
-        unsafe fn dealloc(&self, _ptr: *mut u8, _layout: Layout) {}
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            self.state
+                .with_lock(|heap| unsafe { heap.dealloc(ptr, layout) });
+        }
     }
 
     #[global_allocator]
     static KERNEL_ALLOCATOR: KernelAllocator = KernelAllocator::new();
     static HEAP_READY: AtomicBool = AtomicBool::new(false);
-    static mut HEAP_SPACE: [u8; HEAP_SIZE] = [0; HEAP_SIZE];
 
-    /// Initialisiert den statischen Kernel-Heap einmalig.
-    pub fn init() {
+    /// Initialisiert den Kernel-Heap einmalig.
+    ///
+    /// Benötigt einen laufenden Frame-Allocator und eine geladene IDT, weil der erste Zugriff
+    /// auf jede Heap-Seite einen Page Fault auslöst.
+    pub fn init() -> bool {
         if HEAP_READY.load(Ordering::Acquire) {
-            return;
+            return true;
         }
 
-        let start = core::ptr::addr_of_mut!(HEAP_SPACE) as *mut u8 as usize;
+        if fault::register_lazy_region(HEAP_START as u64, HEAP_SIZE as u64, FLAG_WRITABLE).is_err()
+        {
+            return false;
+        }
         KERNEL_ALLOCATOR
             .state
-            .with_lock(|cursor| cursor.init(start, HEAP_SIZE));
+            .with_lock(|heap| unsafe { heap.init(HEAP_START, HEAP_SIZE) });
         HEAP_READY.store(true, Ordering::Release);
+        true
     }
 }
 
@@ -129,34 +234,89 @@ pub use kernel_heap::init;
 
 #[cfg(test)]
 mod tests {
+    use alloc::vec::Vec;
     use core::alloc::Layout;
 
-    use super::BumpCursor;
+    use super::FreeList;
 
-    #[test]
-    fn cursor_allocates_with_alignment() {
-        let mut buf = [0u8; 128];
-        let start = buf.as_mut_ptr() as usize;
+    #[repr(align(16))]
+    struct Arena<const N: usize>([u8; N]);
 
-        let mut cursor = BumpCursor::new();
-        cursor.init(start, buf.len());
+    fn heap<const N: usize>(arena: &mut Arena<N>) -> FreeList {
+        let mut heap = FreeList::new();
+        unsafe { heap.init(arena.0.as_mut_ptr() as usize, N) };
+        heap
+    }
 
-        let first = cursor.alloc(Layout::from_size_align(1, 1).expect("valid layout")) as usize;
-        let second = cursor.alloc(Layout::from_size_align(8, 8).expect("valid layout")) as usize;
+    fn layout(size: usize, align: usize) -> Layout {
+        Layout::from_size_align(size, align).expect("valid layout")
+    }
+
+    #[test]
+    fn allocates_with_alignment() {
+        let mut arena = Arena([0u8; 128]);
+        let start = arena.0.as_ptr() as usize;
+        let mut heap = heap(&mut arena);
+
+        let first = heap.alloc(layout(1, 1)) as usize;
+        let second = heap.alloc(layout(8, 8)) as usize;
+        let third = heap.alloc(layout(16, 64)) as usize;
         assert_eq!(first, start);
         assert_eq!(second % 8, 0);
+        assert_eq!(third % 64, 0);
     }
 
     #[test]
-    fn cursor_returns_null_when_exhausted() {
-        let mut buf = [0u8; 16];
-        let start = buf.as_mut_ptr() as usize;
+    fn returns_null_when_exhausted() {
+        let mut arena = Arena([0u8; 16]);
+        let mut heap = heap(&mut arena);
 
-        let mut cursor = BumpCursor::new();
-        cursor.init(start, buf.len());
-
-        let _ = cursor.alloc(Layout::from_size_align(12, 1).expect("valid layout"));
-        let exhausted = cursor.alloc(Layout::from_size_align(8, 1).expect("valid layout"));
+        let _ = heap.alloc(layout(12, 1));
+        let exhausted = heap.alloc(layout(8, 1));
         assert!(exhausted.is_null());
     }
+
+    #[test]
+    fn reuses_freed_blocks_and_merges_neighbours() {
+        let mut arena = Arena([0u8; 256]);
+        let mut heap = heap(&mut arena);
+
+        let blocks: Vec<_> = (0..4).map(|_| heap.alloc(layout(64, 8))).collect();
+        assert!(blocks.iter().all(|block| !block.is_null()));
+        assert!(heap.alloc(layout(16, 8)).is_null());
+
+        // In gemischter Reihenfolge freigegeben, ergeben die Blöcke wieder den ganzen Bereich.
+        for index in [1, 3, 0, 2] {
+            unsafe { heap.dealloc(blocks[index], layout(64, 8)) };
+        }
+        assert_eq!(heap.alloc(layout(256, 16)), blocks[0]);
+    }
+
+    #[test]
+    fn survives_many_allocation_rounds() {
+        // Wie eine Shell-Schleife: pro Durchlauf mehrere Puffer anlegen, wachsen lassen und
+        // wieder freigeben. Ohne Freigabe wäre die Arena nach wenigen Runden voll.
+        let mut arena = Arena([0u8; 4096]);
+        let mut heap = heap(&mut arena);
+
+        for round in 0..50_000_usize {
+            let small = heap.alloc(layout(24, 8));
+            let line = heap.alloc(layout(80 + round % 7, 1));
+            let grown = heap.alloc(layout(512, 16));
+            let aligned = heap.alloc(layout(100, 64));
+            assert!(
+                [small, line, grown, aligned]
+                    .iter()
+                    .all(|block| !block.is_null()),
+                "round {round}"
+            );
+            unsafe {
+                heap.dealloc(line, layout(80 + round % 7, 1));
+                heap.dealloc(small, layout(24, 8));
+                heap.dealloc(aligned, layout(100, 64));
+                heap.dealloc(grown, layout(512, 16));
+            }
+        }
+        assert!(!heap.alloc(layout(4096, 16)).is_null());
+    }
 }
@@ -2,8 +2,14 @@
 
 /// Validierung und Zugriff auf Boot-Informationen (u. a. E820-Map).
 pub mod bootinfo;
+/// Page-Fault-Behandlung und Demand Paging.
+pub mod fault;
 /// Einfacher physischer Frame-Allocator.
 pub mod frame_allocator;
 /// Kleiner Heap-Allocator für dynamische Rust-Datenstrukturen.
 pub mod heap;
+/// 4-Level-Page-Tables: Abbilden, Entfernen und Übersetzen von Seiten.
+pub mod paging;
+/// Kernel-Stack mit Guard Page.
+pub mod stack;
 pub mod vm;
@@ -0,0 +1,643 @@
+//! Verwaltung der 4-Level-Page-Tables (PML4 → PDPT → PD → PT).
+//!
+//! Der Mapper kennt physischen Speicher nur über das Trait [`PhysMemory`] und bezieht neue
+//! Tabellen-Frames über [`FrameSource`]. Dadurch lässt sich die komplette Tabellenlogik auf dem
+//! Host gegen simulierten Speicher testen.
+//!
+//! Hintergrund: <https://wiki.osdev.org/Paging>
+
+use super::vm::{
+    FLAG_PAGE_SIZE, FLAG_PRESENT, FLAG_USER, FLAG_WRITABLE, Page, PageSize2M, PageSize4K,
+    PageTableEntry, PhysAddr, VirtAddr, align_down,
+};
+use super::frame_allocator::PhysicalFrame;
+#[cfg(eres_kernel)]
+use super::vm::KERNEL_OFFSET;
+
+/// Einträge pro Tabelle.
+pub const ENTRY_COUNT: usize = 512;
+/// Größe einer kleinen Seite.
+pub const PAGE_SIZE_4K: u64 = 4096;
+/// Größe einer großen Seite (PD-Eintrag mit `FLAG_PAGE_SIZE`).
+pub const PAGE_SIZE_2M: u64 = 2 * 1024 * 1024;
+
+/// Lese-/Schreibzugriff auf Page-Table-Einträge im physischen Speicher.
+pub trait PhysMemory {
+    /// Liest Eintrag `index` der Tabelle im Frame `table`.
+    fn read_entry(&self, table: PhysAddr, index: usize) -> u64;
+    /// Schreibt Eintrag `index` der Tabelle im Frame `table`.
+    fn write_entry(&mut self, table: PhysAddr, index: usize, value: u64);
+    /// Füllt einen Frame mit Nullen (für frisch angelegte Tabellen).
+    fn zero_frame(&mut self, frame: PhysAddr);
+}
+
+/// Quelle für Frames, in denen neue Zwischentabellen angelegt werden.
+pub trait FrameSource {
+    fn alloc_table_frame(&mut self) -> Option<PhysAddr>;
+    /// Nimmt einen Frame zurück, dessen Tabelle nach einem Fehler wieder ausgehängt wurde.
+    fn free_table_frame(&mut self, frame: PhysAddr);
+}
+
+/// Fehler beim Ändern von Abbildungen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MapError {
+    /// Zieladresse ist bereits abgebildet.
+    AlreadyMapped,
+    /// Es existiert keine Abbildung für die Adresse.
+    NotMapped,
+    /// Eine 2-MiB-Seite überdeckt den Bereich bzw. eine Tabelle liegt im Weg.
+    SizeConflict,
+    /// Adresse ist nicht auf die Seitengröße ausgerichtet oder nicht kanonisch.
+    Misaligned,
+    /// Für eine Zwischentabelle war kein Frame mehr frei.
+    OutOfFrames,
+}
+
+/// Aufgelöste Abbildung einer virtuellen Adresse.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Translation {
+    pub phys: PhysAddr,
+    pub flags: u64,
+    pub page_size: u64,
+}
+
+/// Mapper über eine PML4-Hierarchie.
+pub struct PageTableMapper<M: PhysMemory> {
+    pml4: PhysAddr,
+    memory: M,
+}
+
+impl<M: PhysMemory> PageTableMapper<M> {
+    pub fn new(pml4: PhysAddr, memory: M) -> Self {
+        Self { pml4, memory }
+    }
+
+    pub fn pml4(&self) -> PhysAddr {
+        self.pml4
+    }
+
+    /// Bildet eine 4-KiB-Seite auf `phys` ab und legt fehlende Zwischentabellen an.
+    pub fn map_4k(
+        &mut self,
+        page: Page<PageSize4K>,
+        phys: PhysAddr,
+        flags: u64,
+        frames: &mut impl FrameSource,
+    ) -> Result<(), MapError> {
+        check_aligned(page.base, phys, PAGE_SIZE_4K)?;
+        let indices = table_indices(page.base);
+        let pt = self.walk_or_create(page.base, 3, flags, frames)?;
+
+        if PageTableEntry(self.memory.read_entry(pt, indices[3])).is_present() {
+            return Err(MapError::AlreadyMapped);
+        }
+
+        let mut entry = PageTableEntry::empty();
+        entry.set(phys, (flags & !FLAG_PAGE_SIZE) | FLAG_PRESENT);
+        self.memory.write_entry(pt, indices[3], entry.0);
+        flush_tlb(page.base);
+        Ok(())
+    }
+
+    /// Bildet eine 2-MiB-Seite direkt im Page Directory ab.
+    pub fn map_2m(
+        &mut self,
+        page: Page<PageSize2M>,
+        phys: PhysAddr,
+        flags: u64,
+        frames: &mut impl FrameSource,
+    ) -> Result<(), MapError> {
+        check_aligned(page.base, phys, PAGE_SIZE_2M)?;
+        let indices = table_indices(page.base);
+        let pd = self.walk_or_create(page.base, 2, flags, frames)?;
+
+        let existing = PageTableEntry(self.memory.read_entry(pd, indices[2]));
+        if existing.is_present() {
+            return Err(if is_huge(existing) {
+                MapError::AlreadyMapped
+            } else {
+                MapError::SizeConflict
+            });
+        }
+
+        let entry = align_down(phys.0, PAGE_SIZE_2M) | flags | FLAG_PRESENT | FLAG_PAGE_SIZE;
+        self.memory.write_entry(pd, indices[2], entry);
+        flush_tlb(page.base);
+        Ok(())
+    }
+
+    /// Entfernt eine 4-KiB-Abbildung und liefert den zuvor abgebildeten Frame.
+    pub fn unmap_4k(&mut self, page: Page<PageSize4K>) -> Result<PhysAddr, MapError> {
+        check_aligned(page.base, PhysAddr(0), PAGE_SIZE_4K)?;
+        let indices = table_indices(page.base);
+        let pdpt = self.next_table(self.pml4, indices[0])?;
+        let pd = self.next_table(pdpt, indices[1])?;
+        let pt = self.next_table(pd, indices[2])?;
+
+        let entry = PageTableEntry(self.memory.read_entry(pt, indices[3]));
+        if !entry.is_present() {
+            return Err(MapError::NotMapped);
+        }
+
+        self.memory.write_entry(pt, indices[3], 0);
+        flush_tlb(page.base);
+        Ok(entry.addr())
+    }
+
+    /// Entfernt eine 2-MiB-Abbildung und liefert die physische Startadresse.
+    pub fn unmap_2m(&mut self, page: Page<PageSize2M>) -> Result<PhysAddr, MapError> {
+        check_aligned(page.base, PhysAddr(0), PAGE_SIZE_2M)?;
+        let indices = table_indices(page.base);
+        let pdpt = self.next_table(self.pml4, indices[0])?;
+        let pd = self.next_table(pdpt, indices[1])?;
+
+        let entry = PageTableEntry(self.memory.read_entry(pd, indices[2]));
+        if !entry.is_present() {
+            return Err(MapError::NotMapped);
+        }
+        if !is_huge(entry) {
+            return Err(MapError::SizeConflict);
+        }
+
+        self.memory.write_entry(pd, indices[2], 0);
+        flush_tlb(page.base);
+        Ok(PhysAddr(align_down(entry.addr().0, PAGE_SIZE_2M)))
+    }
+
+    /// Übersetzt eine virtuelle Adresse inklusive Seitenoffset.
+    pub fn translate(&self, virt: VirtAddr) -> Option<PhysAddr> {
+        self.translate_page(virt).map(|t| PhysAddr(t.phys.0 + (virt.0 & (t.page_size - 1))))
+    }
+
+    /// Liefert Zielframe, Flags und Seitengröße der Abbildung von `virt`.
+    pub fn translate_page(&self, virt: VirtAddr) -> Option<Translation> {
+        if !is_canonical(virt) {
+            return None;
+        }
+
+        let indices = table_indices(virt);
+        let pdpt = self.next_table(self.pml4, indices[0]).ok()?;
+        let pd = self.next_table(pdpt, indices[1]).ok()?;
+
+        let pd_entry = PageTableEntry(self.memory.read_entry(pd, indices[2]));
+        if !pd_entry.is_present() {
+            return None;
+        }
+        if is_huge(pd_entry) {
+            return Some(Translation {
+                phys: PhysAddr(align_down(pd_entry.addr().0, PAGE_SIZE_2M)),
+                flags: pd_entry.flags(),
+                page_size: PAGE_SIZE_2M,
+            });
+        }
+
+        let entry = PageTableEntry(self.memory.read_entry(pd_entry.addr(), indices[3]));
+        entry.is_present().then_some(Translation {
+            phys: entry.addr(),
+            flags: entry.flags(),
+            page_size: PAGE_SIZE_4K,
+        })
+    }
+
+    /// Folgt einem vorhandenen Tabelleneintrag.
+    fn next_table(&self, table: PhysAddr, index: usize) -> Result<PhysAddr, MapError> {
+        let entry = PageTableEntry(self.memory.read_entry(table, index));
+        if !entry.is_present() {
+            return Err(MapError::NotMapped);
+        }
+        if is_huge(entry) {
+            return Err(MapError::SizeConflict);
+        }
+        Ok(entry.addr())
+    }
+
+    /// Folgt den ersten `levels` Tabellenebenen für `virt` und legt fehlende Tabellen an.
+    ///
+    /// Scheitert eine Ebene, werden die in diesem Aufruf angelegten Tabellen wieder ausgehängt
+    /// und an `frames` zurückgegeben, damit kein halber Pfad verwaister Tabellen stehen bleibt.
+    fn walk_or_create(
+        &mut self,
+        virt: VirtAddr,
+        levels: usize,
+        leaf_flags: u64,
+        frames: &mut impl FrameSource,
+    ) -> Result<PhysAddr, MapError> {
+        let indices = table_indices(virt);
+        // Elterntabelle und Index jeder neu angelegten Tabelle.
+        let mut created = [(PhysAddr(0), 0); 3];
+        let mut count = 0;
+        let mut table = self.pml4;
+        for &index in &indices[..levels] {
+            match self.next_table_or_create(table, index, leaf_flags, frames) {
+                Ok((next, new)) => {
+                    if new {
+                        created[count] = (table, index);
+                        count += 1;
+                    }
+                    table = next;
+                }
+                Err(err) => {
+                    for &(parent, index) in created[..count].iter().rev() {
+                        let frame = PageTableEntry(self.memory.read_entry(parent, index)).addr();
+                        self.memory.write_entry(parent, index, 0);
+                        frames.free_table_frame(frame);
+                    }
+                    if count > 0 {
+                        flush_tlb(virt);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+        Ok(table)
+    }
+
+    /// Folgt einem Tabelleneintrag und legt die Zieltabelle bei Bedarf an; das zweite Feld
+    /// sagt, ob die Tabelle neu ist.
+    ///
+    /// Zwischentabellen sind immer beschreibbar; `FLAG_USER` wird übernommen, damit die
+    /// Rechte allein im Blatteintrag entschieden werden.
+    fn next_table_or_create(
+        &mut self,
+        table: PhysAddr,
+        index: usize,
+        leaf_flags: u64,
+        frames: &mut impl FrameSource,
+    ) -> Result<(PhysAddr, bool), MapError> {
+        let mut entry = PageTableEntry(self.memory.read_entry(table, index));
+        if entry.is_present() {
+            if is_huge(entry) {
+                return Err(MapError::SizeConflict);
+            }
+            if (leaf_flags & FLAG_USER) != 0 && (entry.flags() & FLAG_USER) == 0 {
+                entry.0 |= FLAG_USER;
+                self.memory.write_entry(table, index, entry.0);
+            }
+            return Ok((entry.addr(), false));
+        }
+
+        let frame = frames.alloc_table_frame().ok_or(MapError::OutOfFrames)?;
+        self.memory.zero_frame(frame);
+        entry.set(frame, FLAG_PRESENT | FLAG_WRITABLE | (leaf_flags & FLAG_USER));
+        self.memory.write_entry(table, index, entry.0);
+        Ok((frame, true))
+    }
+}
+
+/// Zerlegt eine virtuelle Adresse in die Indizes für PML4, PDPT, PD und PT.
+pub fn table_indices(virt: VirtAddr) -> [usize; 4] {
+    [
+        ((virt.0 >> 39) & 0x1ff) as usize,
+        ((virt.0 >> 30) & 0x1ff) as usize,
+        ((virt.0 >> 21) & 0x1ff) as usize,
+        ((virt.0 >> 12) & 0x1ff) as usize,
+    ]
+}
+
+/// Prüft, ob Bits 48..63 eine Vorzeichenerweiterung von Bit 47 sind.
+pub fn is_canonical(virt: VirtAddr) -> bool {
+    let upper = virt.0 >> 47;
+    upper == 0 || upper == 0x1_ffff
+}
+
+fn is_huge(entry: PageTableEntry) -> bool {
+    (entry.flags() & FLAG_PAGE_SIZE) != 0
+}
+
+fn check_aligned(virt: VirtAddr, phys: PhysAddr, size: u64) -> Result<(), MapError> {
+    if !is_canonical(virt) || !virt.0.is_multiple_of(size) || !phys.0.is_multiple_of(size) {
+        return Err(MapError::Misaligned);
+    }
+    Ok(())
+}
+
+/// Entfernt den TLB-Eintrag einer einzelnen Seite.
+#[inline]
+pub fn flush_tlb(virt: VirtAddr) {
+    #[cfg(eres_kernel)]
+    unsafe {
+        core::arch::asm!("invlpg [{}]", in(reg) virt.0, options(nostack, preserves_flags));
+    }
+
+    #[cfg(not(eres_kernel))]
+    let _ = virt;
+}
+
+/// Leert den kompletten (nicht-globalen) TLB durch Neuladen von `CR3`.
+#[inline]
+pub fn flush_tlb_all() {
+    #[cfg(eres_kernel)]
+    unsafe {
+        core::arch::asm!(
+            "mov {tmp}, cr3",
+            "mov cr3, {tmp}",
+            tmp = out(reg) _,
+            options(nostack, preserves_flags)
+        );
+    }
+}
+
+/// Physischer Speicher, der ab `offset` linear im virtuellen Adressraum liegt.
+#[derive(Clone, Copy, Debug)]
+pub struct OffsetPhysMemory {
+    offset: u64,
+}
+
+impl OffsetPhysMemory {
+    /// # Safety
+    /// Der gesamte adressierte physische Speicher muss bei `phys + offset` abgebildet sein.
+    pub const unsafe fn new(offset: u64) -> Self {
+        Self { offset }
+    }
+
+    fn table_ptr(&self, table: PhysAddr) -> *mut u64 {
+        (table.0 + self.offset) as *mut u64
+    }
+}
+
+impl PhysMemory for OffsetPhysMemory {
+    fn read_entry(&self, table: PhysAddr, index: usize) -> u64 {
+        unsafe { self.table_ptr(table).add(index).read_volatile() }
+    }
+
+    fn write_entry(&mut self, table: PhysAddr, index: usize, value: u64) {
+        unsafe { self.table_ptr(table).add(index).write_volatile(value) }
+    }
+
+    fn zero_frame(&mut self, frame: PhysAddr) {
+        unsafe { core::ptr::write_bytes(self.table_ptr(frame), 0, ENTRY_COUNT) }
+    }
+}
+
+/// Bezieht Tabellen-Frames aus dem globalen Frame-Allocator.
+pub struct GlobalFrameSource;
+
+impl FrameSource for GlobalFrameSource {
+    fn alloc_table_frame(&mut self) -> Option<PhysAddr> {
+        super::frame_allocator::alloc_frame().map(|frame| PhysAddr(frame.start))
+    }
+
+    fn free_table_frame(&mut self, frame: PhysAddr) {
+        let _ = super::frame_allocator::free_frame(PhysicalFrame { start: frame.0 });
+    }
+}
+
+/// Liest die physische Adresse der aktiven PML4 aus `CR3`.
+#[cfg(eres_kernel)]
+pub fn active_pml4() -> PhysAddr {
+    let value: u64;
+    unsafe {
+        core::arch::asm!("mov {}, cr3", out(reg) value, options(nomem, nostack, preserves_flags));
+    }
+    PhysAddr(value & 0x000f_ffff_ffff_f000)
+}
+
+/// Mapper über die aktiven Page Tables; physischer Speicher liegt ab `KERNEL_OFFSET`.
+#[cfg(eres_kernel)]
+pub fn active_mapper() -> PageTableMapper<OffsetPhysMemory> {
+    PageTableMapper::new(active_pml4(), unsafe { OffsetPhysMemory::new(KERNEL_OFFSET) })
+}
+
+/// Entfernt die Identitätsabbildung der unteren Adresshälfte, die Stage 2 nur für den
+/// Moduswechsel braucht.
+///
+/// Danach ist insbesondere die Nullseite nicht mehr abgebildet, Nullzeiger-Zugriffe lösen
+/// also einen Page Fault aus. Stack, GDT und Boot-Infos müssen vorher auf Higher-Half-Adressen
+/// umgezogen sein.
+#[cfg(eres_kernel)]
+pub fn init() {
+    let mut memory = unsafe { OffsetPhysMemory::new(KERNEL_OFFSET) };
+    memory.write_entry(active_pml4(), 0, 0);
+    flush_tlb_all();
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::collections::BTreeMap;
+    use alloc::vec::Vec;
+
+    use super::{
+        ENTRY_COUNT, FrameSource, MapError, PAGE_SIZE_2M, PageTableMapper, PhysMemory,
+        is_canonical, table_indices,
+    };
+    use crate::memory::vm::{
+        FLAG_NO_EXEC, FLAG_PAGE_SIZE, FLAG_USER, FLAG_WRITABLE, Page, PhysAddr, VirtAddr,
+    };
+
+    #[derive(Default)]
+    struct SimMemory {
+        frames: BTreeMap<u64, [u64; ENTRY_COUNT]>,
+    }
+
+    impl PhysMemory for SimMemory {
+        fn read_entry(&self, table: PhysAddr, index: usize) -> u64 {
+            self.frames.get(&table.0).map_or(0, |t| t[index])
+        }
+
+        fn write_entry(&mut self, table: PhysAddr, index: usize, value: u64) {
+            self.frames.entry(table.0).or_insert([0; ENTRY_COUNT])[index] = value;
+        }
+
+        fn zero_frame(&mut self, frame: PhysAddr) {
+            self.frames.insert(frame.0, [0; ENTRY_COUNT]);
+        }
+    }
+
+    struct SimFrames {
+        next: u64,
+        remaining: usize,
+        freed: Vec<PhysAddr>,
+    }
+
+    impl FrameSource for SimFrames {
+        fn alloc_table_frame(&mut self) -> Option<PhysAddr> {
+            if self.remaining == 0 {
+                return None;
+            }
+            self.remaining -= 1;
+            let frame = self.next;
+            self.next += 0x1000;
+            Some(PhysAddr(frame))
+        }
+
+        fn free_table_frame(&mut self, frame: PhysAddr) {
+            self.remaining += 1;
+            self.freed.push(frame);
+        }
+    }
+
+    fn setup() -> (PageTableMapper<SimMemory>, SimFrames) {
+        let mut memory = SimMemory::default();
+        memory.zero_frame(PhysAddr(0x1000));
+        let frames = SimFrames {
+            next: 0x10_0000,
+            remaining: 64,
+            freed: Vec::new(),
+        };
+        (PageTableMapper::new(PhysAddr(0x1000), memory), frames)
+    }
+
+    #[test]
+    fn splits_virtual_address_into_indices() {
+        let virt = VirtAddr((1 << 39) | (2 << 30) | (3 << 21) | (4 << 12) | 0x123);
+        assert_eq!(table_indices(virt), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn detects_canonical_addresses() {
+        assert!(is_canonical(VirtAddr(0x0000_7fff_ffff_f000)));
+        assert!(is_canonical(VirtAddr(0xffff_8000_0000_0000)));
+        assert!(!is_canonical(VirtAddr(0x0000_8000_0000_0000)));
+    }
+
+    #[test]
+    fn maps_and_translates_4k_page() {
+        let (mut mapper, mut frames) = setup();
+        let page = Page::new(VirtAddr(0x4000_1000));
+        mapper
+            .map_4k(page, PhysAddr(0x50_0000), FLAG_WRITABLE, &mut frames)
+            .expect("map");
+
+        assert_eq!(mapper.translate(VirtAddr(0x4000_1abc)), Some(PhysAddr(0x50_0abc)));
+        assert_eq!(mapper.translate(VirtAddr(0x4000_2000)), None);
+        // PDPT, PD und PT wurden neu angelegt.
+        assert_eq!(frames.remaining, 61);
+
+        let translation = mapper.translate_page(VirtAddr(0x4000_1000)).expect("mapped");
+        assert_eq!(translation.flags & FLAG_WRITABLE, FLAG_WRITABLE);
+        assert_eq!(translation.page_size, 0x1000);
+    }
+
+    #[test]
+    fn reuses_intermediate_tables() {
+        let (mut mapper, mut frames) = setup();
+        mapper
+            .map_4k(Page::new(VirtAddr(0x20_0000)), PhysAddr(0x1000), 0, &mut frames)
+            .expect("map");
+        mapper
+            .map_4k(Page::new(VirtAddr(0x20_1000)), PhysAddr(0x2000), 0, &mut frames)
+            .expect("map");
+        assert_eq!(frames.remaining, 61);
+    }
+
+    #[test]
+    fn rejects_double_map_and_misalignment() {
+        let (mut mapper, mut frames) = setup();
+        let page = Page::new(VirtAddr(0x1000));
+        mapper.map_4k(page, PhysAddr(0x2000), 0, &mut frames).expect("map");
+        assert_eq!(
+            mapper.map_4k(page, PhysAddr(0x3000), 0, &mut frames),
+            Err(MapError::AlreadyMapped)
+        );
+        assert_eq!(
+            mapper.map_4k(Page::new(VirtAddr(0x1800)), PhysAddr(0x3000), 0, &mut frames),
+            Err(MapError::Misaligned)
+        );
+        assert_eq!(
+            mapper.map_4k(Page::new(VirtAddr(0x0000_8000_0000_0000)), PhysAddr(0x3000), 0, &mut frames),
+            Err(MapError::Misaligned)
+        );
+    }
+
+    #[test]
+    fn unmaps_4k_page() {
+        let (mut mapper, mut frames) = setup();
+        let page = Page::new(VirtAddr(0xffff_8000_0010_0000));
+        mapper
+            .map_4k(page, PhysAddr(0x7000), FLAG_NO_EXEC, &mut frames)
+            .expect("map");
+        assert_eq!(mapper.unmap_4k(page), Ok(PhysAddr(0x7000)));
+        assert_eq!(mapper.translate(page.base), None);
+        assert_eq!(mapper.unmap_4k(page), Err(MapError::NotMapped));
+    }
+
+    #[test]
+    fn maps_and_unmaps_2m_page() {
+        let (mut mapper, mut frames) = setup();
+        let page = Page::new(VirtAddr(0x4000_0000));
+        mapper
+            .map_2m(page, PhysAddr(0x80_0000), FLAG_WRITABLE, &mut frames)
+            .expect("map");
+
+        let translation = mapper.translate_page(VirtAddr(0x4012_3456)).expect("mapped");
+        assert_eq!(translation.page_size, PAGE_SIZE_2M);
+        assert_eq!(translation.flags & FLAG_PAGE_SIZE, FLAG_PAGE_SIZE);
+        assert_eq!(mapper.translate(VirtAddr(0x4012_3456)), Some(PhysAddr(0x92_3456)));
+
+        // Eine 4-KiB-Seite innerhalb der großen Seite ist ein Konflikt.
+        assert_eq!(
+            mapper.map_4k(Page::new(VirtAddr(0x4000_1000)), PhysAddr(0x1000), 0, &mut frames),
+            Err(MapError::SizeConflict)
+        );
+        assert_eq!(mapper.unmap_4k(Page::new(VirtAddr(0x4000_1000))), Err(MapError::SizeConflict));
+
+        assert_eq!(mapper.unmap_2m(page), Ok(PhysAddr(0x80_0000)));
+        assert_eq!(mapper.translate(VirtAddr(0x4012_3456)), None);
+    }
+
+    #[test]
+    fn refuses_2m_page_over_existing_table() {
+        let (mut mapper, mut frames) = setup();
+        mapper
+            .map_4k(Page::new(VirtAddr(0x4000_1000)), PhysAddr(0x1000), 0, &mut frames)
+            .expect("map");
+        assert_eq!(
+            mapper.map_2m(Page::new(VirtAddr(0x4000_0000)), PhysAddr(0), 0, &mut frames),
+            Err(MapError::SizeConflict)
+        );
+    }
+
+    #[test]
+    fn propagates_user_flag_to_intermediate_tables() {
+        let (mut mapper, mut frames) = setup();
+        let page = Page::new(VirtAddr(0x40_0000));
+        mapper
+            .map_4k(page, PhysAddr(0x9000), FLAG_USER | FLAG_WRITABLE, &mut frames)
+            .expect("map");
+
+        let indices = table_indices(page.base);
+        let pml4_entry = mapper.memory.read_entry(mapper.pml4(), indices[0]);
+        assert_eq!(pml4_entry & FLAG_USER, FLAG_USER);
+    }
+
+    #[test]
+    fn reports_out_of_frames() {
+        let (mut mapper, mut frames) = setup();
+        frames.remaining = 1;
+        assert_eq!(
+            mapper.map_4k(Page::new(VirtAddr(0x1000)), PhysAddr(0x2000), 0, &mut frames),
+            Err(MapError::OutOfFrames)
+        );
+    }
+
+    #[test]
+    fn releases_new_tables_when_frames_run_dry() {
+        let (mut mapper, mut frames) = setup();
+        let page = Page::new(VirtAddr(0x4000_1000));
+        let indices = table_indices(page.base);
+
+        // PDPT und PD passen noch, für die PT ist kein Frame mehr da.
+        frames.remaining = 2;
+        assert_eq!(
+            mapper.map_4k(page, PhysAddr(0x2000), 0, &mut frames),
+            Err(MapError::OutOfFrames)
+        );
+        assert_eq!(mapper.memory.read_entry(mapper.pml4(), indices[0]), 0);
+        assert_eq!(frames.freed, [PhysAddr(0x10_1000), PhysAddr(0x10_0000)]);
+        assert_eq!(frames.remaining, 2);
+
+        // Vorhandene Tabellen bleiben stehen, nur die neue PD wird zurückgegeben.
+        frames.freed.clear();
+        frames.remaining = 2;
+        mapper
+            .map_2m(Page::new(VirtAddr(0x8000_0000)), PhysAddr(0), 0, &mut frames)
+            .expect("map");
+        frames.remaining = 1;
+        assert_eq!(
+            mapper.map_4k(Page::new(VirtAddr(0xc000_0000)), PhysAddr(0x2000), 0, &mut frames),
+            Err(MapError::OutOfFrames)
+        );
+        assert_eq!(frames.freed, [PhysAddr(0x10_4000)]);
+        assert_eq!(mapper.translate(VirtAddr(0x8000_0000)), Some(PhysAddr(0)));
+    }
+}
@@ -0,0 +1,54 @@
+//! Kernel-Stack mit Guard Page.
+//!
+//! Der Boot-Stack aus Stage 2 ist nur 4 KiB groß und liegt direkt neben anderen Daten; ein
+//! Überlauf würde diese unbemerkt überschreiben. Der Kernel-Stack liegt deshalb in einem eigenen
+//! virtuellen Bereich, unter dem eine nicht abgebildete Guard Page liegt. Ein Überlauf löst dort
+//! einen Page Fault aus, der mangels Stack zum Double Fault eskaliert; der Double-Fault-Handler
+//! läuft auf einem IST-Stack und kann die Ursache über `CR2` erkennen.
+
+use super::paging::PAGE_SIZE_4K;
+
+/// Anzahl abgebildeter Stack-Seiten (64 KiB).
+pub const KERNEL_STACK_PAGES: u64 = 16;
+/// Startadresse der Guard Page (bleibt unabgebildet).
+pub const KERNEL_STACK_GUARD: u64 = 0xffff_ffff_c000_0000;
+/// Unterste abgebildete Stack-Adresse.
+pub const KERNEL_STACK_BOTTOM: u64 = KERNEL_STACK_GUARD + PAGE_SIZE_4K;
+/// Oberes Ende des Stacks (exklusiv, Startwert für `RSP`).
+pub const KERNEL_STACK_TOP: u64 = KERNEL_STACK_BOTTOM + KERNEL_STACK_PAGES * PAGE_SIZE_4K;
+
+/// Prüft, ob `addr` in der Guard Page unter dem Kernel-Stack liegt.
+pub fn is_guard_page(addr: u64) -> bool {
+    (KERNEL_STACK_GUARD..KERNEL_STACK_BOTTOM).contains(&addr)
+}
+
+/// Bildet die Stack-Seiten auf frische Frames ab und liefert den Startwert für `RSP`.
+#[cfg(eres_kernel)]
+pub fn map_kernel_stack() -> Option<u64> {
+    use super::frame_allocator;
+    use super::paging::{self, GlobalFrameSource};
+    use super::vm::{FLAG_WRITABLE, Page, PhysAddr, VirtAddr};
+
+    let mut mapper = paging::active_mapper();
+    for index in 0..KERNEL_STACK_PAGES {
+        let frame = frame_allocator::alloc_frame()?;
+        let page = Page::new(VirtAddr(KERNEL_STACK_BOTTOM + index * PAGE_SIZE_4K));
+        mapper
+            .map_4k(page, PhysAddr(frame.start), FLAG_WRITABLE, &mut GlobalFrameSource)
+            .ok()?;
+    }
+    Some(KERNEL_STACK_TOP)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KERNEL_STACK_BOTTOM, KERNEL_STACK_GUARD, KERNEL_STACK_TOP, is_guard_page};
+
+    #[test]
+    fn detects_guard_page_hits() {
+        assert!(is_guard_page(KERNEL_STACK_GUARD));
+        assert!(is_guard_page(KERNEL_STACK_BOTTOM - 8));
+        assert!(!is_guard_page(KERNEL_STACK_BOTTOM));
+        assert!(!is_guard_page(KERNEL_STACK_TOP - 8));
+    }
+}
@@ -34,6 +34,14 @@ pub const fn align_up(addr: u64, align: u64) -> u64 {
     (addr + align - 1) & !(align - 1)
 }
 
+/// Virtuelle Adresse, unter der physisch 0 liegt (Kernel-Image und das erste GiB RAM).
+pub const KERNEL_OFFSET: u64 = 0xffff_ffff_8000_0000;
+
+/// Übersetzt eine physische Adresse aus dem ersten GiB in ihre Higher-Half-Adresse.
+pub const fn phys_to_virt(phys: PhysAddr) -> VirtAddr {
+    VirtAddr(phys.0 + KERNEL_OFFSET)
+}
+
 pub const FLAG_PRESENT: u64 = 1 << 0;
 pub const FLAG_WRITABLE: u64 = 1 << 1;
 pub const FLAG_USER: u64 = 1 << 2;
@@ -103,15 +111,17 @@ unsafe extern "C" {
 
 #[cfg(eres_kernel)]
 pub fn boot_mapper() -> BootPageDirectoryMapper<'static> {
-    let table = unsafe { &mut *(&raw mut pd_table) };
+    // Die Boot-Tabellen sind mit ihrer physischen Adresse gelinkt.
+    let phys = PhysAddr(&raw mut pd_table as u64);
+    let table = unsafe { &mut *(phys_to_virt(phys).0 as *mut [u64; 512]) };
     BootPageDirectoryMapper::new(table)
 }
 
 #[cfg(test)]
 mod tests {
     use super::{
-        align_down, align_up, BootPageDirectoryMapper, Mapper2M, PageTableEntry, PhysAddr,
-        FLAG_PAGE_SIZE, FLAG_PRESENT, FLAG_WRITABLE,
+        align_down, align_up, phys_to_virt, BootPageDirectoryMapper, Mapper2M, PageTableEntry,
+        PhysAddr, VirtAddr, FLAG_PAGE_SIZE, FLAG_PRESENT, FLAG_WRITABLE,
     };
 
     #[test]
@@ -123,9 +133,9 @@ mod tests {
     #[test]
     fn page_table_entry_roundtrip() {
         let mut entry = PageTableEntry::empty();
-        entry.set(PhysAddr(0x12345_6789), FLAG_PRESENT | FLAG_WRITABLE);
+        entry.set(PhysAddr(0x1_2345_6789), FLAG_PRESENT | FLAG_WRITABLE);
         assert!(entry.is_present());
-        assert_eq!(entry.addr().0, 0x12345_6000);
+        assert_eq!(entry.addr().0, 0x1_2345_6000);
         assert_eq!(entry.flags() & FLAG_WRITABLE, FLAG_WRITABLE);
     }
 
@@ -140,4 +150,9 @@ mod tests {
         assert_eq!(entry.flags() & FLAG_PAGE_SIZE, FLAG_PAGE_SIZE);
         assert_eq!(entry.flags() & FLAG_WRITABLE, FLAG_WRITABLE);
     }
+
+    #[test]
+    fn translates_physical_to_higher_half() {
+        assert_eq!(phys_to_virt(PhysAddr(0xb8000)), VirtAddr(0xffff_ffff_800b_8000));
+    }
 }
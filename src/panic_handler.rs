@@ -1,14 +1,42 @@
 //! Panic-Verhalten im Kernel.
 //!
-//! In `no_std`-Kernen muss ein eigener `#[panic_handler]` definiert werden.
+//! In `no_std`-Kernen muss ein eigener `#[panic_handler]` definiert werden. Der Bericht enthält
+//! Ort, Meldung und einen symbolisierten Backtrace. Er läuft über die normale Konsole und landet
+//! damit auch auf debugcon (`0xE9`), sodass Headless-QEMU-Läufe ihn mitschneiden.
 
+use core::fmt::Write;
 use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicBool, Ordering};
 
-use crate::{arch, console};
+use crate::{arch, console, debug};
 
-/// Gibt eine einfache Fehlermeldung aus und hält das System an.
+static PANICKING: AtomicBool = AtomicBool::new(false);
+
+/// Gibt einen Fehlerbericht aus und hält das System an.
 #[panic_handler]
-fn panic(_info: &PanicInfo) -> ! {
+fn panic(info: &PanicInfo) -> ! {
+    arch::x86_64::disable_interrupts();
+
+    // Eine Panic während des Berichts (z. B. im Backtrace) darf nicht endlos rekursieren.
+    if PANICKING.swap(true, Ordering::SeqCst) {
+        console::write_line(b"Kernel panic while panicking.");
+        arch::x86_64::hang();
+    }
+
+    // Den Bericht auf der Konsole zeigen, auf der er erscheint, auch wenn gerade eine andere
+    // virtuelle Konsole angezeigt wird.
+    console::switch_console(console::output_console());
     console::write_line(b"Kernel panic.");
+    if let Some(location) = info.location() {
+        let _ = writeln!(
+            console::Writer,
+            "  at {}:{}:{}",
+            location.file(),
+            location.line(),
+            location.column()
+        );
+    }
+    let _ = writeln!(console::Writer, "  {}", info.message());
+    debug::backtrace::print_current();
     arch::x86_64::hang()
 }
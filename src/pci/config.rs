@@ -0,0 +1,101 @@
+//! Zugriff auf den Konfigurationsraum.
+//!
+//! Alles oberhalb dieses Moduls geht über [`ConfigAccess`], damit sich Aufzählung und
+//! BAR-Auswertung gegen einen Konfigurationsraum im Speicher testen lassen. [`PortConfig`] ist
+//! der klassische Mechanismus #1: die Dword-Adresse geht nach `CONFIG_ADDRESS` (0xCF8), die
+//! Daten über `CONFIG_DATA` (0xCFC).
+
+use core::fmt;
+
+use crate::arch::x86_64::{self, io};
+
+const CONFIG_ADDRESS: u16 = 0xCF8;
+const CONFIG_DATA: u16 = 0xCFC;
+/// Dword mit Command (untere Hälfte) und Status (obere Hälfte).
+const COMMAND_STATUS: u8 = 0x04;
+/// Write-1-to-clear-Bits des Statusregisters: Parity-, Abort- und SERR-Meldungen (Bits 8, 11-15).
+const STATUS_W1C: u16 = 0xF900;
+
+/// Lage einer Funktion: Bus, Gerät (0..32) und Funktion (0..8).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Address {
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+}
+
+impl Address {
+    pub const fn new(bus: u8, device: u8, function: u8) -> Self {
+        Self {
+            bus,
+            device,
+            function,
+        }
+    }
+}
+
+/// `bus:gerät.funktion` wie bei `lspci`.
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02x}:{:02x}.{}", self.bus, self.device, self.function)
+    }
+}
+
+/// Lese- und Schreibzugriffe auf den Konfigurationsraum einer Funktion.
+pub trait ConfigAccess {
+    /// `offset` ist auf ein Dword ausgerichtet.
+    fn read32(&mut self, address: Address, offset: u8) -> u32;
+    fn write32(&mut self, address: Address, offset: u8, value: u32);
+
+    fn read16(&mut self, address: Address, offset: u8) -> u16 {
+        (self.read32(address, offset & !3) >> ((offset & 2) * 8)) as u16
+    }
+
+    fn read8(&mut self, address: Address, offset: u8) -> u8 {
+        (self.read32(address, offset & !3) >> ((offset & 3) * 8)) as u8
+    }
+
+    /// Liest das umgebende Dword, ändert es und schreibt es zurück. Beim Schreiben von Command
+    /// gehen die Write-1-to-clear-Bits des benachbarten Status als 0 mit, sonst würde jeder
+    /// Zugriff anstehende Fehlermeldungen der Funktion löschen.
+    fn write16(&mut self, address: Address, offset: u8, value: u16) {
+        let shift = (offset & 2) * 8;
+        let mut dword = self.read32(address, offset & !3) & !(0xFFFF << shift);
+        if offset & !3 == COMMAND_STATUS {
+            dword &= !(u32::from(STATUS_W1C) << 16);
+        }
+        self.write32(address, offset & !3, dword | (u32::from(value) << shift));
+    }
+}
+
+/// Konfigurationsmechanismus #1 über die I/O-Ports 0xCF8/0xCFC.
+pub struct PortConfig;
+
+impl PortConfig {
+    fn select(address: Address, offset: u8) {
+        let value = 0x8000_0000
+            | u32::from(address.bus) << 16
+            | u32::from(address.device & 0x1F) << 11
+            | u32::from(address.function & 0x07) << 8
+            | u32::from(offset & 0xFC);
+        io::outl(CONFIG_ADDRESS, value);
+    }
+}
+
+impl ConfigAccess for PortConfig {
+    fn read32(&mut self, address: Address, offset: u8) -> u32 {
+        // Adress- und Datenport bilden einen Zugriff; kein Interrupt-Handler darf dazwischen.
+        let interrupts_were_enabled = x86_64::save_and_disable_interrupts();
+        Self::select(address, offset);
+        let value = io::inl(CONFIG_DATA);
+        x86_64::restore_interrupts(interrupts_were_enabled);
+        value
+    }
+
+    fn write32(&mut self, address: Address, offset: u8, value: u32) {
+        let interrupts_were_enabled = x86_64::save_and_disable_interrupts();
+        Self::select(address, offset);
+        io::outl(CONFIG_DATA, value);
+        x86_64::restore_interrupts(interrupts_were_enabled);
+    }
+}
@@ -0,0 +1,377 @@
+//! Auswertung des Konfigurations-Headers einer Funktion: IDs, Klasse, BARs, Interrupt und
+//! Capabilities.
+
+use alloc::vec::Vec;
+
+use crate::pci::config::{Address, ConfigAccess};
+
+const REG_ID: u8 = 0x00;
+const REG_COMMAND: u8 = 0x04;
+const REG_STATUS: u8 = 0x06;
+const REG_CLASS: u8 = 0x08;
+const REG_HEADER_TYPE: u8 = 0x0E;
+const REG_BAR0: u8 = 0x10;
+const REG_CAPABILITIES: u8 = 0x34;
+const REG_INTERRUPT: u8 = 0x3C;
+
+/// Command-Register: I/O-Ports dekodieren.
+pub const COMMAND_IO: u16 = 0x0001;
+/// Command-Register: Speicherbereiche dekodieren.
+pub const COMMAND_MEMORY: u16 = 0x0002;
+/// Command-Register: Bus-Master-Zugriffe (DMA) erlauben.
+pub const COMMAND_BUS_MASTER: u16 = 0x0004;
+const STATUS_CAPABILITIES: u16 = 0x0010;
+const HEADER_MULTIFUNCTION: u8 = 0x80;
+
+/// Begrenzt den Gang durch die Capability-Liste, falls ein defektes Gerät sie zum Ring verkettet.
+const MAX_CAPABILITIES: usize = 48;
+
+/// Ein Base Address Register: I/O-Ports oder Speicher, jeweils mit der per Sizing ermittelten
+/// Größe. Ein 64-Bit-BAR (`wide`) belegt auch den folgenden Eintrag.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bar {
+    Io {
+        port: u16,
+        size: u32,
+    },
+    Memory {
+        address: u64,
+        size: u64,
+        prefetchable: bool,
+        wide: bool,
+    },
+}
+
+/// Eintrag der Capability-Liste mit seiner Lage im Konfigurationsraum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Capability {
+    pub id: u8,
+    pub offset: u8,
+}
+
+/// Eine aufgezählte PCI-Funktion.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PciDevice {
+    pub address: Address,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub class: u8,
+    pub subclass: u8,
+    pub prog_if: u8,
+    pub revision: u8,
+    /// Header-Layout ohne Multifunktionsbit: 0 Gerät, 1 PCI-Brücke, 2 CardBus-Brücke.
+    pub header_type: u8,
+    pub bars: [Option<Bar>; 6],
+    pub interrupt_line: u8,
+    /// 0 keiner, 1..=4 INTA#..INTD#.
+    pub interrupt_pin: u8,
+    pub capabilities: Vec<Capability>,
+    /// Name des Treibers, der die Funktion übernommen hat; setzt `pci::init`.
+    pub driver: Option<&'static str>,
+}
+
+impl PciDevice {
+    /// Liest und dekodiert die Funktion an `address`; `None`, wenn dort nichts antwortet.
+    pub fn read(cfg: &mut dyn ConfigAccess, address: Address) -> Option<Self> {
+        let id = cfg.read32(address, REG_ID);
+        if id as u16 == 0xFFFF {
+            return None;
+        }
+        let class = cfg.read32(address, REG_CLASS);
+        let header_type = cfg.read8(address, REG_HEADER_TYPE) & !HEADER_MULTIFUNCTION;
+        let bar_count = match header_type {
+            0 => 6,
+            1 => 2,
+            _ => 0,
+        };
+        let interrupt = cfg.read16(address, REG_INTERRUPT);
+
+        Some(Self {
+            address,
+            vendor_id: id as u16,
+            device_id: (id >> 16) as u16,
+            class: (class >> 24) as u8,
+            subclass: (class >> 16) as u8,
+            prog_if: (class >> 8) as u8,
+            revision: class as u8,
+            header_type,
+            bars: read_bars(cfg, address, bar_count),
+            interrupt_line: interrupt as u8,
+            interrupt_pin: (interrupt >> 8) as u8,
+            capabilities: read_capabilities(cfg, address),
+            driver: None,
+        })
+    }
+
+    /// Setzt Bits im Command-Register, z. B. [`COMMAND_IO`], damit die Funktion ihre Ports
+    /// dekodiert.
+    pub fn enable(&self, cfg: &mut dyn ConfigAccess, bits: u16) {
+        let command = cfg.read16(self.address, REG_COMMAND);
+        cfg.write16(self.address, REG_COMMAND, command | bits);
+    }
+}
+
+/// Probiert jeden Bus, jedes Gerät und jede Funktion durch; Brücken brauchen keine
+/// Sonderbehandlung, weil ohnehin alle Busnummern abgefragt werden.
+pub fn enumerate(cfg: &mut dyn ConfigAccess) -> Vec<PciDevice> {
+    let mut devices = Vec::new();
+    for bus in 0..=255 {
+        for device in 0..32 {
+            let Some(first) = PciDevice::read(cfg, Address::new(bus, device, 0)) else {
+                continue;
+            };
+            let multifunction =
+                cfg.read8(first.address, REG_HEADER_TYPE) & HEADER_MULTIFUNCTION != 0;
+            devices.push(first);
+            if multifunction {
+                for function in 1..8 {
+                    devices.extend(PciDevice::read(cfg, Address::new(bus, device, function)));
+                }
+            }
+        }
+    }
+    devices
+}
+
+fn read_bars(cfg: &mut dyn ConfigAccess, address: Address, count: u8) -> [Option<Bar>; 6] {
+    let mut bars = [None; 6];
+    // Solange die BARs das Sizing-Muster aus Einsen enthalten, bleibt die Dekodierung aus.
+    let command = cfg.read16(address, REG_COMMAND);
+    cfg.write16(
+        address,
+        REG_COMMAND,
+        command & !(COMMAND_IO | COMMAND_MEMORY),
+    );
+
+    let mut index = 0;
+    while index < count {
+        let offset = REG_BAR0 + index * 4;
+        let raw = cfg.read32(address, offset);
+        let mask = probe_mask(cfg, address, offset, raw);
+        if raw & 1 != 0 {
+            let size = (!(mask & 0xFFFC) as u16).wrapping_add(1);
+            if mask & 0xFFFC != 0 {
+                bars[index as usize] = Some(Bar::Io {
+                    port: (raw & 0xFFFC) as u16,
+                    size: u32::from(size),
+                });
+            }
+            index += 1;
+            continue;
+        }
+
+        let wide = (raw >> 1) & 0b11 == 0b10 && index + 1 < count;
+        let (mut base, mut size_mask) = (u64::from(raw & !0xF), u64::from(mask & !0xF));
+        if wide {
+            let high = cfg.read32(address, offset + 4);
+            let high_mask = probe_mask(cfg, address, offset + 4, high);
+            base |= u64::from(high) << 32;
+            size_mask |= u64::from(high_mask) << 32;
+        } else {
+            size_mask |= 0xFFFF_FFFF_0000_0000;
+        }
+        if size_mask & 0xFFFF_FFFF != 0 {
+            bars[index as usize] = Some(Bar::Memory {
+                address: base,
+                size: (!size_mask).wrapping_add(1),
+                prefetchable: raw & 0x8 != 0,
+                wide,
+            });
+        }
+        index += if wide { 2 } else { 1 };
+    }
+
+    cfg.write16(address, REG_COMMAND, command);
+    bars
+}
+
+/// Schreibt lauter Einsen in ein BAR und liefert die zurückgelesene Maske; nicht vorhandene
+/// Adressbits lesen sich als 0.
+fn probe_mask(cfg: &mut dyn ConfigAccess, address: Address, offset: u8, raw: u32) -> u32 {
+    cfg.write32(address, offset, 0xFFFF_FFFF);
+    let mask = cfg.read32(address, offset);
+    cfg.write32(address, offset, raw);
+    mask
+}
+
+fn read_capabilities(cfg: &mut dyn ConfigAccess, address: Address) -> Vec<Capability> {
+    let mut capabilities = Vec::new();
+    if cfg.read16(address, REG_STATUS) & STATUS_CAPABILITIES == 0 {
+        return capabilities;
+    }
+    let mut offset = cfg.read8(address, REG_CAPABILITIES) & 0xFC;
+    while offset != 0 && capabilities.len() < MAX_CAPABILITIES {
+        capabilities.push(Capability {
+            id: cfg.read8(address, offset),
+            offset,
+        });
+        offset = cfg.read8(address, offset + 1) & 0xFC;
+    }
+    capabilities
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::collections::BTreeMap;
+    use alloc::format;
+    use alloc::vec::Vec;
+
+    use super::{Bar, COMMAND_BUS_MASTER, COMMAND_IO, Capability, PciDevice, enumerate};
+    use crate::pci::config::{Address, ConfigAccess};
+
+    // Jedes Register hat einen Wert und eine Maske beschreibbarer Bits; das genügt für das
+    // BAR-Sizing.
+    #[derive(Default)]
+    struct FakeConfig {
+        registers: BTreeMap<(Address, u8), (u32, u32)>,
+        /// Bits, die eine geschriebene 1 löscht (Write-1-to-clear).
+        clear_on_write: BTreeMap<(Address, u8), u32>,
+    }
+
+    impl FakeConfig {
+        fn set(&mut self, address: Address, offset: u8, value: u32, writable: u32) {
+            self.registers.insert((address, offset), (value, writable));
+        }
+
+        fn set_w1c(&mut self, address: Address, offset: u8, bits: u32) {
+            self.clear_on_write.insert((address, offset), bits);
+        }
+
+        fn function(&mut self, address: Address, id: u32, class: u32, header: u8) {
+            self.set(address, 0x00, id, 0);
+            self.set(address, 0x04, 0, 0x0000_0007);
+            self.set(address, 0x08, class, 0);
+            self.set(address, 0x0C, u32::from(header) << 16, 0);
+        }
+    }
+
+    impl ConfigAccess for FakeConfig {
+        fn read32(&mut self, address: Address, offset: u8) -> u32 {
+            match self.registers.get(&(address, offset)) {
+                Some((value, _)) => *value,
+                None if offset == 0 => 0xFFFF_FFFF,
+                None => 0,
+            }
+        }
+
+        fn write32(&mut self, address: Address, offset: u8, value: u32) {
+            let w1c = self
+                .clear_on_write
+                .get(&(address, offset))
+                .copied()
+                .unwrap_or(0);
+            if let Some((old, writable)) = self.registers.get_mut(&(address, offset)) {
+                *old = (*old & !*writable) | (value & *writable);
+                *old &= !(value & w1c);
+            }
+        }
+    }
+
+    #[test]
+    fn enumerates_multifunction_devices() {
+        let mut cfg = FakeConfig::default();
+        cfg.function(Address::new(0, 0, 0), 0x1237_8086, 0x0600_0002, 0);
+        cfg.function(Address::new(0, 1, 0), 0x7000_8086, 0x0601_0000, 0x80);
+        cfg.function(Address::new(0, 1, 1), 0x7010_8086, 0x0101_8000, 0);
+        // Funktion 1 eines Einzelfunktionsgeräts wird nicht abgefragt.
+        cfg.function(Address::new(0, 2, 1), 0x1111_1234, 0x0300_0002, 0);
+
+        let devices = enumerate(&mut cfg);
+        let found: Vec<_> = devices
+            .iter()
+            .map(|dev| (dev.address, dev.device_id))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (Address::new(0, 0, 0), 0x1237),
+                (Address::new(0, 1, 0), 0x7000),
+                (Address::new(0, 1, 1), 0x7010),
+            ]
+        );
+        let ide = &devices[2];
+        assert_eq!((ide.class, ide.subclass, ide.prog_if), (0x01, 0x01, 0x80));
+        assert_eq!(format!("{}", ide.address), "00:01.1");
+    }
+
+    #[test]
+    fn decodes_bars_and_capabilities() {
+        let address = Address::new(0, 3, 0);
+        let mut cfg = FakeConfig::default();
+        cfg.function(address, 0x1000_1AF4, 0x0200_0000, 0);
+        cfg.set(address, 0x04, 0x0010_0000, 0x0000_0007);
+        // BAR0: 32 Bytes I/O, BAR1: 4 KiB Speicher, BAR2/3: 16 KiB 64-Bit-Speicher, prefetchable.
+        cfg.set(address, 0x10, 0xC041, 0xFFFF_FFE0);
+        cfg.set(address, 0x14, 0xFEBD_1000, 0xFFFF_F000);
+        cfg.set(address, 0x18, 0xFE00_000C, 0xFFFF_C000);
+        cfg.set(address, 0x1C, 0x0000_0001, 0xFFFF_FFFF);
+        cfg.set(address, 0x34, 0x40, 0);
+        cfg.set(address, 0x3C, 0x0000_010B, 0);
+        cfg.set(address, 0x40, 0x0000_5011, 0);
+        cfg.set(address, 0x50, 0x0000_0009, 0);
+
+        let dev = PciDevice::read(&mut cfg, address).unwrap();
+        assert_eq!(
+            dev.bars[0],
+            Some(Bar::Io {
+                port: 0xC040,
+                size: 32
+            })
+        );
+        assert_eq!(
+            dev.bars[1],
+            Some(Bar::Memory {
+                address: 0xFEBD_1000,
+                size: 0x1000,
+                prefetchable: false,
+                wide: false
+            })
+        );
+        assert_eq!(
+            dev.bars[2],
+            Some(Bar::Memory {
+                address: 0x1_FE00_0000,
+                size: 0x4000,
+                prefetchable: true,
+                wide: true
+            })
+        );
+        assert_eq!(dev.bars[3..], [None; 3]);
+        assert_eq!((dev.interrupt_line, dev.interrupt_pin), (11, 1));
+        assert_eq!(
+            dev.capabilities,
+            [
+                Capability {
+                    id: 0x11,
+                    offset: 0x40
+                },
+                Capability {
+                    id: 0x09,
+                    offset: 0x50
+                }
+            ]
+        );
+        // Das Sizing stellt die BARs und das Command-Register wieder her.
+        assert_eq!(cfg.read32(address, 0x10), 0xC041);
+        assert_eq!(cfg.read32(address, 0x1C), 1);
+    }
+
+    #[test]
+    fn keeps_pending_status_bits_when_writing_command() {
+        let address = Address::new(0, 4, 0);
+        let mut cfg = FakeConfig::default();
+        cfg.function(address, 0x7010_8086, 0x0101_8000, 0);
+        // Status meldet Master Abort und Parity Error; Schreiben einer 1 löscht sie.
+        cfg.set(address, 0x04, 0xA100_0000, 0x0000_0007);
+        cfg.set_w1c(address, 0x04, 0xF900_0000);
+
+        let dev = PciDevice::read(&mut cfg, address).unwrap();
+        dev.enable(&mut cfg, COMMAND_IO | COMMAND_BUS_MASTER);
+        assert_eq!(cfg.read16(address, 0x04), 0x0005);
+        assert_eq!(cfg.read16(address, 0x06), 0xA100);
+
+        // Status selbst lässt sich weiterhin gezielt quittieren.
+        cfg.write16(address, 0x06, 0x2000);
+        assert_eq!(cfg.read32(address, 0x04), 0x8100_0005);
+    }
+}
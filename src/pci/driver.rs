@@ -0,0 +1,119 @@
+//! Zuordnung von Treibern: Jeder Treiber nennt die IDs oder Klassen, die er bedient, und
+//! `pci::init` übergibt jede Funktion dem ersten passenden Treiber aus [`DRIVERS`].
+
+use crate::pci::config::ConfigAccess;
+use crate::pci::device::PciDevice;
+
+/// Ein Eintrag der Treiberliste: genaue Vendor-/Device-ID oder Klasse und Unterklasse.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Match {
+    Id { vendor: u16, device: u16 },
+    Class { class: u8, subclass: u8 },
+}
+
+impl Match {
+    pub fn matches(self, device: &PciDevice) -> bool {
+        match self {
+            Match::Id { vendor, device: id } => {
+                device.vendor_id == vendor && device.device_id == id
+            }
+            Match::Class { class, subclass } => {
+                device.class == class && device.subclass == subclass
+            }
+        }
+    }
+}
+
+/// Ein eingebauter PCI-Treiber.
+pub struct PciDriver {
+    pub name: &'static str,
+    pub matches: &'static [Match],
+    /// Richtet die Funktion ein; bei einem Fehler bleibt sie ungebunden, und er wird geloggt.
+    pub probe: fn(&mut dyn ConfigAccess, &PciDevice) -> Result<(), &'static str>,
+}
+
+impl PciDriver {
+    pub fn handles(&self, device: &PciDevice) -> bool {
+        self.matches.iter().any(|entry| entry.matches(device))
+    }
+}
+
+/// Alle eingebauten Treiber; genauere Einträge (IDs) stehen vor allgemeinen Klassen.
+pub static DRIVERS: [&PciDriver; 2] = [
+    &crate::storage::pci_ide::PIIX,
+    &crate::storage::pci_ide::GENERIC,
+];
+
+/// Erster Treiber aus `drivers`, der die Funktion bedient.
+pub fn find(drivers: &[&'static PciDriver], device: &PciDevice) -> Option<&'static PciDriver> {
+    drivers
+        .iter()
+        .copied()
+        .find(|driver| driver.handles(device))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{Match, PciDriver, find};
+    use crate::pci::config::{Address, ConfigAccess};
+    use crate::pci::device::PciDevice;
+
+    fn probe(_cfg: &mut dyn ConfigAccess, _device: &PciDevice) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    static VIRTIO_NET: PciDriver = PciDriver {
+        name: "virtio-net",
+        matches: &[
+            Match::Id {
+                vendor: 0x1AF4,
+                device: 0x1000,
+            },
+            Match::Id {
+                vendor: 0x1AF4,
+                device: 0x1041,
+            },
+        ],
+        probe,
+    };
+    static ETHERNET: PciDriver = PciDriver {
+        name: "ethernet",
+        matches: &[Match::Class {
+            class: 0x02,
+            subclass: 0x00,
+        }],
+        probe,
+    };
+
+    fn device(vendor_id: u16, device_id: u16, class: u8, subclass: u8) -> PciDevice {
+        PciDevice {
+            address: Address::new(0, 3, 0),
+            vendor_id,
+            device_id,
+            class,
+            subclass,
+            prog_if: 0,
+            revision: 0,
+            header_type: 0,
+            bars: [None; 6],
+            interrupt_line: 11,
+            interrupt_pin: 1,
+            capabilities: Vec::new(),
+            driver: None,
+        }
+    }
+
+    #[test]
+    fn first_matching_driver_wins() {
+        let drivers = [&VIRTIO_NET, &ETHERNET];
+        let name = |dev: &PciDevice| find(&drivers, dev).map(|driver| driver.name);
+        assert_eq!(
+            name(&device(0x1AF4, 0x1041, 0x02, 0x00)),
+            Some("virtio-net")
+        );
+        assert_eq!(name(&device(0x8086, 0x100E, 0x02, 0x00)), Some("ethernet"));
+        assert_eq!(name(&device(0x8086, 0x7010, 0x01, 0x01)), None);
+    }
+}
@@ -0,0 +1,104 @@
+//! Kleine PCI-ID-Datenbank: Namen von Herstellern, Geräten, Klassen und Capabilities für
+//! `lspci`.
+//!
+//! Sie deckt ab, was QEMU und die üblichen Hypervisoren emulieren; alles andere erscheint als
+//! Nummer.
+
+/// Herstellername zur Vendor-ID.
+pub fn vendor_name(vendor: u16) -> Option<&'static str> {
+    Some(match vendor {
+        0x1002 => "AMD/ATI",
+        0x1013 => "Cirrus Logic",
+        0x1022 => "AMD",
+        0x10DE => "NVIDIA",
+        0x10EC => "Realtek",
+        0x1234 => "QEMU",
+        0x15AD => "VMware",
+        0x1AF4 | 0x1B36 => "Red Hat",
+        0x8086 => "Intel",
+        0x80EE => "VirtualBox",
+        _ => return None,
+    })
+}
+
+/// Gerätename zu Vendor- und Device-ID.
+pub fn device_name(vendor: u16, device: u16) -> Option<&'static str> {
+    Some(match (vendor, device) {
+        (0x1013, 0x00B8) => "GD 5446",
+        (0x10EC, 0x8139) => "RTL-8139 Fast Ethernet",
+        (0x1234, 0x1111) => "Standard VGA",
+        (0x1AF4, 0x1000 | 0x1041) => "Virtio network device",
+        (0x1AF4, 0x1001 | 0x1042) => "Virtio block device",
+        (0x1AF4, 0x1002 | 0x1045) => "Virtio memory balloon",
+        (0x1AF4, 0x1003 | 0x1043) => "Virtio console",
+        (0x1AF4, 0x1005 | 0x1044) => "Virtio RNG",
+        (0x1AF4, 0x1050) => "Virtio GPU",
+        (0x1B36, 0x0001) => "QEMU PCI-PCI bridge",
+        (0x1B36, 0x0008) => "QEMU PCIe host bridge",
+        (0x1B36, 0x000D) => "QEMU XHCI host controller",
+        (0x8086, 0x100E) => "82540EM Gigabit Ethernet",
+        (0x8086, 0x10D3) => "82574L Gigabit Ethernet",
+        (0x8086, 0x1237) => "440FX PMC [Natoma]",
+        (0x8086, 0x2415) => "82801AA AC'97 Audio",
+        (0x8086, 0x2668) => "82801FB (ICH6) HD Audio",
+        (0x8086, 0x2918) => "82801IB (ICH9) LPC",
+        (0x8086, 0x2922) => "82801IR (ICH9) SATA [AHCI]",
+        (0x8086, 0x2930) => "82801I (ICH9) SMBus",
+        (0x8086, 0x29C0) => "82G33/Q35 DRAM controller",
+        (0x8086, 0x7000) => "82371SB PIIX3 ISA",
+        (0x8086, 0x7010) => "82371SB PIIX3 IDE",
+        (0x8086, 0x7020) => "82371SB PIIX3 USB",
+        (0x8086, 0x7110) => "82371AB PIIX4 ISA",
+        (0x8086, 0x7111) => "82371AB PIIX4 IDE",
+        (0x8086, 0x7113) => "82371AB PIIX4 ACPI",
+        _ => return None,
+    })
+}
+
+/// Name der Klasse, bei bekannter Unterklasse genauer.
+pub fn class_name(class: u8, subclass: u8) -> &'static str {
+    match (class, subclass) {
+        (0x00, 0x01) => "VGA-compatible unclassified device",
+        (0x00, _) => "Unclassified device",
+        (0x01, 0x00) => "SCSI storage controller",
+        (0x01, 0x01) => "IDE interface",
+        (0x01, 0x05) => "ATA controller",
+        (0x01, 0x06) => "SATA controller",
+        (0x01, 0x08) => "Non-Volatile memory controller",
+        (0x01, _) => "Mass storage controller",
+        (0x02, 0x00) => "Ethernet controller",
+        (0x02, _) => "Network controller",
+        (0x03, 0x00) => "VGA compatible controller",
+        (0x03, _) => "Display controller",
+        (0x04, 0x01) => "Multimedia audio controller",
+        (0x04, 0x03) => "Audio device",
+        (0x04, _) => "Multimedia controller",
+        (0x05, _) => "Memory controller",
+        (0x06, 0x00) => "Host bridge",
+        (0x06, 0x01) => "ISA bridge",
+        (0x06, 0x04) => "PCI bridge",
+        (0x06, _) => "Bridge",
+        (0x07, _) => "Communication controller",
+        (0x08, _) => "System peripheral",
+        (0x0C, 0x03) => "USB controller",
+        (0x0C, 0x05) => "SMBus",
+        (0x0C, _) => "Serial bus controller",
+        (0xFF, _) => "Unassigned class",
+        _ => "Unknown class",
+    }
+}
+
+/// Name einer Capability aus der Liste im Konfigurationsraum.
+pub fn capability_name(id: u8) -> &'static str {
+    match id {
+        0x01 => "Power Management",
+        0x05 => "MSI",
+        0x09 => "Vendor Specific",
+        0x0D => "Bridge subsystem vendor",
+        0x10 => "Express",
+        0x11 => "MSI-X",
+        0x12 => "SATA",
+        0x13 => "Advanced Features",
+        _ => "Unknown",
+    }
+}
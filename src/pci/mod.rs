@@ -0,0 +1,59 @@
+//! PCI-Bus.
+//!
+//! [`init`] zählt beim Start einmal alle Funktionen über Konfigurationsmechanismus #1 auf,
+//! bindet die eingebauten Treiber und merkt sich das Ergebnis; [`devices`] liefert davon eine
+//! Kopie (z. B. für `lspci`).
+
+pub mod config;
+pub mod device;
+pub mod driver;
+pub mod ids;
+
+use alloc::vec::Vec;
+use core::cell::UnsafeCell;
+
+use crate::arch;
+use crate::pci::config::ConfigAccess;
+use crate::pci::device::PciDevice;
+use crate::pci::driver::PciDriver;
+
+struct DeviceCell(UnsafeCell<Vec<PciDevice>>);
+unsafe impl Sync for DeviceCell {}
+
+static DEVICES: DeviceCell = DeviceCell(UnsafeCell::new(Vec::new()));
+
+fn with_devices<T>(f: impl FnOnce(&mut Vec<PciDevice>) -> T) -> T {
+    let interrupts_were_enabled = arch::x86_64::save_and_disable_interrupts();
+    let result = f(unsafe { &mut *DEVICES.0.get() });
+    arch::x86_64::restore_interrupts(interrupts_were_enabled);
+    result
+}
+
+/// Alle beim Start gefundenen Funktionen.
+pub fn devices() -> Vec<PciDevice> {
+    with_devices(|devices| devices.clone())
+}
+
+/// Zählt den Bus auf und bindet die Treiber; einmal aufzurufen, sobald der Heap steht.
+#[cfg(eres_kernel)]
+pub fn init() {
+    let mut cfg = config::PortConfig;
+    let mut devices = device::enumerate(&mut cfg);
+    bind(&mut cfg, &driver::DRIVERS, &mut devices);
+    kinfo!("pci: {} functions", devices.len());
+    with_devices(|slot| *slot = devices);
+}
+
+/// Übergibt jede Funktion dem ersten passenden Treiber; schlägt dessen `probe` fehl, bleibt sie
+/// ungebunden.
+fn bind(cfg: &mut dyn ConfigAccess, drivers: &[&'static PciDriver], devices: &mut [PciDevice]) {
+    for device in devices {
+        let Some(driver) = driver::find(drivers, device) else {
+            continue;
+        };
+        match (driver.probe)(cfg, device) {
+            Ok(()) => device.driver = Some(driver.name),
+            Err(message) => kwarn!("pci {}: {} failed: {message}", device.address, driver.name),
+        }
+    }
+}
@@ -1,585 +1,651 @@
+mod argv;
+mod block_commands;
+mod builtins;
+mod command;
+mod completion;
+mod editor;
+mod env;
+mod fs_commands;
+mod line_editor;
+mod output;
+mod pci_commands;
+mod pipeline;
+mod script;
+mod script_commands;
+mod text_commands;
+
+use crate::arch::x86_64::keyboard::KeyEvent;
 use crate::{arch, console};
-use alloc::string::String;
 use alloc::vec::Vec;
-use core::arch::asm;
-
-#[cfg(eres_kernel)]
-use crate::fs::simplefs::SimpleFs;
-#[cfg(eres_kernel)]
-use crate::fs::vfs::{resolve_path, FileSystem, NodeType};
-#[cfg(eres_kernel)]
-use crate::storage::ata_pio::AtaPio;
-#[cfg(eres_kernel)]
-use crate::storage::cache::CachedBlockDevice;
-
-const MAX_LINE: usize = 128;
+use argv::Token;
+use command::{CommandError, Context};
+use completion::{Candidate, Target};
+use env::Env;
+use line_editor::{Echo, EditResult, LineEditor};
+use output::Output;
+use script::ScriptError;
+
 const MAX_HISTORY: usize = 16;
-const HELP_TEXT: &[u8] = b"commands: help echo clear history mem ticks ls cat stat panic halt reboot";
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum CommandKind {
-    Empty,
-    Help,
-    Echo,
-    Clear,
-    History,
-    Mem,
-    Ticks,
-    Ls,
-    Cat,
-    Stat,
-    Panic,
-    Halt,
-    Reboot,
-    Unknown,
-}
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-struct ParsedCommand<'a> {
-    kind: CommandKind,
-    arg: &'a [u8],
+/// Skript, das die erste Shell vor dem ersten Prompt ausführt.
+const INIT_SCRIPT: &str = "/init.rc";
+
+/// Eingabezustand einer Shell auf einer virtuellen Konsole.
+struct Session {
+    console: usize,
+    editor: LineEditor,
+    history: Vec<Vec<u8>>,
+    history_index: Option<usize>,
+    /// Letzte Taste war ein Tab ohne eindeutigen Treffer; der nächste listet die Kandidaten.
+    tab_pending: bool,
+    env: Env,
 }
 
-pub fn run() -> ! {
-    let mut line_buf = [0_u8; MAX_LINE];
-    let mut len = 0_usize;
-    let mut history: Vec<Vec<u8>> = Vec::new();
-    let mut history_index: Option<usize> = None;
+impl Session {
+    fn new(console: usize) -> Self {
+        Self {
+            console,
+            editor: LineEditor::new(),
+            history: Vec::new(),
+            history_index: None,
+            tab_pending: false,
+            env: Env::new(),
+        }
+    }
 
-    console::write_line(b"Type 'help' for commands.");
-    prompt();
+    /// Führt [`INIT_SCRIPT`] aus; fehlt die Datei, passiert nichts.
+    fn run_init_script(&mut self) {
+        let Ok(text) = fs_commands::read_simplefs_file(INIT_SCRIPT) else {
+            return;
+        };
+        let result = run_script(&text, &self.history, &mut self.env, 1, &mut Output::Console);
+        if let Err((line, err)) = result {
+            kprint!("{INIT_SCRIPT}: line {line}: ");
+            console::write_line(err.describe());
+        }
+    }
 
-    loop {
-        if let Some(key) = arch::x86_64::keyboard::try_read_key() {
-            match key {
-                arch::x86_64::keyboard::KeyEvent::Enter => {
-                    console::write_byte(b'\n');
-                    execute_command(&line_buf[..len], &mut history);
-                    len = 0;
-                    history_index = None;
-                    prompt();
+    fn handle_key(&mut self, key: KeyEvent) {
+        let mut echo = Echo::new();
+        let tab_pending = core::mem::take(&mut self.tab_pending);
+        match key {
+            KeyEvent::Char(b'\t') => self.complete(tab_pending, &mut echo),
+            KeyEvent::Up => {
+                if self.history.is_empty() {
+                    return;
                 }
-                arch::x86_64::keyboard::KeyEvent::Backspace => {
-                    if len > 0 {
-                        len -= 1;
-                        console::backspace();
-                    }
-                    history_index = None;
+
+                let index = match self.history_index {
+                    Some(current) if current > 0 => current - 1,
+                    Some(current) => current,
+                    None => self.history.len() - 1,
+                };
+                self.history_index = Some(index);
+                self.editor.set_line(&self.history[index], &mut echo);
+            }
+            KeyEvent::Down => match self.history_index {
+                Some(current) if current + 1 < self.history.len() => {
+                    self.history_index = Some(current + 1);
+                    self.editor.set_line(&self.history[current + 1], &mut echo);
                 }
-                arch::x86_64::keyboard::KeyEvent::Char(b) if is_printable_ascii(b) => {
-                    if len < MAX_LINE {
-                        line_buf[len] = b;
-                        len += 1;
-                        console::write_byte(b);
-                    }
-                    history_index = None;
+                Some(_) => {
+                    self.history_index = None;
+                    self.editor.set_line(b"", &mut echo);
                 }
-                arch::x86_64::keyboard::KeyEvent::Up => {
-                    if history.is_empty() {
-                        continue;
-                    }
-
-                    history_index = Some(match history_index {
-                        Some(current) if current > 0 => current - 1,
-                        Some(current) => current,
-                        None => history.len() - 1,
-                    });
-
-                    if let Some(index) = history_index {
-                        replace_line(&mut line_buf, &mut len, &history[index]);
+                None => {}
+            },
+            _ => {
+                let result = self.editor.handle_key(key, &mut echo);
+                console::write_str(echo.as_bytes());
+                echo.clear();
+                match result {
+                    EditResult::Pending => {}
+                    EditResult::Submit => {
+                        console::write_byte(b'\n');
+                        execute_line(self.editor.line(), &mut self.history, &mut self.env);
+                        self.editor.clear();
+                        self.history_index = None;
+                        prompt();
                     }
-                }
-                arch::x86_64::keyboard::KeyEvent::Down => {
-                    if history.is_empty() {
-                        continue;
+                    EditResult::Abort => {
+                        console::write_byte(b'\n');
+                        self.editor.clear();
+                        self.history_index = None;
+                        prompt();
                     }
-
-                    match history_index {
-                        Some(current) if current + 1 < history.len() => {
-                            history_index = Some(current + 1);
-                            replace_line(&mut line_buf, &mut len, &history[current + 1]);
-                        }
-                        Some(_) => {
-                            history_index = None;
-                            replace_line(&mut line_buf, &mut len, b"");
-                        }
-                        None => {}
+                    EditResult::ClearScreen => {
+                        // Als Escape-Sequenz, damit auch ein serielles Terminal gelöscht wird.
+                        console::write_str(b"\x1b[H\x1b[2J");
+                        prompt();
+                        self.editor.redraw(&mut echo);
                     }
                 }
-                _ => {}
             }
-        } else {
-            arch::x86_64::halt();
         }
-    }
-}
-
-fn prompt() {
-    console::write_str(b"> ");
-}
-
-fn execute_command(line: &[u8], history: &mut Vec<Vec<u8>>) {
-    let parsed = parse_command(line);
+        console::write_str(echo.as_bytes());
+    }
+
+    /// Tab: ergänzt das Wort vor dem Cursor; beim zweiten Tab ohne Fortschritt werden alle
+    /// Kandidaten aufgelistet.
+    fn complete(&mut self, tab_pending: bool, echo: &mut Echo) {
+        let (prefix, candidates) = match completion::target(self.editor.before_cursor()) {
+            Target::Command { prefix } => (
+                prefix,
+                command::COMMANDS
+                    .iter()
+                    .map(|command| Candidate::new(command.name, false))
+                    .collect(),
+            ),
+            Target::Path { dir, prefix } => (prefix, fs_commands::simplefs_candidates(dir)),
+        };
+        let completion = completion::complete(prefix, candidates);
 
-    if !line.is_empty() {
-        if history.len() >= MAX_HISTORY {
-            let _ = history.remove(0);
+        if !completion.insert.is_empty() {
+            self.editor.insert_bytes(&completion.insert, echo);
+        } else if completion.matches.len() > 1 {
+            if tab_pending {
+                self.list_candidates(&completion.matches, echo);
+            } else {
+                self.tab_pending = true;
+            }
         }
-        history.push(line.to_vec());
     }
 
-    match parsed.kind {
-        CommandKind::Empty => {}
-        CommandKind::Help => {
-            console::write_line(HELP_TEXT);
-        }
-        CommandKind::Echo => {
-            console::write_line(parsed.arg);
-        }
-        CommandKind::Clear => {
-            console::clear();
-        }
-        CommandKind::History => {
-            for entry in history.iter() {
-                console::write_line(entry);
+    fn list_candidates(&mut self, candidates: &[Candidate], echo: &mut Echo) {
+        self.editor.handle_key(KeyEvent::End, echo);
+        echo.push(b"\n");
+        console::write_str(echo.as_bytes());
+        echo.clear();
+        // Die Liste kann länger als `Echo` sein und geht direkt auf die Konsole.
+        for (index, candidate) in candidates.iter().enumerate() {
+            if index > 0 {
+                console::write_str(b"  ");
             }
-        }
-        CommandKind::Ticks => {
-            console::write_str(b"ticks=");
-            console::write_u64(arch::x86_64::pit::ticks());
-            console::write_byte(b'\n');
-        }
-        CommandKind::Mem => {
-            if let Some(stats) = crate::memory::frame_allocator::stats() {
-                console::write_str(b"frames total=");
-                console::write_u64(stats.total_frames);
-                console::write_str(b" allocated=");
-                console::write_u64(stats.allocated_frames);
-                console::write_str(b" free=");
-                console::write_u64(stats.free_frames);
-                console::write_byte(b'\n');
-            } else {
-                console::write_line(b"frame allocator not initialized");
+            console::write_str(&candidate.name);
+            if candidate.directory {
+                console::write_byte(b'/');
             }
         }
-        CommandKind::Ls => {
-            let path = match core::str::from_utf8(parsed.arg) {
-                Ok(path) => path,
-                Err(_) => {
-                    console::write_line(b"invalid path");
-                    return;
-                }
-            };
-            run_ls(path);
-        }
-        CommandKind::Cat => {
-            let path = match core::str::from_utf8(parsed.arg) {
-                Ok(path) => path,
-                Err(_) => {
-                    console::write_line(b"invalid path");
-                    return;
-                }
-            };
-            run_cat(path);
-        }
-        CommandKind::Stat => {
-            let path = match core::str::from_utf8(parsed.arg) {
-                Ok(path) => path,
-                Err(_) => {
-                    console::write_line(b"invalid path");
-                    return;
-                }
-            };
-            run_stat(path);
+        console::write_byte(b'\n');
+
+        prompt();
+        self.editor.redraw(echo);
+    }
+}
+
+/// Startet je eine Shell auf allen virtuellen Konsolen außer der Log-Konsole.
+pub fn run() -> ! {
+    // Ab hier sind Konsolenzeilen Kommandoausgaben und gehören nicht mehr ins Kernel-Log.
+    crate::log::kmsg::set_console_capture(false);
+
+    let mut sessions: Vec<Session> = (0..console::CONSOLE_COUNT)
+        .filter(|index| *index != console::LOG_CONSOLE)
+        .map(Session::new)
+        .collect();
+    for (index, session) in sessions.iter_mut().enumerate() {
+        console::set_output_console(session.console);
+        if index == 0 {
+            session.run_init_script();
         }
-        CommandKind::Panic => {
-            unsafe {
-                asm!("ud2", options(nomem, nostack, preserves_flags));
+        console::write_line(b"Type 'help' for commands.");
+        prompt();
+    }
+
+    loop {
+        let mut idle = true;
+        for session in &mut sessions {
+            if let Some(key) = arch::x86_64::keyboard::try_read_key_from(session.console) {
+                idle = false;
+                console::set_output_console(session.console);
+                session.handle_key(key);
             }
-            arch::x86_64::hang();
-        }
-        CommandKind::Halt => {
-            console::write_line(b"Halting CPU.");
-            arch::x86_64::hang();
         }
-        CommandKind::Reboot => {
-            console::write_line(b"Rebooting.");
-            arch::x86_64::reboot();
-        }
-        CommandKind::Unknown => {
-            console::write_line(b"unknown command");
+        if idle {
+            arch::x86_64::halt();
         }
     }
 }
 
-#[cfg(eres_kernel)]
-type FsDevice = CachedBlockDevice<AtaPio>;
-
-#[cfg(eres_kernel)]
-fn mount_simplefs() -> Result<SimpleFs<FsDevice>, &'static [u8]> {
-    let dev = CachedBlockDevice::new(AtaPio::primary_slave(), 16);
-    SimpleFs::mount(dev).map_err(|_| b"simplefs unavailable".as_slice())
-}
-
-#[cfg(eres_kernel)]
-fn resolve_simplefs_path(fs: &SimpleFs<FsDevice>, path: &str) -> Result<crate::fs::vfs::NodeId, &'static [u8]> {
-    let normalized = normalize_simplefs_path(path);
-    if normalized == "/" {
-        Ok(fs.root())
-    } else {
-        resolve_path(fs, &normalized).map_err(|_| b"path not found".as_slice())
-    }
+fn prompt() {
+    console::write_str(b"\x1b[92m>\x1b[0m ");
 }
 
-fn normalize_simplefs_path(path: &str) -> String {
-    let trimmed = path.trim();
-    if trimmed.is_empty() || trimmed == "/" {
-        return String::from("/");
+/// Führt eine Eingabezeile aus und merkt sie sich in der History.
+fn execute_line(line: &[u8], history: &mut Vec<Vec<u8>>, env: &mut Env) {
+    if !line.is_empty() {
+        if history.len() >= MAX_HISTORY {
+            let _ = history.remove(0);
+        }
+        history.push(line.to_vec());
     }
 
-    if trimmed.as_bytes()[0] == b'/' {
-        String::from(trimmed)
-    } else {
-        let mut out = String::from("/");
-        out.push_str(trimmed);
-        out
+    if let Err((_, err)) = run_script(line, history, env, 0, &mut Output::Console) {
+        console::write_line(err.describe());
+        env.set_status(command::USAGE);
     }
 }
 
-#[cfg(eres_kernel)]
-fn run_ls(path: &str) {
-    let Ok(fs) = mount_simplefs() else {
-        console::write_line(b"simplefs unavailable");
-        return;
+/// Führt Skripttext aus und liefert den letzten Exit-Status. Die letzte Stufe jeder Pipeline
+/// ohne `>` schreibt nach `out`; `depth` ist die Verschachtelungstiefe von `source`.
+fn run_script(
+    text: &[u8],
+    history: &[Vec<u8>],
+    env: &mut Env,
+    depth: usize,
+    out: &mut Output,
+) -> Result<u8, (usize, ScriptError)> {
+    let nodes = script::parse(text)?;
+    let mut shell = Shell {
+        history,
+        env,
+        depth,
+        out,
     };
-
-    let Ok(node) = resolve_simplefs_path(&fs, path) else {
-        console::write_line(b"path not found");
-        return;
-    };
-
-    let Ok(meta) = fs.metadata(node) else {
-        console::write_line(b"stat failed");
-        return;
-    };
-
-    if meta.node_type != NodeType::Directory {
-        console::write_line(b"not a directory");
-        return;
-    }
-
-    let Ok(entries) = fs.list(node) else {
-        console::write_line(b"list failed");
-        return;
-    };
-
-    if entries.is_empty() {
-        console::write_line(b"(empty)");
-        return;
-    }
-
-    for entry in entries {
-        console::write_line(entry.name().as_bytes());
-    }
+    let status = script::run(&nodes, &mut shell).unwrap_or(command::INTERRUPTED);
+    shell.env.set_status(status);
+    Ok(status)
 }
 
-#[cfg(not(eres_kernel))]
-fn run_ls(_path: &str) {
-    console::write_line(b"simplefs unavailable");
+/// Führt die Kommandos eines Skripts aus.
+struct Shell<'a> {
+    history: &'a [Vec<u8>],
+    env: &'a mut Env,
+    depth: usize,
+    out: &'a mut Output,
 }
 
-#[cfg(eres_kernel)]
-fn run_cat(path: &str) {
-    let Ok(fs) = mount_simplefs() else {
-        console::write_line(b"simplefs unavailable");
-        return;
-    };
-
-    let Ok(node) = resolve_simplefs_path(&fs, path) else {
-        console::write_line(b"path not found");
-        return;
-    };
-
-    let Ok(meta) = fs.metadata(node) else {
-        console::write_line(b"stat failed");
-        return;
-    };
-
-    if meta.node_type != NodeType::File {
-        console::write_line(b"not a file");
-        return;
-    }
-
-    let size = meta.size as usize;
-    let mut buffer = Vec::new();
-    buffer.resize(size, 0);
-
-    let mut total = 0_usize;
-    while total < size {
-        match fs.read(node, total as u64, &mut buffer[total..]) {
-            Ok(0) => break,
-            Ok(read) => total += read,
-            Err(_) => {
-                console::write_line(b"read failed");
-                return;
+impl script::Executor for Shell<'_> {
+    fn command(&mut self, tokens: &[Token], line: usize) -> Option<u8> {
+        let status = self.execute_pipeline(tokens, line);
+        self.env.set_status(status);
+        (status != command::INTERRUPTED).then_some(status)
+    }
+
+    /// Ctrl+C bricht Schleifen ab; andere Tasten während eines Skripts gehen verloren.
+    #[cfg(eres_kernel)]
+    fn interrupted(&mut self) -> bool {
+        let console = console::output_console();
+        while let Some(key) = arch::x86_64::keyboard::try_read_key_from(console) {
+            if key == KeyEvent::Ctrl(b'c') {
+                console::write_line(b"interrupted");
+                return true;
             }
         }
+        false
     }
 
-    if total > 0 {
-        console::write_str(&buffer[..total]);
-    }
-    if total == 0 || buffer[total - 1] != b'\n' {
-        console::write_byte(b'\n');
+    #[cfg(not(eres_kernel))]
+    fn interrupted(&mut self) -> bool {
+        false
     }
 }
 
-#[cfg(not(eres_kernel))]
-fn run_cat(_path: &str) {
-    console::write_line(b"simplefs unavailable");
-}
+impl Shell<'_> {
+    /// Fehlermeldung, in Skripten mit Zeilennummer.
+    fn error(&self, line: usize, message: &[u8]) {
+        if self.depth > 0 {
+            kprint!("line {line}: ");
+        }
+        console::write_line(message);
+    }
 
-#[cfg(eres_kernel)]
-fn run_stat(path: &str) {
-    let Ok(fs) = mount_simplefs() else {
-        console::write_line(b"simplefs unavailable");
-        return;
-    };
+    /// Wie [`Shell::error`], aber mit dem betroffenen Pfad vor der Meldung.
+    fn file_error(&self, line: usize, path: &[u8], message: &[u8]) {
+        if self.depth > 0 {
+            kprint!("line {line}: ");
+        }
+        fs_commands::report(path, message);
+    }
 
-    let Ok(node) = resolve_simplefs_path(&fs, path) else {
-        console::write_line(b"path not found");
-        return;
-    };
+    fn execute_pipeline(&mut self, tokens: &[Token], line: usize) -> u8 {
+        let pipeline = match pipeline::parse(tokens, self.env) {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                self.error(line, err.describe());
+                return command::USAGE;
+            }
+        };
 
-    let Ok(meta) = fs.metadata(node) else {
-        console::write_line(b"stat failed");
-        return;
-    };
+        // `NAME=value` allein setzt eine Variable.
+        if let [argv] = pipeline.stages.as_slice()
+            && let [word] = argv.as_slice()
+            && pipeline.input.is_none()
+            && pipeline.output.is_none()
+            && let Some((name, value)) = env::split_assignment(word)
+        {
+            self.env.set(name, value);
+            return command::SUCCESS;
+        }
 
-    let kind = if meta.node_type == NodeType::Directory {
-        b"directory".as_slice()
-    } else {
-        b"file".as_slice()
-    };
+        // Erst alle Stufen auflösen, damit ein Tippfehler hinten nichts vorne ausführt.
+        let argvs: Vec<Vec<&[u8]>> = pipeline
+            .stages
+            .iter()
+            .map(|argv| argv.iter().map(Vec::as_slice).collect())
+            .collect();
+        let mut commands = Vec::new();
+        for argv in &argvs {
+            match command::resolve(argv) {
+                Ok(command) => commands.push(command),
+                Err(CommandError::Unknown) => {
+                    self.error(line, b"unknown command");
+                    return command::NOT_FOUND;
+                }
+                Err(CommandError::Usage(command)) => {
+                    if self.depth > 0 {
+                        kprint!("line {line}: ");
+                    }
+                    command::write_usage(&mut Output::Console, command);
+                    return command::USAGE;
+                }
+            }
+        }
 
-    console::write_str(b"type=");
-    console::write_str(kind);
-    console::write_str(b" size=");
-    console::write_u64(meta.size);
-    console::write_byte(b'\n');
-}
+        let mut input = match &pipeline.input {
+            Some(path) => match redirect_path(path).and_then(fs_commands::read_simplefs_file) {
+                Ok(text) => Some(text),
+                Err(message) => {
+                    self.file_error(line, path, message);
+                    return command::FAILURE;
+                }
+            },
+            None => None,
+        };
 
-#[cfg(not(eres_kernel))]
-fn run_stat(_path: &str) {
-    console::write_line(b"simplefs unavailable");
-}
+        // Jede Stufe außer der letzten schreibt in einen Puffer, der die Eingabe der nächsten
+        // ist. Der Status der Pipeline ist der ihrer letzten Stufe.
+        let last = commands.len() - 1;
+        let mut status = command::SUCCESS;
+        for (index, (command, argv)) in commands.iter().zip(&argvs).enumerate() {
+            let mut buffer = Output::Buffer(Vec::new());
+            let out = if index == last && pipeline.output.is_none() {
+                &mut *self.out
+            } else {
+                &mut buffer
+            };
+            let mut ctx = Context {
+                history: self.history,
+                input: input.as_deref(),
+                out,
+                env: self.env,
+                depth: self.depth,
+            };
+            status = (command.run)(&mut ctx, argv);
+            input = Some(buffer.into_bytes());
+        }
 
-fn parse_command(line: &[u8]) -> ParsedCommand<'_> {
-    let trimmed = trim_spaces(line);
-    if trimmed.is_empty() {
-        return ParsedCommand {
-            kind: CommandKind::Empty,
-            arg: b"",
-        };
+        if let Some(redirect) = &pipeline.output {
+            let text = input.unwrap_or_default();
+            let written = redirect_path(&redirect.path)
+                .and_then(|path| fs_commands::write_simplefs_file(path, &text, redirect.append));
+            if let Err(message) = written {
+                self.file_error(line, &redirect.path, message);
+                return command::FAILURE;
+            }
+        }
+        status
     }
+}
 
-    let (cmd, arg) = split_cmd_arg(trimmed);
-    match cmd {
-        b"help" if arg.is_empty() => ParsedCommand {
-            kind: CommandKind::Help,
-            arg: b"",
-        },
-        b"clear" if arg.is_empty() => ParsedCommand {
-            kind: CommandKind::Clear,
-            arg: b"",
-        },
-        b"history" if arg.is_empty() => ParsedCommand {
-            kind: CommandKind::History,
-            arg: b"",
-        },
-        b"mem" if arg.is_empty() => ParsedCommand {
-            kind: CommandKind::Mem,
-            arg: b"",
-        },
-        b"ticks" if arg.is_empty() => ParsedCommand {
-            kind: CommandKind::Ticks,
-            arg: b"",
-        },
-        b"panic" if arg.is_empty() => ParsedCommand {
-            kind: CommandKind::Panic,
-            arg: b"",
-        },
-        b"halt" if arg.is_empty() => ParsedCommand {
-            kind: CommandKind::Halt,
-            arg: b"",
-        },
-        b"reboot" if arg.is_empty() => ParsedCommand {
-            kind: CommandKind::Reboot,
-            arg: b"",
-        },
-        b"echo" if !arg.is_empty() => ParsedCommand {
-            kind: CommandKind::Echo,
-            arg,
-        },
-        b"ls" => ParsedCommand {
-            kind: CommandKind::Ls,
-            arg: if arg.is_empty() { b"/" } else { arg },
-        },
-        b"cat" if !arg.is_empty() => ParsedCommand {
-            kind: CommandKind::Cat,
-            arg,
-        },
-        b"stat" if !arg.is_empty() => ParsedCommand {
-            kind: CommandKind::Stat,
-            arg,
-        },
-        _ => ParsedCommand {
-            kind: CommandKind::Unknown,
-            arg: b"",
-        },
-    }
+fn redirect_path(path: &[u8]) -> Result<&str, &'static [u8]> {
+    core::str::from_utf8(path).map_err(|_| b"invalid path".as_slice())
 }
 
 #[cfg(any(test, feature = "qemu-test"))]
 pub fn run_command_self_tests() -> bool {
     let mut ok = true;
-    ok &= check_parse(b"", CommandKind::Empty, b"");
-    ok &= check_parse(b"help", CommandKind::Help, b"");
-    ok &= check_parse(b"clear", CommandKind::Clear, b"");
-    ok &= check_parse(b"history", CommandKind::History, b"");
-    ok &= check_parse(b"mem", CommandKind::Mem, b"");
-    ok &= check_parse(b"ticks", CommandKind::Ticks, b"");
-    ok &= check_parse(b"panic", CommandKind::Panic, b"");
-    ok &= check_parse(b"halt", CommandKind::Halt, b"");
-    ok &= check_parse(b"reboot", CommandKind::Reboot, b"");
-    ok &= check_parse(b"echo hello", CommandKind::Echo, b"hello");
-    ok &= check_parse(b"ls", CommandKind::Ls, b"/");
-    ok &= check_parse(b"ls /", CommandKind::Ls, b"/");
-    ok &= check_parse(b"cat /motd.txt", CommandKind::Cat, b"/motd.txt");
-    ok &= check_parse(b"stat /motd.txt", CommandKind::Stat, b"/motd.txt");
-    ok &= check_parse(b"echo", CommandKind::Unknown, b"");
-    ok &= check_parse(b"cat", CommandKind::Unknown, b"");
-    ok &= check_parse(b"stat", CommandKind::Unknown, b"");
-    ok &= check_parse(b"unknown", CommandKind::Unknown, b"");
+    ok &= check_tokens(b"", &[]);
+    ok &= check_tokens(b"  echo   a  ", &[b"echo", b"a"]);
+    ok &= check_tokens(br#"echo "a  b" 'c d' e\ f"#, &[b"echo", b"a  b", b"c d", b"e f"]);
+    ok &= check_tokens(br#"echo "say \"hi\"" x""y"#, &[b"echo", b"say \"hi\"", b"xy"]);
+    ok &= argv::tokenize(b"echo \"open").is_err();
+    ok &= argv::tokenize(b"echo end\\").is_err();
+    ok &= check_tokens(b"echo '>' \\|", &[b"echo", b">", b"|"]);
+    ok &= check_pipeline(b"dmesg | grep ata | wc -l", 3, None, None);
+    ok &= check_pipeline(b"grep x < /in.txt > /out.txt", 1, Some(b"/in.txt"), Some(b"/out.txt"));
+    ok &= check_pipeline(b"ls>>/list.txt", 1, None, Some(b"/list.txt"));
+    ok &= parse_line(b"ls |").is_none();
+    ok &= parse_line(b"ls >").is_none();
+    ok &= parse_line(b"ls > /a | wc").is_none();
+    ok &= check_tokens(b"echo $? \"${HOME}\" # comment", &[b"echo", b"0", b""]);
+    ok &= env::split_assignment(b"GREETING=hi").is_some();
+    ok &= script::parse(b"if test -e /init.rc; then source /init.rc; else echo no; fi").is_ok();
+    ok &= script::parse(b"while true; do echo; done").is_ok();
+    ok &= script::parse(b"if true; then echo").is_err();
+    ok &= script::parse(b"done").is_err();
+    ok &= check_parse(b"help", Some(b"help"));
+    ok &= check_parse(b"help cat", Some(b"help"));
+    ok &= check_parse(b"clear", Some(b"clear"));
+    ok &= check_parse(b"history", Some(b"history"));
+    ok &= check_parse(b"mem", Some(b"mem"));
+    ok &= check_parse(b"ticks", Some(b"ticks"));
+    ok &= check_parse(b"tty", Some(b"tty"));
+    ok &= check_parse(b"showkey", Some(b"showkey"));
+    ok &= check_parse(b"loadkeys", Some(b"loadkeys"));
+    ok &= check_parse(b"loadkeys us", Some(b"loadkeys"));
+    ok &= check_parse(b"log", Some(b"log"));
+    ok &= check_parse(b"log vga debug", Some(b"log"));
+    ok &= check_parse(b"dmesg", Some(b"dmesg"));
+    ok &= check_parse(b"dmesg -l warn,error", Some(b"dmesg"));
+    ok &= builtins::parse_dmesg_args(&[b"-l", b"warn,error"])
+        .is_some_and(|o| o.levels == [true, true, false, false, false]);
+    ok &= builtins::parse_dmesg_args(&[b"-l", b"loud"]).is_none();
+    ok &= check_parse(b"panic", Some(b"panic"));
+    ok &= check_parse(b"halt", Some(b"halt"));
+    ok &= check_parse(b"reboot", Some(b"reboot"));
+    ok &= check_parse(b"echo hello", Some(b"echo"));
+    ok &= check_parse(b"ls", Some(b"ls"));
+    ok &= check_parse(b"ls /", Some(b"ls"));
+    ok &= check_parse(b"cat /motd.txt", Some(b"cat"));
+    ok &= check_parse(b"stat /motd.txt", Some(b"stat"));
+    ok &= check_parse(b"ls -l /docs", Some(b"ls"));
+    ok &= check_parse(b"write /a.txt hello world", Some(b"write"));
+    ok &= check_parse(b"append /a.txt more", Some(b"append"));
+    ok &= check_parse(b"touch /a.txt /b.txt", Some(b"touch"));
+    ok &= check_parse(b"cp /a.txt /docs", Some(b"cp"));
+    ok &= check_parse(b"mv /a.txt /c.txt", Some(b"mv"));
+    ok &= check_parse(b"rm /b.txt /c.txt", Some(b"rm"));
+    ok &= check_parse(b"mkdir /docs", Some(b"mkdir"));
+    ok &= check_parse(b"rmdir /docs", Some(b"rmdir"));
+    ok &= check_parse(b"lsblk", Some(b"lsblk"));
+    ok &= check_parse(b"edit /init.rc", Some(b"edit"));
+    ok &= check_parse(b"hexdump -s 1s -n 64 /dev/hdb", Some(b"hexdump"));
+    ok &= check_parse(b"dd if=/dev/hda of=/mbr.bin count=1", Some(b"dd"));
+    ok &= check_parse(b"dma", Some(b"dma"));
+    ok &= check_parse(b"dma /dev/hda off", Some(b"dma"));
+    ok &= check_parse(b"diskbench hda 1024k", Some(b"diskbench"));
+    ok &= check_parse(b"lspci", Some(b"lspci"));
+    ok &= check_parse(b"lspci -v", Some(b"lspci"));
+    ok &= check_parse(b"cat", Some(b"cat"));
+    ok &= check_parse(b"grep -v x /a.txt", Some(b"grep"));
+    ok &= check_parse(b"wc -l", Some(b"wc"));
+    ok &= check_parse(b"head -n 3", Some(b"head"));
+    ok &= check_parse(b"tail", Some(b"tail"));
+    ok &= check_parse(b"source /init.rc", Some(b"source"));
+    ok &= check_parse(b"set", Some(b"set"));
+    ok &= check_parse(b"unset A B", Some(b"unset"));
+    ok &= check_parse(b"test -e /motd.txt", Some(b"test"));
+    ok &= check_parse(b"true", Some(b"true"));
+    ok &= check_parse(b"false", Some(b"false"));
+    ok &= check_parse(b"source", None);
+    ok &= check_parse(b"unset", None);
+    ok &= check_parse(b"grep", None);
+    ok &= check_parse(b"echo", None);
+    ok &= check_parse(b"stat", None);
+    ok &= check_parse(b"cp /a.txt", None);
+    ok &= check_parse(b"rm", None);
+    ok &= check_parse(b"lsblk -a", None);
+    ok &= check_parse(b"lspci -v -n", None);
+    ok &= check_parse(b"diskbench", None);
+    ok &= check_parse(b"edit", None);
+    ok &= check_parse(b"tty 2", None);
+    ok &= check_parse(b"unknown", None);
     ok
 }
 
+/// Pipeline eines einzelnen Kommandos ohne gesetzte Variablen.
 #[cfg(any(test, feature = "qemu-test"))]
-fn check_parse(line: &[u8], expected_kind: CommandKind, expected_arg: &[u8]) -> bool {
-    let parsed = parse_command(line);
-    parsed.kind == expected_kind && parsed.arg == expected_arg
+fn parse_line(line: &[u8]) -> Option<pipeline::Pipeline> {
+    let tokens = argv::tokenize(line).ok()?;
+    pipeline::parse(&tokens, &Env::new()).ok()
 }
 
-fn is_printable_ascii(byte: u8) -> bool {
-    (0x20..=0x7E).contains(&byte)
+#[cfg(any(test, feature = "qemu-test"))]
+fn check_tokens(line: &[u8], expected: &[&[u8]]) -> bool {
+    parse_line(line).is_some_and(|pipeline| match pipeline.stages.as_slice() {
+        [] => expected.is_empty(),
+        [argv] => argv == expected,
+        _ => false,
+    })
 }
 
-fn trim_spaces(mut input: &[u8]) -> &[u8] {
-    while let Some((first, rest)) = input.split_first() {
-        if *first == b' ' {
-            input = rest;
-        } else {
-            break;
-        }
-    }
-
-    while matches!(input.last(), Some(b' ')) {
-        input = &input[..input.len() - 1];
-    }
-
-    input
+/// Prüft, welches Kommando eine Zeile aufruft (`None`: unbekannt oder falsche Argumente).
+#[cfg(any(test, feature = "qemu-test"))]
+fn check_parse(line: &[u8], expected: Option<&[u8]>) -> bool {
+    let Some(pipeline) = parse_line(line) else {
+        return false;
+    };
+    let [argv] = pipeline.stages.as_slice() else {
+        return false;
+    };
+    let argv: Vec<&[u8]> = argv.iter().map(Vec::as_slice).collect();
+    command::resolve(&argv).ok().map(|command| command.name) == expected
 }
 
-fn split_cmd_arg(input: &[u8]) -> (&[u8], &[u8]) {
-    match input.iter().position(|b| *b == b' ') {
-        Some(space) => {
-            let cmd = &input[..space];
-            let arg = trim_spaces(&input[space + 1..]);
-            (cmd, arg)
-        }
-        None => (input, b""),
-    }
+/// Prüft die Stufen und Umleitungen einer Pipeline.
+#[cfg(any(test, feature = "qemu-test"))]
+fn check_pipeline(line: &[u8], stages: usize, input: Option<&[u8]>, output: Option<&[u8]>) -> bool {
+    parse_line(line).is_some_and(|pipeline| {
+        pipeline.stages.len() == stages
+            && pipeline.input.as_deref() == input
+            && pipeline.output.map(|redirect| redirect.path).as_deref() == output
+    })
 }
 
-fn replace_line(line_buf: &mut [u8], len: &mut usize, replacement: &[u8]) {
-    while *len > 0 {
-        console::backspace();
-        *len -= 1;
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::argv::tokenize;
+    use super::builtins::parse_dmesg_args;
+    use super::command::{self, COMMANDS, CommandError};
+    use super::fs_commands::normalize_simplefs_path;
+    use super::parse_line;
+
+    /// Name des aufgerufenen Kommandos und seine Argumente (ohne den Namen).
+    fn parse(line: &[u8]) -> (Result<&'static [u8], ()>, Vec<Vec<u8>>) {
+        let mut stages = parse_line(line).expect("pipeline").stages;
+        assert_eq!(stages.len(), 1);
+        let tokens = stages.remove(0);
+        let argv: Vec<&[u8]> = tokens.iter().map(Vec::as_slice).collect();
+        let name = match command::resolve(&argv) {
+            Ok(command) => Ok(command.name),
+            Err(CommandError::Unknown | CommandError::Usage(_)) => Err(()),
+        };
+        (name, tokens.into_iter().skip(1).collect())
     }
 
-    let copy_len = replacement.len().min(line_buf.len());
-    line_buf[..copy_len].copy_from_slice(&replacement[..copy_len]);
-    *len = copy_len;
-    for byte in &replacement[..copy_len] {
-        console::write_byte(*byte);
+    #[test]
+    fn command_names_are_unique() {
+        for (index, command) in COMMANDS.iter().enumerate() {
+            assert!(COMMANDS[..index].iter().all(|other| other.name != command.name));
+            assert!(command.min_args <= command.max_args);
+        }
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::{normalize_simplefs_path, parse_command, CommandKind};
 
     #[test]
     fn parses_help() {
-        let parsed = parse_command(b"help");
-        assert_eq!(parsed.kind, CommandKind::Help);
-        assert_eq!(parsed.arg, b"");
+        assert_eq!(parse(b"help"), (Ok(&b"help"[..]), Vec::new()));
+        assert_eq!(parse(b"help cat").1, [b"cat"]);
+        assert!(parse(b"help cat ls").0.is_err());
     }
 
     #[test]
     fn parses_echo_argument() {
-        let parsed = parse_command(b"echo hallo");
-        assert_eq!(parsed.kind, CommandKind::Echo);
-        assert_eq!(parsed.arg, b"hallo");
+        let (name, args) = parse(b"echo hallo");
+        assert_eq!(name, Ok(&b"echo"[..]));
+        assert_eq!(args, [b"hallo"]);
+        assert_eq!(parse(b"echo 'a  b' c").1, [&b"a  b"[..], b"c"]);
+        assert!(parse(b"echo").0.is_err());
     }
 
     #[test]
     fn parses_unknown_command() {
-        let parsed = parse_command(b"foo");
-        assert_eq!(parsed.kind, CommandKind::Unknown);
-        assert_eq!(parsed.arg, b"");
+        assert!(matches!(
+            command::resolve(&[b"foo"]),
+            Err(CommandError::Unknown)
+        ));
     }
 
     #[test]
     fn parses_history_command() {
-        let parsed = parse_command(b"history");
-        assert_eq!(parsed.kind, CommandKind::History);
-        assert_eq!(parsed.arg, b"");
+        assert_eq!(parse(b"history"), (Ok(&b"history"[..]), Vec::new()));
     }
 
     #[test]
     fn parses_ticks_command() {
-        let parsed = parse_command(b"ticks");
-        assert_eq!(parsed.kind, CommandKind::Ticks);
-        assert_eq!(parsed.arg, b"");
+        assert_eq!(parse(b"ticks"), (Ok(&b"ticks"[..]), Vec::new()));
+    }
+
+    #[test]
+    fn parses_tty_command() {
+        assert_eq!(parse(b"tty").0, Ok(&b"tty"[..]));
+        assert!(matches!(
+            command::resolve(&[b"tty", b"2"]),
+            Err(CommandError::Usage(command)) if command.name == b"tty"
+        ));
+    }
+
+    #[test]
+    fn parses_loadkeys_command() {
+        assert_eq!(parse(b"loadkeys").0, Ok(&b"loadkeys"[..]));
+        let (name, args) = parse(b"loadkeys /dvorak.map");
+        assert_eq!(name, Ok(&b"loadkeys"[..]));
+        assert_eq!(args, [b"/dvorak.map"]);
     }
 
     #[test]
     fn parses_mem_command() {
-        let parsed = parse_command(b"mem");
-        assert_eq!(parsed.kind, CommandKind::Mem);
-        assert_eq!(parsed.arg, b"");
+        assert_eq!(parse(b"mem"), (Ok(&b"mem"[..]), Vec::new()));
+    }
+
+    #[test]
+    fn parses_log_setting() {
+        let (name, args) = parse(b"log memory::heap trace");
+        assert_eq!(name, Ok(&b"log"[..]));
+        assert_eq!(args, [&b"memory::heap"[..], b"trace"]);
+    }
+
+    #[test]
+    fn parses_dmesg_options() {
+        let options = parse_dmesg_args(&[b"-c", b"-l", b"info", b"-r"]).expect("options");
+        assert!(options.clear);
+        assert!(options.raw);
+        assert_eq!(options.levels, [false, false, true, false, false]);
+        assert_eq!(parse_dmesg_args(&[]).map(|o| o.levels), Some([true; 5]));
+        assert!(parse_dmesg_args(&[b"-l"]).is_none());
+        assert!(parse_dmesg_args(&[b"-x"]).is_none());
     }
 
     #[test]
     fn parses_ls_default_path() {
-        let parsed = parse_command(b"ls");
-        assert_eq!(parsed.kind, CommandKind::Ls);
-        assert_eq!(parsed.arg, b"/");
+        assert_eq!(parse(b"ls"), (Ok(&b"ls"[..]), Vec::new()));
+        assert_eq!(parse(b"ls /docs").1, [b"/docs"]);
     }
 
     #[test]
     fn parses_cat_path() {
-        let parsed = parse_command(b"cat /motd.txt");
-        assert_eq!(parsed.kind, CommandKind::Cat);
-        assert_eq!(parsed.arg, b"/motd.txt");
+        let (name, args) = parse(b"cat /motd.txt \"/docs/intro.txt\"");
+        assert_eq!(name, Ok(&b"cat"[..]));
+        assert_eq!(args, [&b"/motd.txt"[..], b"/docs/intro.txt"]);
+        assert_eq!(parse(b"cat"), (Ok(&b"cat"[..]), Vec::new()));
     }
 
     #[test]
     fn parses_stat_path() {
-        let parsed = parse_command(b"stat /version.txt");
-        assert_eq!(parsed.kind, CommandKind::Stat);
-        assert_eq!(parsed.arg, b"/version.txt");
+        let (name, args) = parse(b"stat /version.txt");
+        assert_eq!(name, Ok(&b"stat"[..]));
+        assert_eq!(args, [b"/version.txt"]);
     }
 
     #[test]
@@ -594,8 +660,18 @@ mod tests {
 
     #[test]
     fn parses_empty_line() {
-        let parsed = parse_command(b"");
-        assert_eq!(parsed.kind, CommandKind::Empty);
-        assert_eq!(parsed.arg, b"");
+        assert!(tokenize(b"").expect("argv").is_empty());
+        assert!(matches!(command::resolve(&[]), Err(CommandError::Unknown)));
+    }
+
+    #[test]
+    fn parses_init_script() {
+        let nodes = super::script::parse(include_bytes!("../fs/root/init.rc")).expect("init.rc");
+        assert!(!nodes.is_empty());
+    }
+
+    #[test]
+    fn runs_command_self_tests() {
+        assert!(super::run_command_self_tests());
     }
 }
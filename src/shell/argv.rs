@@ -0,0 +1,329 @@
+//! Zerlegt eine Eingabezeile in Wörter und Operatoren.
+//!
+//! Wörter werden durch Leerzeichen oder Tabs getrennt. Wie in POSIX-Shells gilt:
+//!
+//! - `'...'` übernimmt alles bis zum nächsten `'` wörtlich.
+//! - `"..."` übernimmt alles bis zum nächsten `"`; darin maskiert `\` nur `"`, `\` und `$`.
+//! - Außerhalb von Anführungszeichen maskiert `\` das folgende Zeichen.
+//! - `$NAME`, `${NAME}` und `$?` werden außerhalb von `'...'` erst beim Ausführen durch den
+//!   Wert der Variablen ersetzt ([`Word::expand`]), ohne das Ergebnis weiter aufzuteilen.
+//! - `#` am Anfang eines Wortes leitet einen Kommentar bis zum Zeilenende ein.
+//!
+//! Anführungszeichen können mitten in einem Argument stehen (`a"b c"d` ergibt `ab cd`), und
+//! `""` ergibt ein leeres Argument. Unmaskiertes `|`, `<`, `>`, `>>` und `;` sind Operatoren
+//! (siehe [`super::pipeline`] und [`super::script`]) und trennen Wörter auch ohne Leerzeichen.
+
+use alloc::vec::Vec;
+use core::iter::Peekable;
+
+use super::env::{self, Env};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenizeError {
+    UnterminatedQuote,
+    TrailingBackslash,
+    /// `${` ohne `}` oder mit ungültigem Namen.
+    BadSubstitution,
+}
+
+impl TokenizeError {
+    pub fn describe(self) -> &'static [u8] {
+        match self {
+            TokenizeError::UnterminatedQuote => b"unterminated quote",
+            TokenizeError::TrailingBackslash => b"trailing backslash",
+            TokenizeError::BadSubstitution => b"bad substitution",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Pipe,
+    Input,
+    Output,
+    Append,
+    /// `;` trennt Kommandos auf einer Zeile.
+    Separator,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Literal(Vec<u8>),
+    Variable(Vec<u8>),
+}
+
+/// Wort vor dem Einsetzen der Variablen.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Word {
+    parts: Vec<Part>,
+    /// Enthält Anführungszeichen; das Wort bleibt dann auch leer expandiert erhalten.
+    quoted: bool,
+}
+
+impl Word {
+    fn push(&mut self, byte: u8) {
+        match self.parts.last_mut() {
+            Some(Part::Literal(literal)) => literal.push(byte),
+            _ => self.parts.push(Part::Literal(alloc::vec![byte])),
+        }
+    }
+
+    /// Text eines Wortes ohne Anführungszeichen und Variablen, z. B. für Schlüsselwörter.
+    pub fn literal(&self) -> Option<&[u8]> {
+        match self.parts.as_slice() {
+            _ if self.quoted => None,
+            [] => Some(b""),
+            [Part::Literal(literal)] => Some(literal),
+            _ => None,
+        }
+    }
+
+    /// Setzt die Variablen aus `env` ein; `None`, wenn ein Wort ohne Anführungszeichen leer
+    /// wird (`$UNSET` ergibt kein Argument, `"$UNSET"` ein leeres).
+    pub fn expand(&self, env: &Env) -> Option<Vec<u8>> {
+        let mut out = Vec::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => out.extend_from_slice(literal),
+                Part::Variable(name) => out.extend_from_slice(&env.expand(name)),
+            }
+        }
+        (self.quoted || !out.is_empty()).then_some(out)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Token {
+    Word(Word),
+    Operator(Operator),
+}
+
+pub fn tokenize(line: &[u8]) -> Result<Vec<Token>, TokenizeError> {
+    let mut tokens = Vec::new();
+    let mut bytes = line.iter().copied().peekable();
+    // `None`, solange zwischen zwei Wörtern nur Trennzeichen gelesen wurden.
+    let mut current: Option<Word> = None;
+
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b' ' | b'\t' => {
+                if let Some(word) = current.take() {
+                    tokens.push(Token::Word(word));
+                }
+            }
+            b'#' if current.is_none() => break,
+            b'|' | b'<' | b'>' | b';' => {
+                if let Some(word) = current.take() {
+                    tokens.push(Token::Word(word));
+                }
+                let operator = match byte {
+                    b'|' => Operator::Pipe,
+                    b'<' => Operator::Input,
+                    b';' => Operator::Separator,
+                    _ if bytes.next_if_eq(&b'>').is_some() => Operator::Append,
+                    _ => Operator::Output,
+                };
+                tokens.push(Token::Operator(operator));
+            }
+            b'\'' => {
+                let word = current.get_or_insert_with(Word::default);
+                word.quoted = true;
+                loop {
+                    match bytes.next() {
+                        Some(b'\'') => break,
+                        Some(byte) => word.push(byte),
+                        None => return Err(TokenizeError::UnterminatedQuote),
+                    }
+                }
+            }
+            b'"' => {
+                let word = current.get_or_insert_with(Word::default);
+                word.quoted = true;
+                loop {
+                    match bytes.next() {
+                        Some(b'"') => break,
+                        Some(b'\\') => match bytes.next() {
+                            Some(escaped @ (b'"' | b'\\' | b'$')) => word.push(escaped),
+                            Some(other) => {
+                                word.push(b'\\');
+                                word.push(other);
+                            }
+                            None => return Err(TokenizeError::UnterminatedQuote),
+                        },
+                        Some(b'$') => push_dollar(word, &mut bytes)?,
+                        Some(byte) => word.push(byte),
+                        None => return Err(TokenizeError::UnterminatedQuote),
+                    }
+                }
+            }
+            b'\\' => {
+                let escaped = bytes.next().ok_or(TokenizeError::TrailingBackslash)?;
+                current.get_or_insert_with(Word::default).push(escaped);
+            }
+            b'$' => push_dollar(current.get_or_insert_with(Word::default), &mut bytes)?,
+            _ => current.get_or_insert_with(Word::default).push(byte),
+        }
+    }
+
+    if let Some(word) = current {
+        tokens.push(Token::Word(word));
+    }
+    Ok(tokens)
+}
+
+/// Liest nach einem `$` den Variablennamen; ohne gültigen Namen bleibt `$` ein Zeichen.
+fn push_dollar<I: Iterator<Item = u8>>(
+    word: &mut Word,
+    bytes: &mut Peekable<I>,
+) -> Result<(), TokenizeError> {
+    let mut name = Vec::new();
+    if bytes.next_if_eq(&b'?').is_some() {
+        name.push(b'?');
+    } else if bytes.next_if_eq(&b'{').is_some() {
+        loop {
+            match bytes.next() {
+                Some(b'}') => break,
+                Some(byte) => name.push(byte),
+                None => return Err(TokenizeError::BadSubstitution),
+            }
+        }
+        if !env::is_valid_name(&name) && name != b"?" {
+            return Err(TokenizeError::BadSubstitution);
+        }
+    } else if bytes
+        .peek()
+        .is_some_and(|byte| byte.is_ascii_alphabetic() || *byte == b'_')
+    {
+        while let Some(byte) = bytes.next_if(|byte| byte.is_ascii_alphanumeric() || *byte == b'_') {
+            name.push(byte);
+        }
+    } else {
+        word.push(b'$');
+        return Ok(());
+    }
+    word.parts.push(Part::Variable(name));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{Operator, Token, TokenizeError, Word, tokenize};
+    use crate::shell::env::Env;
+
+    fn words_in(line: &[u8], env: &Env) -> Vec<Vec<u8>> {
+        tokenize(line)
+            .expect("argv")
+            .into_iter()
+            .filter_map(|token| match token {
+                Token::Word(word) => word.expand(env),
+                Token::Operator(operator) => panic!("unexpected {operator:?}"),
+            })
+            .collect()
+    }
+
+    fn words(line: &[u8]) -> Vec<Vec<u8>> {
+        words_in(line, &Env::new())
+    }
+
+    #[test]
+    fn splits_on_whitespace() {
+        assert_eq!(words(b"  ls \t /docs  "), [&b"ls"[..], b"/docs"]);
+        assert!(words(b"   ").is_empty());
+    }
+
+    #[test]
+    fn splits_operators() {
+        let word = |text: &[u8]| {
+            let mut word = Word::default();
+            text.iter().for_each(|byte| word.push(*byte));
+            Token::Word(word)
+        };
+        assert_eq!(
+            tokenize(b"cat<a|wc >>b>c;").expect("argv"),
+            [
+                word(b"cat"),
+                Token::Operator(Operator::Input),
+                word(b"a"),
+                Token::Operator(Operator::Pipe),
+                word(b"wc"),
+                Token::Operator(Operator::Append),
+                word(b"b"),
+                Token::Operator(Operator::Output),
+                word(b"c"),
+                Token::Operator(Operator::Separator),
+            ]
+        );
+        assert_eq!(
+            words(br#"echo '|' "<" \>"#),
+            [&b"echo"[..], b"|", b"<", b">"]
+        );
+    }
+
+    #[test]
+    fn handles_quotes_and_escapes() {
+        assert_eq!(
+            words(br#"echo "a  b" 'c "d"' e\ f "g\"h\n" x""y ''"#),
+            [
+                &b"echo"[..],
+                b"a  b",
+                b"c \"d\"",
+                b"e f",
+                b"g\"h\\n",
+                b"xy",
+                b""
+            ]
+        );
+        assert_eq!(
+            tokenize(b"echo \"open"),
+            Err(TokenizeError::UnterminatedQuote)
+        );
+        assert_eq!(
+            tokenize(b"echo 'open"),
+            Err(TokenizeError::UnterminatedQuote)
+        );
+        assert_eq!(
+            tokenize(b"echo end\\"),
+            Err(TokenizeError::TrailingBackslash)
+        );
+    }
+
+    #[test]
+    fn expands_variables_when_asked() {
+        let mut env = Env::new();
+        env.set(b"NAME", b"eres os");
+        env.set_status(3);
+        assert_eq!(
+            words_in(
+                br#"$NAME "$NAME!" '$NAME' \$NAME ${NAME}_x $? $ $UNSET "$UNSET""#,
+                &env
+            ),
+            [
+                &b"eres os"[..],
+                b"eres os!",
+                b"$NAME",
+                b"$NAME",
+                b"eres os_x",
+                b"3",
+                b"$",
+                b""
+            ]
+        );
+        assert_eq!(
+            tokenize(b"echo ${NAME"),
+            Err(TokenizeError::BadSubstitution)
+        );
+        assert_eq!(tokenize(b"echo ${1}"), Err(TokenizeError::BadSubstitution));
+
+        let tokens = tokenize(b"if \"if\" $X").expect("argv");
+        let literals: Vec<_> = tokens
+            .iter()
+            .map(|token| match token {
+                Token::Word(word) => word.literal(),
+                Token::Operator(_) => None,
+            })
+            .collect();
+        assert_eq!(literals, [Some(&b"if"[..]), None, None]);
+    }
+}
@@ -0,0 +1,837 @@
+//! Kommandos für rohe Laufwerkszugriffe: `lsblk`, `hexdump`, `dd`, `dma` und `diskbench`.
+//!
+//! Laufwerke heißen wie in der Storage-Registry `/dev/hda` … `/dev/hdd` (primärer/sekundärer
+//! Kanal, jeweils Master und Slave) und werden über [`BlockDevice`] gelesen und geschrieben;
+//! alle anderen Pfade sind SimpleFS-Dateien und gehen über das VFS. So lässt sich auch eine
+//! Platte untersuchen, auf der kein SimpleFS liegt.
+
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec::Vec;
+use core::cell::UnsafeCell;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use super::command::{self, Command, Context, FAILURE, SUCCESS, USAGE};
+use super::fs_commands::{read_simplefs_file, report, unmount_simplefs, write_simplefs_at};
+use super::output::Output;
+use crate::arch::x86_64::pit::{self, PIT_TARGET_HZ};
+use crate::console;
+use crate::storage::block::{self, BlockDevice, BlockError};
+use crate::storage::identify::IdentifyData;
+use crate::storage::registry::{self, DriveInfo, TransferMode};
+
+/// Präfix der Laufwerkspfade.
+const DEVICE_PREFIX: &[u8] = b"/dev/";
+const SECTOR_SIZE: u64 = 512;
+/// Bytes pro `hexdump`-Zeile.
+const HEXDUMP_WIDTH: usize = 16;
+/// Laufwerke werden in Stücken dieser Größe gelesen und geschrieben (eine DMA-Übertragung).
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Größte Datenmenge für eine umgeleitete Ausgabe. Sie sammelt sich ganz im Heap (4 MiB) und
+/// darf ihn nicht für die folgenden Kommandos der Pipeline aufbrauchen.
+const MAX_BUFFERED: u64 = 512 * 1024;
+/// Eine `hexdump`-Zeile braucht für 16 Bytes 78 Zeichen, also knapp das Fünffache.
+const HEXDUMP_EXPANSION: u64 = 5;
+/// Vorgabe für die von `diskbench` je Modus gelesene Datenmenge.
+const BENCH_LENGTH: u64 = 4 * 1024 * 1024;
+
+type IoResult<T> = Result<T, &'static [u8]>;
+
+/// Puffer für die Stücke aus [`CHUNK_SIZE`]. Er liegt statisch im Kernel, damit große
+/// Übertragungen den Heap nicht belegen; Kommandos laufen nacheinander, nie gleichzeitig.
+struct ChunkBuffer(UnsafeCell<[u8; CHUNK_SIZE]>);
+unsafe impl Sync for ChunkBuffer {}
+
+static CHUNK: ChunkBuffer = ChunkBuffer(UnsafeCell::new([0; CHUNK_SIZE]));
+static CHUNK_IN_USE: AtomicBool = AtomicBool::new(false);
+
+fn with_chunk<T>(f: impl FnOnce(&mut [u8]) -> T) -> T {
+    let busy = CHUNK_IN_USE.swap(true, Ordering::Acquire);
+    assert!(!busy, "chunk buffer already in use");
+    let result = f(unsafe { &mut *CHUNK.0.get() });
+    CHUNK_IN_USE.store(false, Ordering::Release);
+    result
+}
+
+/// Ein per IDENTIFY erkanntes Laufwerk.
+struct Drive {
+    name: &'static str,
+    dev: Box<dyn BlockDevice>,
+    info: IdentifyData,
+    mode: TransferMode,
+}
+
+/// Öffnet ein Laufwerk aus der Registry in seinem aktuellen Übertragungsmodus.
+#[cfg(eres_kernel)]
+fn open_drive(info: DriveInfo) -> Drive {
+    Drive {
+        name: info.name(),
+        dev: Box::new(info.open()),
+        info: info.identify,
+        mode: info.mode,
+    }
+}
+
+/// Alle Laufwerke aus der Registry, jeweils mit eigenem Zugriff.
+#[cfg(eres_kernel)]
+fn drives() -> Vec<Drive> {
+    registry::drives().into_iter().map(open_drive).collect()
+}
+
+#[cfg(not(eres_kernel))]
+fn drives() -> Vec<Drive> {
+    Vec::new()
+}
+
+#[cfg(eres_kernel)]
+fn find_drive(name: &[u8]) -> Option<Drive> {
+    let name = core::str::from_utf8(name).ok()?;
+    registry::find(name).map(open_drive)
+}
+
+#[cfg(not(eres_kernel))]
+fn find_drive(_name: &[u8]) -> Option<Drive> {
+    None
+}
+
+pub const LSBLK: Command = Command {
+    name: b"lsblk",
+    usage: b"",
+    summary: b"list ATA drives with their identify data",
+    min_args: 0,
+    max_args: 0,
+    run: run_lsblk,
+};
+
+fn run_lsblk(ctx: &mut Context<'_>, _argv: &[&[u8]]) -> u8 {
+    let drives = drives();
+    if drives.is_empty() {
+        console::write_line(b"no drives found");
+        return FAILURE;
+    }
+
+    let _ = writeln!(
+        ctx.out,
+        "NAME       SIZE   SECTORS  LBA  MODE  {:<24} {:<12} FIRMWARE",
+        "MODEL", "SERIAL"
+    );
+    for drive in &drives {
+        let info = &drive.info;
+        let _ = writeln!(
+            ctx.out,
+            "{:<4} {:>10} {:>9}  {:>3}  {:<4}  {:<24} {:<12} {}",
+            drive.name,
+            Size(info.size_bytes()),
+            info.sectors,
+            if info.lba48 { 48 } else { 28 },
+            drive.mode.name(),
+            text(info.model()),
+            text(info.serial()),
+            text(info.firmware()),
+        );
+    }
+    SUCCESS
+}
+
+fn text(bytes: &[u8]) -> &str {
+    core::str::from_utf8(bytes).unwrap_or("?")
+}
+
+/// Größe in Bytes, ab 1 KiB mit binärem Präfix und einer Nachkommastelle.
+struct Size(u64);
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+        if self.0 < 1024 {
+            return f.pad(&format!("{} B", self.0));
+        }
+        // In Zehnteln der Einheit, damit die Nachkommastelle ohne Gleitkomma auskommt.
+        let mut tenths = u128::from(self.0) * 10 / 1024;
+        let mut unit = 0;
+        while tenths >= 10 * 1024 && unit + 1 < UNITS.len() {
+            tenths /= 1024;
+            unit += 1;
+        }
+        f.pad(&format!("{}.{} {}", tenths / 10, tenths % 10, UNITS[unit]))
+    }
+}
+
+pub const HEXDUMP: Command = Command {
+    name: b"hexdump",
+    usage: b"[-s <offset>] [-n <length>] [path]",
+    summary: b"show a file, a drive or the input in hex",
+    min_args: 0,
+    max_args: 5,
+    run: run_hexdump,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct HexdumpOptions<'a> {
+    offset: u64,
+    length: Option<u64>,
+    path: Option<&'a [u8]>,
+}
+
+fn parse_hexdump_args<'a>(mut args: &[&'a [u8]]) -> Option<HexdumpOptions<'a>> {
+    let mut options = HexdumpOptions {
+        offset: 0,
+        length: None,
+        path: None,
+    };
+    loop {
+        match args {
+            [b"-s", value, ..] => options.offset = parse_number(value)?,
+            [b"-n", value, ..] => options.length = Some(parse_number(value)?),
+            [path] if !path.starts_with(b"-") => {
+                options.path = Some(path);
+                return Some(options);
+            }
+            [] => return Some(options),
+            _ => return None,
+        }
+        args = &args[2..];
+    }
+}
+
+fn run_hexdump(ctx: &mut Context<'_>, argv: &[&[u8]]) -> u8 {
+    let Some(options) = parse_hexdump_args(&argv[1..]) else {
+        command::write_usage(&mut Output::Console, &HEXDUMP);
+        return USAGE;
+    };
+
+    let (name, mut source, length) = match options.path {
+        Some(path) => match Source::open(path) {
+            // Laufwerke zeigen ohne `-n` nur einen Sektor.
+            Ok(source @ Source::Drive(_)) => (path, source, options.length.or(Some(SECTOR_SIZE))),
+            Ok(source) => (path, source, options.length),
+            Err(message) => {
+                report(path, message);
+                return FAILURE;
+            }
+        },
+        None => match ctx.input {
+            Some(input) => (
+                HEXDUMP.name,
+                Source::Memory(Cow::Borrowed(input)),
+                options.length,
+            ),
+            None => {
+                command::write_usage(&mut Output::Console, &HEXDUMP);
+                return USAGE;
+            }
+        },
+    };
+    let length = source.clamp(options.offset, length);
+    if let Err(message) = check_buffered(ctx.out, length.saturating_mul(HEXDUMP_EXPANSION)) {
+        report(name, message);
+        return FAILURE;
+    }
+
+    let mut hexdump = Hexdump::new(options.offset);
+    let result = with_chunk(|buffer| {
+        stream(&mut source, options.offset, length, buffer, |chunk| {
+            hexdump.write(ctx.out, chunk);
+            Ok(())
+        })
+    });
+    hexdump.finish(ctx.out);
+    match result {
+        Ok(()) => SUCCESS,
+        Err(CopyError::Read(message) | CopyError::Write(message)) => {
+            report(name, message);
+            FAILURE
+        }
+    }
+}
+
+/// Darstellung wie `hexdump -C`: Offset, 16 Bytes in Hex und als ASCII. Gleiche Folgezeilen
+/// werden zu einem `*` zusammengefasst; die letzte Zeile enthält das Ende als Offset. Die Daten
+/// kommen stückweise; alle Stücke außer dem letzten sind ein Vielfaches von [`HEXDUMP_WIDTH`].
+struct Hexdump {
+    offset: u64,
+    previous: Option<[u8; HEXDUMP_WIDTH]>,
+    skipping: bool,
+}
+
+impl Hexdump {
+    fn new(start: u64) -> Self {
+        Self {
+            offset: start,
+            previous: None,
+            skipping: false,
+        }
+    }
+
+    fn write(&mut self, out: &mut Output, data: &[u8]) {
+        for line in data.chunks(HEXDUMP_WIDTH) {
+            let offset = self.offset;
+            self.offset += line.len() as u64;
+            if self.previous.as_ref().map(<[u8; HEXDUMP_WIDTH]>::as_slice) == Some(line) {
+                if !self.skipping {
+                    out.write_line(b"*");
+                    self.skipping = true;
+                }
+                continue;
+            }
+            self.previous = line.try_into().ok();
+            self.skipping = false;
+
+            let _ = write!(out, "{offset:08x} ");
+            for column in 0..HEXDUMP_WIDTH {
+                if column % 8 == 0 {
+                    out.write_byte(b' ');
+                }
+                match line.get(column) {
+                    Some(byte) => {
+                        let _ = write!(out, "{byte:02x} ");
+                    }
+                    None => out.write_bytes(b"   "),
+                }
+            }
+            out.write_bytes(b" |");
+            for &byte in line {
+                let printable = byte.is_ascii_graphic() || byte == b' ';
+                out.write_byte(if printable { byte } else { b'.' });
+            }
+            out.write_line(b"|");
+        }
+    }
+
+    fn finish(self, out: &mut Output) {
+        let _ = writeln!(out, "{:08x}", self.offset);
+    }
+}
+
+pub const DD: Command = Command {
+    name: b"dd",
+    usage: b"[if=<path>] [of=<path>] [bs=<bytes>] [skip=<n>] [seek=<n>] [count=<n>]",
+    summary: b"copy blocks between drives, files and the input/output",
+    min_args: 0,
+    max_args: 6,
+    run: run_dd,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct DdOptions<'a> {
+    input: Option<&'a [u8]>,
+    output: Option<&'a [u8]>,
+    block_size: u64,
+    /// Übersprungene Blöcke der Quelle.
+    skip: u64,
+    /// Übersprungene Blöcke des Ziels.
+    seek: u64,
+    count: Option<u64>,
+}
+
+fn parse_dd_args<'a>(args: &[&'a [u8]]) -> Option<DdOptions<'a>> {
+    let mut options = DdOptions {
+        input: None,
+        output: None,
+        block_size: SECTOR_SIZE,
+        skip: 0,
+        seek: 0,
+        count: None,
+    };
+    for arg in args {
+        let split = arg.iter().position(|byte| *byte == b'=')?;
+        let (key, value) = (&arg[..split], &arg[split + 1..]);
+        match key {
+            b"if" => options.input = Some(value),
+            b"of" => options.output = Some(value),
+            b"bs" => options.block_size = parse_number(value).filter(|size| *size > 0)?,
+            b"skip" => options.skip = parse_number(value)?,
+            b"seek" => options.seek = parse_number(value)?,
+            b"count" => options.count = Some(parse_number(value)?),
+            _ => return None,
+        }
+    }
+    Some(options)
+}
+
+/// Liest `count` Blöcke ab Block `skip` der Quelle (oder bis zu ihrem Ende) und schreibt sie ab
+/// Block `seek` ins Ziel. Ohne `if=` liest `dd` die Eingabe, ohne `of=` schreibt es in die
+/// Ausgabe. Dateien werden dabei nicht gekürzt.
+fn run_dd(ctx: &mut Context<'_>, argv: &[&[u8]]) -> u8 {
+    let Some(options) = parse_dd_args(&argv[1..]) else {
+        command::write_usage(&mut Output::Console, &DD);
+        return USAGE;
+    };
+    let offset = options.skip.saturating_mul(options.block_size);
+    let length = options
+        .count
+        .map(|count| count.saturating_mul(options.block_size));
+
+    let (input, mut source) = match options.input {
+        Some(path) => match Source::open(path) {
+            Ok(source) => (path, source),
+            Err(message) => {
+                report(path, message);
+                return FAILURE;
+            }
+        },
+        None => match ctx.input {
+            Some(input) => (DD.name, Source::Memory(Cow::Borrowed(input))),
+            None => {
+                command::write_usage(&mut Output::Console, &DD);
+                return USAGE;
+            }
+        },
+    };
+    let length = source.clamp(offset, length);
+
+    let target_offset = options.seek.saturating_mul(options.block_size);
+    let (output, result) = match options.output {
+        Some(path) => (
+            path,
+            open(path)
+                .and_then(|target| copy_to(target, &mut source, offset, length, target_offset)),
+        ),
+        None => (
+            DD.name,
+            copy_to_output(ctx.out, &mut source, offset, length),
+        ),
+    };
+    match result {
+        Ok(()) => {
+            // Die Bilanz geht wie bei `dd` nicht in die Ausgabe, damit sie keine Daten verfälscht.
+            kprintln!("{length} bytes copied");
+            SUCCESS
+        }
+        Err(CopyError::Read(message)) => {
+            report(input, message);
+            FAILURE
+        }
+        Err(CopyError::Write(message)) => {
+            report(output, message);
+            FAILURE
+        }
+    }
+}
+
+/// Gibt `length` Bytes ab `offset` der Quelle aus.
+fn copy_to_output(
+    out: &mut Output,
+    source: &mut Source<'_>,
+    offset: u64,
+    length: u64,
+) -> Result<(), CopyError> {
+    check_buffered(out, length).map_err(CopyError::Write)?;
+    with_chunk(|buffer| {
+        stream(source, offset, length, buffer, |chunk| {
+            out.write_bytes(chunk);
+            Ok(())
+        })
+    })
+}
+
+/// Kopiert `length` Bytes ab `offset` der Quelle ab `target_offset` ins Ziel.
+fn copy_to(
+    target: Endpoint<'_>,
+    source: &mut Source<'_>,
+    offset: u64,
+    length: u64,
+    target_offset: u64,
+) -> Result<(), CopyError> {
+    match target {
+        Endpoint::Drive(mut drive) => {
+            if target_offset.saturating_add(length) > drive.info.size_bytes() {
+                return Err(CopyError::Write(b"no space left on device"));
+            }
+            // Das eingehängte SimpleFS kennt die neuen Sektoren nicht und wird neu eingehängt.
+            unmount_simplefs();
+            let mut position = target_offset;
+            with_chunk(|buffer| {
+                stream(source, offset, length, buffer, |chunk| {
+                    block::write_bytes(&mut *drive.dev, position, chunk)
+                        .map_err(BlockError::describe)?;
+                    position += chunk.len() as u64;
+                    Ok(())
+                })
+            })
+        }
+        Endpoint::File(path) => {
+            // Legt die Datei auch ohne Daten an, wie `dd count=0`.
+            write_simplefs_at(path, target_offset, &[]).map_err(CopyError::Write)?;
+            let mut position = target_offset;
+            with_chunk(|buffer| {
+                stream(source, offset, length, buffer, |chunk| {
+                    write_simplefs_at(path, position, chunk)?;
+                    position += chunk.len() as u64;
+                    Ok(())
+                })
+            })
+        }
+    }
+}
+
+pub const DMA: Command = Command {
+    name: b"dma",
+    usage: b"[<drive> [on|off]]",
+    summary: b"show or switch the DMA mode of ATA drives",
+    min_args: 0,
+    max_args: 2,
+    run: run_dma,
+};
+
+fn run_dma(ctx: &mut Context<'_>, argv: &[&[u8]]) -> u8 {
+    let (name, mode) = match &argv[1..] {
+        [] => {
+            for drive in registry::drives() {
+                let _ = write_mode(ctx.out, &drive);
+            }
+            return SUCCESS;
+        }
+        [name] => (*name, None),
+        [name, b"on"] => (*name, Some(TransferMode::Dma)),
+        [name, b"off"] => (*name, Some(TransferMode::Pio)),
+        _ => {
+            command::write_usage(&mut Output::Console, &DMA);
+            return USAGE;
+        }
+    };
+    let Some(drive) = find_info(name) else {
+        report(name, b"no such drive");
+        return FAILURE;
+    };
+    if let Some(mode) = mode {
+        if let Err(message) = registry::set_mode(drive.name(), mode) {
+            report(name, message);
+            return FAILURE;
+        }
+        // SimpleFS hält einen Zugriff im alten Modus offen.
+        unmount_simplefs();
+    }
+    match registry::find(drive.name()) {
+        Some(drive) => {
+            let _ = write_mode(ctx.out, &drive);
+            SUCCESS
+        }
+        None => FAILURE,
+    }
+}
+
+fn write_mode(out: &mut Output, drive: &DriveInfo) -> fmt::Result {
+    let available = if drive.dma_available() {
+        ""
+    } else {
+        " (DMA not available)"
+    };
+    writeln!(out, "{}: {}{available}", drive.name(), drive.mode.name())
+}
+
+/// Registry-Eintrag zu `hda` oder `/dev/hda`.
+fn find_info(name: &[u8]) -> Option<DriveInfo> {
+    let name = name.strip_prefix(DEVICE_PREFIX).unwrap_or(name);
+    registry::find(core::str::from_utf8(name).ok()?)
+}
+
+pub const DISKBENCH: Command = Command {
+    name: b"diskbench",
+    usage: b"<drive> [length]",
+    summary: b"compare PIO and DMA read throughput of a drive",
+    min_args: 1,
+    max_args: 2,
+    run: run_diskbench,
+};
+
+fn run_diskbench(ctx: &mut Context<'_>, argv: &[&[u8]]) -> u8 {
+    let Some(drive) = find_info(argv[1]) else {
+        report(argv[1], b"no such drive");
+        return FAILURE;
+    };
+    let length = match argv.get(2).map(|arg| parse_number(arg)) {
+        None => BENCH_LENGTH,
+        Some(Some(length)) if length >= SECTOR_SIZE => length,
+        Some(_) => {
+            command::write_usage(&mut Output::Console, &DISKBENCH);
+            return USAGE;
+        }
+    };
+    // Ganze Sektoren ab LBA 0, höchstens bis zum Ende des Laufwerks.
+    let length = length.min(drive.identify.size_bytes()) / SECTOR_SIZE * SECTOR_SIZE;
+
+    let mut status = SUCCESS;
+    for mode in [TransferMode::Pio, TransferMode::Dma] {
+        let Some(mut dev) = open_with(&drive, mode) else {
+            let _ = writeln!(ctx.out, "{} {}: not available", drive.name(), mode.name());
+            continue;
+        };
+        match with_chunk(|buffer| measure(dev.as_mut(), length, buffer)) {
+            Ok(ticks) => {
+                let _ = write_throughput(ctx.out, drive.name(), mode, length, ticks);
+            }
+            Err(err) => {
+                report(argv[1], err.describe());
+                status = FAILURE;
+            }
+        }
+    }
+    status
+}
+
+#[cfg(eres_kernel)]
+fn open_with(drive: &DriveInfo, mode: TransferMode) -> Option<Box<dyn BlockDevice>> {
+    drive
+        .open_with(mode)
+        .map(|dev| Box::new(dev) as Box<dyn BlockDevice>)
+}
+
+#[cfg(not(eres_kernel))]
+fn open_with(_drive: &DriveInfo, _mode: TransferMode) -> Option<Box<dyn BlockDevice>> {
+    None
+}
+
+/// Liest `length` Bytes ab LBA 0 in Blöcken von `buffer.len()` Bytes und liefert die Dauer in
+/// PIT-Ticks.
+fn measure(dev: &mut dyn BlockDevice, length: u64, buffer: &mut [u8]) -> Result<u64, BlockError> {
+    let start = pit::ticks();
+    let mut done = 0;
+    while done < length {
+        let len = (length - done).min(buffer.len() as u64) as usize;
+        dev.read_sectors(done / SECTOR_SIZE, &mut buffer[..len])?;
+        done += len as u64;
+    }
+    Ok(pit::ticks() - start)
+}
+
+/// `hda DMA: 4096 KiB in 0.12 s, 34133 KiB/s`; bei weniger als einem Tick zählt ein Tick.
+fn write_throughput(
+    out: &mut Output,
+    name: &str,
+    mode: TransferMode,
+    bytes: u64,
+    ticks: u64,
+) -> fmt::Result {
+    let hz = u64::from(PIT_TARGET_HZ);
+    let hundredths = ticks * 100 / hz;
+    let rate = bytes / 1024 * hz / ticks.max(1);
+    writeln!(
+        out,
+        "{name} {}: {} KiB in {}.{:02} s, {rate} KiB/s",
+        mode.name(),
+        bytes / 1024,
+        hundredths / 100,
+        hundredths % 100
+    )
+}
+
+/// Zahl dezimal oder mit `0x` hexadezimal; die Endung `s` zählt Sektoren (512 Bytes), `k` KiB.
+fn parse_number(text: &[u8]) -> Option<u64> {
+    let (digits, unit) = match text {
+        [rest @ .., b's'] => (rest, SECTOR_SIZE),
+        [rest @ .., b'k'] => (rest, 1024),
+        _ => (text, 1),
+    };
+    let digits = core::str::from_utf8(digits).ok()?;
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+        None => digits.parse().ok()?,
+    };
+    value.checked_mul(unit)
+}
+
+/// Ziel eines Zugriffs.
+enum Endpoint<'a> {
+    Drive(Drive),
+    File(&'a str),
+}
+
+fn open(path: &[u8]) -> Result<Endpoint<'_>, CopyError> {
+    if let Some(name) = path.strip_prefix(DEVICE_PREFIX) {
+        return find_drive(name)
+            .map(Endpoint::Drive)
+            .ok_or(CopyError::Write(b"no such drive"));
+    }
+    core::str::from_utf8(path)
+        .map(Endpoint::File)
+        .map_err(|_| CopyError::Write(b"invalid path"))
+}
+
+/// Quelle eines Zugriffs. Laufwerke werden stückweise gelesen; Dateien und die Eingabe liegen
+/// ohnehin ganz im Speicher.
+enum Source<'a> {
+    Drive(Drive),
+    Memory(Cow<'a, [u8]>),
+}
+
+impl Source<'_> {
+    fn open(path: &[u8]) -> IoResult<Self> {
+        if let Some(name) = path.strip_prefix(DEVICE_PREFIX) {
+            return find_drive(name).map(Source::Drive).ok_or(b"no such drive");
+        }
+        let path = core::str::from_utf8(path).map_err(|_| b"invalid path".as_slice())?;
+        Ok(Source::Memory(Cow::Owned(read_simplefs_file(path)?)))
+    }
+
+    fn size(&self) -> u64 {
+        match self {
+            Source::Drive(drive) => drive.info.size_bytes(),
+            Source::Memory(data) => data.len() as u64,
+        }
+    }
+
+    /// Länge ab `offset`: höchstens `length` Bytes, am Ende der Quelle entsprechend weniger.
+    fn clamp(&self, offset: u64, length: Option<u64>) -> u64 {
+        let available = self.size().saturating_sub(offset);
+        length.map_or(available, |length| length.min(available))
+    }
+
+    /// Liest `buffer.len()` Bytes ab `offset`, die innerhalb der Quelle liegen müssen. Aus dem
+    /// Speicher kommt der Ausschnitt direkt, ohne Kopie in den Puffer.
+    fn read<'s>(&'s mut self, offset: u64, buffer: &'s mut [u8]) -> IoResult<&'s [u8]> {
+        match self {
+            Source::Drive(drive) => {
+                block::read_bytes(&mut *drive.dev, offset, buffer).map_err(BlockError::describe)?;
+                Ok(buffer)
+            }
+            Source::Memory(data) => Ok(&data[offset as usize..][..buffer.len()]),
+        }
+    }
+}
+
+/// Fehler beim Kopieren, getrennt nach Quelle und Ziel.
+enum CopyError {
+    Read(&'static [u8]),
+    Write(&'static [u8]),
+}
+
+/// Reicht `length` Bytes ab `offset` der Quelle in Stücken von höchstens `buffer.len()` Bytes an
+/// `sink` weiter; `length` muss innerhalb der Quelle liegen (siehe [`Source::clamp`]).
+fn stream(
+    source: &mut Source<'_>,
+    offset: u64,
+    length: u64,
+    buffer: &mut [u8],
+    mut sink: impl FnMut(&[u8]) -> IoResult<()>,
+) -> Result<(), CopyError> {
+    let mut done = 0;
+    while done < length {
+        let len = (length - done).min(buffer.len() as u64) as usize;
+        let chunk = source
+            .read(offset + done, &mut buffer[..len])
+            .map_err(CopyError::Read)?;
+        sink(chunk).map_err(CopyError::Write)?;
+        done += len as u64;
+    }
+    Ok(())
+}
+
+/// Umgeleitete Ausgaben sammeln sich im Speicher und sind deshalb auf [`MAX_BUFFERED`] begrenzt.
+fn check_buffered(out: &Output, bytes: u64) -> IoResult<()> {
+    if !out.is_console() && bytes > MAX_BUFFERED {
+        return Err(b"transfer too large (use count=)");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use alloc::vec::Vec;
+
+    use alloc::borrow::Cow;
+
+    use super::{
+        CopyError, DdOptions, Hexdump, HexdumpOptions, Size, Source, parse_dd_args,
+        parse_hexdump_args, parse_number, stream, write_throughput,
+    };
+    use crate::shell::output::Output;
+    use crate::storage::registry::TransferMode;
+
+    #[test]
+    fn parses_numbers_and_arguments() {
+        assert_eq!(parse_number(b"42"), Some(42));
+        assert_eq!(parse_number(b"0x1f"), Some(31));
+        assert_eq!(parse_number(b"2s"), Some(1024));
+        assert_eq!(parse_number(b"4k"), Some(4096));
+        assert_eq!(parse_number(b"x"), None);
+
+        assert_eq!(
+            parse_hexdump_args(&[b"-s", b"1s", b"-n", b"32", b"/dev/hdb"]),
+            Some(HexdumpOptions {
+                offset: 512,
+                length: Some(32),
+                path: Some(b"/dev/hdb".as_slice()),
+            })
+        );
+        assert_eq!(parse_hexdump_args(&[b"-s"]), None);
+        assert_eq!(parse_hexdump_args(&[b"/a", b"/b"]), None);
+
+        assert_eq!(
+            parse_dd_args(&[b"if=/dev/hda", b"of=/mbr.bin", b"count=1"]),
+            Some(DdOptions {
+                input: Some(b"/dev/hda".as_slice()),
+                output: Some(b"/mbr.bin".as_slice()),
+                block_size: 512,
+                skip: 0,
+                seek: 0,
+                count: Some(1),
+            })
+        );
+        assert_eq!(parse_dd_args(&[b"bs=0"]), None);
+        assert_eq!(parse_dd_args(&[b"conv=notrunc"]), None);
+        assert_eq!(parse_dd_args(&[b"/dev/hda"]), None);
+    }
+
+    #[test]
+    fn formats_hexdump_and_sizes() {
+        let mut data = Vec::from(*b"ERESFS1\0");
+        data.resize(56, 0);
+        // In Stücken wie beim Lesen vom Laufwerk; der `*` reicht über die Stückgrenze.
+        let mut out = Output::Buffer(Vec::new());
+        let mut hexdump = Hexdump::new(0x200);
+        hexdump.write(&mut out, &data[..32]);
+        hexdump.write(&mut out, &data[32..]);
+        hexdump.finish(&mut out);
+        assert_eq!(
+            out.into_bytes(),
+            b"00000200  45 52 45 53 46 53 31 00  00 00 00 00 00 00 00 00  |ERESFS1.........|\n\
+              00000210  00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00  |................|\n\
+              *\n\
+              00000230  00 00 00 00 00 00 00 00                           |........|\n\
+              00000238\n"
+        );
+
+        assert_eq!(format!("{}", Size(512)), "512 B");
+        assert_eq!(format!("{}", Size(1536)), "1.5 KiB");
+        assert_eq!(format!("{:>8}", Size(1024 * 1024)), " 1.0 MiB");
+        assert_eq!(format!("{}", Size(3 << 30)), "3.0 GiB");
+    }
+
+    #[test]
+    fn streams_in_chunks_of_the_buffer() {
+        let data: Vec<u8> = (0..=255).collect();
+        let mut source = Source::Memory(Cow::Borrowed(&data));
+        assert_eq!(source.clamp(200, None), 56);
+        assert_eq!(source.clamp(200, Some(10)), 10);
+        assert_eq!(source.clamp(300, Some(10)), 0);
+
+        let mut buffer = [0; 48];
+        let mut chunks = Vec::new();
+        let result = stream(&mut source, 100, 120, &mut buffer, |chunk| {
+            chunks.push(chunk.to_vec());
+            Ok(())
+        });
+        assert!(result.is_ok());
+        let lengths: Vec<usize> = chunks.iter().map(Vec::len).collect();
+        assert_eq!(lengths, [48, 48, 24]);
+        assert_eq!(chunks.concat(), &data[100..220]);
+
+        let result = stream(&mut source, 0, 100, &mut buffer, |_| Err(b"full"));
+        assert!(matches!(result, Err(CopyError::Write(b"full"))));
+    }
+
+    #[test]
+    fn formats_throughput() {
+        let mut out = Output::Buffer(Vec::new());
+        write_throughput(&mut out, "hda", TransferMode::Pio, 4 << 20, 123).unwrap();
+        write_throughput(&mut out, "hda", TransferMode::Dma, 64 << 10, 0).unwrap();
+        assert_eq!(
+            out.into_bytes(),
+            b"hda PIO: 4096 KiB in 1.23 s, 3330 KiB/s\nhda DMA: 64 KiB in 0.00 s, 6400 KiB/s\n"
+        );
+    }
+}
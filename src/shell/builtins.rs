@@ -0,0 +1,411 @@
+//! Allgemeine eingebaute Kommandos (System, Konsole, Log).
+
+use core::arch::asm;
+use core::fmt::Write;
+
+use super::command::{self, COMMANDS, Command, Context, FAILURE, SUCCESS, USAGE};
+use super::fs_commands::{read_simplefs_file, report};
+use super::output::Output;
+use crate::{arch, console};
+
+/// Spaltenbreite von Name und Argumenten in der `help`-Übersicht.
+const HELP_COLUMN: usize = 30;
+
+pub const HELP: Command = Command {
+    name: b"help",
+    usage: b"[command]",
+    summary: b"list commands or show the usage of one",
+    min_args: 0,
+    max_args: 1,
+    run: run_help,
+};
+
+fn run_help(ctx: &mut Context<'_>, argv: &[&[u8]]) -> u8 {
+    if let Some(name) = argv.get(1) {
+        let Some(command) = command::find(name) else {
+            console::write_line(b"unknown command");
+            return FAILURE;
+        };
+        command::write_usage(ctx.out, command);
+        ctx.out.write_line(command.summary);
+        return SUCCESS;
+    }
+
+    for command in COMMANDS {
+        ctx.out.write_bytes(b"  ");
+        ctx.out.write_bytes(command.name);
+        let mut width = command.name.len();
+        if !command.usage.is_empty() {
+            ctx.out.write_byte(b' ');
+            ctx.out.write_bytes(command.usage);
+            width += 1 + command.usage.len();
+        }
+        for _ in width..HELP_COLUMN {
+            ctx.out.write_byte(b' ');
+        }
+        ctx.out.write_byte(b' ');
+        ctx.out.write_line(command.summary);
+    }
+    SUCCESS
+}
+
+pub const ECHO: Command = Command {
+    name: b"echo",
+    usage: b"<text>...",
+    summary: b"print the arguments separated by spaces",
+    min_args: 1,
+    max_args: usize::MAX,
+    run: run_echo,
+};
+
+fn run_echo(ctx: &mut Context<'_>, argv: &[&[u8]]) -> u8 {
+    for (index, arg) in argv[1..].iter().enumerate() {
+        if index > 0 {
+            ctx.out.write_byte(b' ');
+        }
+        ctx.out.write_bytes(arg);
+    }
+    ctx.out.write_byte(b'\n');
+    SUCCESS
+}
+
+pub const CLEAR: Command = Command {
+    name: b"clear",
+    usage: b"",
+    summary: b"clear the screen",
+    min_args: 0,
+    max_args: 0,
+    run: |_, _| {
+        console::clear();
+        SUCCESS
+    },
+};
+
+pub const HISTORY: Command = Command {
+    name: b"history",
+    usage: b"",
+    summary: b"show previous command lines",
+    min_args: 0,
+    max_args: 0,
+    run: run_history,
+};
+
+fn run_history(ctx: &mut Context<'_>, _argv: &[&[u8]]) -> u8 {
+    for entry in ctx.history {
+        ctx.out.write_line(entry);
+    }
+    SUCCESS
+}
+
+pub const MEM: Command = Command {
+    name: b"mem",
+    usage: b"",
+    summary: b"show frame allocator statistics",
+    min_args: 0,
+    max_args: 0,
+    run: run_mem,
+};
+
+fn run_mem(ctx: &mut Context<'_>, _argv: &[&[u8]]) -> u8 {
+    if let Some(stats) = crate::memory::frame_allocator::stats() {
+        ctx.out.write_bytes(b"frames total=");
+        ctx.out.write_u64(stats.total_frames);
+        ctx.out.write_bytes(b" allocated=");
+        ctx.out.write_u64(stats.allocated_frames);
+        ctx.out.write_bytes(b" free=");
+        ctx.out.write_u64(stats.free_frames);
+        ctx.out.write_bytes(b" demand-paged=");
+        ctx.out
+            .write_u64(crate::memory::fault::demand_mapped_pages());
+        ctx.out.write_byte(b'\n');
+        SUCCESS
+    } else {
+        console::write_line(b"frame allocator not initialized");
+        FAILURE
+    }
+}
+
+pub const TICKS: Command = Command {
+    name: b"ticks",
+    usage: b"",
+    summary: b"show timer ticks since boot",
+    min_args: 0,
+    max_args: 0,
+    run: run_ticks,
+};
+
+fn run_ticks(ctx: &mut Context<'_>, _argv: &[&[u8]]) -> u8 {
+    ctx.out.write_bytes(b"ticks=");
+    ctx.out.write_u64(arch::x86_64::pit::ticks());
+    ctx.out.write_byte(b'\n');
+    SUCCESS
+}
+
+pub const TTY: Command = Command {
+    name: b"tty",
+    usage: b"",
+    summary: b"print the virtual console of this shell",
+    min_args: 0,
+    max_args: 0,
+    run: |ctx, _| {
+        let _ = writeln!(ctx.out, "tty{}", console::output_console() + 1);
+        SUCCESS
+    },
+};
+
+pub const SHOWKEY: Command = Command {
+    name: b"showkey",
+    usage: b"",
+    summary: b"print key events until 10 s without input",
+    min_args: 0,
+    max_args: 0,
+    run: run_showkey,
+};
+
+/// Zeigt Tastenereignisse samt Loslassen und Modifikatoren, bis 10 s keine Taste kommt.
+fn run_showkey(_ctx: &mut Context<'_>, _argv: &[&[u8]]) -> u8 {
+    use arch::x86_64::keyboard;
+
+    const IDLE_SECONDS: u64 = 10;
+
+    console::write_line(b"press any key (program terminates 10s after last keypress)...");
+    keyboard::set_release_events(true);
+    let idle_ticks = IDLE_SECONDS * arch::x86_64::pit::PIT_TARGET_HZ as u64;
+    let mut last_key = arch::x86_64::pit::ticks();
+    while arch::x86_64::pit::ticks().wrapping_sub(last_key) < idle_ticks {
+        let Some(event) = keyboard::try_read_key_from(console::output_console()) else {
+            arch::x86_64::halt();
+            continue;
+        };
+        last_key = arch::x86_64::pit::ticks();
+
+        let modifiers = keyboard::modifiers();
+        kprint!("{event:?}");
+        for (active, name) in [
+            (modifiers.shift, "shift"),
+            (modifiers.ctrl, "ctrl"),
+            (modifiers.alt, "alt"),
+            (modifiers.altgr, "altgr"),
+            (modifiers.caps_lock, "caps"),
+            (modifiers.num_lock, "num"),
+            (modifiers.scroll_lock, "scroll"),
+        ] {
+            if active {
+                kprint!(" +{name}");
+            }
+        }
+        console::write_byte(b'\n');
+    }
+    keyboard::set_release_events(false);
+    SUCCESS
+}
+
+pub const LOADKEYS: Command = Command {
+    name: b"loadkeys",
+    usage: b"[us|de|<path>]",
+    summary: b"show or switch the keyboard layout",
+    min_args: 0,
+    max_args: 1,
+    run: run_loadkeys,
+};
+
+/// `loadkeys [us|de|<datei>]`: zeigt oder wechselt die Tastaturbelegung.
+fn run_loadkeys(ctx: &mut Context<'_>, argv: &[&[u8]]) -> u8 {
+    use arch::x86_64::{keyboard, keymap};
+
+    let Some(arg) = argv.get(1) else {
+        ctx.out.write_bytes(b"keymap: ");
+        ctx.out.write_line(keyboard::keymap_name());
+        return SUCCESS;
+    };
+
+    if let Some(layout) = keymap::builtin(arg) {
+        keyboard::set_keymap(layout);
+    } else {
+        let Ok(path) = core::str::from_utf8(arg) else {
+            console::write_line(b"invalid path");
+            return FAILURE;
+        };
+        let text = match read_simplefs_file(path) {
+            Ok(text) => text,
+            Err(message) => {
+                report(arg, message);
+                return FAILURE;
+            }
+        };
+        match keymap::parse(&text) {
+            Ok(layout) => keyboard::set_keymap(&layout),
+            Err((line, err)) => {
+                kprint!("line {line}: ");
+                console::write_line(err.describe());
+                return FAILURE;
+            }
+        }
+    }
+    ctx.out.write_bytes(b"keymap: ");
+    ctx.out.write_line(keyboard::keymap_name());
+    SUCCESS
+}
+
+pub const LOG: Command = Command {
+    name: b"log",
+    usage: b"[<default|vga|debugcon|serial|module> <level|off>]",
+    summary: b"show or change log filters",
+    min_args: 0,
+    max_args: 2,
+    run: run_log,
+};
+
+fn run_log(ctx: &mut Context<'_>, argv: &[&[u8]]) -> u8 {
+    match argv {
+        [_] => {}
+        [_, target, value] => {
+            return match crate::log::update_config(|config| config.apply(target, value)) {
+                Ok(()) => SUCCESS,
+                Err(err) => {
+                    console::write_line(err.describe());
+                    FAILURE
+                }
+            };
+        }
+        _ => {
+            command::write_usage(&mut Output::Console, &LOG);
+            return USAGE;
+        }
+    }
+
+    let config = crate::log::config();
+    let out = &mut *ctx.out;
+    let _ = write!(out, "default={}", config.default_level.name());
+    for sink in console::Sink::ALL {
+        out.write_byte(b' ');
+        out.write_bytes(sink.name());
+        out.write_byte(b'=');
+        match config.sink_level(sink) {
+            Some(level) => out.write_bytes(level.name().as_bytes()),
+            None => out.write_bytes(b"off"),
+        }
+    }
+    out.write_byte(b'\n');
+    for filter in config.module_filters() {
+        out.write_bytes(filter.name());
+        let _ = writeln!(out, "={}", filter.level.name());
+    }
+    SUCCESS
+}
+
+pub const DMESG: Command = Command {
+    name: b"dmesg",
+    usage: b"[-c] [-r] [-l level[,level...]]",
+    summary: b"show the kernel log (-c clears, -r raw)",
+    min_args: 0,
+    max_args: usize::MAX,
+    run: run_dmesg,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct DmesgOptions {
+    /// Anzuzeigende Levels, indiziert wie `Level::ALL`.
+    pub levels: [bool; 5],
+    pub clear: bool,
+    /// Rohformat wie `/dev/kmsg`.
+    pub raw: bool,
+}
+
+/// Wertet die Optionen nach `dmesg` aus; `None` bei ungültigen Optionen.
+pub(super) fn parse_dmesg_args(args: &[&[u8]]) -> Option<DmesgOptions> {
+    let mut options = DmesgOptions {
+        levels: [true; 5],
+        clear: false,
+        raw: false,
+    };
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        match *flag {
+            b"-c" => options.clear = true,
+            b"-r" => options.raw = true,
+            b"-l" => {
+                let list = args.next()?;
+                options.levels = [false; 5];
+                for name in list.split(|b| *b == b',') {
+                    let level = crate::log::Level::from_name(name)?;
+                    options.levels[level as usize - 1] = true;
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some(options)
+}
+
+fn run_dmesg(ctx: &mut Context<'_>, argv: &[&[u8]]) -> u8 {
+    let Some(options) = parse_dmesg_args(&argv[1..]) else {
+        command::write_usage(&mut Output::Console, &DMESG);
+        return USAGE;
+    };
+
+    // Farben nur auf der Konsole, nicht in Dateien oder Pipes.
+    let color = ctx.out.is_console();
+    let mut seq = 0;
+    while let Some(entry) = crate::log::kmsg::read_from(seq) {
+        seq = entry.seq + 1;
+        if !options.levels[entry.level as usize - 1] {
+            continue;
+        }
+        if options.raw {
+            let _ = crate::log::kmsg::write_kmsg_line(ctx.out, &entry);
+        } else {
+            let _ = crate::log::write_prefix(ctx.out, entry.ticks, entry.level, color);
+            ctx.out.write_line(entry.text.as_bytes());
+        }
+    }
+    if options.clear {
+        crate::log::kmsg::clear();
+    }
+    SUCCESS
+}
+
+pub const PANIC: Command = Command {
+    name: b"panic",
+    usage: b"",
+    summary: b"trigger an invalid opcode exception",
+    min_args: 0,
+    max_args: 0,
+    run: run_panic,
+};
+
+fn run_panic(_ctx: &mut Context<'_>, _argv: &[&[u8]]) -> u8 {
+    unsafe {
+        asm!("ud2", options(nomem, nostack, preserves_flags));
+    }
+    arch::x86_64::hang();
+}
+
+pub const HALT: Command = Command {
+    name: b"halt",
+    usage: b"",
+    summary: b"stop the CPU",
+    min_args: 0,
+    max_args: 0,
+    run: run_halt,
+};
+
+fn run_halt(_ctx: &mut Context<'_>, _argv: &[&[u8]]) -> u8 {
+    console::write_line(b"Halting CPU.");
+    arch::x86_64::hang();
+}
+
+pub const REBOOT: Command = Command {
+    name: b"reboot",
+    usage: b"",
+    summary: b"reset the machine",
+    min_args: 0,
+    max_args: 0,
+    run: run_reboot,
+};
+
+fn run_reboot(_ctx: &mut Context<'_>, _argv: &[&[u8]]) -> u8 {
+    console::write_line(b"Rebooting.");
+    arch::x86_64::reboot();
+}
@@ -0,0 +1,131 @@
+//! Kommandotabelle der Shell.
+//!
+//! Jedes Kommando beschreibt sich selbst mit einem [`Command`] (Name, Aufruf, Kurzbeschreibung,
+//! erlaubte Argumentanzahl, Handler), das neben seinem Handler definiert ist. [`COMMANDS`]
+//! sammelt sie; daraus entstehen Dispatch, `help` und die Tab-Vervollständigung.
+//!
+//! Handler liefern einen Exit-Status wie in POSIX-Shells; `if` und `while` verzweigen darauf,
+//! `$?` zeigt den letzten.
+
+use alloc::vec::Vec;
+
+use super::env::Env;
+use super::output::Output;
+use super::{
+    block_commands, builtins, editor, fs_commands, pci_commands, script_commands, text_commands,
+};
+
+pub const SUCCESS: u8 = 0;
+/// Allgemeiner Fehler, z. B. eine fehlende Datei oder kein Treffer bei `grep`.
+pub const FAILURE: u8 = 1;
+/// Falsche Argumente.
+pub const USAGE: u8 = 2;
+pub const NOT_FOUND: u8 = 127;
+/// Mit Ctrl+C abgebrochenes Skript (128 + SIGINT).
+pub const INTERRUPTED: u8 = 130;
+
+/// Zustand der aufrufenden Shell-Sitzung.
+pub struct Context<'a> {
+    pub history: &'a [Vec<u8>],
+    /// Standardeingabe aus `<` oder der vorherigen Pipeline-Stufe.
+    pub input: Option<&'a [u8]>,
+    pub out: &'a mut Output,
+    pub env: &'a mut Env,
+    /// Verschachtelungstiefe von `source`, 0 für interaktive Zeilen.
+    pub depth: usize,
+}
+
+/// Eingebautes Shell-Kommando.
+pub struct Command {
+    pub name: &'static [u8],
+    /// Argumente für `help` und Fehlermeldungen, ohne den Namen (z. B. `<path>`).
+    pub usage: &'static [u8],
+    pub summary: &'static [u8],
+    pub min_args: usize,
+    pub max_args: usize,
+    /// Erhält das vollständige argv, `argv[0]` ist der Kommandoname, und liefert den
+    /// Exit-Status.
+    pub run: fn(&mut Context<'_>, &[&[u8]]) -> u8,
+}
+
+/// Alle eingebauten Kommandos in der Reihenfolge, in der `help` sie zeigt.
+pub static COMMANDS: [&Command; 42] = [
+    &builtins::HELP,
+    &builtins::ECHO,
+    &builtins::CLEAR,
+    &builtins::HISTORY,
+    &builtins::MEM,
+    &builtins::TICKS,
+    &builtins::TTY,
+    &builtins::SHOWKEY,
+    &builtins::LOADKEYS,
+    &builtins::LOG,
+    &builtins::DMESG,
+    &fs_commands::LS,
+    &fs_commands::CAT,
+    &fs_commands::STAT,
+    &fs_commands::WRITE,
+    &fs_commands::APPEND,
+    &fs_commands::TOUCH,
+    &fs_commands::CP,
+    &fs_commands::MV,
+    &fs_commands::RM,
+    &fs_commands::MKDIR,
+    &fs_commands::RMDIR,
+    &editor::EDIT,
+    &block_commands::LSBLK,
+    &block_commands::HEXDUMP,
+    &block_commands::DD,
+    &block_commands::DMA,
+    &block_commands::DISKBENCH,
+    &pci_commands::LSPCI,
+    &text_commands::GREP,
+    &text_commands::WC,
+    &text_commands::HEAD,
+    &text_commands::TAIL,
+    &script_commands::SOURCE,
+    &script_commands::SET,
+    &script_commands::UNSET,
+    &script_commands::TEST,
+    &script_commands::TRUE,
+    &script_commands::FALSE,
+    &builtins::PANIC,
+    &builtins::HALT,
+    &builtins::REBOOT,
+];
+
+#[derive(Clone, Copy)]
+pub enum CommandError {
+    Unknown,
+    /// Falsche Anzahl Argumente.
+    Usage(&'static Command),
+}
+
+pub fn find(name: &[u8]) -> Option<&'static Command> {
+    COMMANDS
+        .iter()
+        .copied()
+        .find(|command| command.name == name)
+}
+
+/// Sucht das Kommando zu `argv` und prüft die Anzahl der Argumente.
+pub fn resolve(argv: &[&[u8]]) -> Result<&'static Command, CommandError> {
+    let (name, args) = argv.split_first().ok_or(CommandError::Unknown)?;
+    let command = find(name).ok_or(CommandError::Unknown)?;
+    if (command.min_args..=command.max_args).contains(&args.len()) {
+        Ok(command)
+    } else {
+        Err(CommandError::Usage(command))
+    }
+}
+
+/// Gibt `usage: <name> <args>` aus.
+pub fn write_usage(out: &mut Output, command: &Command) {
+    out.write_bytes(b"usage: ");
+    out.write_bytes(command.name);
+    if !command.usage.is_empty() {
+        out.write_byte(b' ');
+        out.write_bytes(command.usage);
+    }
+    out.write_byte(b'\n');
+}
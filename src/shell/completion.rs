@@ -0,0 +1,230 @@
+//! Tab-Vervollständigung für Kommandonamen und Pfade.
+//!
+//! Das erste Wort einer Zeile wird gegen die Kommandotabelle vervollständigt, jedes weitere
+//! als Pfad: der Teil bis zum letzten `/` bestimmt das Verzeichnis, dessen Einträge
+//! ([`FileSystem::list`]) die Kandidaten liefern.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::fs::vfs::{FileSystem, NodeType, resolve_path};
+
+/// Möglicher Ersatz für das Wort vor dem Cursor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Candidate {
+    pub name: Vec<u8>,
+    /// Verzeichnisse werden mit `/` statt mit einem Leerzeichen abgeschlossen.
+    pub directory: bool,
+}
+
+impl Candidate {
+    pub fn new(name: &[u8], directory: bool) -> Self {
+        Self {
+            name: name.to_vec(),
+            directory,
+        }
+    }
+}
+
+/// Was vor dem Cursor vervollständigt werden soll.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target<'a> {
+    Command {
+        prefix: &'a [u8],
+    },
+    /// `dir` ist der Pfad bis einschließlich des letzten `/` (leer für relative Namen).
+    Path {
+        dir: &'a [u8],
+        prefix: &'a [u8],
+    },
+}
+
+/// Bestimmt das Wort vor dem Cursor und ob es ein Kommando oder ein Pfad ist.
+pub fn target(before_cursor: &[u8]) -> Target<'_> {
+    let start = before_cursor
+        .iter()
+        .rposition(|byte| *byte == b' ')
+        .map_or(0, |space| space + 1);
+    let word = &before_cursor[start..];
+    if before_cursor[..start].iter().all(|byte| *byte == b' ') {
+        return Target::Command { prefix: word };
+    }
+
+    let split = word
+        .iter()
+        .rposition(|byte| *byte == b'/')
+        .map_or(0, |slash| slash + 1);
+    Target::Path {
+        dir: &word[..split],
+        prefix: &word[split..],
+    }
+}
+
+/// Ergebnis einer Vervollständigung.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Completion {
+    /// An der Cursorposition einzufügender Text (ggf. leer).
+    pub insert: Vec<u8>,
+    /// Alle passenden Kandidaten.
+    pub matches: Vec<Candidate>,
+}
+
+/// Sucht alle Kandidaten mit `prefix` und ergänzt so weit, wie sie übereinstimmen.
+///
+/// Bei genau einem Treffer wird das Wort mit `/` oder einem Leerzeichen abgeschlossen.
+pub fn complete(prefix: &[u8], candidates: Vec<Candidate>) -> Completion {
+    let mut matches: Vec<Candidate> = candidates
+        .into_iter()
+        .filter(|candidate| candidate.name.starts_with(prefix))
+        .collect();
+    matches.sort_by(|a, b| a.name.cmp(&b.name));
+    matches.dedup();
+
+    let Some(first) = matches.first() else {
+        return Completion::default();
+    };
+    let common = matches.iter().fold(first.name.len(), |len, candidate| {
+        first.name[..len]
+            .iter()
+            .zip(&candidate.name)
+            .take_while(|(a, b)| a == b)
+            .count()
+    });
+
+    let mut insert = first.name[prefix.len()..common].to_vec();
+    if let [only] = matches.as_slice() {
+        insert.push(if only.directory { b'/' } else { b' ' });
+    }
+    Completion { insert, matches }
+}
+
+/// Kandidaten aus den Einträgen des Verzeichnisses `dir` (relativ zu `/`).
+pub fn path_candidates<F: FileSystem + ?Sized>(fs: &F, dir: &[u8]) -> Vec<Candidate> {
+    let Ok(dir) = core::str::from_utf8(dir) else {
+        return Vec::new();
+    };
+    let mut path = String::from("/");
+    path.push_str(dir.trim_start_matches('/'));
+
+    let Ok(node) = resolve_path(fs, &path) else {
+        return Vec::new();
+    };
+    fs.list(node)
+        .map(|entries| {
+            entries
+                .iter()
+                .map(|entry| {
+                    Candidate::new(
+                        entry.name().as_bytes(),
+                        entry.node_type == NodeType::Directory,
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::{Candidate, Target, complete, path_candidates, target};
+    use crate::fs::vfs::{DirEntry, FileSystem, Metadata, NodeId, NodeType, VfsError};
+
+    struct MockFs;
+
+    impl FileSystem for MockFs {
+        fn root(&self) -> NodeId {
+            NodeId(1)
+        }
+
+        fn lookup(&self, parent: NodeId, name: &str) -> Result<NodeId, VfsError> {
+            match (parent.0, name) {
+                (1, "docs") => Ok(NodeId(2)),
+                _ => Err(VfsError::NotFound),
+            }
+        }
+
+        fn metadata(&self, node: NodeId) -> Result<Metadata, VfsError> {
+            Ok(Metadata {
+                node_type: if node.0 <= 2 {
+                    NodeType::Directory
+                } else {
+                    NodeType::File
+                },
+                size: 0,
+                created: 0,
+                modified: 0,
+            })
+        }
+
+        fn read(&self, _node: NodeId, _offset: u64, _out: &mut [u8]) -> Result<usize, VfsError> {
+            Err(VfsError::Unsupported)
+        }
+
+        fn list(&self, dir: NodeId) -> Result<Vec<DirEntry>, VfsError> {
+            match dir.0 {
+                1 => Ok(vec![
+                    DirEntry::new("motd.txt", NodeId(3), NodeType::File)?,
+                    DirEntry::new("docs", NodeId(2), NodeType::Directory)?,
+                    DirEntry::new("dvorak.map", NodeId(4), NodeType::File)?,
+                ]),
+                2 => Ok(vec![DirEntry::new("intro.txt", NodeId(5), NodeType::File)?]),
+                _ => Err(VfsError::NotDirectory),
+            }
+        }
+    }
+
+    fn names(candidates: &[Candidate]) -> Vec<&[u8]> {
+        candidates
+            .iter()
+            .map(|candidate| candidate.name.as_slice())
+            .collect()
+    }
+
+    #[test]
+    fn finds_the_word_before_the_cursor() {
+        assert_eq!(target(b"ca"), Target::Command { prefix: b"ca" });
+        assert_eq!(target(b"  st"), Target::Command { prefix: b"st" });
+        assert_eq!(
+            target(b"cat /docs/in"),
+            Target::Path {
+                dir: b"/docs/",
+                prefix: b"in"
+            }
+        );
+        assert_eq!(
+            target(b"ls "),
+            Target::Path {
+                dir: b"",
+                prefix: b""
+            }
+        );
+    }
+
+    #[test]
+    fn completes_common_prefix_and_single_matches() {
+        let commands = || {
+            [&b"cat"[..], b"clear", b"clear", b"ls"]
+                .map(|name| Candidate::new(name, false))
+                .to_vec()
+        };
+        let completion = complete(b"c", commands());
+        assert_eq!(completion.insert, b"");
+        assert_eq!(names(&completion.matches), [&b"cat"[..], b"clear"]);
+
+        assert_eq!(complete(b"cl", commands()).insert, b"ear ");
+        assert_eq!(complete(b"x", commands()).matches, []);
+
+        let candidates = path_candidates(&MockFs, b"/");
+        assert_eq!(complete(b"d", candidates.clone()).insert, b"");
+        assert_eq!(complete(b"do", candidates.clone()).insert, b"cs/");
+        assert_eq!(complete(b"dv", candidates).insert, b"orak.map ");
+        assert_eq!(
+            names(&path_candidates(&MockFs, b"docs/")),
+            [&b"intro.txt"[..]]
+        );
+        assert_eq!(path_candidates(&MockFs, b"/motd.txt/"), []);
+    }
+}
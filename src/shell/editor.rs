@@ -0,0 +1,435 @@
+//! Vollbild-Texteditor `edit` für SimpleFS-Dateien.
+//!
+//! [`Editor`] hält den Text als Zeilen und erzeugt die Bildschirmausgabe als ANSI-Sequenzen
+//! (`CSI r;c H`, `CSI K`, `CSI 7m`), genau wie der Zeileneditor der Shell. Die obersten
+//! [`TEXT_ROWS`] Zeilen zeigen den Text, die letzte eine Statuszeile. Lange Zeilen werden
+//! nicht umbrochen, sondern die Ansicht rollt waagerecht mit dem Cursor.
+//!
+//! Tasten: Pfeile, `Home`/`End`, `PageUp`/`PageDown`, `Backspace`/`Delete`, `Enter`,
+//! `Tab` (Leerzeichen bis zur nächsten Tabulatorposition), Strg+S speichert, Strg+Q beendet.
+
+use alloc::format;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+use super::command::{Command, Context, FAILURE, SUCCESS};
+use super::fs_commands::{read_simplefs_file, report, simplefs_node_type, write_simplefs_file};
+use super::line_editor::is_printable;
+use super::output::Output;
+use crate::arch::x86_64::keyboard::KeyEvent;
+use crate::console::terminal::{HEIGHT, WIDTH};
+use crate::fs::vfs::{NodeType, VfsError};
+use crate::{arch, console};
+
+/// Bildschirmzeilen für Text; darunter steht die Statuszeile.
+const TEXT_ROWS: usize = HEIGHT - 1;
+/// Sichtbare Spalten. Die letzte Spalte bleibt frei, damit kein Terminal umbricht oder rollt.
+const TEXT_COLS: usize = WIDTH - 1;
+const TAB_WIDTH: usize = 4;
+
+pub const EDIT: Command = Command {
+    name: b"edit",
+    usage: b"<path>",
+    summary: b"edit a file full-screen (^S save, ^Q quit)",
+    min_args: 1,
+    max_args: 1,
+    run: run_edit,
+};
+
+fn run_edit(ctx: &mut Context<'_>, argv: &[&[u8]]) -> u8 {
+    if !ctx.out.is_console() {
+        console::write_line(b"edit: output is not the console");
+        return FAILURE;
+    }
+    let Ok(path) = core::str::from_utf8(argv[1]) else {
+        report(argv[1], b"invalid path");
+        return FAILURE;
+    };
+    // Eine fehlende Datei wird erst beim Speichern angelegt.
+    let text = match simplefs_node_type(path) {
+        Some(NodeType::Directory) => {
+            report(argv[1], VfsError::NotFile.describe());
+            return FAILURE;
+        }
+        Some(NodeType::File) => match read_simplefs_file(path) {
+            Ok(text) => text,
+            Err(message) => {
+                report(argv[1], message);
+                return FAILURE;
+            }
+        },
+        None => Vec::new(),
+    };
+
+    let mut editor = Editor::new(argv[1], &text);
+    let console = console::output_console();
+    // Gezeichnet wird nur nach Tasten, nicht nach jedem Aufwachen durch den Timer: `render`
+    // schreibt direkt auf die Konsole und belegt dabei keinen Heap.
+    editor.render(ctx.out);
+    loop {
+        let Some(key) = arch::x86_64::keyboard::try_read_key_from(console) else {
+            arch::x86_64::halt();
+            continue;
+        };
+        match editor.handle_key(key) {
+            Action::Continue => {}
+            Action::Save => {
+                let text = editor.text();
+                match write_simplefs_file(path, &text, false) {
+                    Ok(()) => editor.saved(text.len()),
+                    Err(message) => editor.message = message.to_vec(),
+                }
+            }
+            Action::Quit => break,
+        }
+        editor.render(ctx.out);
+    }
+    console::write_str(b"\x1b[0m\x1b[H\x1b[2J");
+    SUCCESS
+}
+
+/// Was `run_edit` nach einer Taste tun soll.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Action {
+    Continue,
+    Save,
+    Quit,
+}
+
+/// Umfang der nächsten Bildschirmaktualisierung; Statuszeile und Cursor kommen immer dazu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Redraw {
+    Cursor,
+    Line,
+    All,
+}
+
+struct Editor {
+    name: Vec<u8>,
+    lines: Vec<Vec<u8>>,
+    /// Der Text endet mit `\n`; gilt auch für neue Dateien.
+    final_newline: bool,
+    row: usize,
+    col: usize,
+    /// Erste sichtbare Zeile und Spalte.
+    top: usize,
+    left: usize,
+    modified: bool,
+    /// Strg+Q bei ungespeicherten Änderungen: ein zweites Strg+Q verwirft sie.
+    quit_pending: bool,
+    /// Meldung in der Statuszeile bis zur nächsten Taste.
+    message: Vec<u8>,
+    redraw: Redraw,
+}
+
+impl Editor {
+    fn new(name: &[u8], text: &[u8]) -> Self {
+        let final_newline = text.is_empty() || text.ends_with(b"\n");
+        let body = text.strip_suffix(b"\n").unwrap_or(text);
+        Self {
+            name: name.to_vec(),
+            lines: body
+                .split(|byte| *byte == b'\n')
+                .map(<[u8]>::to_vec)
+                .collect(),
+            final_newline,
+            row: 0,
+            col: 0,
+            top: 0,
+            left: 0,
+            modified: false,
+            quit_pending: false,
+            message: Vec::new(),
+            redraw: Redraw::All,
+        }
+    }
+
+    /// Dateiinhalt; ein leerer Text bleibt eine leere Datei.
+    fn text(&self) -> Vec<u8> {
+        let mut text = self.lines.join(&b'\n');
+        if self.final_newline && !(self.lines.len() == 1 && self.lines[0].is_empty()) {
+            text.push(b'\n');
+        }
+        text
+    }
+
+    fn saved(&mut self, bytes: usize) {
+        self.modified = false;
+        self.message = format!("saved {bytes} bytes").into_bytes();
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        let quit_pending = core::mem::take(&mut self.quit_pending);
+        self.message.clear();
+        match key {
+            KeyEvent::Ctrl(b's') => return Action::Save,
+            KeyEvent::Ctrl(b'q') if !self.modified || quit_pending => return Action::Quit,
+            KeyEvent::Ctrl(b'q') => {
+                self.quit_pending = true;
+                self.message = b"unsaved changes, ^Q again discards them".to_vec();
+            }
+            KeyEvent::Char(b'\t') => {
+                let spaces = TAB_WIDTH - self.col % TAB_WIDTH;
+                for _ in 0..spaces {
+                    self.insert(b' ');
+                }
+            }
+            KeyEvent::Char(byte) if is_printable(byte) => self.insert(byte),
+            KeyEvent::Enter => self.split_line(),
+            KeyEvent::Backspace => self.backspace(),
+            KeyEvent::Delete => self.delete(),
+            KeyEvent::Left => {
+                if self.col > 0 {
+                    self.col -= 1;
+                } else if self.row > 0 {
+                    self.row -= 1;
+                    self.col = self.lines[self.row].len();
+                }
+            }
+            KeyEvent::Right => {
+                if self.col < self.lines[self.row].len() {
+                    self.col += 1;
+                } else if self.row + 1 < self.lines.len() {
+                    self.row += 1;
+                    self.col = 0;
+                }
+            }
+            KeyEvent::Up => self.move_rows(-1),
+            KeyEvent::Down => self.move_rows(1),
+            KeyEvent::PageUp => self.move_rows(-(TEXT_ROWS as isize)),
+            KeyEvent::PageDown => self.move_rows(TEXT_ROWS as isize),
+            KeyEvent::Home => self.col = 0,
+            KeyEvent::End => self.col = self.lines[self.row].len(),
+            _ => {}
+        }
+        self.scroll_to_cursor();
+        Action::Continue
+    }
+
+    fn insert(&mut self, byte: u8) {
+        self.lines[self.row].insert(self.col, byte);
+        self.col += 1;
+        self.changed(Redraw::Line);
+    }
+
+    fn split_line(&mut self) {
+        let rest = self.lines[self.row].split_off(self.col);
+        self.row += 1;
+        self.col = 0;
+        self.lines.insert(self.row, rest);
+        self.changed(Redraw::All);
+    }
+
+    fn backspace(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+            self.lines[self.row].remove(self.col);
+            self.changed(Redraw::Line);
+        } else if self.row > 0 {
+            // Hängt die Zeile an die vorige an.
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.lines[self.row].len();
+            self.lines[self.row].extend_from_slice(&line);
+            self.changed(Redraw::All);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.col < self.lines[self.row].len() {
+            self.lines[self.row].remove(self.col);
+            self.changed(Redraw::Line);
+        } else if self.row + 1 < self.lines.len() {
+            let line = self.lines.remove(self.row + 1);
+            self.lines[self.row].extend_from_slice(&line);
+            self.changed(Redraw::All);
+        }
+    }
+
+    fn move_rows(&mut self, delta: isize) {
+        let last = self.lines.len() as isize - 1;
+        self.row = (self.row as isize + delta).clamp(0, last) as usize;
+        self.col = self.col.min(self.lines[self.row].len());
+    }
+
+    fn changed(&mut self, redraw: Redraw) {
+        self.modified = true;
+        self.redraw = self.redraw.max(redraw);
+    }
+
+    /// Rollt die Ansicht, bis der Cursor sichtbar ist.
+    fn scroll_to_cursor(&mut self) {
+        let (top, left) = (self.top, self.left);
+        self.top = self
+            .top
+            .clamp((self.row + 1).saturating_sub(TEXT_ROWS), self.row);
+        self.left = self
+            .left
+            .clamp((self.col + 1).saturating_sub(TEXT_COLS), self.col);
+        if (top, left) != (self.top, self.left) {
+            self.redraw = Redraw::All;
+        }
+    }
+
+    /// Aktualisiert geänderte Textzeilen und die Statuszeile und setzt den Cursor.
+    fn render(&mut self, out: &mut Output) {
+        match self.redraw {
+            Redraw::All => (0..TEXT_ROWS).for_each(|screen_row| self.render_row(screen_row, out)),
+            Redraw::Line => self.render_row(self.row - self.top, out),
+            Redraw::Cursor => {}
+        }
+        self.redraw = Redraw::Cursor;
+        self.render_status(out);
+        let _ = write!(
+            out,
+            "\x1b[{};{}H",
+            self.row - self.top + 1,
+            self.col - self.left + 1
+        );
+    }
+
+    /// Zeile hinter dem Textende erscheinen wie bei vi als `~`.
+    fn render_row(&self, screen_row: usize, out: &mut Output) {
+        let _ = write!(out, "\x1b[{};1H", screen_row + 1);
+        match self.lines.get(self.top + screen_row) {
+            Some(line) => {
+                for &byte in line.iter().skip(self.left).take(TEXT_COLS) {
+                    out.write_byte(if is_printable(byte) { byte } else { b'.' });
+                }
+            }
+            None => out.write_byte(b'~'),
+        }
+        out.write_bytes(b"\x1b[K");
+    }
+
+    fn render_status(&self, out: &mut Output) {
+        let mut status = StatusLine {
+            bytes: [b' '; TEXT_COLS],
+            len: 0,
+        };
+        status.push(b" ");
+        status.push(&self.name);
+        if self.modified {
+            status.push(b" [modified]");
+        }
+        let _ = write!(
+            status,
+            "  line {}/{}, col {}  ",
+            self.row + 1,
+            self.lines.len(),
+            self.col + 1
+        );
+        if self.message.is_empty() {
+            status.push(b"^S save  ^Q quit");
+        } else {
+            status.push(&self.message);
+        }
+
+        let _ = write!(out, "\x1b[{};1H\x1b[7m", HEIGHT);
+        out.write_bytes(&status.bytes);
+        out.write_bytes(b"\x1b[0m");
+    }
+}
+
+/// Statuszeile fester Breite auf dem Stack; was nicht hineinpasst, fällt weg.
+struct StatusLine {
+    bytes: [u8; TEXT_COLS],
+    len: usize,
+}
+
+impl StatusLine {
+    fn push(&mut self, bytes: &[u8]) {
+        let count = bytes.len().min(TEXT_COLS - self.len);
+        self.bytes[self.len..self.len + count].copy_from_slice(&bytes[..count]);
+        self.len += count;
+    }
+}
+
+impl fmt::Write for StatusLine {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push(s.as_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{Action, Editor, Redraw, TEXT_ROWS};
+    use crate::arch::x86_64::keyboard::KeyEvent;
+    use crate::shell::output::Output;
+
+    fn press(editor: &mut Editor, keys: &[KeyEvent]) {
+        for key in keys {
+            editor.handle_key(*key);
+        }
+    }
+
+    fn type_text(editor: &mut Editor, text: &[u8]) {
+        for byte in text {
+            editor.handle_key(KeyEvent::Char(*byte));
+        }
+    }
+
+    #[test]
+    fn edits_lines_and_keeps_final_newline() {
+        let mut editor = Editor::new(b"/init.rc", b"echo hi\nls\n");
+        assert_eq!(editor.lines.len(), 2);
+        press(&mut editor, &[KeyEvent::End, KeyEvent::Enter]);
+        type_text(&mut editor, b"ticks");
+        assert_eq!(editor.text(), b"echo hi\nticks\nls\n");
+        assert!(editor.modified);
+
+        // Backspace am Zeilenanfang und Delete am Zeilenende verbinden Zeilen.
+        press(&mut editor, &[KeyEvent::Home, KeyEvent::Backspace]);
+        assert_eq!((editor.row, editor.col), (0, 7));
+        press(&mut editor, &[KeyEvent::End, KeyEvent::Delete]);
+        assert_eq!(editor.text(), b"echo hiticksls\n");
+
+        press(&mut editor, &[KeyEvent::Home, KeyEvent::Char(b'\t')]);
+        assert_eq!(&editor.lines[0][..5], b"    e");
+
+        assert_eq!(Editor::new(b"/new", b"").text(), b"");
+        assert_eq!(Editor::new(b"/raw", b"a\nb").text(), b"a\nb");
+    }
+
+    #[test]
+    fn asks_before_discarding_changes() {
+        let mut editor = Editor::new(b"/a.txt", b"x");
+        assert_eq!(editor.handle_key(KeyEvent::Ctrl(b's')), Action::Save);
+        type_text(&mut editor, b"y");
+        assert_eq!(editor.handle_key(KeyEvent::Ctrl(b'q')), Action::Continue);
+        assert!(!editor.message.is_empty());
+        assert_eq!(editor.handle_key(KeyEvent::Ctrl(b'q')), Action::Quit);
+
+        editor.saved(3);
+        assert_eq!(editor.message, b"saved 3 bytes");
+        assert_eq!(editor.handle_key(KeyEvent::Ctrl(b'q')), Action::Quit);
+    }
+
+    #[test]
+    fn scrolls_and_redraws_only_what_changed() {
+        let text: Vec<u8> = (0..40).flat_map(|_| *b"line\n").collect();
+        let mut editor = Editor::new(b"/long.txt", &text);
+        let mut out = Output::Buffer(Vec::new());
+        editor.render(&mut out);
+        let out = out.into_bytes();
+        assert!(out.starts_with(b"\x1b[1;1Hline\x1b[K\x1b[2;1Hline"));
+        assert!(out.ends_with(b"\x1b[0m\x1b[1;1H"));
+
+        press(&mut editor, &[KeyEvent::Right]);
+        assert_eq!(editor.redraw, Redraw::Cursor);
+        type_text(&mut editor, b"-");
+        assert_eq!(editor.redraw, Redraw::Line);
+        let mut out = Output::Buffer(Vec::new());
+        editor.render(&mut out);
+        let out = out.into_bytes();
+        assert!(out.starts_with(b"\x1b[1;1Hl-ine\x1b[K\x1b[25;1H\x1b[7m /long.txt [modified]"));
+
+        press(&mut editor, &[KeyEvent::PageDown, KeyEvent::PageDown]);
+        assert_eq!(editor.row, 39);
+        assert_eq!(editor.top, 40 - TEXT_ROWS);
+        assert_eq!(editor.redraw, Redraw::All);
+    }
+}
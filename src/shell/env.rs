@@ -0,0 +1,128 @@
+//! Shell-Variablen und Exit-Status.
+//!
+//! Variablen werden mit `NAME=value` gesetzt und beim Ausführen einer Zeile über `$NAME`
+//! bzw. `${NAME}` eingesetzt (siehe [`super::argv`]); `$?` ist der Exit-Status des letzten
+//! Kommandos. Namen bestehen aus ASCII-Buchstaben, Ziffern und `_` und beginnen nicht mit einer
+//! Ziffer.
+
+use alloc::vec::Vec;
+
+pub struct Env {
+    /// Nach Namen sortiert.
+    vars: Vec<(Vec<u8>, Vec<u8>)>,
+    status: u8,
+}
+
+impl Env {
+    pub const fn new() -> Self {
+        Self {
+            vars: Vec::new(),
+            status: 0,
+        }
+    }
+
+    pub fn get(&self, name: &[u8]) -> Option<&[u8]> {
+        self.position(name)
+            .ok()
+            .map(|index| self.vars[index].1.as_slice())
+    }
+
+    /// Setzt eine Variable; `false` bei ungültigem Namen.
+    pub fn set(&mut self, name: &[u8], value: &[u8]) -> bool {
+        if !is_valid_name(name) {
+            return false;
+        }
+        match self.position(name) {
+            Ok(index) => self.vars[index].1 = value.to_vec(),
+            Err(index) => self.vars.insert(index, (name.to_vec(), value.to_vec())),
+        }
+        true
+    }
+
+    pub fn unset(&mut self, name: &[u8]) {
+        if let Ok(index) = self.position(name) {
+            self.vars.remove(index);
+        }
+    }
+
+    pub fn vars(&self) -> impl Iterator<Item = (&[u8], &[u8])> {
+        self.vars
+            .iter()
+            .map(|(name, value)| (name.as_slice(), value.as_slice()))
+    }
+
+    pub fn set_status(&mut self, status: u8) {
+        self.status = status;
+    }
+
+    /// Wert von `$name`: `?` ist der letzte Exit-Status, unbekannte Variablen sind leer.
+    pub fn expand(&self, name: &[u8]) -> Vec<u8> {
+        if name == b"?" {
+            return alloc::format!("{}", self.status).into_bytes();
+        }
+        self.get(name).map(<[u8]>::to_vec).unwrap_or_default()
+    }
+
+    fn position(&self, name: &[u8]) -> Result<usize, usize> {
+        self.vars
+            .binary_search_by(|(candidate, _)| candidate.as_slice().cmp(name))
+    }
+}
+
+pub fn is_valid_name(name: &[u8]) -> bool {
+    match name.split_first() {
+        Some((first, rest)) => {
+            (first.is_ascii_alphabetic() || *first == b'_')
+                && rest.iter().all(|b| b.is_ascii_alphanumeric() || *b == b'_')
+        }
+        None => false,
+    }
+}
+
+/// Zerlegt eine Zuweisung `NAME=value` in Name und Wert.
+pub fn split_assignment(word: &[u8]) -> Option<(&[u8], &[u8])> {
+    let equals = word.iter().position(|byte| *byte == b'=')?;
+    let (name, value) = (&word[..equals], &word[equals + 1..]);
+    is_valid_name(name).then_some((name, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Env, is_valid_name, split_assignment};
+
+    #[test]
+    fn sets_expands_and_unsets_variables() {
+        let mut env = Env::new();
+        assert!(env.set(b"ZED", b"z"));
+        assert!(env.set(b"A_1", b"one"));
+        assert!(env.set(b"ZED", b"last"));
+        assert!(!env.set(b"1A", b"x"));
+        assert_eq!(env.get(b"ZED"), Some(&b"last"[..]));
+        assert_eq!(
+            env.vars().collect::<alloc::vec::Vec<_>>(),
+            [(&b"A_1"[..], &b"one"[..]), (b"ZED", b"last")]
+        );
+
+        env.unset(b"ZED");
+        assert_eq!(env.expand(b"ZED"), b"");
+        env.set_status(127);
+        assert_eq!(env.expand(b"?"), b"127");
+        env.set_status(0);
+        assert_eq!(env.expand(b"?"), b"0");
+    }
+
+    #[test]
+    fn recognizes_names_and_assignments() {
+        assert!(is_valid_name(b"_tmp9"));
+        assert!(!is_valid_name(b""));
+        assert!(!is_valid_name(b"a-b"));
+        assert_eq!(
+            split_assignment(b"GREETING=hello=world"),
+            Some((&b"GREETING"[..], &b"hello=world"[..]))
+        );
+        assert_eq!(split_assignment(b"X="), Some((&b"X"[..], &b""[..])));
+        assert_eq!(split_assignment(b"=x"), None);
+        assert_eq!(split_assignment(b"a b=c"), None);
+        assert_eq!(split_assignment(b"echo"), None);
+    }
+}
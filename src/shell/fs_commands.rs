@@ -0,0 +1,578 @@
+//! Kommandos für das SimpleFS-Laufwerk (das erste erkannte Laufwerk mit SimpleFS-Superblock).
+//!
+//! Pfade sind absolut oder relativ zu `/`. Das Dateisystem wird beim ersten Zugriff eingehängt
+//! und bleibt es, bis ein roher Schreibzugriff auf ein Laufwerk es verwirft.
+//! Fehler erscheinen einheitlich als `<pfad>: <meldung>`, die Meldungen stammen aus
+//! [`VfsError::describe`].
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use super::command::{self, Command, Context, FAILURE, SUCCESS, USAGE};
+use super::completion::Candidate;
+use super::output::Output;
+use crate::arch::x86_64::rtc::DateTime;
+use crate::console;
+use crate::fs::vfs::{FileSystem, Metadata, NodeId, NodeType, VfsError, resolve_path, split_parent};
+
+type Fs = dyn FileSystem;
+
+/// Meldung zu einem fehlgeschlagenen Dateizugriff.
+type FsResult<T> = Result<T, &'static [u8]>;
+
+pub const LS: Command = Command {
+    name: b"ls",
+    usage: b"[-l] [path]",
+    summary: b"list a directory (default /), -l with type, size and time",
+    min_args: 0,
+    max_args: 2,
+    run: run_ls,
+};
+
+fn run_ls(ctx: &mut Context<'_>, argv: &[&[u8]]) -> u8 {
+    let (long, path): (bool, &[u8]) = match &argv[1..] {
+        [] => (false, b"/"),
+        [b"-l"] => (true, b"/"),
+        [b"-l", path] => (true, path),
+        [path] if !path.starts_with(b"-") => (false, path),
+        _ => {
+            command::write_usage(&mut Output::Console, &LS);
+            return USAGE;
+        }
+    };
+    let out = &mut *ctx.out;
+    for_each_path(&[path], |fs, path| list(fs, out, path, long))
+}
+
+pub const CAT: Command = Command {
+    name: b"cat",
+    usage: b"[path]...",
+    summary: b"print files or the input",
+    min_args: 0,
+    max_args: usize::MAX,
+    run: run_cat,
+};
+
+fn run_cat(ctx: &mut Context<'_>, argv: &[&[u8]]) -> u8 {
+    let Some(text) = read_input(ctx, &CAT, &argv[1..]) else {
+        return FAILURE;
+    };
+    ctx.out.write_text(&text);
+    SUCCESS
+}
+
+pub const STAT: Command = Command {
+    name: b"stat",
+    usage: b"<path>",
+    summary: b"show type, size and timestamps",
+    min_args: 1,
+    max_args: 1,
+    run: |ctx, argv| {
+        let out = &mut *ctx.out;
+        for_each_path(&argv[1..], |fs, path| stat(fs, out, path))
+    },
+};
+
+pub const WRITE: Command = Command {
+    name: b"write",
+    usage: b"<path> [text]...",
+    summary: b"replace a file with the text or the input",
+    min_args: 1,
+    max_args: usize::MAX,
+    run: |ctx, argv| run_write(ctx, argv, &WRITE, false),
+};
+
+pub const APPEND: Command = Command {
+    name: b"append",
+    usage: b"<path> [text]...",
+    summary: b"append the text or the input to a file",
+    min_args: 1,
+    max_args: usize::MAX,
+    run: |ctx, argv| run_write(ctx, argv, &APPEND, true),
+};
+
+/// `write`/`append`: Text aus den Argumenten als eine Zeile, sonst die Standardeingabe.
+fn run_write(ctx: &mut Context<'_>, argv: &[&[u8]], command: &Command, append: bool) -> u8 {
+    let data = match &argv[2..] {
+        [] => match ctx.input {
+            Some(input) => input.to_vec(),
+            None => {
+                command::write_usage(&mut Output::Console, command);
+                return USAGE;
+            }
+        },
+        words => {
+            let mut line = words.join(&b' ');
+            line.push(b'\n');
+            line
+        }
+    };
+    for_each_path(&argv[1..2], |fs, path| write_file(fs, path, &data, append))
+}
+
+pub const TOUCH: Command = Command {
+    name: b"touch",
+    usage: b"<path>...",
+    summary: b"create empty files or update their time",
+    min_args: 1,
+    max_args: usize::MAX,
+    run: |_, argv| for_each_path(&argv[1..], touch),
+};
+
+pub const CP: Command = Command {
+    name: b"cp",
+    usage: b"<source> <target>",
+    summary: b"copy a file (into a directory if the target is one)",
+    min_args: 2,
+    max_args: 2,
+    run: |_, argv| for_each_path(&argv[1..2], |fs, source| copy(fs, source, argv[2])),
+};
+
+pub const MV: Command = Command {
+    name: b"mv",
+    usage: b"<source> <target>",
+    summary: b"move or rename a file or directory",
+    min_args: 2,
+    max_args: 2,
+    run: |_, argv| for_each_path(&argv[1..2], |fs, source| rename(fs, source, argv[2])),
+};
+
+pub const RM: Command = Command {
+    name: b"rm",
+    usage: b"<path>...",
+    summary: b"remove files",
+    min_args: 1,
+    max_args: usize::MAX,
+    run: |_, argv| for_each_path(&argv[1..], |fs, path| remove(fs, path, NodeType::File)),
+};
+
+pub const MKDIR: Command = Command {
+    name: b"mkdir",
+    usage: b"<path>...",
+    summary: b"create directories",
+    min_args: 1,
+    max_args: usize::MAX,
+    run: |_, argv| {
+        for_each_path(&argv[1..], |fs, path| {
+            let (parent, name) = resolve_parent(fs, path)?;
+            fs.create_dir(parent, &name).map(|_| ()).map_err(VfsError::describe)
+        })
+    },
+};
+
+pub const RMDIR: Command = Command {
+    name: b"rmdir",
+    usage: b"<path>...",
+    summary: b"remove empty directories",
+    min_args: 1,
+    max_args: usize::MAX,
+    run: |_, argv| for_each_path(&argv[1..], |fs, path| remove(fs, path, NodeType::Directory)),
+};
+
+/// Gibt eine Fehlermeldung zu einem Pfad aus: `<pfad>: <meldung>`.
+pub(super) fn report(path: &[u8], message: &[u8]) {
+    console::write_str(path);
+    console::write_str(b": ");
+    console::write_line(message);
+}
+
+/// Ruft `f` für jeden Pfad auf dem Dateisystem auf; Fehler werden pro Pfad gemeldet und ergeben
+/// den Status 1.
+fn for_each_path(args: &[&[u8]], mut f: impl FnMut(&mut Fs, &str) -> FsResult<()>) -> u8 {
+    let result = with_simplefs(|fs| {
+        let mut status = SUCCESS;
+        for arg in args {
+            let result = core::str::from_utf8(arg)
+                .map_err(|_| b"invalid path".as_slice())
+                .and_then(|path| f(fs, path));
+            if let Err(message) = result {
+                report(arg, message);
+                status = FAILURE;
+            }
+        }
+        Ok(status)
+    });
+    result.unwrap_or_else(|message| {
+        console::write_line(message);
+        FAILURE
+    })
+}
+
+/// Eingabe eines Filters: die Dateien aus `paths` nacheinander oder, ohne Pfade, die
+/// Standardeingabe. Gibt bei Fehlern eine Meldung aus und liefert `None`.
+pub(super) fn read_input(ctx: &Context<'_>, command: &Command, paths: &[&[u8]]) -> Option<Vec<u8>> {
+    if paths.is_empty() {
+        if ctx.input.is_none() {
+            command::write_usage(&mut Output::Console, command);
+        }
+        return ctx.input.map(<[u8]>::to_vec);
+    }
+
+    let mut text = Vec::new();
+    for path in paths {
+        let data = core::str::from_utf8(path)
+            .map_err(|_| b"invalid path".as_slice())
+            .and_then(read_simplefs_file);
+        match data {
+            Ok(data) => text.extend_from_slice(&data),
+            Err(message) => {
+                report(path, message);
+                return None;
+            }
+        }
+    }
+    Some(text)
+}
+
+#[cfg(eres_kernel)]
+struct MountCell(core::cell::UnsafeCell<Option<alloc::boxed::Box<Fs>>>);
+#[cfg(eres_kernel)]
+unsafe impl Sync for MountCell {}
+
+/// Das eingehängte SimpleFS; Kommandos laufen nacheinander, nie gleichzeitig.
+#[cfg(eres_kernel)]
+static MOUNTED: MountCell = MountCell(core::cell::UnsafeCell::new(None));
+#[cfg(eres_kernel)]
+static MOUNT_IN_USE: core::sync::atomic::AtomicBool = core::sync::atomic::AtomicBool::new(false);
+
+#[cfg(eres_kernel)]
+fn with_mount<T>(f: impl FnOnce(&mut Option<alloc::boxed::Box<Fs>>) -> T) -> T {
+    use core::sync::atomic::Ordering;
+
+    let busy = MOUNT_IN_USE.swap(true, Ordering::Acquire);
+    assert!(!busy, "simplefs already in use");
+    let result = f(unsafe { &mut *MOUNTED.0.get() });
+    MOUNT_IN_USE.store(false, Ordering::Release);
+    result
+}
+
+/// Ruft `f` mit dem SimpleFS auf; eingehängt wird beim ersten Zugriff und nach
+/// [`unmount_simplefs`].
+#[cfg(eres_kernel)]
+fn with_simplefs<T>(f: impl FnOnce(&mut Fs) -> FsResult<T>) -> FsResult<T> {
+    with_mount(|mounted| {
+        if mounted.is_none() {
+            *mounted = crate::fs::simplefs::mount_registered()
+                .map(|(_, fs)| alloc::boxed::Box::new(fs) as alloc::boxed::Box<Fs>);
+        }
+        match mounted {
+            Some(fs) => f(&mut **fs),
+            None => Err(b"simplefs unavailable".as_slice()),
+        }
+    })
+}
+
+#[cfg(not(eres_kernel))]
+fn with_simplefs<T>(_f: impl FnOnce(&mut Fs) -> FsResult<T>) -> FsResult<T> {
+    Err(b"simplefs unavailable")
+}
+
+/// Verwirft das eingehängte SimpleFS, damit der nächste Zugriff neu einhängt. Nötig, wenn ein
+/// Laufwerk an SimpleFS vorbei beschrieben oder sein Übertragungsmodus umgeschaltet wurde.
+#[cfg(eres_kernel)]
+pub(super) fn unmount_simplefs() {
+    with_mount(|mounted| *mounted = None);
+}
+
+#[cfg(not(eres_kernel))]
+pub(super) fn unmount_simplefs() {}
+
+/// Vervollständigungskandidaten aus einem SimpleFS-Verzeichnis.
+pub(super) fn simplefs_candidates(dir: &[u8]) -> Vec<Candidate> {
+    with_simplefs(|fs| Ok(super::completion::path_candidates(fs, dir))).unwrap_or_default()
+}
+
+/// Typ des Knotens unter `path`; `None`, wenn er nicht existiert.
+pub(super) fn simplefs_node_type(path: &str) -> Option<NodeType> {
+    with_simplefs(|fs| metadata(fs, resolve(fs, path)?).map(|meta| meta.node_type)).ok()
+}
+
+pub(super) fn normalize_simplefs_path(path: &str) -> String {
+    let trimmed = path.trim();
+    if trimmed.is_empty() || trimmed == "/" {
+        return String::from("/");
+    }
+
+    if trimmed.as_bytes()[0] == b'/' {
+        String::from(trimmed)
+    } else {
+        let mut out = String::from("/");
+        out.push_str(trimmed);
+        out
+    }
+}
+
+fn resolve(fs: &Fs, path: &str) -> FsResult<NodeId> {
+    resolve_path(fs, &normalize_simplefs_path(path)).map_err(VfsError::describe)
+}
+
+/// Elternverzeichnis und Name des letzten Pfadteils, z. B. für neue Einträge.
+fn resolve_parent(fs: &Fs, path: &str) -> FsResult<(NodeId, String)> {
+    let normalized = normalize_simplefs_path(path);
+    let (parent, name) = split_parent(&normalized).map_err(VfsError::describe)?;
+    let parent = resolve_path(fs, parent).map_err(VfsError::describe)?;
+    Ok((parent, String::from(name)))
+}
+
+fn metadata(fs: &Fs, node: NodeId) -> FsResult<Metadata> {
+    fs.metadata(node).map_err(VfsError::describe)
+}
+
+fn list(fs: &Fs, out: &mut Output, path: &str, long: bool) -> FsResult<()> {
+    let node = resolve(fs, path)?;
+    let meta = metadata(fs, node)?;
+    if meta.node_type == NodeType::File {
+        // Wie bei POSIX-`ls` erscheint eine Datei selbst.
+        write_entry(out, &meta, path.as_bytes(), long);
+        return Ok(());
+    }
+
+    let entries = fs.list(node).map_err(VfsError::describe)?;
+    if entries.is_empty() {
+        out.write_line(b"(empty)");
+    }
+    for entry in entries {
+        let meta = metadata(fs, entry.node)?;
+        write_entry(out, &meta, entry.name().as_bytes(), long);
+    }
+    Ok(())
+}
+
+/// Eine Zeile von `ls`; mit `long` als `<typ> <größe> <änderungszeit> <name>`.
+fn write_entry(out: &mut Output, meta: &Metadata, name: &[u8], long: bool) {
+    let directory = meta.node_type == NodeType::Directory;
+    if long {
+        let kind = if directory { 'd' } else { '-' };
+        let _ = write!(out, "{kind} {:>8}  ", meta.size);
+        // Nur bis zur Minute, die Sekunden zeigt `stat`.
+        match time(meta.modified) {
+            Some(time) => {
+                let _ = write!(
+                    out,
+                    "{:04}-{:02}-{:02} {:02}:{:02}  ",
+                    time.year, time.month, time.day, time.hour, time.minute
+                );
+            }
+            None => out.write_bytes(b"-                 "),
+        }
+    }
+    out.write_bytes(name);
+    if directory {
+        out.write_byte(b'/');
+    }
+    out.write_byte(b'\n');
+}
+
+/// Zeitstempel als Datum; `None` für 0 (unbekannt).
+fn time(seconds: u64) -> Option<DateTime> {
+    (seconds != 0).then(|| DateTime::from_unix(seconds))
+}
+
+fn stat(fs: &Fs, out: &mut Output, path: &str) -> FsResult<()> {
+    let meta = metadata(fs, resolve(fs, path)?)?;
+    let kind = match meta.node_type {
+        NodeType::Directory => "directory",
+        NodeType::File => "file",
+    };
+    let _ = writeln!(out, "type={kind} size={}", meta.size);
+    for (label, seconds) in [("created", meta.created), ("modified", meta.modified)] {
+        let _ = match time(seconds) {
+            Some(time) => writeln!(out, "{label}={time}"),
+            None => writeln!(out, "{label}=unknown"),
+        };
+    }
+    Ok(())
+}
+
+fn read_file(fs: &Fs, path: &str) -> FsResult<Vec<u8>> {
+    let node = resolve(fs, path)?;
+    let meta = metadata(fs, node)?;
+    if meta.node_type != NodeType::File {
+        return Err(VfsError::NotFile.describe());
+    }
+
+    let size = meta.size as usize;
+    let mut buffer = alloc::vec![0; size];
+
+    let mut total = 0_usize;
+    while total < size {
+        match fs.read(node, total as u64, &mut buffer[total..]) {
+            Ok(0) => break,
+            Ok(read) => total += read,
+            Err(err) => return Err(err.describe()),
+        }
+    }
+    buffer.truncate(total);
+    Ok(buffer)
+}
+
+/// Schreibt `data` in eine Datei, die bei Bedarf angelegt wird; `append` hängt an statt zu
+/// überschreiben.
+fn write_file(fs: &mut Fs, path: &str, data: &[u8], append: bool) -> FsResult<()> {
+    let (node, size) = open_file(fs, path)?;
+    let offset = if append {
+        size
+    } else {
+        fs.truncate(node, 0).map_err(VfsError::describe)?;
+        0
+    };
+    fs.write(node, offset, data).map_err(VfsError::describe)?;
+    Ok(())
+}
+
+/// Knoten und Größe der Datei unter `path`; fehlt sie, wird sie leer angelegt.
+fn open_file(fs: &mut Fs, path: &str) -> FsResult<(NodeId, u64)> {
+    let node = match resolve(fs, path) {
+        Ok(node) => node,
+        Err(_) => {
+            let (parent, name) = resolve_parent(fs, path)?;
+            fs.create(parent, &name).map_err(VfsError::describe)?
+        }
+    };
+    let meta = metadata(fs, node)?;
+    if meta.node_type != NodeType::File {
+        return Err(VfsError::NotFile.describe());
+    }
+    Ok((node, meta.size))
+}
+
+/// Legt eine leere Datei an oder setzt die Änderungszeit einer vorhandenen Datei.
+fn touch(fs: &mut Fs, path: &str) -> FsResult<()> {
+    match resolve(fs, path) {
+        Ok(node) => {
+            let meta = metadata(fs, node)?;
+            if meta.node_type == NodeType::File {
+                // Ein leerer Schreibzugriff aktualisiert nur die Änderungszeit.
+                fs.write(node, meta.size, &[]).map_err(VfsError::describe)?;
+            }
+            Ok(())
+        }
+        Err(_) => {
+            let (parent, name) = resolve_parent(fs, path)?;
+            fs.create(parent, &name).map(|_| ()).map_err(VfsError::describe)
+        }
+    }
+}
+
+/// Ziel von `cp` und `mv`: ein vorhandenes Verzeichnis nimmt den Eintrag unter seinem Namen
+/// auf, sonst ist `target` der neue Pfad.
+fn target_path(fs: &Fs, source: &str, target: &str) -> FsResult<String> {
+    let target = normalize_simplefs_path(target);
+    let is_directory = resolve(fs, &target)
+        .and_then(|node| metadata(fs, node))
+        .is_ok_and(|meta| meta.node_type == NodeType::Directory);
+    if !is_directory {
+        return Ok(target);
+    }
+
+    let normalized = normalize_simplefs_path(source);
+    let (_, name) = split_parent(&normalized).map_err(VfsError::describe)?;
+    let mut path = target;
+    if !path.ends_with('/') {
+        path.push('/');
+    }
+    path.push_str(name);
+    Ok(path)
+}
+
+fn copy(fs: &mut Fs, source: &str, target: &[u8]) -> FsResult<()> {
+    let data = read_file(fs, source)?;
+    let target = core::str::from_utf8(target).map_err(|_| b"invalid path".as_slice())?;
+    let target = target_path(fs, source, target)?;
+    write_file(fs, &target, &data, false).map_err(|message| {
+        report(target.as_bytes(), message);
+        b"copy failed".as_slice()
+    })
+}
+
+fn rename(fs: &mut Fs, source: &str, target: &[u8]) -> FsResult<()> {
+    let node = resolve(fs, source)?;
+    let is_directory = metadata(fs, node)?.node_type == NodeType::Directory;
+    let target = core::str::from_utf8(target).map_err(|_| b"invalid path".as_slice())?;
+    let target = target_path(fs, source, target)?;
+
+    let result = (|| {
+        // Eine vorhandene Zieldatei wird wie bei POSIX-`mv` ersetzt.
+        if let Ok(existing) = resolve(fs, &target) {
+            if existing == node {
+                return Ok(());
+            }
+            if is_directory || metadata(fs, existing)?.node_type != NodeType::File {
+                return Err(VfsError::AlreadyExists.describe());
+            }
+            fs.remove(existing).map_err(VfsError::describe)?;
+        }
+        let (parent, name) = resolve_parent(fs, &target)?;
+        fs.rename(node, parent, &name).map_err(VfsError::describe)
+    })();
+    result.map_err(|message| {
+        report(target.as_bytes(), message);
+        b"move failed".as_slice()
+    })
+}
+
+/// `rm` und `rmdir`: entfernt nur Einträge vom Typ `expected`.
+fn remove(fs: &mut Fs, path: &str, expected: NodeType) -> FsResult<()> {
+    let node = resolve(fs, path)?;
+    match (metadata(fs, node)?.node_type, expected) {
+        (NodeType::Directory, NodeType::File) => Err(VfsError::NotFile.describe()),
+        (NodeType::File, NodeType::Directory) => Err(VfsError::NotDirectory.describe()),
+        _ => fs.remove(node).map_err(VfsError::describe),
+    }
+}
+
+/// Liest eine Datei von SimpleFS vollständig ein.
+pub(super) fn read_simplefs_file(path: &str) -> FsResult<Vec<u8>> {
+    with_simplefs(|fs| read_file(fs, path))
+}
+
+/// Schreibt `data` in eine SimpleFS-Datei, die bei Bedarf angelegt wird; `append` hängt an
+/// statt zu überschreiben.
+pub(super) fn write_simplefs_file(path: &str, data: &[u8], append: bool) -> FsResult<()> {
+    with_simplefs(|fs| write_file(fs, path, data, append))
+}
+
+/// Schreibt `data` ab Byte `offset` in eine SimpleFS-Datei, ohne sie zu kürzen; fehlt sie, wird
+/// sie angelegt.
+pub(super) fn write_simplefs_at(path: &str, offset: u64, data: &[u8]) -> FsResult<()> {
+    with_simplefs(|fs| {
+        let (node, _) = open_file(fs, path)?;
+        fs.write(node, offset, data).map(|_| ()).map_err(VfsError::describe)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::write_entry;
+    use crate::fs::vfs::{Metadata, NodeType};
+    use crate::shell::output::Output;
+
+    #[test]
+    fn formats_long_listing() {
+        let mut out = Output::Buffer(Vec::new());
+        let file = Metadata {
+            node_type: NodeType::File,
+            size: 1234,
+            created: 0,
+            modified: 1_700_000_000,
+        };
+        let dir = Metadata {
+            node_type: NodeType::Directory,
+            size: 2,
+            created: 0,
+            modified: 0,
+        };
+        write_entry(&mut out, &file, b"motd.txt", true);
+        write_entry(&mut out, &dir, b"docs", true);
+        write_entry(&mut out, &dir, b"docs", false);
+        assert_eq!(
+            out.into_bytes(),
+            b"-     1234  2023-11-14 22:13  motd.txt\n\
+              d        2  -                 docs/\n\
+              docs/\n"
+        );
+    }
+}
@@ -0,0 +1,327 @@
+//! Zeileneditor der Shell.
+//!
+//! Bearbeitet die Eingabezeile an beliebiger Cursorposition und erzeugt dafür die nötigen
+//! Terminalausgaben (Zeichen plus ANSI-Sequenzen `CSI A/B/C/D` und `CSI J`). Die Sequenzen
+//! versteht der VGA-Terminalemulator ebenso wie ein Terminal an COM1; für beide wird eine
+//! Breite von 80 Spalten angenommen, damit Zeilen über den Bildschirmrand korrekt umbrechen.
+//!
+//! Der Bildschirmcursor steht nach jeder Operation an der Editierposition. Ein Cursor im
+//! „Umbruch ausstehend“-Zustand hinter der letzten Spalte wird dabei vermieden, weil Terminals
+//! dort unterschiedlich reagieren.
+
+use core::fmt::{self, Write};
+
+use crate::arch::x86_64::keyboard::KeyEvent;
+use crate::console::terminal::WIDTH;
+
+/// Maximale Länge einer Eingabezeile.
+pub const MAX_LINE: usize = 128;
+/// Sichtbare Breite des Prompts `> `.
+pub const PROMPT_WIDTH: usize = 2;
+/// Platz für die Ausgabe einer Taste: die neu ausgegebene Zeile plus Cursorbewegungen.
+const ECHO_CAPACITY: usize = 2 * MAX_LINE;
+
+/// Ergebnis einer Taste für die Shell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditResult {
+    /// Zeile wird weiter bearbeitet.
+    Pending,
+    /// Enter: Zeile ausführen.
+    Submit,
+    /// Strg+C: Zeile verwerfen.
+    Abort,
+    /// Strg+L: Bildschirm löschen, danach Prompt und Zeile neu ausgeben.
+    ClearScreen,
+}
+
+/// Terminalausgabe einer Taste. Sie liegt auf dem Stack, damit Tastendrücke den Heap nicht
+/// berühren.
+pub struct Echo {
+    bytes: [u8; ECHO_CAPACITY],
+    len: usize,
+}
+
+impl Echo {
+    pub const fn new() -> Self {
+        Self {
+            bytes: [0; ECHO_CAPACITY],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        let count = bytes.len().min(ECHO_CAPACITY - self.len);
+        self.bytes[self.len..self.len + count].copy_from_slice(&bytes[..count]);
+        self.len += count;
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl fmt::Write for Echo {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push(s.as_bytes());
+        Ok(())
+    }
+}
+
+pub struct LineEditor {
+    buf: [u8; MAX_LINE],
+    len: usize,
+    cursor: usize,
+}
+
+impl LineEditor {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; MAX_LINE],
+            len: 0,
+            cursor: 0,
+        }
+    }
+
+    pub fn line(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Text links vom Cursor (Grundlage der Tab-Vervollständigung).
+    pub fn before_cursor(&self) -> &[u8] {
+        &self.buf[..self.cursor]
+    }
+
+    /// Fügt `bytes` an der Cursorposition ein, soweit die Zeile Platz hat.
+    pub fn insert_bytes(&mut self, bytes: &[u8], out: &mut Echo) {
+        let count = bytes.len().min(MAX_LINE - self.len);
+        if count == 0 {
+            return;
+        }
+        self.buf.copy_within(self.cursor..self.len, self.cursor + count);
+        self.buf[self.cursor..self.cursor + count].copy_from_slice(&bytes[..count]);
+        self.len += count;
+        let from = self.cursor;
+        self.cursor += count;
+        self.refresh_from(from, out);
+    }
+
+    /// Leert die Zeile ohne Ausgabe (nach Enter oder Strg+C, vor dem nächsten Prompt).
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.cursor = 0;
+    }
+
+    /// Ersetzt die Zeile (z. B. durch einen History-Eintrag); der Cursor steht danach am Ende.
+    pub fn set_line(&mut self, line: &[u8], out: &mut Echo) {
+        self.move_cursor(out, self.cursor, 0);
+        self.len = line.len().min(MAX_LINE);
+        self.buf[..self.len].copy_from_slice(&line[..self.len]);
+        self.cursor = self.len;
+        self.refresh_from(0, out);
+    }
+
+    /// Gibt die ganze Zeile aus; der Bildschirmcursor muss direkt hinter dem Prompt stehen.
+    pub fn redraw(&mut self, out: &mut Echo) {
+        self.refresh_from(0, out);
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent, out: &mut Echo) -> EditResult {
+        match key {
+            KeyEvent::Enter => {
+                self.move_to(self.len, out);
+                return EditResult::Submit;
+            }
+            KeyEvent::Ctrl(b'c') => {
+                self.move_to(self.len, out);
+                out.push(b"^C");
+                return EditResult::Abort;
+            }
+            KeyEvent::Ctrl(b'l') => return EditResult::ClearScreen,
+            KeyEvent::Char(byte) if is_printable(byte) => self.insert_bytes(&[byte], out),
+            KeyEvent::Backspace if self.cursor > 0 => {
+                self.delete(self.cursor - 1, self.cursor, out);
+            }
+            KeyEvent::Delete if self.cursor < self.len => {
+                self.delete(self.cursor, self.cursor + 1, out);
+            }
+            KeyEvent::Left if self.cursor > 0 => self.move_to(self.cursor - 1, out),
+            KeyEvent::Right if self.cursor < self.len => self.move_to(self.cursor + 1, out),
+            KeyEvent::Home | KeyEvent::Ctrl(b'a') => self.move_to(0, out),
+            KeyEvent::End | KeyEvent::Ctrl(b'e') => self.move_to(self.len, out),
+            KeyEvent::Ctrl(b'u') => self.delete(0, self.cursor, out),
+            KeyEvent::Ctrl(b'k') => self.delete(self.cursor, self.len, out),
+            KeyEvent::Ctrl(b'w') => self.delete(self.word_start(), self.cursor, out),
+            _ => {}
+        }
+        EditResult::Pending
+    }
+
+    /// Entfernt `start..end` und zeichnet den Rest der Zeile neu.
+    fn delete(&mut self, start: usize, end: usize, out: &mut Echo) {
+        if start == end {
+            return;
+        }
+        self.move_cursor(out, self.cursor, start);
+        self.buf.copy_within(end..self.len, start);
+        self.len -= end - start;
+        self.cursor = start;
+        self.refresh_from(start, out);
+    }
+
+    /// Anfang des Worts vor dem Cursor (Leerzeichen davor werden mitgelöscht).
+    fn word_start(&self) -> usize {
+        let mut start = self.cursor;
+        while start > 0 && self.buf[start - 1] == b' ' {
+            start -= 1;
+        }
+        while start > 0 && self.buf[start - 1] != b' ' {
+            start -= 1;
+        }
+        start
+    }
+
+    fn move_to(&mut self, index: usize, out: &mut Echo) {
+        self.move_cursor(out, self.cursor, index);
+        self.cursor = index;
+    }
+
+    /// Gibt die Zeile ab `from` aus (Bildschirmcursor steht dort), löscht alte Reste dahinter
+    /// und stellt den Cursor zurück.
+    fn refresh_from(&self, from: usize, out: &mut Echo) {
+        out.push(&self.buf[from..self.len]);
+        // Endet die Zeile genau am Rand, erzwingen Leerzeichen und `\r` den Umbruch.
+        if (PROMPT_WIDTH + self.len).is_multiple_of(WIDTH) {
+            out.push(b" \r");
+        }
+        out.push(b"\x1b[J");
+        self.move_cursor(out, self.len, self.cursor);
+    }
+
+    fn move_cursor(&self, out: &mut Echo, from: usize, to: usize) {
+        let (from, to) = (PROMPT_WIDTH + from, PROMPT_WIDTH + to);
+        let (from_row, from_col) = (from / WIDTH, from % WIDTH);
+        let (to_row, to_col) = (to / WIDTH, to % WIDTH);
+        if to_row < from_row {
+            let _ = write!(out, "\x1b[{}A", from_row - to_row);
+        } else if to_row > from_row {
+            let _ = write!(out, "\x1b[{}B", to_row - from_row);
+        }
+        if to_col > from_col {
+            let _ = write!(out, "\x1b[{}C", to_col - from_col);
+        } else if to_col < from_col {
+            let _ = write!(out, "\x1b[{}D", from_col - to_col);
+        }
+    }
+}
+
+/// Druckbares ASCII oder ein Zeichen der oberen Codepage-437-Hälfte (Umlaute usw.).
+pub(super) fn is_printable(byte: u8) -> bool {
+    matches!(byte, 0x20..=0x7E | 0x80..=0xFE)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{Echo, EditResult, LineEditor, MAX_LINE};
+    use crate::arch::x86_64::keyboard::KeyEvent;
+
+    fn type_keys(editor: &mut LineEditor, keys: &[KeyEvent]) -> Vec<u8> {
+        let mut out = Echo::new();
+        for key in keys {
+            editor.handle_key(*key, &mut out);
+        }
+        out.as_bytes().to_vec()
+    }
+
+    fn type_text(editor: &mut LineEditor, text: &[u8]) {
+        for byte in text {
+            editor.handle_key(KeyEvent::Char(*byte), &mut Echo::new());
+        }
+    }
+
+    #[test]
+    fn inserts_and_deletes_in_the_middle() {
+        let mut editor = LineEditor::new();
+        type_text(&mut editor, b"ecoh");
+        let out = type_keys(
+            &mut editor,
+            &[KeyEvent::Left, KeyEvent::Left, KeyEvent::Backspace],
+        );
+        assert_eq!(editor.line(), b"eoh");
+        assert_eq!(out, b"\x1b[1D\x1b[1D\x1b[1Doh\x1b[J\x1b[2D");
+
+        let out = type_keys(&mut editor, &[KeyEvent::Char(b'c')]);
+        assert_eq!(out, b"coh\x1b[J\x1b[2D");
+        type_keys(&mut editor, &[KeyEvent::Delete, KeyEvent::End]);
+        type_text(&mut editor, b"o");
+        assert_eq!(editor.line(), b"echo");
+        assert_eq!(editor.cursor, 4);
+    }
+
+    #[test]
+    fn supports_ctrl_shortcuts() {
+        let mut editor = LineEditor::new();
+        type_text(&mut editor, b"cat  /motd.txt");
+        type_keys(&mut editor, &[KeyEvent::Ctrl(b'w')]);
+        assert_eq!(editor.line(), b"cat  ");
+        type_keys(&mut editor, &[KeyEvent::Ctrl(b'w')]);
+        assert_eq!(editor.line(), b"");
+
+        type_text(&mut editor, b"ls /dir");
+        type_keys(
+            &mut editor,
+            &[KeyEvent::Ctrl(b'a'), KeyEvent::Right, KeyEvent::Right],
+        );
+        type_keys(&mut editor, &[KeyEvent::Ctrl(b'k')]);
+        assert_eq!(editor.line(), b"ls");
+        type_keys(&mut editor, &[KeyEvent::Ctrl(b'e'), KeyEvent::Left]);
+        type_keys(&mut editor, &[KeyEvent::Ctrl(b'u')]);
+        assert_eq!((editor.line(), editor.cursor), (&b"s"[..], 0));
+
+        let mut out = Echo::new();
+        assert_eq!(
+            editor.handle_key(KeyEvent::Ctrl(b'c'), &mut out),
+            EditResult::Abort
+        );
+        assert_eq!(out.as_bytes(), b"\x1b[1C^C");
+        assert_eq!(
+            editor.handle_key(KeyEvent::Ctrl(b'l'), &mut out),
+            EditResult::ClearScreen
+        );
+    }
+
+    #[test]
+    fn moves_across_wrapped_lines() {
+        let mut editor = LineEditor::new();
+        // Prompt (2) + 78 Zeichen füllen die erste Bildschirmzeile genau.
+        let mut out = Echo::new();
+        editor.set_line(&[b'x'; 78], &mut out);
+        assert!(out.as_bytes().ends_with(b" \r\x1b[J"));
+
+        let out = type_keys(&mut editor, &[KeyEvent::Home]);
+        assert_eq!(out, b"\x1b[1A\x1b[2C");
+        let mut out = Echo::new();
+        assert_eq!(
+            editor.handle_key(KeyEvent::Enter, &mut out),
+            EditResult::Submit
+        );
+        assert_eq!(out.as_bytes(), b"\x1b[1B\x1b[2D");
+    }
+
+    #[test]
+    fn echo_holds_a_full_line_redraw() {
+        let mut editor = LineEditor::new();
+        type_text(&mut editor, &[b'x'; MAX_LINE - 1]);
+        let out = type_keys(&mut editor, &[KeyEvent::Home, KeyEvent::Char(b'y')]);
+        // Die ganze Zeile wird neu ausgegeben, danach geht der Cursor zurück hinter das `y`.
+        assert!(out.starts_with(b"\x1b[1A\x1b[47Dy"));
+        assert!(out.ends_with(b"x\x1b[J\x1b[1A\x1b[47D"));
+        assert_eq!(out.len(), 9 + MAX_LINE + 3 + 9);
+        assert_eq!(editor.line().len(), MAX_LINE);
+    }
+}
@@ -0,0 +1,87 @@
+//! Ausgabeziel der Shell-Kommandos.
+//!
+//! Kommandos schreiben ihre regulären Ausgaben in ein [`Output`]: direkt auf die Konsole oder,
+//! bei Umleitungen (`>`, `>>`) und Pipes, in einen Puffer. Fehlermeldungen gehen weiterhin
+//! direkt auf die Konsole und landen so nie in Dateien oder der nächsten Pipeline-Stufe.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::console;
+
+pub enum Output {
+    Console,
+    Buffer(Vec<u8>),
+}
+
+impl Output {
+    pub fn is_console(&self) -> bool {
+        matches!(self, Output::Console)
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        match self {
+            Output::Console => console::write_str(bytes),
+            Output::Buffer(buffer) => buffer.extend_from_slice(bytes),
+        }
+    }
+
+    pub fn write_byte(&mut self, byte: u8) {
+        self.write_bytes(&[byte]);
+    }
+
+    pub fn write_line(&mut self, bytes: &[u8]) {
+        self.write_bytes(bytes);
+        self.write_byte(b'\n');
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        let _ = fmt::Write::write_fmt(self, format_args!("{value}"));
+    }
+
+    /// Schreibt Dateiinhalt unverändert; auf der Konsole endet er immer mit einem `\n`, damit
+    /// der Prompt am Zeilenanfang steht.
+    pub fn write_text(&mut self, text: &[u8]) {
+        self.write_bytes(text);
+        if self.is_console() && !text.is_empty() && text.last() != Some(&b'\n') {
+            self.write_byte(b'\n');
+        }
+    }
+
+    /// Gepufferte Ausgabe (leer bei der Konsole).
+    pub fn into_bytes(self) -> Vec<u8> {
+        match self {
+            Output::Console => Vec::new(),
+            Output::Buffer(buffer) => buffer,
+        }
+    }
+}
+
+impl fmt::Write for Output {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_bytes(s.as_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::fmt::Write;
+
+    use super::Output;
+
+    #[test]
+    fn buffers_text_and_numbers() {
+        let mut out = Output::Buffer(Vec::new());
+        out.write_bytes(b"n=");
+        out.write_u64(42);
+        out.write_byte(b' ');
+        let name = "x";
+        let _ = write!(out, "{name}");
+        out.write_line(b"");
+        out.write_text(b"no newline");
+        assert!(!out.is_console());
+        assert_eq!(out.into_bytes(), b"n=42 x\nno newline");
+    }
+}
@@ -0,0 +1,206 @@
+//! Kommandos für den PCI-Bus: `lspci`.
+//!
+//! Zeigt die beim Start aufgezählten Funktionen im Format von Linux' `lspci -nn`; mit `-v`
+//! zusätzlich Interrupt, BARs, Capabilities und den gebundenen Treiber.
+
+use core::fmt::{self, Write};
+
+use super::command::{self, Command, Context, FAILURE, SUCCESS, USAGE};
+use super::output::Output;
+use crate::console;
+use crate::pci::{self, device::Bar, device::PciDevice, ids};
+
+pub const LSPCI: Command = Command {
+    name: b"lspci",
+    usage: b"[-v]",
+    summary: b"list PCI devices (-v: BARs, IRQ, capabilities, driver)",
+    min_args: 0,
+    max_args: 1,
+    run: run_lspci,
+};
+
+fn run_lspci(ctx: &mut Context<'_>, argv: &[&[u8]]) -> u8 {
+    let verbose = match &argv[1..] {
+        [] => false,
+        [b"-v"] => true,
+        _ => {
+            command::write_usage(&mut Output::Console, &LSPCI);
+            return USAGE;
+        }
+    };
+    let devices = pci::devices();
+    if devices.is_empty() {
+        console::write_line(b"no PCI devices found");
+        return FAILURE;
+    }
+
+    for (index, device) in devices.iter().enumerate() {
+        if verbose && index > 0 {
+            let _ = writeln!(ctx.out);
+        }
+        let _ = write_summary(ctx.out, device);
+        if verbose {
+            let _ = write_details(ctx.out, device);
+        }
+    }
+    SUCCESS
+}
+
+/// `00:01.1 IDE interface [0101]: Intel 82371SB PIIX3 IDE [8086:7010] (rev 00)`
+fn write_summary(out: &mut Output, device: &PciDevice) -> fmt::Result {
+    write!(
+        out,
+        "{} {} [{:02x}{:02x}]: ",
+        device.address,
+        ids::class_name(device.class, device.subclass),
+        device.class,
+        device.subclass
+    )?;
+    match ids::vendor_name(device.vendor_id) {
+        Some(vendor) => write!(out, "{vendor} ")?,
+        None => write!(out, "Vendor {:04x} ", device.vendor_id)?,
+    }
+    match ids::device_name(device.vendor_id, device.device_id) {
+        Some(name) => write!(out, "{name} ")?,
+        None => write!(out, "Device {:04x} ", device.device_id)?,
+    }
+    write!(out, "[{:04x}:{:04x}]", device.vendor_id, device.device_id)?;
+    if device.prog_if != 0 {
+        write!(out, " (prog-if {:02x})", device.prog_if)?;
+    }
+    writeln!(out, " (rev {:02x})", device.revision)
+}
+
+fn write_details(out: &mut Output, device: &PciDevice) -> fmt::Result {
+    if device.interrupt_pin != 0 {
+        let pin = char::from(b'A' + device.interrupt_pin.saturating_sub(1).min(3));
+        writeln!(
+            out,
+            "\tInterrupt: pin {pin} routed to IRQ {}",
+            device.interrupt_line
+        )?;
+    }
+    for (index, bar) in device.bars.iter().enumerate() {
+        match bar {
+            Some(Bar::Io { port, size }) => {
+                writeln!(
+                    out,
+                    "\tRegion {index}: I/O ports at {port:04x} [size={}]",
+                    Size(u64::from(*size))
+                )?;
+            }
+            Some(Bar::Memory {
+                address,
+                size,
+                prefetchable,
+                wide,
+            }) => writeln!(
+                out,
+                "\tRegion {index}: Memory at {address:08x} ({}-bit, {}) [size={}]",
+                if *wide { 64 } else { 32 },
+                if *prefetchable {
+                    "prefetchable"
+                } else {
+                    "non-prefetchable"
+                },
+                Size(*size)
+            )?,
+            None => {}
+        }
+    }
+    for capability in &device.capabilities {
+        writeln!(
+            out,
+            "\tCapabilities: [{:02x}] {}",
+            capability.offset,
+            ids::capability_name(capability.id)
+        )?;
+    }
+    if let Some(driver) = device.driver {
+        writeln!(out, "\tKernel driver in use: {driver}")?;
+    }
+    Ok(())
+}
+
+/// BAR-Größe wie bei `lspci`: in der größten Einheit, die sie ohne Rest teilt.
+struct Size(u64);
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut value = self.0;
+        let mut unit = 0;
+        while value >= 1024 && value.is_multiple_of(1024) && unit < 3 {
+            value /= 1024;
+            unit += 1;
+        }
+        write!(f, "{value}{}", ["", "K", "M", "G"][unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use alloc::vec::Vec;
+
+    use super::{Size, write_details, write_summary};
+    use crate::pci::config::Address;
+    use crate::pci::device::{Bar, Capability, PciDevice};
+    use crate::shell::output::Output;
+
+    #[test]
+    fn formats_like_lspci() {
+        let mut device = PciDevice {
+            address: Address::new(0, 1, 1),
+            vendor_id: 0x8086,
+            device_id: 0x7010,
+            class: 0x01,
+            subclass: 0x01,
+            prog_if: 0x80,
+            revision: 0,
+            header_type: 0,
+            bars: [None; 6],
+            interrupt_line: 0,
+            interrupt_pin: 0,
+            capabilities: Vec::new(),
+            driver: Some("ide"),
+        };
+        device.bars[4] = Some(Bar::Io {
+            port: 0xC040,
+            size: 16,
+        });
+
+        let mut out = Output::Buffer(Vec::new());
+        write_summary(&mut out, &device).unwrap();
+        write_details(&mut out, &device).unwrap();
+        assert_eq!(
+            out.into_bytes(),
+            b"00:01.1 IDE interface [0101]: Intel 82371SB PIIX3 IDE [8086:7010] (prog-if 80) (rev 00)\n\
+              \tRegion 4: I/O ports at c040 [size=16]\n\
+              \tKernel driver in use: ide\n"
+        );
+
+        device.vendor_id = 0x1AF5;
+        device.capabilities.push(Capability {
+            id: 0x11,
+            offset: 0x98,
+        });
+        device.interrupt_pin = 1;
+        device.interrupt_line = 11;
+        let mut out = Output::Buffer(Vec::new());
+        write_summary(&mut out, &device).unwrap();
+        write_details(&mut out, &device).unwrap();
+        assert_eq!(
+            out.into_bytes(),
+            b"00:01.1 IDE interface [0101]: Vendor 1af5 Device 7010 [1af5:7010] (prog-if 80) (rev 00)\n\
+              \tInterrupt: pin A routed to IRQ 11\n\
+              \tRegion 4: I/O ports at c040 [size=16]\n\
+              \tCapabilities: [98] MSI-X\n\
+              \tKernel driver in use: ide\n"
+        );
+
+        assert_eq!(
+            format!("{} {} {}", Size(16), Size(0x4000), Size(16 << 20)),
+            "16 16K 16M"
+        );
+    }
+}
@@ -0,0 +1,180 @@
+//! Zerlegt eine Kommandozeile in eine Pipeline mit Umleitungen.
+//!
+//! `a | b | c` verbindet die Ausgabe jeder Stufe mit der Eingabe der nächsten. `< path` liest
+//! die Eingabe der ersten Stufe aus einer Datei, `> path` und `>> path` schreiben die Ausgabe
+//! der letzten Stufe in eine Datei (überschreiben bzw. anhängen).
+
+use alloc::vec::Vec;
+
+use super::argv::{Operator, Token};
+use super::env::Env;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// `|` ohne Kommando davor oder danach, oder nur Umleitungen.
+    EmptyCommand,
+    MissingTarget,
+    /// `<` nach der ersten bzw. `>`/`>>` vor der letzten Stufe oder doppelt.
+    MisplacedRedirect,
+}
+
+impl ParseError {
+    pub fn describe(self) -> &'static [u8] {
+        match self {
+            ParseError::EmptyCommand => b"missing command",
+            ParseError::MissingTarget => b"missing redirection target",
+            ParseError::MisplacedRedirect => b"misplaced redirection",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Redirect {
+    pub path: Vec<u8>,
+    pub append: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Pipeline {
+    /// argv jeder Stufe; leer bei einer leeren Zeile.
+    pub stages: Vec<Vec<Vec<u8>>>,
+    pub input: Option<Vec<u8>>,
+    pub output: Option<Redirect>,
+}
+
+/// Baut die Pipeline eines einzelnen Kommandos (ohne `;`) und setzt dabei die Variablen ein.
+pub fn parse(tokens: &[Token], env: &Env) -> Result<Pipeline, ParseError> {
+    let mut pipeline = Pipeline::default();
+    if tokens.is_empty() {
+        return Ok(pipeline);
+    }
+
+    let mut stage = Vec::new();
+    let mut tokens = tokens.iter();
+    while let Some(token) = tokens.next() {
+        let operator = match token {
+            Token::Word(word) => {
+                // Nach `>` folgen keine weiteren Argumente oder Stufen mehr.
+                if pipeline.output.is_some() {
+                    return Err(ParseError::MisplacedRedirect);
+                }
+                stage.extend(word.expand(env));
+                continue;
+            }
+            Token::Operator(operator) => *operator,
+        };
+
+        match operator {
+            Operator::Pipe => {
+                if stage.is_empty() {
+                    return Err(ParseError::EmptyCommand);
+                }
+                if pipeline.output.is_some() {
+                    return Err(ParseError::MisplacedRedirect);
+                }
+                pipeline.stages.push(core::mem::take(&mut stage));
+                continue;
+            }
+            // Der Skript-Parser trennt Kommandos schon vorher an `;`.
+            Operator::Separator => return Err(ParseError::EmptyCommand),
+            Operator::Input | Operator::Output | Operator::Append => {}
+        }
+
+        let Some(Token::Word(path)) = tokens.next() else {
+            return Err(ParseError::MissingTarget);
+        };
+        let Some(path) = path.expand(env) else {
+            return Err(ParseError::MissingTarget);
+        };
+        match operator {
+            Operator::Input if pipeline.stages.is_empty() && pipeline.input.is_none() => {
+                pipeline.input = Some(path);
+            }
+            Operator::Output | Operator::Append if pipeline.output.is_none() => {
+                pipeline.output = Some(Redirect {
+                    path,
+                    append: operator == Operator::Append,
+                });
+            }
+            _ => return Err(ParseError::MisplacedRedirect),
+        }
+    }
+
+    if stage.is_empty() {
+        return Err(ParseError::EmptyCommand);
+    }
+    pipeline.stages.push(stage);
+    Ok(pipeline)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{ParseError, Pipeline, Redirect};
+    use crate::shell::argv::tokenize;
+    use crate::shell::env::Env;
+
+    fn parse(line: &[u8]) -> Result<Pipeline, ParseError> {
+        let mut env = Env::new();
+        env.set(b"OUT", b"/out.txt");
+        super::parse(&tokenize(line).expect("tokens"), &env)
+    }
+
+    fn stages(line: &[u8]) -> Vec<Vec<Vec<u8>>> {
+        parse(line).expect("pipeline").stages
+    }
+
+    #[test]
+    fn splits_stages_and_redirections() {
+        let pipeline = parse(b"grep -v x < /in.txt | wc -l >> /count.txt").expect("pipeline");
+        assert_eq!(
+            pipeline.stages,
+            [
+                [&b"grep"[..], b"-v", b"x"].map(<[u8]>::to_vec).to_vec(),
+                [&b"wc"[..], b"-l"].map(<[u8]>::to_vec).to_vec(),
+            ]
+        );
+        assert_eq!(pipeline.input, Some(b"/in.txt".to_vec()));
+        assert_eq!(
+            pipeline.output,
+            Some(Redirect {
+                path: b"/count.txt".to_vec(),
+                append: true
+            })
+        );
+
+        assert_eq!(stages(b"dmesg>/log.txt"), [[b"dmesg".to_vec()]]);
+        assert_eq!(
+            stages(b"echo '|' \">\""),
+            [[&b"echo"[..], b"|", b">"].map(<[u8]>::to_vec)]
+        );
+        assert!(stages(b"  ").is_empty());
+        assert_eq!(
+            stages(b"echo $UNSET x"),
+            [[&b"echo"[..], b"x"].map(<[u8]>::to_vec)]
+        );
+        assert_eq!(
+            parse(b"ls > $OUT")
+                .expect("pipeline")
+                .output
+                .map(|redirect| redirect.path),
+            Some(b"/out.txt".to_vec())
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_pipelines() {
+        assert_eq!(parse(b"| wc"), Err(ParseError::EmptyCommand));
+        assert_eq!(parse(b"ls |"), Err(ParseError::EmptyCommand));
+        assert_eq!(parse(b"> /out.txt"), Err(ParseError::EmptyCommand));
+        assert_eq!(parse(b"ls >"), Err(ParseError::MissingTarget));
+        assert_eq!(parse(b"ls > |"), Err(ParseError::MissingTarget));
+        assert_eq!(parse(b"ls > /a | wc"), Err(ParseError::MisplacedRedirect));
+        assert_eq!(parse(b"ls > /a x"), Err(ParseError::MisplacedRedirect));
+        assert_eq!(parse(b"ls | wc < /a"), Err(ParseError::MisplacedRedirect));
+        assert_eq!(parse(b"ls > /a > /b"), Err(ParseError::MisplacedRedirect));
+        assert_eq!(parse(b"ls > $UNSET"), Err(ParseError::MissingTarget));
+        assert_eq!(parse(b"$UNSET"), Err(ParseError::EmptyCommand));
+    }
+}
@@ -0,0 +1,382 @@
+//! Skriptsprache der Shell: Kommandolisten, `if` und `while`.
+//!
+//! Ein Skript besteht aus Kommandos, getrennt durch Zeilenumbrüche oder `;`. Schlüsselwörter
+//! werden nur am Anfang eines Kommandos erkannt und nur ohne Anführungszeichen:
+//!
+//! ```text
+//! if <kommandos>; then <kommandos>; [elif <kommandos>; then <kommandos>;] [else <kommandos>;] fi
+//! while <kommandos>; do <kommandos>; done
+//! ```
+//!
+//! Bedingungen sind erfüllt, wenn das letzte Kommando den Exit-Status 0 liefert. Interaktive
+//! Zeilen werden genauso ausgeführt wie Skriptdateien, `if` und `while` müssen dann auf einer
+//! Zeile stehen.
+
+use alloc::vec::Vec;
+
+use super::argv::{self, Operator, Token, TokenizeError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Keyword {
+    If,
+    Then,
+    Elif,
+    Else,
+    Fi,
+    While,
+    Do,
+    Done,
+}
+
+impl Keyword {
+    const ALL: [Keyword; 8] = [
+        Keyword::If,
+        Keyword::Then,
+        Keyword::Elif,
+        Keyword::Else,
+        Keyword::Fi,
+        Keyword::While,
+        Keyword::Do,
+        Keyword::Done,
+    ];
+
+    pub fn name(self) -> &'static [u8] {
+        match self {
+            Keyword::If => b"if",
+            Keyword::Then => b"then",
+            Keyword::Elif => b"elif",
+            Keyword::Else => b"else",
+            Keyword::Fi => b"fi",
+            Keyword::While => b"while",
+            Keyword::Do => b"do",
+            Keyword::Done => b"done",
+        }
+    }
+
+    fn from_token(token: &Token) -> Option<Self> {
+        let Token::Word(word) = token else {
+            return None;
+        };
+        let literal = word.literal()?;
+        Self::ALL
+            .into_iter()
+            .find(|keyword| keyword.name() == literal)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScriptError {
+    Tokenize(TokenizeError),
+    /// Schlüsselwort an falscher Stelle, z. B. `fi` ohne `if`.
+    Unexpected(Keyword),
+    /// `if` oder `while` ohne Abschluss.
+    UnexpectedEnd,
+}
+
+impl ScriptError {
+    pub fn describe(self) -> &'static [u8] {
+        match self {
+            ScriptError::Tokenize(err) => err.describe(),
+            ScriptError::Unexpected(_) => b"unexpected keyword",
+            ScriptError::UnexpectedEnd => b"unexpected end of script",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Node {
+    /// Eine Pipeline, Variablen noch nicht eingesetzt.
+    Command { tokens: Vec<Token>, line: usize },
+    If {
+        /// Bedingung und Rumpf von `if` und jedem `elif`.
+        branches: Vec<(Vec<Node>, Vec<Node>)>,
+        otherwise: Vec<Node>,
+    },
+    While {
+        condition: Vec<Node>,
+        body: Vec<Node>,
+    },
+}
+
+/// Zerlegt ein Skript; Fehler tragen die Zeilennummer (ab 1).
+pub fn parse(text: &[u8]) -> Result<Vec<Node>, (usize, ScriptError)> {
+    let mut items = Vec::new();
+    let mut lines = 0;
+    for (index, line) in text.split(|byte| *byte == b'\n').enumerate() {
+        let line_number = index + 1;
+        lines = line_number;
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let tokens =
+            argv::tokenize(line).map_err(|err| (line_number, ScriptError::Tokenize(err)))?;
+        for command in tokens.split(|token| *token == Token::Operator(Operator::Separator)) {
+            push_items(&mut items, command, line_number);
+        }
+    }
+
+    let mut parser = Parser {
+        items,
+        pos: 0,
+        lines,
+    };
+    let nodes = parser.block(&[])?;
+    Ok(nodes)
+}
+
+enum Item {
+    Keyword(Keyword, usize),
+    Command(Vec<Token>, usize),
+}
+
+/// Trennt ein führendes Schlüsselwort vom Rest des Kommandos (`then echo x`).
+fn push_items(items: &mut Vec<Item>, mut command: &[Token], line: usize) {
+    while let Some(keyword) = command.first().and_then(Keyword::from_token) {
+        items.push(Item::Keyword(keyword, line));
+        command = &command[1..];
+    }
+    if !command.is_empty() {
+        items.push(Item::Command(command.to_vec(), line));
+    }
+}
+
+struct Parser {
+    items: Vec<Item>,
+    pos: usize,
+    /// Zeilenzahl des Skripts, für Fehler am Ende.
+    lines: usize,
+}
+
+impl Parser {
+    /// Liest Knoten bis zu einem der Schlüsselwörter in `ends` (nicht verbraucht) oder, bei
+    /// leerem `ends`, bis zum Ende.
+    fn block(&mut self, ends: &[Keyword]) -> Result<Vec<Node>, (usize, ScriptError)> {
+        let mut nodes = Vec::new();
+        loop {
+            let Some(item) = self.items.get_mut(self.pos) else {
+                return if ends.is_empty() {
+                    Ok(nodes)
+                } else {
+                    Err((self.lines, ScriptError::UnexpectedEnd))
+                };
+            };
+            match item {
+                Item::Command(tokens, line) => {
+                    let tokens = core::mem::take(tokens);
+                    nodes.push(Node::Command {
+                        tokens,
+                        line: *line,
+                    });
+                    self.pos += 1;
+                }
+                Item::Keyword(keyword, _) if ends.contains(keyword) => return Ok(nodes),
+                Item::Keyword(Keyword::If, _) => {
+                    self.pos += 1;
+                    nodes.push(self.if_node()?);
+                }
+                Item::Keyword(Keyword::While, _) => {
+                    self.pos += 1;
+                    let condition = self.block(&[Keyword::Do])?;
+                    self.expect(Keyword::Do)?;
+                    let body = self.block(&[Keyword::Done])?;
+                    self.expect(Keyword::Done)?;
+                    nodes.push(Node::While { condition, body });
+                }
+                Item::Keyword(keyword, line) => {
+                    return Err((*line, ScriptError::Unexpected(*keyword)));
+                }
+            }
+        }
+    }
+
+    /// Rest von `if … fi` nach dem `if`.
+    fn if_node(&mut self) -> Result<Node, (usize, ScriptError)> {
+        let mut branches = Vec::new();
+        loop {
+            let condition = self.block(&[Keyword::Then])?;
+            self.expect(Keyword::Then)?;
+            let body = self.block(&[Keyword::Elif, Keyword::Else, Keyword::Fi])?;
+            branches.push((condition, body));
+            match self.next_keyword()? {
+                Keyword::Elif => continue,
+                Keyword::Else => {
+                    let otherwise = self.block(&[Keyword::Fi])?;
+                    self.expect(Keyword::Fi)?;
+                    return Ok(Node::If {
+                        branches,
+                        otherwise,
+                    });
+                }
+                _ => {
+                    return Ok(Node::If {
+                        branches,
+                        otherwise: Vec::new(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Verbraucht das Schlüsselwort, an dem [`Parser::block`] angehalten hat.
+    fn next_keyword(&mut self) -> Result<Keyword, (usize, ScriptError)> {
+        match self.items.get(self.pos) {
+            Some(Item::Keyword(keyword, _)) => {
+                self.pos += 1;
+                Ok(*keyword)
+            }
+            _ => Err((self.lines, ScriptError::UnexpectedEnd)),
+        }
+    }
+
+    fn expect(&mut self, expected: Keyword) -> Result<(), (usize, ScriptError)> {
+        let keyword = self.next_keyword()?;
+        debug_assert_eq!(keyword, expected);
+        Ok(())
+    }
+}
+
+/// Führt einzelne Kommandos für [`run`] aus.
+pub trait Executor {
+    /// Führt eine Pipeline aus und liefert ihren Exit-Status; `None` bricht das Skript ab.
+    fn command(&mut self, tokens: &[Token], line: usize) -> Option<u8>;
+    /// Wird vor jedem Schleifendurchlauf gefragt; `true` bricht das Skript ab.
+    fn interrupted(&mut self) -> bool;
+}
+
+/// Führt `nodes` aus und liefert den Status des letzten Kommandos (`None`: abgebrochen).
+pub fn run(nodes: &[Node], exec: &mut dyn Executor) -> Option<u8> {
+    let mut status = 0;
+    for node in nodes {
+        status = match node {
+            Node::Command { tokens, line } => exec.command(tokens, *line)?,
+            Node::If {
+                branches,
+                otherwise,
+            } => {
+                let mut taken = None;
+                for (condition, body) in branches {
+                    if run(condition, exec)? == 0 {
+                        taken = Some(body);
+                        break;
+                    }
+                }
+                run(taken.unwrap_or(otherwise), exec)?
+            }
+            Node::While { condition, body } => {
+                let mut status = 0;
+                loop {
+                    if exec.interrupted() {
+                        return None;
+                    }
+                    if run(condition, exec)? != 0 {
+                        break status;
+                    }
+                    status = run(body, exec)?;
+                }
+            }
+        };
+    }
+    Some(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{Executor, Keyword, ScriptError, parse, run};
+    use crate::shell::argv::Token;
+    use crate::shell::env::Env;
+
+    /// Führt `true`, `false` und `count` aus; `count` ist erfolgreich, bis es dreimal lief.
+    #[derive(Default)]
+    struct Recorder {
+        calls: Vec<Vec<u8>>,
+        counted: usize,
+        interrupt_after: Option<usize>,
+    }
+
+    impl Executor for Recorder {
+        fn command(&mut self, tokens: &[Token], _line: usize) -> Option<u8> {
+            let env = Env::new();
+            let words: Vec<Vec<u8>> = tokens
+                .iter()
+                .filter_map(|token| match token {
+                    Token::Word(word) => word.expand(&env),
+                    Token::Operator(_) => None,
+                })
+                .collect();
+            let status = match words[0].as_slice() {
+                b"false" => 1,
+                b"count" => {
+                    self.counted += 1;
+                    u8::from(self.counted > 3)
+                }
+                _ => 0,
+            };
+            self.calls.push(words.join(&b' '));
+            Some(status)
+        }
+
+        fn interrupted(&mut self) -> bool {
+            self.interrupt_after
+                .is_some_and(|limit| self.calls.len() >= limit)
+        }
+    }
+
+    fn record(script: &[u8]) -> (Option<u8>, Vec<Vec<u8>>) {
+        let nodes = parse(script).expect("script");
+        let mut recorder = Recorder::default();
+        let status = run(&nodes, &mut recorder);
+        (status, recorder.calls)
+    }
+
+    #[test]
+    fn runs_conditionals() {
+        let script = b"# greeting\nif false; then echo no\nelif true\nthen\n  echo yes # ok\n\
+                       else echo never; fi\necho done";
+        let (status, calls) = record(script);
+        assert_eq!(status, Some(0));
+        assert_eq!(calls, [&b"false"[..], b"true", b"echo yes", b"echo done"]);
+
+        let (status, calls) = record(b"if false; then echo a; fi; false");
+        assert_eq!(status, Some(1));
+        assert_eq!(calls, [b"false", b"false"]);
+
+        // Schlüsselwörter zählen nur am Kommandoanfang und ohne Anführungszeichen.
+        let (_, calls) = record(b"echo if; 'if' x");
+        assert_eq!(calls, [&b"echo if"[..], b"if x"]);
+    }
+
+    #[test]
+    fn runs_loops_until_the_condition_fails() {
+        let (status, calls) = record(b"while count\ndo\n  echo body\ndone");
+        assert_eq!(status, Some(0));
+        assert_eq!(calls.iter().filter(|call| *call == b"echo body").count(), 3);
+
+        let nodes = parse(b"while true; do echo forever; done").expect("script");
+        let mut recorder = Recorder {
+            interrupt_after: Some(5),
+            ..Recorder::default()
+        };
+        assert_eq!(run(&nodes, &mut recorder), None);
+    }
+
+    #[test]
+    fn reports_syntax_errors_with_line() {
+        assert_eq!(
+            parse(b"echo a\nfi"),
+            Err((2, ScriptError::Unexpected(Keyword::Fi)))
+        );
+        assert_eq!(
+            parse(b"if true\nthen echo a\n"),
+            Err((3, ScriptError::UnexpectedEnd))
+        );
+        assert_eq!(
+            parse(b"while true; done"),
+            Err((1, ScriptError::Unexpected(Keyword::Done)))
+        );
+        assert!(matches!(
+            parse(b"ok\necho 'open"),
+            Err((2, ScriptError::Tokenize(_)))
+        ));
+        assert_eq!(parse(b"").map(|nodes| nodes.len()), Ok(0));
+    }
+}
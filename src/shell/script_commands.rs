@@ -0,0 +1,182 @@
+//! Kommandos für Skripte: `source`, `set`, `unset`, `test`, `true` und `false`.
+
+use super::command::{self, Command, Context, FAILURE, SUCCESS, USAGE};
+use super::env;
+use super::fs_commands::{read_simplefs_file, report, simplefs_node_type};
+use super::output::Output;
+use crate::console;
+use crate::fs::vfs::NodeType;
+
+/// Maximale Verschachtelung von `source`, damit sich ein Skript nicht endlos selbst lädt.
+const MAX_DEPTH: usize = 8;
+
+pub const SOURCE: Command = Command {
+    name: b"source",
+    usage: b"<path>",
+    summary: b"run a shell script",
+    min_args: 1,
+    max_args: 1,
+    run: run_source,
+};
+
+/// Führt ein Skript in dieser Shell aus; Variablen bleiben danach gesetzt.
+fn run_source(ctx: &mut Context<'_>, argv: &[&[u8]]) -> u8 {
+    if ctx.depth >= MAX_DEPTH {
+        console::write_line(b"too many nested scripts");
+        return FAILURE;
+    }
+    let Ok(path) = core::str::from_utf8(argv[1]) else {
+        console::write_line(b"invalid path");
+        return FAILURE;
+    };
+    let text = match read_simplefs_file(path) {
+        Ok(text) => text,
+        Err(message) => {
+            report(argv[1], message);
+            return FAILURE;
+        }
+    };
+
+    match super::run_script(&text, ctx.history, ctx.env, ctx.depth + 1, ctx.out) {
+        Ok(status) => status,
+        Err((line, err)) => {
+            kprint!("{path}: line {line}: ");
+            console::write_line(err.describe());
+            USAGE
+        }
+    }
+}
+
+pub const SET: Command = Command {
+    name: b"set",
+    usage: b"",
+    summary: b"list shell variables (assign with NAME=value)",
+    min_args: 0,
+    max_args: 0,
+    run: run_set,
+};
+
+fn run_set(ctx: &mut Context<'_>, _argv: &[&[u8]]) -> u8 {
+    for (name, value) in ctx.env.vars() {
+        ctx.out.write_bytes(name);
+        ctx.out.write_byte(b'=');
+        ctx.out.write_line(value);
+    }
+    SUCCESS
+}
+
+pub const UNSET: Command = Command {
+    name: b"unset",
+    usage: b"<name>...",
+    summary: b"remove shell variables",
+    min_args: 1,
+    max_args: usize::MAX,
+    run: run_unset,
+};
+
+fn run_unset(ctx: &mut Context<'_>, argv: &[&[u8]]) -> u8 {
+    let mut status = SUCCESS;
+    for name in &argv[1..] {
+        if env::is_valid_name(name) {
+            ctx.env.unset(name);
+        } else {
+            console::write_line(b"invalid variable name");
+            status = FAILURE;
+        }
+    }
+    status
+}
+
+pub const TEST: Command = Command {
+    name: b"test",
+    usage: b"[!] [-e|-f|-d <path>] [-n|-z <text>] [<a> =|!= <b>]",
+    summary: b"check files and strings (status 0 if true)",
+    min_args: 0,
+    max_args: usize::MAX,
+    run: run_test,
+};
+
+fn run_test(_ctx: &mut Context<'_>, argv: &[&[u8]]) -> u8 {
+    let node_type = |path: &[u8]| simplefs_node_type(core::str::from_utf8(path).ok()?);
+    match evaluate(&argv[1..], &node_type) {
+        Some(true) => SUCCESS,
+        Some(false) => FAILURE,
+        None => {
+            command::write_usage(&mut Output::Console, &TEST);
+            USAGE
+        }
+    }
+}
+
+/// Wertet einen `test`-Ausdruck aus; `None` bei ungültigem Ausdruck.
+fn evaluate(args: &[&[u8]], node_type: &dyn Fn(&[u8]) -> Option<NodeType>) -> Option<bool> {
+    let result = match args {
+        [] => false,
+        // Ein einzelnes Argument ist wahr, wenn es nicht leer ist (auch `-n` oder `!`).
+        [text] => !text.is_empty(),
+        [b"!", rest @ ..] => !evaluate(rest, node_type)?,
+        [b"-n", text] => !text.is_empty(),
+        [b"-z", text] => text.is_empty(),
+        [b"-e", path] => node_type(path).is_some(),
+        [b"-f", path] => node_type(path) == Some(NodeType::File),
+        [b"-d", path] => node_type(path) == Some(NodeType::Directory),
+        [left, b"=", right] => left == right,
+        [left, b"!=", right] => left != right,
+        _ => return None,
+    };
+    Some(result)
+}
+
+pub const TRUE: Command = Command {
+    name: b"true",
+    usage: b"",
+    summary: b"do nothing, successfully",
+    min_args: 0,
+    max_args: usize::MAX,
+    run: |_, _| SUCCESS,
+};
+
+pub const FALSE: Command = Command {
+    name: b"false",
+    usage: b"",
+    summary: b"do nothing, unsuccessfully",
+    min_args: 0,
+    max_args: usize::MAX,
+    run: |_, _| FAILURE,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::evaluate;
+    use crate::fs::vfs::NodeType;
+
+    fn node_type(path: &[u8]) -> Option<NodeType> {
+        match path {
+            b"/motd.txt" => Some(NodeType::File),
+            b"/" => Some(NodeType::Directory),
+            _ => None,
+        }
+    }
+
+    fn test(args: &[&[u8]]) -> Option<bool> {
+        evaluate(args, &node_type)
+    }
+
+    #[test]
+    fn evaluates_test_expressions() {
+        assert_eq!(test(&[]), Some(false));
+        assert_eq!(test(&[b""]), Some(false));
+        assert_eq!(test(&[b"-n"]), Some(true));
+        assert_eq!(test(&[b"-n", b""]), Some(false));
+        assert_eq!(test(&[b"-z", b""]), Some(true));
+        assert_eq!(test(&[b"-e", b"/motd.txt"]), Some(true));
+        assert_eq!(test(&[b"-e", b"/missing"]), Some(false));
+        assert_eq!(test(&[b"-f", b"/"]), Some(false));
+        assert_eq!(test(&[b"-d", b"/"]), Some(true));
+        assert_eq!(test(&[b"a", b"=", b"a"]), Some(true));
+        assert_eq!(test(&[b"a", b"!=", b"a"]), Some(false));
+        assert_eq!(test(&[b"!", b"-e", b"/missing"]), Some(true));
+        assert_eq!(test(&[b"a", b"-eq", b"b"]), None);
+        assert_eq!(test(&[b"!", b"-x", b"a"]), None);
+    }
+}
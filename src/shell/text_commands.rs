@@ -0,0 +1,275 @@
+//! Textfilter für Pipes und Umleitungen: `grep`, `wc`, `head` und `tail`.
+//!
+//! Alle lesen die angegebenen Dateien nacheinander oder, ohne Pfade, die Standardeingabe
+//! (siehe [`read_input`]).
+
+use core::fmt::Write;
+
+use super::command::{self, Command, Context, FAILURE, SUCCESS, USAGE};
+use super::fs_commands::read_input;
+use super::output::Output;
+
+/// Zeilenzahl von `head` und `tail` ohne `-n`.
+const DEFAULT_LINES: usize = 10;
+
+pub const GREP: Command = Command {
+    name: b"grep",
+    usage: b"[-v] [-i] [-c] <pattern> [path]...",
+    summary: b"print lines containing a pattern",
+    min_args: 1,
+    max_args: usize::MAX,
+    run: run_grep,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct GrepOptions {
+    /// Nicht passende Zeilen ausgeben.
+    invert: bool,
+    ignore_case: bool,
+    /// Nur die Anzahl der Treffer ausgeben.
+    count: bool,
+}
+
+/// Exit-Status 1, wenn keine Zeile ausgewählt wurde.
+fn run_grep(ctx: &mut Context<'_>, argv: &[&[u8]]) -> u8 {
+    let mut options = GrepOptions::default();
+    let mut args = &argv[1..];
+    while let [flag, rest @ ..] = args {
+        match *flag {
+            b"-v" => options.invert = true,
+            b"-i" => options.ignore_case = true,
+            b"-c" => options.count = true,
+            _ => break,
+        }
+        args = rest;
+    }
+    let Some((pattern, paths)) = args.split_first() else {
+        command::write_usage(&mut Output::Console, &GREP);
+        return USAGE;
+    };
+    let Some(text) = read_input(ctx, &GREP, paths) else {
+        return FAILURE;
+    };
+
+    let mut matches = 0_u64;
+    for line in lines(&text) {
+        if contains(line, pattern, options.ignore_case) != options.invert {
+            matches += 1;
+            if !options.count {
+                ctx.out.write_line(line);
+            }
+        }
+    }
+    if options.count {
+        ctx.out.write_u64(matches);
+        ctx.out.write_byte(b'\n');
+    }
+    if matches > 0 { SUCCESS } else { FAILURE }
+}
+
+pub const WC: Command = Command {
+    name: b"wc",
+    usage: b"[-l|-w|-c] [path]...",
+    summary: b"count lines, words and bytes",
+    min_args: 0,
+    max_args: usize::MAX,
+    run: run_wc,
+};
+
+fn run_wc(ctx: &mut Context<'_>, argv: &[&[u8]]) -> u8 {
+    let (only, paths) = match argv.get(1).copied() {
+        Some(flag @ (b"-l" | b"-w" | b"-c")) => (Some(flag[1]), &argv[2..]),
+        _ => (None, &argv[1..]),
+    };
+    let Some(text) = read_input(ctx, &WC, paths) else {
+        return FAILURE;
+    };
+
+    let counts = count(&text);
+    let _ = match only {
+        Some(b'l') => writeln!(ctx.out, "{}", counts.lines),
+        Some(b'w') => writeln!(ctx.out, "{}", counts.words),
+        Some(_) => writeln!(ctx.out, "{}", counts.bytes),
+        None => writeln!(
+            ctx.out,
+            "{} {} {}",
+            counts.lines, counts.words, counts.bytes
+        ),
+    };
+    SUCCESS
+}
+
+pub const HEAD: Command = Command {
+    name: b"head",
+    usage: b"[-n <lines>] [path]...",
+    summary: b"print the first lines (default 10)",
+    min_args: 0,
+    max_args: usize::MAX,
+    run: |ctx, argv| run_head_tail(ctx, argv, &HEAD, head),
+};
+
+pub const TAIL: Command = Command {
+    name: b"tail",
+    usage: b"[-n <lines>] [path]...",
+    summary: b"print the last lines (default 10)",
+    min_args: 0,
+    max_args: usize::MAX,
+    run: |ctx, argv| run_head_tail(ctx, argv, &TAIL, tail),
+};
+
+fn run_head_tail(
+    ctx: &mut Context<'_>,
+    argv: &[&[u8]],
+    command: &Command,
+    select: fn(&[u8], usize) -> &[u8],
+) -> u8 {
+    let Some((count, paths)) = parse_line_count(&argv[1..]) else {
+        command::write_usage(&mut Output::Console, command);
+        return USAGE;
+    };
+    let Some(text) = read_input(ctx, command, paths) else {
+        return FAILURE;
+    };
+    ctx.out.write_text(select(&text, count));
+    SUCCESS
+}
+
+/// Wertet ein führendes `-n <lines>` aus; liefert die Zeilenzahl und die restlichen Argumente.
+fn parse_line_count<'a>(args: &'a [&'a [u8]]) -> Option<(usize, &'a [&'a [u8]])> {
+    match args {
+        [b"-n", count, paths @ ..] => {
+            let count = core::str::from_utf8(count).ok()?.parse().ok()?;
+            Some((count, paths))
+        }
+        [b"-n"] => None,
+        _ => Some((DEFAULT_LINES, args)),
+    }
+}
+
+/// Zeilen ohne `\n`; ein abschließendes `\n` erzeugt keine leere letzte Zeile.
+fn lines(text: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let body = text.strip_suffix(b"\n").unwrap_or(text);
+    body.split(|byte| *byte == b'\n')
+        .filter(move |_| !text.is_empty())
+}
+
+fn contains(line: &[u8], pattern: &[u8], ignore_case: bool) -> bool {
+    pattern.is_empty()
+        || line.windows(pattern.len()).any(|window| {
+            if ignore_case {
+                window.eq_ignore_ascii_case(pattern)
+            } else {
+                window == pattern
+            }
+        })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Counts {
+    /// Anzahl der `\n` wie bei POSIX-`wc`.
+    lines: usize,
+    words: usize,
+    bytes: usize,
+}
+
+fn count(text: &[u8]) -> Counts {
+    Counts {
+        lines: text.iter().filter(|byte| **byte == b'\n').count(),
+        words: text
+            .split(|byte| byte.is_ascii_whitespace())
+            .filter(|word| !word.is_empty())
+            .count(),
+        bytes: text.len(),
+    }
+}
+
+/// Die ersten `count` Zeilen einschließlich ihrer Zeilenumbrüche.
+fn head(text: &[u8], count: usize) -> &[u8] {
+    if count == 0 {
+        return &[];
+    }
+    match text
+        .iter()
+        .enumerate()
+        .filter(|(_, byte)| **byte == b'\n')
+        .nth(count - 1)
+    {
+        Some((newline, _)) => &text[..=newline],
+        None => text,
+    }
+}
+
+/// Die letzten `count` Zeilen einschließlich ihrer Zeilenumbrüche.
+fn tail(text: &[u8], count: usize) -> &[u8] {
+    if count == 0 {
+        return &[];
+    }
+    let body = text.strip_suffix(b"\n").unwrap_or(text);
+    match body
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, byte)| **byte == b'\n')
+        .nth(count - 1)
+    {
+        Some((newline, _)) => &text[newline + 1..],
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{Counts, DEFAULT_LINES, contains, count, head, lines, parse_line_count, tail};
+
+    const TEXT: &[u8] = b"one\ntwo words\n\nFour\n";
+
+    #[test]
+    fn splits_lines_and_matches_patterns() {
+        assert_eq!(
+            lines(TEXT).collect::<Vec<_>>(),
+            [&b"one"[..], b"two words", b"", b"Four"]
+        );
+        assert_eq!(lines(b"a\nb").count(), 2);
+        assert_eq!(lines(b"").count(), 0);
+        assert_eq!(lines(b"\n").collect::<Vec<_>>(), [b""]);
+        assert!(contains(b"two words", b"wo", false));
+        assert!(!contains(b"Four", b"four", false));
+        assert!(contains(b"Four", b"four", true));
+        assert!(contains(b"", b"", false));
+    }
+
+    #[test]
+    fn counts_lines_words_and_bytes() {
+        assert_eq!(
+            count(TEXT),
+            Counts {
+                lines: 4,
+                words: 4,
+                bytes: 20
+            }
+        );
+        assert_eq!(count(b"no newline").lines, 0);
+    }
+
+    #[test]
+    fn selects_first_and_last_lines() {
+        assert_eq!(head(TEXT, 2), b"one\ntwo words\n");
+        assert_eq!(head(TEXT, 0), b"");
+        assert_eq!(head(TEXT, 9), TEXT);
+        assert_eq!(tail(TEXT, 2), b"\nFour\n");
+        assert_eq!(tail(b"a\nb", 1), b"b");
+        assert_eq!(tail(TEXT, 9), TEXT);
+        assert_eq!(tail(TEXT, 0), b"");
+
+        let args: [&[u8]; 3] = [b"-n", b"3", b"/a"];
+        assert_eq!(parse_line_count(&args), Some((3, &args[2..])));
+        assert_eq!(
+            parse_line_count(&args[2..]),
+            Some((DEFAULT_LINES, &args[2..]))
+        );
+        assert_eq!(parse_line_count(&[b"-n", b"x"]), None);
+        assert_eq!(parse_line_count(&[b"-n"]), None);
+    }
+}
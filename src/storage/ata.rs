@@ -0,0 +1,106 @@
+//! Die vier klassischen IDE-Positionen: primärer/sekundärer Kanal, Master-/Slave-Laufwerk.
+//!
+//! Jeder Kanal löst einen eigenen IRQ aus (14 primär, 15 sekundär), wenn ein Kommando fertig
+//! oder ein Sektor bereit ist; [`handle_irq`] reicht das Statusbyte an die [`Completion`] des
+//! Kanals weiter.
+
+use crate::arch::x86_64::io;
+use crate::sync::Completion;
+
+/// Statusregister relativ zu [`Channel::io_base`].
+const ATA_STATUS: u16 = 7;
+/// Statusbit: Laufwerk beschäftigt.
+const ATA_STATUS_BSY: u8 = 0x80;
+
+static COMPLETIONS: [Completion; 2] = [Completion::new(), Completion::new()];
+
+/// Ein IDE-Kanal mit seinen festen Ports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channel {
+    Primary,
+    Secondary,
+}
+
+impl Channel {
+    /// Erster Port der Command-Block-Register.
+    pub fn io_base(self) -> u16 {
+        match self {
+            Channel::Primary => 0x1F0,
+            Channel::Secondary => 0x170,
+        }
+    }
+
+    /// Device-Control- bzw. Alternate-Status-Register.
+    pub fn control_port(self) -> u16 {
+        match self {
+            Channel::Primary => 0x3F6,
+            Channel::Secondary => 0x376,
+        }
+    }
+
+    /// Wird bei jedem Interrupt des Kanals mit dem Statusbyte signalisiert.
+    pub fn completion(self) -> &'static Completion {
+        &COMPLETIONS[self as usize]
+    }
+}
+
+/// Handler für IRQ14/IRQ15. Das Lesen des Statusregisters quittiert den Interrupt am Laufwerk;
+/// meldet es noch BSY, kam der IRQ nicht von ihm (unechter IRQ15 vom Slave-PIC).
+pub fn handle_irq(channel: Channel) {
+    let status = io::inb(channel.io_base() + ATA_STATUS);
+    if status & ATA_STATUS_BSY == 0 {
+        channel.completion().complete(status.into());
+    }
+}
+
+/// Lage eines Laufwerks: Kanal und Master oder Slave.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AtaPosition {
+    pub channel: Channel,
+    pub slave: bool,
+}
+
+impl AtaPosition {
+    /// Alle Positionen in Namensreihenfolge.
+    pub const ALL: [AtaPosition; 4] = [
+        AtaPosition::new(Channel::Primary, false),
+        AtaPosition::new(Channel::Primary, true),
+        AtaPosition::new(Channel::Secondary, false),
+        AtaPosition::new(Channel::Secondary, true),
+    ];
+
+    pub const fn new(channel: Channel, slave: bool) -> Self {
+        Self { channel, slave }
+    }
+
+    /// Gerätename wie beim alten IDE-Treiber von Linux.
+    pub fn name(self) -> &'static str {
+        match (self.channel, self.slave) {
+            (Channel::Primary, false) => "hda",
+            (Channel::Primary, true) => "hdb",
+            (Channel::Secondary, false) => "hdc",
+            (Channel::Secondary, true) => "hdd",
+        }
+    }
+
+    pub fn io_base(self) -> u16 {
+        self.channel.io_base()
+    }
+
+    pub fn control_port(self) -> u16 {
+        self.channel.control_port()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AtaPosition, Channel};
+
+    #[test]
+    fn names_positions_like_linux() {
+        let names: [&str; 4] = AtaPosition::ALL.map(AtaPosition::name);
+        assert_eq!(names, ["hda", "hdb", "hdc", "hdd"]);
+        let hdc = AtaPosition::new(Channel::Secondary, false);
+        assert_eq!((hdc.io_base(), hdc.control_port()), (0x170, 0x376));
+    }
+}
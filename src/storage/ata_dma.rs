@@ -0,0 +1,187 @@
+//! Bus-Master-DMA für die klassischen IDE-Kanäle (PIIX und kompatible Controller).
+//!
+//! Kommandos gehen weiter über das Task-File von [`AtaPio`]; nur die Daten fließen per DMA.
+//! Jeder Kanal besitzt eine Physical Region Descriptor Table (PRDT) und einen 64-KiB-Bounce-
+//! Buffer aus dem Frame-Allocator, die [`init`] einmal anlegt. Der Puffer ist auf 64 KiB
+//! ausgerichtet, sodass ein einzelner PRD-Eintrag nie eine 64-KiB-Grenze überschreitet, und der
+//! Frame-Allocator vergibt ohnehin nur Speicher unterhalb von 4 GiB.
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{Ordering, fence};
+
+use crate::arch::x86_64::{self, io};
+use crate::memory::frame_allocator::{self, FRAME_SIZE};
+use crate::memory::vm::{PhysAddr, phys_to_virt};
+use crate::storage::ata::{AtaPosition, Channel};
+use crate::storage::ata_pio::{AtaPio, check_status};
+use crate::storage::block::{BlockDevice, BlockError};
+
+/// Bus-Master-Register relativ zum Block eines Kanals (primär an BAR4, sekundär bei +8).
+const BM_COMMAND: u16 = 0;
+const BM_STATUS: u16 = 2;
+const BM_PRDT: u16 = 4;
+const BM_CHANNEL_STRIDE: u16 = 8;
+
+const BM_COMMAND_START: u8 = 0x01;
+/// Richtung: gesetzt, wenn das Gerät in den Speicher schreibt (Lesen von der Platte).
+const BM_COMMAND_TO_MEMORY: u8 = 0x08;
+const BM_STATUS_ERROR: u8 = 0x02;
+const BM_STATUS_IRQ: u8 = 0x04;
+const PRD_END_OF_TABLE: u32 = 0x8000_0000;
+
+const ATA_CMD_READ_DMA: u8 = 0xC8;
+const ATA_CMD_WRITE_DMA: u8 = 0xCA;
+
+const BUFFER_SIZE: usize = 64 * 1024;
+/// Sektoren pro Kommando, begrenzt durch den Bounce-Buffer.
+const MAX_SECTORS: usize = BUFFER_SIZE / 512;
+
+/// DMA-Ausstattung eines Kanals; Adressen sind physisch.
+#[derive(Clone, Copy)]
+struct ChannelDma {
+    bus_master: u16,
+    prdt: u64,
+    buffer: u64,
+}
+
+struct DmaCell(UnsafeCell<[Option<ChannelDma>; 2]>);
+unsafe impl Sync for DmaCell {}
+
+static CHANNELS: DmaCell = DmaCell(UnsafeCell::new([None; 2]));
+
+fn with_channels<T>(f: impl FnOnce(&mut [Option<ChannelDma>; 2]) -> T) -> T {
+    let interrupts_were_enabled = x86_64::save_and_disable_interrupts();
+    let result = f(unsafe { &mut *CHANNELS.0.get() });
+    x86_64::restore_interrupts(interrupts_were_enabled);
+    result
+}
+
+/// Richtet beide Kanäle des Controllers ein, dessen Bus-Master-Register bei `base` beginnen.
+pub fn init(base: u16) {
+    for channel in [Channel::Primary, Channel::Secondary] {
+        let Some(prdt) = frame_allocator::alloc_frame() else {
+            kwarn!("ata dma: no frame for the PRD table");
+            return;
+        };
+        let frames = BUFFER_SIZE / FRAME_SIZE as usize;
+        let Some(buffer) = frame_allocator::alloc_frames(frames, BUFFER_SIZE as u64) else {
+            let _ = frame_allocator::free_frame(prdt);
+            kwarn!("ata dma: no memory for the bounce buffer");
+            return;
+        };
+        let dma = ChannelDma {
+            bus_master: base + channel as u16 * BM_CHANNEL_STRIDE,
+            prdt: prdt.start,
+            buffer: buffer.start,
+        };
+        with_channels(|channels| channels[channel as usize] = Some(dma));
+    }
+}
+
+/// Ob `init` für den Kanal PRDT und Puffer anlegen konnte.
+pub fn available(channel: Channel) -> bool {
+    with_channels(|channels| channels[channel as usize].is_some())
+}
+
+/// Zugriff auf ein Laufwerk per DMA; Kommandos und Status laufen über das eingebettete
+/// [`AtaPio`].
+pub struct AtaDma {
+    pio: AtaPio,
+    dma: ChannelDma,
+}
+
+impl AtaDma {
+    /// `None`, wenn auf dem Kanal des Laufwerks kein DMA eingerichtet ist.
+    pub fn new(position: AtaPosition) -> Option<Self> {
+        let dma = with_channels(|channels| channels[position.channel as usize])?;
+        Some(Self {
+            pio: AtaPio::new(position),
+            dma,
+        })
+    }
+
+    fn buffer(&mut self, len: usize) -> &mut [u8] {
+        let ptr = phys_to_virt(PhysAddr(self.dma.buffer)).0 as *mut u8;
+        unsafe { core::slice::from_raw_parts_mut(ptr, len) }
+    }
+
+    fn bm_outb(&self, register: u16, value: u8) {
+        io::outb(self.dma.bus_master + register, value);
+    }
+
+    /// Überträgt `sectors` Sektoren zwischen Laufwerk und Bounce-Buffer.
+    fn transfer(&mut self, lba: u64, sectors: usize, to_memory: bool) -> Result<(), BlockError> {
+        let bytes = (sectors * 512) as u32;
+        let prdt = phys_to_virt(PhysAddr(self.dma.prdt)).0 as *mut u32;
+        unsafe {
+            prdt.write_volatile(self.dma.buffer as u32);
+            // Eine Byteanzahl von 0 steht für 64 KiB.
+            prdt.add(1)
+                .write_volatile(PRD_END_OF_TABLE | (bytes & 0xFFFF));
+        }
+        // PRDT und Pufferinhalt müssen im Speicher stehen, bevor der Controller sie liest.
+        fence(Ordering::SeqCst);
+
+        let direction = if to_memory { BM_COMMAND_TO_MEMORY } else { 0 };
+        self.bm_outb(BM_COMMAND, direction);
+        self.bm_outb(BM_STATUS, BM_STATUS_ERROR | BM_STATUS_IRQ);
+        io::outl(self.dma.bus_master + BM_PRDT, self.dma.prdt as u32);
+
+        let command = if to_memory {
+            ATA_CMD_READ_DMA
+        } else {
+            ATA_CMD_WRITE_DMA
+        };
+        self.pio.issue(lba, sectors as u8, command)?;
+        self.bm_outb(BM_COMMAND, direction | BM_COMMAND_START);
+        let status = self.pio.wait_irq();
+
+        self.bm_outb(BM_COMMAND, direction);
+        let bm_status = io::inb(self.dma.bus_master + BM_STATUS);
+        self.bm_outb(BM_STATUS, BM_STATUS_ERROR | BM_STATUS_IRQ);
+        fence(Ordering::SeqCst);
+        let status = status?;
+        if bm_status & BM_STATUS_ERROR != 0 {
+            return Err(BlockError::DeviceFault);
+        }
+        check_status(status)
+    }
+}
+
+impl BlockDevice for AtaDma {
+    fn read_sector(&mut self, lba: u64, out: &mut [u8]) -> Result<(), BlockError> {
+        if out.len() != 512 {
+            return Err(BlockError::InvalidBufferSize);
+        }
+        self.read_sectors(lba, out)
+    }
+
+    fn write_sector(&mut self, lba: u64, data: &[u8]) -> Result<(), BlockError> {
+        if data.len() != 512 {
+            return Err(BlockError::InvalidBufferSize);
+        }
+        self.write_sectors(lba, data)
+    }
+
+    fn read_sectors(&mut self, lba: u64, out: &mut [u8]) -> Result<(), BlockError> {
+        if !out.len().is_multiple_of(512) {
+            return Err(BlockError::InvalidBufferSize);
+        }
+        for (index, chunk) in out.chunks_mut(BUFFER_SIZE).enumerate() {
+            self.transfer(lba + (index * MAX_SECTORS) as u64, chunk.len() / 512, true)?;
+            chunk.copy_from_slice(self.buffer(chunk.len()));
+        }
+        Ok(())
+    }
+
+    fn write_sectors(&mut self, lba: u64, data: &[u8]) -> Result<(), BlockError> {
+        if !data.len().is_multiple_of(512) {
+            return Err(BlockError::InvalidBufferSize);
+        }
+        for (index, chunk) in data.chunks(BUFFER_SIZE).enumerate() {
+            self.buffer(chunk.len()).copy_from_slice(chunk);
+            self.transfer(lba + (index * MAX_SECTORS) as u64, chunk.len() / 512, false)?;
+        }
+        self.pio.flush()
+    }
+}
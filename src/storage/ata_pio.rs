@@ -1,50 +1,171 @@
+//! PIO-Zugriff auf ATA-Laufwerke an den klassischen IDE-Ports.
+//!
+//! Aufträge laufen über Interrupts: Der Treiber gibt ein Kommando aus und schläft auf der
+//! Completion des Kanals, bis IRQ14/IRQ15 den Status des Laufwerks meldet, statt das
+//! Statusregister abzufragen. Nur der kurze DRQ-Handshake vor PIO-Schreibzugriffen wird noch
+//! gepollt, weil das Laufwerk dafür keinen Interrupt auslöst.
+
 use crate::arch::x86_64::io;
+use crate::arch::x86_64::pit::PIT_TARGET_HZ;
+use crate::storage::ata::{AtaPosition, Channel};
 use crate::storage::block::{BlockDevice, BlockError};
+use crate::storage::identify::IdentifyData;
 
-const ATA_DATA: u16 = 0x1F0;
-const ATA_SECTOR_COUNT: u16 = 0x1F2;
-const ATA_LBA_LOW: u16 = 0x1F3;
-const ATA_LBA_MID: u16 = 0x1F4;
-const ATA_LBA_HIGH: u16 = 0x1F5;
-const ATA_DRIVE_HEAD: u16 = 0x1F6;
-const ATA_STATUS_COMMAND: u16 = 0x1F7;
+// Registeroffsets relativ zur I/O-Basis des Kanals.
+const ATA_DATA: u16 = 0;
+const ATA_SECTOR_COUNT: u16 = 2;
+const ATA_LBA_LOW: u16 = 3;
+const ATA_LBA_MID: u16 = 4;
+const ATA_LBA_HIGH: u16 = 5;
+const ATA_DRIVE_HEAD: u16 = 6;
+const ATA_STATUS_COMMAND: u16 = 7;
 
 const ATA_CMD_READ_SECTORS: u8 = 0x20;
+const ATA_CMD_WRITE_SECTORS: u8 = 0x30;
+const ATA_CMD_CACHE_FLUSH: u8 = 0xE7;
+const ATA_CMD_IDENTIFY: u8 = 0xEC;
 const ATA_STATUS_ERR: u8 = 0x01;
 const ATA_STATUS_DF: u8 = 0x20;
 const ATA_STATUS_DRQ: u8 = 0x08;
 const ATA_STATUS_BSY: u8 = 0x80;
 
+/// Obergrenze, bis ein Laufwerk ein Kommando mit seinem Interrupt beantwortet.
+const IRQ_TIMEOUT_TICKS: u64 = 2 * PIT_TARGET_HZ as u64;
 const STATUS_POLL_LIMIT: usize = 100_000;
 
+/// Ein Laufwerk an einer der vier IDE-Positionen, angesprochen per PIO.
 pub struct AtaPio {
+    channel: Channel,
+    io_base: u16,
+    control_port: u16,
     drive_select: u8,
 }
 
 impl AtaPio {
-    pub fn primary_master() -> Self {
-        Self { drive_select: 0xE0 }
+    pub fn new(position: AtaPosition) -> Self {
+        Self {
+            channel: position.channel,
+            io_base: position.io_base(),
+            control_port: position.control_port(),
+            drive_select: if position.slave { 0xF0 } else { 0xE0 },
+        }
+    }
+
+    fn inb(&self, register: u16) -> u8 {
+        io::inb(self.io_base + register)
+    }
+
+    fn outb(&self, register: u16, value: u8) {
+        io::outb(self.io_base + register, value);
+    }
+
+    /// Nach dem Wechsel des Laufwerks braucht der Bus etwa 400 ns; vier Lesezugriffe auf das
+    /// Alternate-Status-Register warten so lange. Device Control 0 löscht außerdem nIEN, damit
+    /// das Laufwerk seine Interrupts auslöst.
+    fn select_drive(&self, value: u8) {
+        io::outb(self.control_port, 0);
+        self.outb(ATA_DRIVE_HEAD, value);
+        for _ in 0..4 {
+            io::inb(self.control_port);
+        }
+    }
+
+    /// Schickt IDENTIFY DEVICE; `Ok(None)`, wenn an dieser Position kein ATA-Laufwerk antwortet.
+    pub fn identify(&mut self) -> Result<Option<IdentifyData>, BlockError> {
+        // IDENTIFY nimmt nur das Laufwerksbit (0xA0/0xB0), ohne LBA-Adressbits.
+        self.select_drive(0xA0 | (self.drive_select & 0x10));
+        self.outb(ATA_SECTOR_COUNT, 0);
+        self.outb(ATA_LBA_LOW, 0);
+        self.outb(ATA_LBA_MID, 0);
+        self.outb(ATA_LBA_HIGH, 0);
+        self.start(ATA_CMD_IDENTIFY);
+
+        // Ein offener Bus liest sich als 0xFF, eine leere Position als 0. Gelesen wird das
+        // Alternate-Status-Register, damit der Interrupt eines schnellen Laufwerks nicht vor dem
+        // Handler quittiert wird.
+        let status = io::inb(self.control_port);
+        if status == 0 || status == 0xFF {
+            return Ok(None);
+        }
+        let status = self.wait_irq()?;
+        // ATAPI- und SATA-Geräte brechen das Kommando ab und hinterlassen ihre Signatur in
+        // LBA mid/high.
+        if self.inb(ATA_LBA_MID) != 0 || self.inb(ATA_LBA_HIGH) != 0 {
+            return Ok(None);
+        }
+        check_data_ready(status)?;
+
+        let mut words = [0_u16; 256];
+        for word in &mut words {
+            *word = io::inw(self.io_base + ATA_DATA);
+        }
+        Ok(Some(IdentifyData::parse(&words)))
+    }
+
+    /// Gibt `command` an das gewählte Laufwerk. Die Completion wird vorher zurückgesetzt, damit
+    /// ein Interrupt, der vor dem Warten eintrifft, nicht verloren geht.
+    pub(super) fn start(&self, command: u8) {
+        self.channel.completion().reset();
+        self.outb(ATA_STATUS_COMMAND, command);
     }
 
-    pub fn primary_slave() -> Self {
-        Self { drive_select: 0xF0 }
+    /// Schläft bis zum Interrupt des Kanals und liefert das vom Handler gelesene Statusbyte.
+    pub(super) fn wait_irq(&self) -> Result<u8, BlockError> {
+        match self.channel.completion().wait(IRQ_TIMEOUT_TICKS) {
+            Some(status) => Ok(status as u8),
+            None => Err(BlockError::Timeout),
+        }
     }
 
-    fn wait_ready(&self) -> Result<u8, BlockError> {
+    /// PIO-Schreiben: Das Laufwerk fordert den ersten Sektor per DRQ an, ohne Interrupt.
+    fn wait_drq(&self) -> Result<(), BlockError> {
         for _ in 0..STATUS_POLL_LIMIT {
-            let status = io::inb(ATA_STATUS_COMMAND);
-            if (status & ATA_STATUS_BSY) != 0 {
-                continue;
-            }
-            if (status & ATA_STATUS_ERR) != 0 || (status & ATA_STATUS_DF) != 0 {
-                return Err(BlockError::DeviceFault);
-            }
-            if (status & ATA_STATUS_DRQ) != 0 {
-                return Ok(status);
+            let status = self.inb(ATA_STATUS_COMMAND);
+            if (status & ATA_STATUS_BSY) == 0 {
+                return check_data_ready(status);
             }
         }
         Err(BlockError::Timeout)
     }
+
+    /// Programmiert eine LBA28-Übertragung von `count` Sektoren (0 heißt 256) und gibt
+    /// `command` aus.
+    pub(super) fn issue(&self, lba: u64, count: u8, command: u8) -> Result<(), BlockError> {
+        if lba + u64::from(count) > 0x1000_0000 {
+            return Err(BlockError::Unsupported);
+        }
+
+        let lba = lba as u32;
+        self.select_drive(self.drive_select | (((lba >> 24) as u8) & 0x0F));
+        self.outb(ATA_SECTOR_COUNT, count);
+        self.outb(ATA_LBA_LOW, (lba & 0xFF) as u8);
+        self.outb(ATA_LBA_MID, ((lba >> 8) & 0xFF) as u8);
+        self.outb(ATA_LBA_HIGH, ((lba >> 16) & 0xFF) as u8);
+        self.start(command);
+        Ok(())
+    }
+
+    /// Leert den Schreibcache des Laufwerks, damit geschriebene Sektoren dauerhaft sind.
+    pub(super) fn flush(&self) -> Result<(), BlockError> {
+        self.start(ATA_CMD_CACHE_FLUSH);
+        check_status(self.wait_irq()?)
+    }
+}
+
+/// Fehler, wenn das Statusbyte ERR oder DF meldet.
+pub(super) fn check_status(status: u8) -> Result<(), BlockError> {
+    if (status & ATA_STATUS_ERR) != 0 || (status & ATA_STATUS_DF) != 0 {
+        return Err(BlockError::DeviceFault);
+    }
+    Ok(())
+}
+
+fn check_data_ready(status: u8) -> Result<(), BlockError> {
+    check_status(status)?;
+    if (status & ATA_STATUS_DRQ) == 0 {
+        return Err(BlockError::DeviceFault);
+    }
+    Ok(())
 }
 
 impl BlockDevice for AtaPio {
@@ -52,29 +173,33 @@ impl BlockDevice for AtaPio {
         if out.len() != 512 {
             return Err(BlockError::InvalidBufferSize);
         }
-        if lba > 0x0FFF_FFFF {
-            return Err(BlockError::Unsupported);
-        }
-
-        let lba = lba as u32;
-        io::outb(
-            ATA_DRIVE_HEAD,
-            self.drive_select | (((lba >> 24) as u8) & 0x0F),
-        );
-        io::outb(ATA_SECTOR_COUNT, 1);
-        io::outb(ATA_LBA_LOW, (lba & 0xFF) as u8);
-        io::outb(ATA_LBA_MID, ((lba >> 8) & 0xFF) as u8);
-        io::outb(ATA_LBA_HIGH, ((lba >> 16) & 0xFF) as u8);
-        io::outb(ATA_STATUS_COMMAND, ATA_CMD_READ_SECTORS);
-
-        self.wait_ready()?;
+        self.issue(lba, 1, ATA_CMD_READ_SECTORS)?;
+        check_data_ready(self.wait_irq()?)?;
 
         for i in 0..256 {
-            let word = io::inw(ATA_DATA);
+            let word = io::inw(self.io_base + ATA_DATA);
             out[i * 2] = (word & 0x00FF) as u8;
             out[i * 2 + 1] = (word >> 8) as u8;
         }
 
         Ok(())
     }
+    fn write_sector(&mut self, lba: u64, data: &[u8]) -> Result<(), BlockError> {
+        if data.len() != 512 {
+            return Err(BlockError::InvalidBufferSize);
+        }
+
+        self.issue(lba, 1, ATA_CMD_WRITE_SECTORS)?;
+        self.wait_drq()?;
+
+        for i in 0..256 {
+            io::outw(
+                self.io_base + ATA_DATA,
+                u16::from_le_bytes([data[i * 2], data[i * 2 + 1]]),
+            );
+        }
+        check_status(self.wait_irq()?)?;
+        // Erst dauerhaft geschrieben, dann Erfolg melden.
+        self.flush()
+    }
 }
@@ -6,17 +6,116 @@ pub enum BlockError {
     Unsupported,
 }
 
+impl BlockError {
+    pub fn describe(self) -> &'static [u8] {
+        match self {
+            Self::InvalidBufferSize => b"invalid buffer size",
+            Self::DeviceFault => b"device fault",
+            Self::Timeout => b"device timeout",
+            Self::Unsupported => b"operation not supported",
+        }
+    }
+}
+
 pub trait BlockDevice {
     fn sector_size(&self) -> usize {
         512
     }
 
     fn read_sector(&mut self, lba: u64, out: &mut [u8]) -> Result<(), BlockError>;
+
+    fn write_sector(&mut self, _lba: u64, _data: &[u8]) -> Result<(), BlockError> {
+        Err(BlockError::Unsupported)
+    }
+
+    /// Liest mehrere Sektoren ab `lba`; `out.len()` ist ein Vielfaches von 512. Geräte, die
+    /// mehrere Sektoren pro Kommando übertragen (etwa per DMA), überschreiben dies, die Vorgabe
+    /// liest Sektor für Sektor.
+    fn read_sectors(&mut self, lba: u64, out: &mut [u8]) -> Result<(), BlockError> {
+        if !out.len().is_multiple_of(512) {
+            return Err(BlockError::InvalidBufferSize);
+        }
+        for (index, sector) in out.chunks_exact_mut(512).enumerate() {
+            self.read_sector(lba + index as u64, sector)?;
+        }
+        Ok(())
+    }
+
+    /// Schreibt mehrere Sektoren ab `lba`; Gegenstück zu [`BlockDevice::read_sectors`].
+    fn write_sectors(&mut self, lba: u64, data: &[u8]) -> Result<(), BlockError> {
+        if !data.len().is_multiple_of(512) {
+            return Err(BlockError::InvalidBufferSize);
+        }
+        for (index, sector) in data.chunks_exact(512).enumerate() {
+            self.write_sector(lba + index as u64, sector)?;
+        }
+        Ok(())
+    }
+}
+
+/// Liest `out.len()` Bytes ab Byte `offset`, das nicht auf Sektoren ausgerichtet sein muss.
+/// Ganze Sektoren gehen in einem `read_sectors`-Aufruf, nur angeschnittene Sektoren am Anfang
+/// und Ende werden einzeln gelesen.
+pub fn read_bytes<D: BlockDevice + ?Sized>(
+    dev: &mut D,
+    offset: u64,
+    out: &mut [u8],
+) -> Result<(), BlockError> {
+    let mut sector = [0_u8; 512];
+    let mut done = 0;
+    while done < out.len() {
+        let position = offset + done as u64;
+        let start = (position % 512) as usize;
+        let rest = out.len() - done;
+        if start == 0 && rest >= 512 {
+            let len = rest / 512 * 512;
+            dev.read_sectors(position / 512, &mut out[done..done + len])?;
+            done += len;
+            continue;
+        }
+        let len = (512 - start).min(rest);
+        dev.read_sector(position / 512, &mut sector)?;
+        out[done..done + len].copy_from_slice(&sector[start..start + len]);
+        done += len;
+    }
+    Ok(())
+}
+
+/// Schreibt `data` ab Byte `offset`. Ganze Sektoren gehen in einem `write_sectors`-Aufruf;
+/// angeschnittene Sektoren an den Enden werden vorher gelesen und zusammengeführt.
+pub fn write_bytes<D: BlockDevice + ?Sized>(
+    dev: &mut D,
+    offset: u64,
+    data: &[u8],
+) -> Result<(), BlockError> {
+    let mut sector = [0_u8; 512];
+    let mut done = 0;
+    while done < data.len() {
+        let position = offset + done as u64;
+        let lba = position / 512;
+        let start = (position % 512) as usize;
+        let rest = data.len() - done;
+        if start == 0 && rest >= 512 {
+            let len = rest / 512 * 512;
+            dev.write_sectors(lba, &data[done..done + len])?;
+            done += len;
+            continue;
+        }
+        let len = (512 - start).min(rest);
+        dev.read_sector(lba, &mut sector)?;
+        sector[start..start + len].copy_from_slice(&data[done..done + len]);
+        dev.write_sector(lba, &sector)?;
+        done += len;
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{BlockDevice, BlockError};
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::{BlockDevice, BlockError, read_bytes, write_bytes};
 
     struct MockBlock {
         sector0: [u8; 512],
@@ -52,6 +151,12 @@ mod tests {
         assert_eq!((buf[510], buf[511]), (0x55, 0xAA));
     }
 
+    #[test]
+    fn writes_are_unsupported_by_default() {
+        let mut dev = MockBlock::new();
+        assert_eq!(dev.write_sector(0, &[0; 512]), Err(BlockError::Unsupported));
+    }
+
     #[test]
     fn rejects_wrong_buffer_size() {
         let mut dev = MockBlock::new();
@@ -61,4 +166,118 @@ mod tests {
             Err(BlockError::InvalidBufferSize)
         );
     }
+
+    struct MemBlock {
+        data: Vec<u8>,
+    }
+
+    impl BlockDevice for MemBlock {
+        fn read_sector(&mut self, lba: u64, out: &mut [u8]) -> Result<(), BlockError> {
+            let start = lba as usize * 512;
+            out.copy_from_slice(
+                self.data
+                    .get(start..start + 512)
+                    .ok_or(BlockError::Unsupported)?,
+            );
+            Ok(())
+        }
+
+        fn write_sector(&mut self, lba: u64, data: &[u8]) -> Result<(), BlockError> {
+            let start = lba as usize * 512;
+            self.data
+                .get_mut(start..start + 512)
+                .ok_or(BlockError::Unsupported)?
+                .copy_from_slice(data);
+            Ok(())
+        }
+    }
+
+    // Überträgt wie DMA mehrere Sektoren pro Kommando und protokolliert jedes als
+    // (Schreiben, LBA, Sektoren).
+    struct MultiBlock {
+        mem: MemBlock,
+        log: Vec<(bool, u64, usize)>,
+    }
+
+    impl BlockDevice for MultiBlock {
+        fn read_sector(&mut self, lba: u64, out: &mut [u8]) -> Result<(), BlockError> {
+            self.read_sectors(lba, out)
+        }
+
+        fn write_sector(&mut self, lba: u64, data: &[u8]) -> Result<(), BlockError> {
+            self.write_sectors(lba, data)
+        }
+
+        fn read_sectors(&mut self, lba: u64, out: &mut [u8]) -> Result<(), BlockError> {
+            self.log.push((false, lba, out.len() / 512));
+            self.mem.read_sectors(lba, out)
+        }
+
+        fn write_sectors(&mut self, lba: u64, data: &[u8]) -> Result<(), BlockError> {
+            self.log.push((true, lba, data.len() / 512));
+            self.mem.write_sectors(lba, data)
+        }
+    }
+
+    #[test]
+    fn reads_and_writes_unaligned_byte_ranges() {
+        let mut dev = MemBlock {
+            data: vec![0; 3 * 512],
+        };
+        let data: Vec<u8> = (0..700).map(|i| i as u8).collect();
+        write_bytes(&mut dev, 300, &data).expect("write");
+        assert_eq!(&dev.data[300..1000], data.as_slice());
+        assert!(
+            dev.data[..300]
+                .iter()
+                .chain(&dev.data[1000..])
+                .all(|b| *b == 0)
+        );
+
+        let mut out = [0_u8; 10];
+        read_bytes(&mut dev, 505, &mut out).expect("read");
+        assert_eq!(out, data[205..215]);
+        assert_eq!(
+            read_bytes(&mut dev, 3 * 512, &mut out),
+            Err(BlockError::Unsupported)
+        );
+
+        let mut sectors = [0_u8; 1024];
+        dev.read_sectors(1, &mut sectors).expect("read sectors");
+        assert_eq!(&sectors[..], &dev.data[512..]);
+        dev.write_sectors(0, &[0xEE; 1024]).expect("write sectors");
+        assert!(dev.data[..1024].iter().all(|b| *b == 0xEE));
+        assert_eq!(
+            dev.read_sectors(0, &mut out),
+            Err(BlockError::InvalidBufferSize)
+        );
+    }
+
+    #[test]
+    fn moves_whole_sectors_in_one_command() {
+        let mut dev = MultiBlock {
+            mem: MemBlock {
+                data: vec![0; 4 * 512],
+            },
+            log: Vec::new(),
+        };
+        write_bytes(&mut dev, 100, &[1; 1500]).expect("write");
+        assert_eq!(
+            dev.log,
+            [
+                (false, 0, 1),
+                (true, 0, 1),
+                (true, 1, 2),
+                (false, 3, 1),
+                (true, 3, 1)
+            ]
+        );
+
+        dev.log.clear();
+        let mut out = vec![0_u8; 4 * 512];
+        read_bytes(&mut dev, 0, &mut out).expect("read");
+        assert_eq!(dev.log, [(false, 0, 4)]);
+        assert!(out[100..1600].iter().all(|b| *b == 1));
+        assert!(out[..100].iter().chain(&out[1600..]).all(|b| *b == 0));
+    }
 }
@@ -95,6 +95,18 @@ impl<D: BlockDevice> BlockDevice for CachedBlockDevice<D> {
         out.copy_from_slice(&self.lines[replace_idx].data);
         Ok(())
     }
+    fn write_sector(&mut self, lba: u64, data: &[u8]) -> Result<(), BlockError> {
+        if data.len() != 512 {
+            return Err(BlockError::InvalidBufferSize);
+        }
+
+        // Write-through: the device is updated immediately, a cached copy is kept in sync.
+        self.inner.write_sector(lba, data)?;
+        if let Some(line) = self.lines.iter_mut().find(|l| l.valid && l.lba == lba) {
+            line.data.copy_from_slice(data);
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -116,6 +128,12 @@ mod tests {
             self.reads += 1;
             Ok(())
         }
+
+        fn write_sector(&mut self, lba: u64, data: &[u8]) -> Result<(), BlockError> {
+            let dst = self.sectors.get_mut(lba as usize).ok_or(BlockError::Unsupported)?;
+            dst.copy_from_slice(data);
+            Ok(())
+        }
     }
 
     #[test]
@@ -134,4 +152,20 @@ mod tests {
         assert_eq!(stats.hits, 1);
         assert_eq!(stats.misses, 1);
     }
+
+    #[test]
+    fn writes_through_and_updates_cached_sector() {
+        let dev = MockDev {
+            sectors: vec![[0_u8; 512]],
+            reads: 0,
+        };
+        let mut cached = CachedBlockDevice::new(dev, 4);
+        let mut buf = [0_u8; 512];
+        cached.read_sector(0, &mut buf).expect("read");
+        cached.write_sector(0, &[7; 512]).expect("write");
+        cached.read_sector(0, &mut buf).expect("read");
+        assert_eq!(buf, [7; 512]);
+        assert_eq!(cached.inner.sectors[0], [7; 512]);
+        assert_eq!(cached.inner.reads, 1);
+    }
 }
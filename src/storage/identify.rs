@@ -0,0 +1,127 @@
+//! Auswertung des 256-Wort-Blocks, den ATA IDENTIFY DEVICE liefert (ATA8-ACS, Tabelle 45).
+
+const SERIAL_WORDS: core::ops::Range<usize> = 10..20;
+const FIRMWARE_WORDS: core::ops::Range<usize> = 23..27;
+const MODEL_WORDS: core::ops::Range<usize> = 27..47;
+const CAPABILITIES_WORD: usize = 49;
+const DMA_SUPPORTED: u16 = 1 << 8;
+const LBA28_SECTORS_WORD: usize = 60;
+const COMMAND_SETS_WORD: usize = 83;
+const LBA48_SECTORS_WORD: usize = 100;
+const LBA48_SUPPORTED: u16 = 1 << 10;
+
+/// Die für den Kernel wichtigen Felder der IDENTIFY-Daten.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IdentifyData {
+    serial: [u8; 20],
+    firmware: [u8; 8],
+    model: [u8; 40],
+    pub lba48: bool,
+    /// Das Laufwerk beherrscht DMA (Wort 49, Bit 8).
+    pub dma: bool,
+    /// Adressierbare Sektoren; bei LBA48-Unterstützung deren Anzahl.
+    pub sectors: u64,
+}
+
+impl IdentifyData {
+    /// Dekodiert die Rohdaten, wie sie aus dem Datenregister kommen.
+    pub fn parse(words: &[u16; 256]) -> Self {
+        let lba48 = words[COMMAND_SETS_WORD] & LBA48_SUPPORTED != 0;
+        let sectors = if lba48 {
+            words[LBA48_SECTORS_WORD..LBA48_SECTORS_WORD + 4]
+                .iter()
+                .rev()
+                .fold(0_u64, |acc, word| (acc << 16) | u64::from(*word))
+        } else {
+            u64::from(words[LBA28_SECTORS_WORD]) | (u64::from(words[LBA28_SECTORS_WORD + 1]) << 16)
+        };
+
+        Self {
+            serial: ata_string(&words[SERIAL_WORDS]),
+            firmware: ata_string(&words[FIRMWARE_WORDS]),
+            model: ata_string(&words[MODEL_WORDS]),
+            lba48,
+            dma: words[CAPABILITIES_WORD] & DMA_SUPPORTED != 0,
+            sectors,
+        }
+    }
+
+    /// Seriennummer ohne Füllzeichen; ebenso `firmware` und `model`.
+    pub fn serial(&self) -> &[u8] {
+        trim(&self.serial)
+    }
+
+    pub fn firmware(&self) -> &[u8] {
+        trim(&self.firmware)
+    }
+
+    pub fn model(&self) -> &[u8] {
+        trim(&self.model)
+    }
+
+    pub fn size_bytes(&self) -> u64 {
+        self.sectors * 512
+    }
+}
+
+/// ATA-Strings speichern zwei Zeichen pro Wort, das erste im höherwertigen Byte.
+fn ata_string<const N: usize>(words: &[u16]) -> [u8; N] {
+    let mut out = [b' '; N];
+    for (chunk, word) in out.chunks_exact_mut(2).zip(words) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
+
+fn trim(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|b| *b != b' ' && *b != 0)
+        .unwrap_or(bytes.len());
+    let end = bytes
+        .iter()
+        .rposition(|b| *b != b' ' && *b != 0)
+        .map_or(start, |i| i + 1);
+    &bytes[start..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IdentifyData;
+
+    fn put_string(words: &mut [u16; 256], start: usize, text: &[u8]) {
+        for (i, pair) in text.chunks(2).enumerate() {
+            let low = pair.get(1).copied().unwrap_or(b' ');
+            words[start + i] = u16::from_be_bytes([pair[0], low]);
+        }
+    }
+
+    #[test]
+    fn parses_strings_and_sector_counts() {
+        let mut words = [0x2020_u16; 256];
+        put_string(&mut words, 10, b"QM00002");
+        put_string(&mut words, 23, b"2.5+");
+        put_string(&mut words, 27, b"QEMU HARDDISK");
+        words[60] = 0x0800;
+        words[61] = 0;
+        words[83] = 0;
+
+        let info = IdentifyData::parse(&words);
+        assert_eq!(info.serial(), b"QM00002");
+        assert_eq!(info.firmware(), b"2.5+");
+        assert_eq!(info.model(), b"QEMU HARDDISK");
+        assert!(!info.lba48 && !info.dma);
+        assert_eq!(info.sectors, 2048);
+        assert_eq!(info.size_bytes(), 1024 * 1024);
+
+        words[49] = 1 << 8;
+        words[83] = 1 << 10;
+        words[100] = 0x0000;
+        words[101] = 0x0010;
+        words[102] = 0x0001;
+        words[103] = 0;
+        let info = IdentifyData::parse(&words);
+        assert!(info.lba48 && info.dma);
+        assert_eq!(info.sectors, 0x0001_0010_0000);
+    }
+}
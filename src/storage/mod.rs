@@ -1,6 +1,12 @@
+pub mod ata;
 pub mod block;
 pub mod cache;
 pub mod error;
+pub mod identify;
+pub mod pci_ide;
+pub mod registry;
 
+#[cfg(eres_kernel)]
+pub mod ata_dma;
 #[cfg(eres_kernel)]
 pub mod ata_pio;
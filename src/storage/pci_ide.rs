@@ -0,0 +1,72 @@
+//! PCI-IDE-Controller (Klasse 01:01), z. B. die PIIX3/PIIX4-Funktion, die QEMU emuliert.
+//!
+//! Kommandos laufen weiter über die klassischen Ports und IRQ14/IRQ15, deshalb werden nur
+//! Controller übernommen, deren beide Kanäle im Kompatibilitätsmodus laufen. BAR4 enthält die
+//! Bus-Master-Register für DMA, das nur auf den PIIX-Funktionen eingeschaltet wird; andere
+//! Controller bleiben bei PIO.
+
+use core::sync::atomic::{AtomicU16, Ordering};
+
+use crate::pci::config::ConfigAccess;
+use crate::pci::device::{Bar, COMMAND_BUS_MASTER, COMMAND_IO, PciDevice};
+use crate::pci::driver::{Match, PciDriver};
+
+/// Programmierschnittstelle: primärer Kanal im Native-Modus.
+const PROG_IF_PRIMARY_NATIVE: u8 = 0x01;
+/// Programmierschnittstelle: sekundärer Kanal im Native-Modus.
+const PROG_IF_SECONDARY_NATIVE: u8 = 0x04;
+
+/// I/O-Basis der Bus-Master-Register; 0, bis eine PIIX-Funktion gebunden ist.
+static BUS_MASTER_BASE: AtomicU16 = AtomicU16::new(0);
+
+/// IDE-Funktionen von Intels PIIX3/PIIX4, wie auf QEMUs i440FX-Maschine.
+pub static PIIX: PciDriver = PciDriver {
+    name: "ata_piix",
+    matches: &[
+        Match::Id {
+            vendor: 0x8086,
+            device: 0x7010,
+        },
+        Match::Id {
+            vendor: 0x8086,
+            device: 0x7111,
+        },
+    ],
+    probe: probe_piix,
+};
+
+/// Jeder andere IDE-Controller im Kompatibilitätsmodus.
+pub static GENERIC: PciDriver = PciDriver {
+    name: "ide",
+    matches: &[Match::Class {
+        class: 0x01,
+        subclass: 0x01,
+    }],
+    probe: probe_generic,
+};
+
+/// I/O-Basis der Bus-Master-Register, falls ein PIIX-Controller gebunden ist.
+pub fn bus_master_base() -> Option<u16> {
+    match BUS_MASTER_BASE.load(Ordering::Relaxed) {
+        0 => None,
+        base => Some(base),
+    }
+}
+
+fn probe_piix(cfg: &mut dyn ConfigAccess, device: &PciDevice) -> Result<(), &'static str> {
+    probe_generic(cfg, device)?;
+    if let Some(Bar::Io { port, .. }) = device.bars[4] {
+        device.enable(cfg, COMMAND_BUS_MASTER);
+        BUS_MASTER_BASE.store(port, Ordering::Relaxed);
+        kinfo!("pci {}: IDE bus master at {port:#x}", device.address);
+    }
+    Ok(())
+}
+
+fn probe_generic(cfg: &mut dyn ConfigAccess, device: &PciDevice) -> Result<(), &'static str> {
+    if device.prog_if & (PROG_IF_PRIMARY_NATIVE | PROG_IF_SECONDARY_NATIVE) != 0 {
+        return Err("native-mode channels are not supported");
+    }
+    device.enable(cfg, COMMAND_IO);
+    Ok(())
+}
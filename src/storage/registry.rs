@@ -0,0 +1,246 @@
+//! Beim Start gefundene Laufwerke mit Namen.
+//!
+//! [`probe`] schickt einmal IDENTIFY DEVICE an alle vier klassischen IDE-Positionen; alles
+//! andere sucht Laufwerke hier über ihren Namen (`hda` … `hdd`), statt anzunehmen, an welcher
+//! Position welche Platte hängt. Jedes Laufwerk trägt außerdem seinen Übertragungsmodus: DMA,
+//! wo Laufwerk und Controller es unterstützen, sonst PIO. [`set_mode`] schaltet ihn zur
+//! Laufzeit um.
+
+use alloc::vec::Vec;
+use core::cell::UnsafeCell;
+
+use crate::arch;
+use crate::storage::ata::AtaPosition;
+#[cfg(eres_kernel)]
+use crate::storage::ata_dma::AtaDma;
+#[cfg(eres_kernel)]
+use crate::storage::ata_pio::AtaPio;
+#[cfg(eres_kernel)]
+use crate::storage::block::{BlockDevice, BlockError};
+use crate::storage::identify::IdentifyData;
+
+/// Wie die Daten eines Laufwerks übertragen werden.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferMode {
+    Pio,
+    Dma,
+}
+
+impl TransferMode {
+    /// `PIO` oder `DMA`, wie in `lsblk` und `dma`.
+    pub fn name(self) -> &'static str {
+        match self {
+            TransferMode::Pio => "PIO",
+            TransferMode::Dma => "DMA",
+        }
+    }
+}
+
+/// Ein registriertes Laufwerk mit seinen IDENTIFY-Daten.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DriveInfo {
+    pub position: AtaPosition,
+    pub identify: IdentifyData,
+    pub mode: TransferMode,
+}
+
+impl DriveInfo {
+    /// `hda` … `hdd`.
+    pub fn name(&self) -> &'static str {
+        self.position.name()
+    }
+
+    /// DMA braucht ein Laufwerk, das es beherrscht, und einen Bus-Master auf seinem Kanal.
+    pub fn dma_available(&self) -> bool {
+        self.identify.dma && channel_has_dma(self.position)
+    }
+
+    /// Neuer Zugriff im aktuellen Modus des Laufwerks. Zugriffe sind billig und halten keinen
+    /// Zustand außer den Ports und den DMA-Puffern des Kanals.
+    #[cfg(eres_kernel)]
+    pub fn open(&self) -> AtaDevice {
+        self.open_with(self.mode)
+            .unwrap_or_else(|| AtaDevice::Pio(AtaPio::new(self.position)))
+    }
+
+    /// Zugriff in einem bestimmten Modus; `None`, wenn das Laufwerk ihn nicht nutzen kann.
+    #[cfg(eres_kernel)]
+    pub fn open_with(&self, mode: TransferMode) -> Option<AtaDevice> {
+        match mode {
+            TransferMode::Pio => Some(AtaDevice::Pio(AtaPio::new(self.position))),
+            TransferMode::Dma if self.dma_available() => {
+                AtaDma::new(self.position).map(AtaDevice::Dma)
+            }
+            TransferMode::Dma => None,
+        }
+    }
+}
+
+#[cfg(eres_kernel)]
+fn channel_has_dma(position: AtaPosition) -> bool {
+    crate::storage::ata_dma::available(position.channel)
+}
+
+#[cfg(not(eres_kernel))]
+fn channel_has_dma(_position: AtaPosition) -> bool {
+    false
+}
+
+/// Ein geöffnetes ATA-Laufwerk, je nach Modus per PIO oder DMA.
+#[cfg(eres_kernel)]
+pub enum AtaDevice {
+    Pio(AtaPio),
+    Dma(AtaDma),
+}
+
+#[cfg(eres_kernel)]
+impl BlockDevice for AtaDevice {
+    fn read_sector(&mut self, lba: u64, out: &mut [u8]) -> Result<(), BlockError> {
+        match self {
+            AtaDevice::Pio(dev) => dev.read_sector(lba, out),
+            AtaDevice::Dma(dev) => dev.read_sector(lba, out),
+        }
+    }
+
+    fn write_sector(&mut self, lba: u64, data: &[u8]) -> Result<(), BlockError> {
+        match self {
+            AtaDevice::Pio(dev) => dev.write_sector(lba, data),
+            AtaDevice::Dma(dev) => dev.write_sector(lba, data),
+        }
+    }
+
+    fn read_sectors(&mut self, lba: u64, out: &mut [u8]) -> Result<(), BlockError> {
+        match self {
+            AtaDevice::Pio(dev) => dev.read_sectors(lba, out),
+            AtaDevice::Dma(dev) => dev.read_sectors(lba, out),
+        }
+    }
+
+    fn write_sectors(&mut self, lba: u64, data: &[u8]) -> Result<(), BlockError> {
+        match self {
+            AtaDevice::Pio(dev) => dev.write_sectors(lba, data),
+            AtaDevice::Dma(dev) => dev.write_sectors(lba, data),
+        }
+    }
+}
+
+struct RegistryCell(UnsafeCell<Vec<DriveInfo>>);
+unsafe impl Sync for RegistryCell {}
+
+static DRIVES: RegistryCell = RegistryCell(UnsafeCell::new(Vec::new()));
+
+fn with_drives<T>(f: impl FnOnce(&mut Vec<DriveInfo>) -> T) -> T {
+    let interrupts_were_enabled = arch::x86_64::save_and_disable_interrupts();
+    let result = f(unsafe { &mut *DRIVES.0.get() });
+    arch::x86_64::restore_interrupts(interrupts_were_enabled);
+    result
+}
+
+/// Trägt ein Laufwerk ein und ersetzt dabei einen älteren Eintrag derselben Position; die
+/// Einträge bleiben nach Namen sortiert.
+pub fn register(drive: DriveInfo) {
+    with_drives(|drives| {
+        drives.retain(|entry| entry.position != drive.position);
+        let index = drives.partition_point(|entry| entry.name() < drive.name());
+        drives.insert(index, drive);
+    });
+}
+
+/// Alle Laufwerke nach Namen sortiert.
+pub fn drives() -> Vec<DriveInfo> {
+    with_drives(|drives| drives.clone())
+}
+
+/// Laufwerk mit dem Namen `name` (ohne `/dev/`).
+pub fn find(name: &str) -> Option<DriveInfo> {
+    with_drives(|drives| drives.iter().find(|drive| drive.name() == name).copied())
+}
+
+/// Schaltet ein Laufwerk zwischen PIO und DMA um; danach geöffnete Zugriffe nutzen den neuen
+/// Modus.
+pub fn set_mode(name: &str, mode: TransferMode) -> Result<(), &'static [u8]> {
+    with_drives(|drives| {
+        let Some(drive) = drives.iter_mut().find(|drive| drive.name() == name) else {
+            return Err(b"no such drive".as_slice());
+        };
+        if mode == TransferMode::Dma && !drive.dma_available() {
+            return Err(b"DMA not available".as_slice());
+        }
+        drive.mode = mode;
+        Ok(())
+    })
+}
+
+/// Fragt alle vier Positionen ab und registriert jedes ATA-Laufwerk, das auf IDENTIFY antwortet,
+/// wenn möglich im DMA-Modus. Läuft nach der PCI-Aufzählung, die den IDE-Bus-Master meldet.
+#[cfg(eres_kernel)]
+pub fn probe() {
+    if let Some(base) = crate::storage::pci_ide::bus_master_base() {
+        crate::storage::ata_dma::init(base);
+    }
+
+    for position in AtaPosition::ALL {
+        match AtaPio::new(position).identify() {
+            Ok(Some(identify)) => {
+                let mut drive = DriveInfo {
+                    position,
+                    identify,
+                    mode: TransferMode::Pio,
+                };
+                if drive.dma_available() {
+                    drive.mode = TransferMode::Dma;
+                }
+                let model = core::str::from_utf8(identify.model()).unwrap_or("?");
+                kinfo!(
+                    "{}: {model}, {} sectors, {}",
+                    position.name(),
+                    identify.sectors,
+                    drive.mode.name()
+                );
+                register(drive);
+            }
+            Ok(None) => {}
+            Err(err) => kwarn!("{}: IDENTIFY failed: {err:?}", position.name()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DriveInfo, TransferMode, drives, find, register, set_mode};
+    use crate::storage::ata::{AtaPosition, Channel};
+    use crate::storage::identify::IdentifyData;
+
+    #[test]
+    fn registers_drives_by_name() {
+        let drive = |channel, slave, sectors: u16| {
+            let mut words = [0_u16; 256];
+            words[60] = sectors;
+            DriveInfo {
+                position: AtaPosition::new(channel, slave),
+                identify: IdentifyData::parse(&words),
+                mode: TransferMode::Pio,
+            }
+        };
+
+        register(drive(Channel::Secondary, false, 100));
+        register(drive(Channel::Primary, true, 100));
+        register(drive(Channel::Primary, true, 200));
+
+        let names: alloc::vec::Vec<_> = drives().iter().map(DriveInfo::name).collect();
+        assert_eq!(names, ["hdb", "hdc"]);
+        assert_eq!(find("hdb").map(|drive| drive.identify.sectors), Some(200));
+        assert_eq!(find("hda"), None);
+
+        // Ohne Bus-Master (wie auf dem Host) lässt sich DMA nicht wählen.
+        assert_eq!(
+            set_mode("hdb", TransferMode::Dma),
+            Err(&b"DMA not available"[..])
+        );
+        assert_eq!(set_mode("hdb", TransferMode::Pio), Ok(()));
+        assert_eq!(
+            set_mode("hda", TransferMode::Pio),
+            Err(&b"no such drive"[..])
+        );
+    }
+}
@@ -0,0 +1,84 @@
+//! Synchronisation zwischen Interrupt-Handlern und wartendem Kernelcode.
+//!
+//! Eine [`Completion`] meldet das Ende eines Auftrags, den ein Gerät per IRQ quittiert. Der
+//! Auftraggeber setzt sie vor dem Start zurück und wartet danach; solange es keinen Scheduler
+//! gibt, schläft er dabei per `hlt` bis zum nächsten Interrupt.
+
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use crate::arch::x86_64::{self, pit};
+
+/// Einmaliges Fertig-Signal mit einem 32-Bit-Ergebnis (z. B. einem Statusregister).
+pub struct Completion {
+    done: AtomicBool,
+    value: AtomicU32,
+}
+
+impl Completion {
+    pub const fn new() -> Self {
+        Self {
+            done: AtomicBool::new(false),
+            value: AtomicU32::new(0),
+        }
+    }
+
+    /// Verwirft ein altes Signal; muss vor dem Start des Auftrags passieren, weil der IRQ
+    /// sonst schon vor dem Warten eintreffen und verloren gehen könnte.
+    pub fn reset(&self) {
+        self.done.store(false, Ordering::Release);
+    }
+
+    /// Meldet den Auftrag als erledigt (aus dem Interrupt-Handler).
+    pub fn complete(&self, value: u32) {
+        self.value.store(value, Ordering::Relaxed);
+        self.done.store(true, Ordering::Release);
+    }
+
+    /// Holt das Ergebnis ab, falls der Auftrag erledigt ist.
+    pub fn try_take(&self) -> Option<u32> {
+        self.done
+            .swap(false, Ordering::Acquire)
+            .then(|| self.value.load(Ordering::Relaxed))
+    }
+
+    /// Wartet höchstens `timeout_ticks` PIT-Ticks auf das Signal.
+    ///
+    /// Läuft der Aufrufer mit gesperrten Interrupts (frühe Boot-Phase), werden sie nur für die
+    /// Dauer des `hlt` freigegeben; danach gilt wieder der vorige Zustand.
+    pub fn wait(&self, timeout_ticks: u64) -> Option<u32> {
+        let interrupts_were_enabled = x86_64::save_and_disable_interrupts();
+        let deadline = pit::ticks() + timeout_ticks;
+        let result = loop {
+            if let Some(value) = self.try_take() {
+                break Some(value);
+            }
+            if pit::ticks() >= deadline {
+                break None;
+            }
+            // Prüfen und Schlafen ohne Lücke: `sti; hlt` nimmt einen IRQ erst im `hlt` an.
+            x86_64::enable_interrupts_and_halt();
+            x86_64::disable_interrupts();
+        };
+        x86_64::restore_interrupts(interrupts_were_enabled);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Completion;
+
+    #[test]
+    fn delivers_value_once() {
+        let completion = Completion::new();
+        assert_eq!(completion.wait(0), None);
+
+        completion.complete(0x58);
+        assert_eq!(completion.wait(0), Some(0x58));
+        assert_eq!(completion.try_take(), None);
+
+        completion.complete(0x50);
+        completion.reset();
+        assert_eq!(completion.try_take(), None);
+    }
+}